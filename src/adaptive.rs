@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+/// How long frame times are averaged before the count is adjusted.
+const MEASURE_INTERVAL: Duration = Duration::from_secs(1);
+/// How long the count stops growing after it had to shrink, so it does not keep
+/// overshooting the GPU.
+const GROW_COOLDOWN: Duration = Duration::from_secs(10);
+/// Shrink when the frame rate drops below this fraction of the target.
+const SHRINK_BELOW: f32 = 0.9;
+/// Grow while the frame rate stays above this fraction of the target.
+const GROW_ABOVE: f32 = 0.98;
+/// Fraction of the count removed when shrinking and added when growing.
+const SHRINK_STEP: f32 = 0.15;
+const GROW_STEP: f32 = 0.05;
+/// Seconds the intensity a count follows is smoothed over.
+const INTENSITY_SMOOTHING: f32 = 0.75;
+/// Fraction of the range the target has to move away from the count before it changes, so
+/// the points do not flicker in and out with every beat.
+const INTENSITY_HYSTERESIS: f32 = 0.08;
+
+pub struct AdaptiveCountOptions {
+    pub target_fps: f32,
+    pub min_count: usize,
+    pub max_count: usize,
+}
+
+/// Scales the number of points between bounds to hold a target frame rate.
+pub struct AdaptiveCount {
+    options: AdaptiveCountOptions,
+    elapsed: Duration,
+    frames: u32,
+    since_shrink: Duration,
+}
+
+impl AdaptiveCount {
+    pub fn new(options: AdaptiveCountOptions) -> Self {
+        Self {
+            options,
+            elapsed: Duration::ZERO,
+            frames: 0,
+            since_shrink: GROW_COOLDOWN,
+        }
+    }
+
+    pub fn max_count(&self) -> usize {
+        self.options.max_count
+    }
+
+    /// Clamps a count into the configured bounds.
+    pub fn clamp(&self, count: usize) -> usize {
+        count.clamp(self.options.min_count, self.options.max_count)
+    }
+
+    /// Records a frame and returns the count to switch to, if it should change.
+    pub fn tick(&mut self, delta_time: Duration, count: usize) -> Option<usize> {
+        self.elapsed += delta_time;
+        self.since_shrink += delta_time;
+        self.frames += 1;
+
+        if self.elapsed < MEASURE_INTERVAL {
+            return None;
+        }
+
+        let fps = self.frames as f32 / self.elapsed.as_secs_f32();
+        self.elapsed = Duration::ZERO;
+        self.frames = 0;
+
+        let new_count = if fps < self.options.target_fps * SHRINK_BELOW {
+            self.since_shrink = Duration::ZERO;
+            count - (count as f32 * SHRINK_STEP) as usize
+        } else if fps >= self.options.target_fps * GROW_ABOVE && self.since_shrink >= GROW_COOLDOWN
+        {
+            count + ((count as f32 * GROW_STEP) as usize).max(1)
+        } else {
+            return None;
+        };
+
+        let new_count = self.clamp(new_count);
+        (new_count != count).then(|| {
+            log::info!("Adjusting the point count from {count} to {new_count} at {fps:.1} FPS");
+            new_count
+        })
+    }
+}
+
+/// Scales the number of points between bounds with the intensity.
+pub struct IntensityCount {
+    min_count: usize,
+    max_count: usize,
+    smoothed_intensity: f32,
+}
+
+impl IntensityCount {
+    /// Starts out following from `count`, so the first frames do not change it.
+    pub fn new(min_count: usize, max_count: usize, count: usize) -> Self {
+        let range = (max_count - min_count).max(1) as f32;
+        Self {
+            min_count,
+            max_count,
+            smoothed_intensity: (count.clamp(min_count, max_count) - min_count) as f32 / range,
+        }
+    }
+
+    pub fn max_count(&self) -> usize {
+        self.max_count
+    }
+
+    /// Clamps a count into the configured bounds.
+    pub fn clamp(&self, count: usize) -> usize {
+        count.clamp(self.min_count, self.max_count)
+    }
+
+    /// Records the intensity of a frame and returns the count to switch to, if it should change.
+    pub fn tick(&mut self, delta_time: f32, intensity: f32, count: usize) -> Option<usize> {
+        self.smoothed_intensity += (intensity - self.smoothed_intensity)
+            * (1.0 - (-delta_time / INTENSITY_SMOOTHING).exp());
+
+        let range = (self.max_count - self.min_count) as f32;
+        let target =
+            self.min_count + (range * self.smoothed_intensity.clamp(0.0, 1.0)).round() as usize;
+
+        // Always settle on the bounds themselves, they would otherwise only be reached to
+        // within the hysteresis.
+        let at_bound = target == self.min_count || target == self.max_count;
+        let moved = target.abs_diff(count) as f32 > range * INTENSITY_HYSTERESIS;
+        (target != count && (moved || at_bound)).then_some(target)
+    }
+}
@@ -0,0 +1,236 @@
+use std::{
+    f32::consts::TAU,
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+
+use anyhow::{Result, anyhow};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+use crate::simulation::Point;
+
+/// How often the point field is read back and analyzed.
+const ANALYSIS_INTERVAL: Duration = Duration::from_millis(500);
+/// Cell size in pixels used to bin points when looking for clusters and collisions.
+const ANALYSIS_CELL_SIZE: f32 = 8.0;
+/// Upper bound on simultaneously ringing plucks, so dense fields don't turn into noise.
+const MAX_VOICES: usize = 8;
+/// Most plucks queued per analysis, however many points collided.
+const MAX_PLUCKS_PER_ANALYSIS: u32 = 4;
+/// Minimum time between two plucks.
+const PLUCK_SPACING: Duration = Duration::from_millis(100);
+/// Base frequency of the drone in Hz.
+const DRONE_FREQUENCY: f32 = 55.0;
+/// Pentatonic ratios plucks are picked from.
+const PLUCK_RATIOS: [f32; 5] = [1.0, 9.0 / 8.0, 5.0 / 4.0, 3.0 / 2.0, 5.0 / 3.0];
+
+/// Parameters shared with the audio thread. Floats are stored as their bit patterns.
+#[derive(Default)]
+struct SynthParameters {
+    intensity: AtomicU32,
+    clustering: AtomicU32,
+    pending_plucks: AtomicU32,
+}
+
+impl SynthParameters {
+    fn load(value: &AtomicU32) -> f32 {
+        f32::from_bits(value.load(Ordering::Relaxed))
+    }
+
+    fn store(value: &AtomicU32, v: f32) {
+        value.store(v.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// A small generative synth whose drone follows how clustered the points are and
+/// which plucks a note whenever points collide.
+pub struct AmbientAudio {
+    parameters: Arc<SynthParameters>,
+    since_analysis: Duration,
+    _stream: cpal::Stream,
+}
+
+impl AmbientAudio {
+    pub fn new(volume: f32) -> Result<Self> {
+        let host = cpal::default_host();
+        let device = host
+            .default_output_device()
+            .ok_or(anyhow!("No audio output device found"))?;
+        let supported_config = device.default_output_config()?;
+
+        if supported_config.sample_format() != cpal::SampleFormat::F32 {
+            return Err(anyhow!(
+                "Unsupported output sample format {}",
+                supported_config.sample_format()
+            ));
+        }
+
+        let stream_config: cpal::StreamConfig = supported_config.into();
+        let parameters = Arc::new(SynthParameters::default());
+        let mut synth = Synth::new(
+            Arc::clone(&parameters),
+            stream_config.sample_rate.0 as f32,
+            volume.clamp(0.0, 1.0),
+        );
+        let channels = stream_config.channels as usize;
+
+        let stream = device.build_output_stream(
+            &stream_config,
+            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                for frame in data.chunks_mut(channels) {
+                    let sample = synth.next_sample();
+                    frame.fill(sample);
+                }
+            },
+            |e| log::error!("Ambient audio stream error: {e}"),
+            None,
+        )?;
+        stream.play()?;
+
+        Ok(Self {
+            parameters,
+            since_analysis: ANALYSIS_INTERVAL,
+            _stream: stream,
+        })
+    }
+
+    pub fn set_intensity(&self, intensity: f32) {
+        SynthParameters::store(&self.parameters.intensity, intensity);
+    }
+
+    /// Advances the analysis timer, returning true when the points should be analyzed.
+    pub fn tick(&mut self, delta_time: Duration) -> bool {
+        self.since_analysis += delta_time;
+
+        if self.since_analysis >= ANALYSIS_INTERVAL {
+            self.since_analysis = Duration::ZERO;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn analyze(&self, points: &[Point], width: u32, height: u32) {
+        let columns = (width as f32 / ANALYSIS_CELL_SIZE).ceil().max(1.0) as usize;
+        let rows = (height as f32 / ANALYSIS_CELL_SIZE).ceil().max(1.0) as usize;
+        let mut cells = vec![0u32; columns * rows];
+
+        for point in points {
+            let column = ((point.position[0] / ANALYSIS_CELL_SIZE) as usize).min(columns - 1);
+            let row = ((point.position[1] / ANALYSIS_CELL_SIZE) as usize).min(rows - 1);
+            cells[row * columns + column] += 1;
+        }
+
+        // Points sharing a cell are close enough to count as colliding.
+        let collisions: u32 = cells.iter().map(|&c| c * c.saturating_sub(1) / 2).sum();
+
+        // How much fuller the busiest cell is than average, squashed into 0-1.
+        let mean = points.len() as f32 / cells.len() as f32;
+        let max = cells.iter().copied().max().unwrap_or(0) as f32;
+        let clustering = if mean > 0.0 {
+            1.0 - 1.0 / (max / mean).max(1.0)
+        } else {
+            0.0
+        };
+
+        SynthParameters::store(&self.parameters.clustering, clustering);
+        self.parameters
+            .pending_plucks
+            .store(collisions.min(MAX_PLUCKS_PER_ANALYSIS), Ordering::Relaxed);
+    }
+}
+
+struct Voice {
+    phase: f32,
+    frequency: f32,
+    amplitude: f32,
+}
+
+struct Synth {
+    parameters: Arc<SynthParameters>,
+    sample_rate: f32,
+    volume: f32,
+    drone_phases: [f32; 3],
+    smoothed_intensity: f32,
+    smoothed_clustering: f32,
+    voices: Vec<Voice>,
+    next_pluck: usize,
+    pluck_cooldown: u32,
+}
+
+impl Synth {
+    fn new(parameters: Arc<SynthParameters>, sample_rate: f32, volume: f32) -> Self {
+        Self {
+            parameters,
+            sample_rate,
+            volume,
+            drone_phases: [0.0; 3],
+            smoothed_intensity: 0.0,
+            smoothed_clustering: 0.0,
+            voices: Vec::with_capacity(MAX_VOICES),
+            next_pluck: 0,
+            pluck_cooldown: 0,
+        }
+    }
+
+    fn next_sample(&mut self) -> f32 {
+        // Smooth parameter changes over roughly a tenth of a second to avoid clicks.
+        let smoothing = 1.0 / (0.1 * self.sample_rate);
+        let intensity = SynthParameters::load(&self.parameters.intensity);
+        let clustering = SynthParameters::load(&self.parameters.clustering);
+        self.smoothed_intensity += (intensity - self.smoothed_intensity) * smoothing;
+        self.smoothed_clustering += (clustering - self.smoothed_clustering) * smoothing;
+
+        self.start_pending_pluck();
+
+        // Denser clusters bend the drone upwards by up to a fifth.
+        let drone_frequency = DRONE_FREQUENCY * (1.0 + 0.5 * self.smoothed_clustering);
+        let mut sample = 0.0;
+        for (phase, ratio) in self.drone_phases.iter_mut().zip([1.0, 1.5, 2.003]) {
+            sample += phase.sin() * 0.2;
+            *phase = (*phase + TAU * drone_frequency * ratio / self.sample_rate) % TAU;
+        }
+        sample *= 0.3 + 0.7 * self.smoothed_intensity;
+
+        let decay = 1.0 - 4.0 / self.sample_rate;
+        for voice in &mut self.voices {
+            sample += voice.phase.sin() * voice.amplitude;
+            voice.phase = (voice.phase + TAU * voice.frequency / self.sample_rate) % TAU;
+            voice.amplitude *= decay;
+        }
+        self.voices.retain(|voice| voice.amplitude > 0.001);
+
+        (sample * self.volume).clamp(-1.0, 1.0)
+    }
+
+    fn start_pending_pluck(&mut self) {
+        if self.pluck_cooldown > 0 {
+            self.pluck_cooldown -= 1;
+            return;
+        }
+
+        if self.voices.len() >= MAX_VOICES {
+            return;
+        }
+
+        let took_pluck = self
+            .parameters
+            .pending_plucks
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |p| p.checked_sub(1))
+            .is_ok();
+
+        if took_pluck {
+            let ratio = PLUCK_RATIOS[self.next_pluck % PLUCK_RATIOS.len()];
+            self.next_pluck = self.next_pluck.wrapping_add(3);
+            self.pluck_cooldown = (PLUCK_SPACING.as_secs_f32() * self.sample_rate) as u32;
+            self.voices.push(Voice {
+                phase: 0.0,
+                frequency: DRONE_FREQUENCY * 8.0 * ratio,
+                amplitude: 0.15,
+            });
+        }
+    }
+}
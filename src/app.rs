@@ -1,127 +1,519 @@
-use std::{sync::Arc, time::Instant};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{ffi::OsString, time::Instant};
+use std::{sync::Arc, time::Duration};
 
+#[cfg(not(target_arch = "wasm32"))]
+use anyhow::Context;
 use log::info;
 #[cfg(target_arch = "wasm32")]
-use winit::event_loop;
+use web_time::Instant;
+#[cfg(target_os = "linux")]
+use winit::platform::wayland::WindowAttributesExtWayland;
+#[cfg(not(target_arch = "wasm32"))]
+use winit::window::WindowAttributes;
 use winit::{
-    application::ApplicationHandler, platform::wayland::WindowAttributesExtWayland, window::Window,
+    application::ApplicationHandler,
+    dpi::{PhysicalPosition, PhysicalSize},
+    event_loop::ControlFlow,
+    window::{Fullscreen, Window, WindowLevel},
+};
+#[cfg(target_arch = "wasm32")]
+use winit::{
+    dpi::LogicalSize,
+    event_loop::{EventLoop, EventLoopProxy},
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::config::PowerProfile;
+#[cfg(target_os = "linux")]
+use crate::power::PowerMonitor;
+#[cfg(target_os = "linux")]
+use crate::session::SessionMonitor;
+#[cfg(target_arch = "wasm32")]
+use crate::web::{CanvasObserver, Command, Page};
+#[cfg(target_os = "linux")]
+use crate::xscreensaver;
+use crate::{canvas::Canvas, config::Config, hooks::Hooks, state::State, wallpaper};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    control::{Controller, Request},
+    hotkeys,
+    hotkeys::Hotkeys,
 };
 
-use crate::state::State;
+/// How often a paused app checks whether it should resume.
+const PAUSED_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Factor the + and - keys speed up or slow down the simulation by.
+const TIME_SCALE_STEP: f32 = 1.25;
+/// Factor the up and down arrow keys change the number of points by.
+const POINTS_STEP: f32 = 1.1;
+/// Factor the right and left arrow keys change the connection distance by.
+const CONNECTION_DISTANCE_STEP: f32 = 1.1;
+/// Factor the ] and [ keys change the intensity gain by.
+const INTENSITY_STEP: f32 = 1.25;
+/// How often the monitors are checked for being plugged in, unplugged or changing resolution.
+#[cfg(not(target_arch = "wasm32"))]
+const MONITOR_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Names, positions and sizes of the monitors.
+#[cfg(not(target_arch = "wasm32"))]
+type MonitorLayout = Vec<(Option<String>, PhysicalPosition<i32>, PhysicalSize<u32>)>;
 
 pub struct App {
     #[cfg(target_arch = "wasm32")]
-    proxy: Option<winit::event_loop::EventLoopProxy<State>>,
+    proxy: EventLoopProxy<State>,
+    #[cfg(target_arch = "wasm32")]
+    page: Page,
+    #[cfg(target_arch = "wasm32")]
+    canvas_observer: Option<CanvasObserver>,
     state: Option<State>,
     last_update: Instant,
-    background_image: Option<String>,
+    config: Config,
+    hooks: Hooks,
+    paused: bool,
+    /// Whether the window is minimized or fully covered, as far as the platform tells.
+    hidden: bool,
+    #[cfg(target_os = "linux")]
+    session_monitor: Option<SessionMonitor>,
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Takes requests when the app was started from a command line to apply them to.
+    controller: Option<Controller>,
+    #[cfg(not(target_arch = "wasm32"))]
+    _hotkeys: Option<Hotkeys>,
+    #[cfg(target_os = "linux")]
+    power_monitor: Option<PowerMonitor>,
+    /// Whether the state was created with the low power profile.
+    low_power: bool,
+    /// When the next frame may be drawn with a frame rate cap.
+    frame_due: Option<Instant>,
     #[cfg(not(target_arch = "wasm32"))]
-    class: String,
+    monitors: MonitorLayout,
+    #[cfg(not(target_arch = "wasm32"))]
+    monitors_checked: Instant,
+    /// What the windows of a span are opened with, to open them again when the monitors
+    /// change.
+    #[cfg(not(target_arch = "wasm32"))]
+    span_attributes: Option<WindowAttributes>,
+    /// Why the event loop was stopped, when it could not go on.
+    error: Option<anyhow::Error>,
 }
 
 impl App {
     pub fn new(
         #[cfg(target_arch = "wasm32")] event_loop: &EventLoop<State>,
-        background_image: Option<String>,
-        #[cfg(not(target_arch = "wasm32"))] class: String,
+        config: Config,
+        hooks: Hooks,
+        #[cfg(not(target_arch = "wasm32"))] command_line: Option<Vec<OsString>>,
+        #[cfg(target_arch = "wasm32")] page: Page,
     ) -> Self {
         #[cfg(target_arch = "wasm32")]
-        let proxy = Some(event_loop.create_proxy());
+        let proxy = event_loop.create_proxy();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let controller = command_line.map(|args| Controller::start(args, &config));
+        #[cfg(not(target_arch = "wasm32"))]
+        let hotkeys = controller
+            .as_ref()
+            .filter(|_| config.hotkeys)
+            .map(|controller| hotkeys::register(&config, controller.sender()))
+            .transpose()
+            .unwrap_or_else(|e| {
+                log::warn!("Unable to register the hotkeys: {e}");
+                None
+            });
 
         Self {
             state: None,
             #[cfg(target_arch = "wasm32")]
             proxy,
+            #[cfg(target_arch = "wasm32")]
+            page,
+            #[cfg(target_arch = "wasm32")]
+            canvas_observer: None,
             last_update: Instant::now(),
-            background_image,
-            class,
+            #[cfg(target_os = "linux")]
+            session_monitor: (!config.keep_running_when_locked).then(SessionMonitor::spawn),
+            #[cfg(not(target_arch = "wasm32"))]
+            controller,
+            #[cfg(not(target_arch = "wasm32"))]
+            _hotkeys: hotkeys,
+            #[cfg(target_os = "linux")]
+            power_monitor: (config.power_profile == PowerProfile::Auto)
+                .then(|| PowerMonitor::spawn(config.low_power_threshold)),
+            config,
+            hooks,
+            paused: false,
+            hidden: false,
+            low_power: false,
+            frame_due: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            monitors: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            monitors_checked: Instant::now(),
+            #[cfg(not(target_arch = "wasm32"))]
+            span_attributes: None,
+            error: None,
+        }
+    }
+
+    /// What stopped the event loop, if it did not stop normally.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn into_result(self) -> anyhow::Result<()> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(()),
         }
     }
+
+    /// Stops the event loop because of `error`, which `into_result` hands on.
+    fn fail(&mut self, event_loop: &winit::event_loop::ActiveEventLoop, error: anyhow::Error) {
+        // The page gets no result from the event loop to find the error in.
+        #[cfg(target_arch = "wasm32")]
+        log::error!("{error:#}");
+        self.error = Some(error);
+        event_loop.exit();
+    }
 }
 
 impl ApplicationHandler<State> for App {
     fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
         #[allow(unused_mut)]
         let mut window_attributes = Window::default_attributes()
-            .with_name(self.class.clone(), self.class.clone())
             .with_title("connecting-dots")
             .with_decorations(false);
 
+        #[cfg(target_os = "linux")]
+        {
+            window_attributes =
+                window_attributes.with_name(self.config.class.clone(), self.config.class.clone());
+        }
+
+        if let (Some(width), Some(height)) = (self.config.width, self.config.height) {
+            window_attributes = window_attributes.with_inner_size(PhysicalSize::new(width, height));
+        }
+        if let (Some(x), Some(y)) = (self.config.x, self.config.y) {
+            window_attributes = window_attributes.with_position(PhysicalPosition::new(x, y));
+        }
+
+        if self.config.below {
+            #[cfg(target_os = "linux")]
+            if winit::platform::wayland::ActiveEventLoopExtWayland::is_wayland(event_loop) {
+                log::warn!(
+                    "Wayland windows cannot ask to stay below others, use --layer-shell bottom"
+                );
+            }
+            window_attributes = window_attributes.with_window_level(WindowLevel::AlwaysOnBottom);
+        }
+
+        if let Some(monitor_name) = &self.config.fullscreen {
+            let monitor = monitor_name.as_ref().and_then(|name| {
+                let monitor = event_loop
+                    .available_monitors()
+                    .find(|monitor| monitor.name().as_deref() == Some(name));
+                if monitor.is_none() {
+                    log::warn!("No monitor named {name}, going fullscreen on the current one");
+                }
+                monitor
+            });
+            window_attributes =
+                window_attributes.with_fullscreen(Some(Fullscreen::Borderless(monitor)));
+        }
+
+        #[cfg(target_os = "linux")]
+        if self.config.screensaver {
+            match xscreensaver::window() {
+                Ok(Some((parent, size))) => {
+                    use winit::platform::x11::WindowAttributesExtX11;
+
+                    window_attributes = window_attributes
+                        .with_embed_parent_window(parent)
+                        .with_inner_size(size)
+                        .with_position(PhysicalPosition::new(0, 0));
+                }
+                // Started outside of XScreenSaver, like to try it out.
+                Ok(None) => {
+                    window_attributes =
+                        window_attributes.with_fullscreen(Some(Fullscreen::Borderless(None)));
+                }
+                Err(e) => {
+                    log::error!("Unable to draw into the XScreenSaver window: {e}");
+                    window_attributes =
+                        window_attributes.with_fullscreen(Some(Fullscreen::Borderless(None)));
+                }
+            }
+        }
+
         #[cfg(target_arch = "wasm32")]
         {
             use wasm_bindgen::JsCast;
             use winit::platform::web::WindowAttributesExtWebSys;
 
-            const CANVAS_ID: &str = "canvas";
-
-            let window = wgpu::web_sys::window().unrwap_throw();
-            let document = window.document().unwrap_throw();
-            let canvas = document.get_element_by_id(CANVAS_ID).unwrap_throw();
-            let html_canvas_element = canvas.unchecked_into();
-            window_attributes = window_attributes.with_canvas(Some(html_canvas_element));
+            let canvas = wgpu::web_sys::window()
+                .and_then(|window| window.document())
+                .and_then(|document| document.get_element_by_id(&self.page.canvas_id));
+            let Some(canvas) = canvas else {
+                return log::error!("There is no canvas with the id {}", self.page.canvas_id);
+            };
+            let canvas: wgpu::web_sys::HtmlCanvasElement = canvas.unchecked_into();
+            self.canvas_observer = CanvasObserver::new(canvas.clone(), &self.page)
+                .inspect_err(|e| log::error!("{e}"))
+                .ok();
+            window_attributes = window_attributes.with_canvas(Some(canvas));
         }
 
-        let window = Arc::new(event_loop.create_window(window_attributes).unwrap());
-
         #[cfg(not(target_arch = "wasm32"))]
         {
-            self.state = Some(
-                pollster::block_on(State::new(window, self.background_image.clone())).unwrap(),
-            );
+            self.monitors = monitor_layout(event_loop);
+            self.monitors_checked = Instant::now();
         }
 
-        #[cfg(target_arch = "wasm32")]
-        {
-            if let Some(proxy) = self.proxy.take() {
-                wasm_bindgen_futures::spawn_local(async move {
-                    assert!(
-                        proxy
-                            .send_event(State::new(window).await.expect("Unable to create canvas"))
-                            .is_ok()
-                    )
-                });
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.config.span {
+            let result = open_span(event_loop, &window_attributes)
+                .and_then(|windows| self.start(Canvas::Span(windows)));
+            self.span_attributes = Some(window_attributes);
+            if let Err(e) = result {
+                self.fail(event_loop, e);
             }
+            return;
+        }
+
+        let window = match event_loop.create_window(window_attributes) {
+            Ok(window) => Arc::new(window),
+            Err(e) => {
+                return self.fail(
+                    event_loop,
+                    anyhow::Error::new(e).context("Unable to open a window"),
+                );
+            }
+        };
+
+        if self.config.wallpaper
+            && let Err(e) = wallpaper::attach(&window)
+        {
+            log::error!("Unable to run as a wallpaper: {e}");
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Err(e) = self.start(Canvas::Window(window)) {
+            self.fail(event_loop, e);
         }
+
+        #[cfg(target_arch = "wasm32")]
+        self.start_async(Canvas::Window(window));
     }
 
     #[allow(unused_mut)]
     fn user_event(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop, mut event: State) {
         #[cfg(target_arch = "wasm32")]
         {
-            event.window.request_redraw();
-            event.resize(
-                event.window.inner_size().width,
-                event.window.inner_size().height,
-            );
+            let (width, height) = match &self.canvas_observer {
+                Some(canvas_observer) => canvas_observer.fit(),
+                None => (event.canvas.size().width, event.canvas.size().height),
+            };
+            event.canvas.request_redraw();
+            event.resize(width, height);
         }
 
         self.state = Some(event);
     }
 
+    fn about_to_wait(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        #[cfg(target_arch = "wasm32")]
+        self.run_page_commands(event_loop);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.monitors_checked.elapsed() >= MONITOR_POLL_INTERVAL {
+            self.monitors_checked = Instant::now();
+            self.check_monitors(event_loop);
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let config_changed = self
+                .controller
+                .as_mut()
+                .is_some_and(|controller| controller.poll(&mut self.state, &mut self.config));
+            self.apply_power_profile(config_changed);
+        }
+
+        if let Some(state) = &mut self.state
+            && state.is_recording()
+        {
+            // Frames are rendered as fast as they can be encoded, independent of the display.
+            event_loop.set_control_flow(ControlFlow::Poll);
+            match state.record_frame() {
+                Ok(false) => {}
+                Ok(true) => {
+                    info!("Recording finished");
+                    event_loop.exit();
+                }
+                Err(e) => {
+                    log::error!("Unable to record: {e}");
+                    event_loop.exit();
+                }
+            }
+            return;
+        }
+
+        if let Some(frame_due) = self.frame_due
+            && Instant::now() >= frame_due
+            && let Some(state) = &self.state
+        {
+            self.frame_due = None;
+            event_loop.set_control_flow(ControlFlow::Wait);
+            state.canvas.request_redraw();
+        }
+
+        if !self.paused {
+            return;
+        }
+
+        if self.should_pause() {
+            event_loop.set_control_flow(ControlFlow::WaitUntil(
+                Instant::now() + PAUSED_POLL_INTERVAL,
+            ));
+            return;
+        }
+
+        if let Some(state) = &self.state {
+            info!("Resuming rendering");
+            self.paused = false;
+            state.set_paused(false);
+            // Don't let the simulation jump ahead by the time spent paused.
+            self.last_update = Instant::now();
+            event_loop.set_control_flow(ControlFlow::Wait);
+            state.canvas.request_redraw();
+        }
+    }
+
     fn window_event(
         &mut self,
         event_loop: &winit::event_loop::ActiveEventLoop,
-        _window_id: winit::window::WindowId,
+        window_id: winit::window::WindowId,
         event: winit::event::WindowEvent,
     ) {
+        let should_pause = self.should_pause();
+        let frame_interval = self.frame_interval();
+
         let state = match &mut self.state {
             Some(canvas) => canvas,
             None => return,
         };
 
         match event {
-            winit::event::WindowEvent::Resized(size) => state.resize(size.width, size.height),
-            winit::event::WindowEvent::Moved(pos) => info!("Moved {pos:?}"),
+            winit::event::WindowEvent::Resized(size) => {
+                // Windows shrinks minimized windows to nothing instead of reporting occlusion.
+                self.hidden = size.width == 0 || size.height == 0;
+                // The windows of a span cover its size together.
+                #[cfg(not(target_arch = "wasm32"))]
+                let size = match &state.canvas {
+                    Canvas::Span(_) => state.canvas.size(),
+                    _ => size,
+                };
+                state.resize(size.width, size.height);
+                state.refresh_monitor();
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            winit::event::WindowEvent::ScaleFactorChanged { scale_factor, .. }
+                if state.needs_rescale() =>
+            {
+                info!("Scale factor changed to {scale_factor}");
+                if let Err(e) = self.restart() {
+                    log::error!("Unable to follow the scale factor: {e}");
+                }
+            }
+            winit::event::WindowEvent::Occluded(occluded) => self.hidden = occluded,
+            winit::event::WindowEvent::Moved(_) => {
+                state.refresh_monitor();
+                state.update_window_pos();
+            }
             winit::event::WindowEvent::CloseRequested => event_loop.exit(),
+            winit::event::WindowEvent::KeyboardInput { event, .. } => {
+                state.register_activity();
+                if event.state == winit::event::ElementState::Pressed {
+                    use winit::keyboard::{Key, NamedKey};
+                    match &event.logical_key {
+                        Key::Character(key) => match key.as_str() {
+                            "+" | "=" => state.scale_time(TIME_SCALE_STEP),
+                            "-" => state.scale_time(1.0 / TIME_SCALE_STEP),
+                            "]" => state.scale_intensity(INTENSITY_STEP),
+                            "[" => state.scale_intensity(1.0 / INTENSITY_STEP),
+                            #[cfg(not(target_arch = "wasm32"))]
+                            "p" => {
+                                if let Some(controller) = &self.controller {
+                                    controller
+                                        .sender()
+                                        .send(Request::SwitchPreset { name: None });
+                                }
+                            }
+                            _ => {}
+                        },
+                        Key::Named(NamedKey::ArrowUp) => state.scale_points(POINTS_STEP),
+                        Key::Named(NamedKey::ArrowDown) => state.scale_points(1.0 / POINTS_STEP),
+                        Key::Named(NamedKey::ArrowRight) => {
+                            state.scale_connection_distance(CONNECTION_DISTANCE_STEP)
+                        }
+                        Key::Named(NamedKey::ArrowLeft) => {
+                            state.scale_connection_distance(1.0 / CONNECTION_DISTANCE_STEP)
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            winit::event::WindowEvent::MouseInput {
+                state: button_state,
+                button,
+                ..
+            } => {
+                state.register_activity();
+                if button_state == winit::event::ElementState::Pressed {
+                    match button {
+                        winit::event::MouseButton::Left => state.spawn_points_at_cursor(),
+                        winit::event::MouseButton::Right => state.remove_points_at_cursor(),
+                        _ => {}
+                    }
+                }
+            }
+            winit::event::WindowEvent::CursorMoved { position, .. } => {
+                state.register_activity();
+                let origin = state.canvas.origin(window_id);
+                state.set_cursor(Some([
+                    (position.x + origin.x as f64) as f32,
+                    (position.y + origin.y as f64) as f32,
+                ]));
+            }
+            winit::event::WindowEvent::CursorLeft { .. } => state.set_cursor(None),
             winit::event::WindowEvent::RedrawRequested => {
+                if self.paused || state.is_recording() || !state.canvas.draws_on(window_id) {
+                    return;
+                }
+
+                if should_pause {
+                    info!("Pausing rendering");
+                    self.paused = true;
+                    state.set_paused(true);
+                    return;
+                }
+
                 let now = Instant::now();
+                if let Some(frame_interval) = frame_interval
+                    && now < self.last_update + frame_interval
+                {
+                    // Sleep until the frame is due instead of drawing as fast as the display.
+                    let frame_due = self.last_update + frame_interval;
+                    self.frame_due = Some(frame_due);
+                    event_loop.set_control_flow(ControlFlow::WaitUntil(frame_due));
+                    return;
+                }
+
                 let delta_time = now - self.last_update;
                 self.last_update = now;
                 state.update(delta_time);
                 match state.render() {
                     Ok(_) => {}
                     Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
-                        let size = state.window.inner_size();
+                        let size = state.canvas.size();
                         state.resize(size.width, size.height);
                     }
                     Err(e) => {
@@ -133,3 +525,234 @@ impl ApplicationHandler<State> for App {
         }
     }
 }
+
+impl App {
+    /// Creates the state drawing into `canvas`, with the low power profile when it applies.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn start(&mut self, canvas: Canvas) -> anyhow::Result<()> {
+        self.low_power = self.wants_low_power();
+        let config = if self.low_power {
+            info!("Saving power");
+            &self.config.low_power()
+        } else {
+            &self.config
+        };
+        let state = pollster::block_on(State::with_canvas(canvas, config, self.hooks.clone()))
+            .context("Unable to set up the GPU")?;
+        self.state = Some(state);
+
+        Ok(())
+    }
+
+    /// Creates the state drawing into `canvas` in the background, as the browser can not
+    /// wait for the GPU. It reaches the app as a user event.
+    #[cfg(target_arch = "wasm32")]
+    fn start_async(&self, canvas: Canvas) {
+        let proxy = self.proxy.clone();
+        let config = self.config.clone();
+        let hooks = self.hooks.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            match State::with_canvas(canvas, &config, hooks).await {
+                Ok(state) => {
+                    // Only fails once the event loop has stopped, which drops the state.
+                    let _ = proxy.send_event(state);
+                }
+                Err(e) => log::error!("Unable to set up the GPU: {e:#}"),
+            }
+        });
+    }
+
+    /// Carries out the calls the page made through the JavaScript API since the last time.
+    #[cfg(target_arch = "wasm32")]
+    fn run_page_commands(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        if let Some(canvas_observer) = &self.canvas_observer {
+            canvas_observer.poll();
+        }
+
+        for command in self.page.take_commands() {
+            match command {
+                Command::Stop => {
+                    info!("Stopping");
+                    self.state = None;
+                    self.canvas_observer = None;
+                    event_loop.exit();
+                }
+                Command::Configure(config) => {
+                    let reconfigured = self
+                        .state
+                        .as_mut()
+                        .is_some_and(|state| state.reconfigure(&self.config, &config));
+                    self.config = *config;
+                    if !reconfigured && let Some(state) = self.state.take() {
+                        let canvas = state.canvas.clone();
+                        // The old state has to let go of the surface before the new one can
+                        // take it.
+                        drop(state);
+                        self.start_async(canvas);
+                    }
+                }
+                Command::Resize(width, height) => {
+                    if let Some(state) = &self.state {
+                        let Canvas::Window(window) = &state.canvas;
+                        let _ = window.request_inner_size(LogicalSize::new(width, height));
+                    }
+                }
+                // Waits for the state while it is set up, which fits the canvas once there.
+                Command::Fit => {
+                    if let Some(state) = &mut self.state
+                        && let Some(canvas_observer) = &self.canvas_observer
+                    {
+                        let (width, height) = canvas_observer.fit();
+                        state.resize(width, height);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Follows monitors being plugged in, unplugged or changing resolution, which winit has no
+    /// events for. A span opens its windows again on the new monitors and starts over, other
+    /// windows reload the background for their monitor.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn check_monitors(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        let monitors = monitor_layout(event_loop);
+        if monitors == self.monitors {
+            return;
+        }
+        info!("Monitors changed: {monitors:?}");
+        self.monitors = monitors;
+
+        if let Some(window_attributes) = &self.span_attributes {
+            let windows = match open_span(event_loop, window_attributes) {
+                Ok(windows) => windows,
+                Err(e) => return log::error!("Unable to span the new monitors: {e:#}"),
+            };
+            if let Some(state) = &mut self.state {
+                state.canvas = Canvas::Span(windows);
+            }
+            if let Err(e) = self.restart() {
+                log::error!("Unable to span the new monitors: {e}");
+            }
+        } else if let Some(state) = &mut self.state {
+            state.refresh_monitor();
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn wants_low_power(&self) -> bool {
+        // Recordings are rendered offscreen at full quality no matter the power source.
+        if self.config.record.is_some() || self.config.export_gif.is_some() {
+            return false;
+        }
+
+        match self.config.power_profile {
+            PowerProfile::Performance => false,
+            PowerProfile::LowPower => true,
+            #[cfg(target_os = "linux")]
+            PowerProfile::Auto => self
+                .power_monitor
+                .as_ref()
+                .is_some_and(PowerMonitor::is_low_power),
+            #[cfg(not(target_os = "linux"))]
+            PowerProfile::Auto => false,
+        }
+    }
+
+    /// Shortest time between two frames, when the frame rate is capped by --max-fps or
+    /// while saving power, whichever is lower.
+    fn frame_interval(&self) -> Option<Duration> {
+        let low_power = self
+            .low_power
+            .then(|| Duration::from_secs_f64(1.0 / self.config.low_power_fps as f64));
+        low_power
+            .into_iter()
+            .chain(self.config.frame_interval())
+            .max()
+    }
+
+    /// Starts the state over with the low power profile when the machine starts or stops
+    /// saving power, or when the config changed underneath it.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn apply_power_profile(&mut self, config_changed: bool) {
+        let low_power = self.wants_low_power();
+        if low_power == self.low_power && !(low_power && config_changed) {
+            return;
+        }
+
+        if low_power != self.low_power {
+            info!(
+                "{}",
+                if low_power {
+                    "Saving power"
+                } else {
+                    "No longer saving power"
+                }
+            );
+        }
+        self.low_power = low_power;
+
+        if let Err(e) = self.restart() {
+            log::error!("Unable to switch the power profile: {e}");
+        }
+    }
+
+    /// Starts the state over in the same canvas, with the low power profile when it is in
+    /// use.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn restart(&mut self) -> anyhow::Result<()> {
+        let config = if self.low_power {
+            &self.config.low_power()
+        } else {
+            &self.config
+        };
+        State::restart(&mut self.state, config, &self.config)
+    }
+
+    fn should_pause(&self) -> bool {
+        if self.hidden && !self.config.keep_running_when_hidden {
+            return true;
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.controller.as_ref().is_some_and(Controller::is_paused) {
+            return true;
+        }
+
+        #[cfg(target_os = "linux")]
+        if let Some(session_monitor) = &self.session_monitor
+            && session_monitor.is_inactive()
+        {
+            return true;
+        }
+
+        false
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn monitor_layout(event_loop: &winit::event_loop::ActiveEventLoop) -> MonitorLayout {
+    event_loop
+        .available_monitors()
+        .map(|monitor| (monitor.name(), monitor.position(), monitor.size()))
+        .collect()
+}
+
+/// Opens a borderless fullscreen window on every monitor.
+#[cfg(not(target_arch = "wasm32"))]
+fn open_span(
+    event_loop: &winit::event_loop::ActiveEventLoop,
+    window_attributes: &WindowAttributes,
+) -> anyhow::Result<Vec<Arc<Window>>> {
+    event_loop
+        .available_monitors()
+        .map(|monitor| {
+            let window_attributes = window_attributes
+                .clone()
+                .with_fullscreen(Some(Fullscreen::Borderless(Some(monitor))));
+            let window = event_loop
+                .create_window(window_attributes)
+                .context("Unable to open a window")?;
+            Ok(Arc::new(window))
+        })
+        .collect()
+}
@@ -0,0 +1,553 @@
+#[cfg(feature = "video")]
+use std::path::Path;
+use std::{fs::File, io::BufReader, time::Duration};
+
+use anyhow::anyhow;
+use bytemuck::{Pod, Zeroable};
+use image::{
+    AnimationDecoder, DynamicImage, Frames, ImageFormat, RgbaImage,
+    codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder},
+};
+use wgpu::util::DeviceExt;
+use winit::dpi::PhysicalSize;
+
+use crate::config::{BackgroundAlign, BackgroundMode};
+use crate::renderer::Renderer;
+#[cfg(feature = "video")]
+use crate::video::{VideoDecoder, is_video, probe_size};
+
+/// A background texture drawn behind the points, aligned to the window's position on
+/// the monitor so it lines up with the desktop wallpaper.
+pub struct BackgroundImage {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    texture: wgpu::Texture,
+    size: wgpu::Extent3d,
+    animation: Option<Animation>,
+    #[cfg(feature = "video")]
+    video: Option<VideoDecoder>,
+}
+
+/// The decoded frames of an animated GIF, APNG or WebP, kept at their native resolution
+/// to bound memory use and scaled on the GPU instead.
+struct Animation {
+    frames: Vec<(RgbaImage, Duration)>,
+    current: usize,
+    elapsed: Duration,
+}
+
+#[derive(Clone, Copy)]
+pub struct BackgroundImageOptions {
+    pub mode: BackgroundMode,
+    pub align: BackgroundAlign,
+    /// Standard deviation in monitor pixels of the gaussian blur applied at load time.
+    pub blur: f32,
+    /// How far the image zooms in at full intensity, as a fraction of its size.
+    pub zoom: f32,
+    #[cfg(feature = "video")]
+    pub video_sync_to_render: bool,
+}
+
+/// The rectangle the background covers on the monitor, in monitor pixels.
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct Placement {
+    origin: [f32; 2],
+    size: [f32; 2],
+    tile: u32,
+    zoom: f32,
+    _padding: [u32; 2],
+}
+
+impl Placement {
+    fn new(
+        mode: BackgroundMode,
+        align: BackgroundAlign,
+        zoom: f32,
+        image_size: PhysicalSize<u32>,
+        monitor_size: PhysicalSize<u32>,
+    ) -> Self {
+        let image = [image_size.width as f32, image_size.height as f32];
+        let monitor = [monitor_size.width as f32, monitor_size.height as f32];
+        let scale = [
+            monitor[0] / image[0].max(1.0),
+            monitor[1] / image[1].max(1.0),
+        ];
+
+        let size = match mode {
+            BackgroundMode::Fill => {
+                let scale = scale[0].max(scale[1]);
+                [image[0] * scale, image[1] * scale]
+            }
+            BackgroundMode::Fit => {
+                let scale = scale[0].min(scale[1]);
+                [image[0] * scale, image[1] * scale]
+            }
+            BackgroundMode::Stretch => monitor,
+            BackgroundMode::Tile | BackgroundMode::Center => image,
+        };
+
+        let (anchor_x, anchor_y) = align.anchor();
+
+        Self {
+            origin: [
+                (monitor[0] - size[0]) * anchor_x,
+                (monitor[1] - size[1]) * anchor_y,
+            ],
+            size,
+            tile: (mode == BackgroundMode::Tile) as u32,
+            zoom,
+            _padding: [0; 2],
+        }
+    }
+
+    /// Returns the resolution to decode the image at: the size it covers on the monitor
+    /// when it is scaled, or its native size otherwise.
+    fn texture_size(
+        &self,
+        mode: BackgroundMode,
+        image_size: PhysicalSize<u32>,
+    ) -> PhysicalSize<u32> {
+        match mode {
+            BackgroundMode::Fill | BackgroundMode::Fit | BackgroundMode::Stretch => {
+                PhysicalSize::new(
+                    (self.size[0].round() as u32).max(1),
+                    (self.size[1].round() as u32).max(1),
+                )
+            }
+            BackgroundMode::Tile | BackgroundMode::Center => image_size,
+        }
+    }
+}
+
+impl BackgroundImage {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        path: &str,
+        monitor_size: PhysicalSize<u32>,
+        window_size_buffer: &wgpu::Buffer,
+        window_pos_buffer: &wgpu::Buffer,
+        intensity_buffer: &wgpu::Buffer,
+        options: BackgroundImageOptions,
+    ) -> anyhow::Result<Self> {
+        #[cfg(feature = "video")]
+        if is_video(Path::new(path)) {
+            let (width, height) = probe_size(Path::new(path))?;
+            let video_size = PhysicalSize::new(width, height);
+            let placement = Placement::new(
+                options.mode,
+                options.align,
+                options.zoom,
+                video_size,
+                monitor_size,
+            );
+            let size = placement.texture_size(options.mode, video_size);
+
+            let video = VideoDecoder::spawn(
+                Path::new(path),
+                size.width,
+                size.height,
+                options.blur,
+                options.video_sync_to_render,
+            )?;
+
+            // Start out black until the first frame has been decoded.
+            let rgba = vec![0u8; (size.width * size.height * 4) as usize];
+            let mut background_image = Self::from_rgba(
+                device,
+                queue,
+                format,
+                sample_count,
+                &rgba,
+                size,
+                placement,
+                window_size_buffer,
+                window_pos_buffer,
+                intensity_buffer,
+            )?;
+            background_image.video = Some(video);
+
+            return Ok(background_image);
+        }
+
+        if let Some(frames) = Self::decode_animation(path)? {
+            let (width, height) = frames[0].0.dimensions();
+            let size = PhysicalSize::new(width, height);
+            let placement = Placement::new(
+                options.mode,
+                options.align,
+                options.zoom,
+                size,
+                monitor_size,
+            );
+
+            let frames = if options.blur > 0.0 {
+                // Frames keep their native resolution, so scale the blur to match.
+                let scale = width as f32 / placement.size[0].max(1.0);
+                frames
+                    .into_iter()
+                    .map(|(rgba, delay)| {
+                        (
+                            image::imageops::fast_blur(&rgba, options.blur * scale),
+                            delay,
+                        )
+                    })
+                    .collect()
+            } else {
+                frames
+            };
+
+            let mut background_image = Self::from_rgba(
+                device,
+                queue,
+                format,
+                sample_count,
+                &frames[0].0,
+                size,
+                placement,
+                window_size_buffer,
+                window_pos_buffer,
+                intensity_buffer,
+            )?;
+            background_image.animation = Some(Animation {
+                frames,
+                current: 0,
+                elapsed: Duration::ZERO,
+            });
+
+            return Ok(background_image);
+        }
+
+        let image = image::ImageReader::open(path)?.decode()?;
+        let image_size = PhysicalSize::new(image.width(), image.height());
+        let placement = Placement::new(
+            options.mode,
+            options.align,
+            options.zoom,
+            image_size,
+            monitor_size,
+        );
+        let size = placement.texture_size(options.mode, image_size);
+
+        let image = if size == image_size {
+            image
+        } else {
+            image.resize_exact(
+                size.width,
+                size.height,
+                image::imageops::FilterType::Lanczos3,
+            )
+        };
+        let mut rgba = DynamicImage::into_rgba8(image);
+        if options.blur > 0.0 {
+            rgba = image::imageops::fast_blur(&rgba, options.blur);
+        }
+
+        Self::from_rgba(
+            device,
+            queue,
+            format,
+            sample_count,
+            &rgba,
+            size,
+            placement,
+            window_size_buffer,
+            window_pos_buffer,
+            intensity_buffer,
+        )
+    }
+
+    /// Decodes every frame of an animated image, or returns `None` if `path` is not animated.
+    fn decode_animation(path: &str) -> anyhow::Result<Option<Vec<(RgbaImage, Duration)>>> {
+        let reader = || -> anyhow::Result<_> { Ok(BufReader::new(File::open(path)?)) };
+
+        let frames: Frames = match ImageFormat::from_path(path) {
+            Ok(ImageFormat::Gif) => GifDecoder::new(reader()?)?.into_frames(),
+            Ok(ImageFormat::Png) => {
+                let decoder = PngDecoder::new(reader()?)?;
+                if !decoder.is_apng()? {
+                    return Ok(None);
+                }
+                decoder.apng()?.into_frames()
+            }
+            Ok(ImageFormat::WebP) => {
+                let decoder = WebPDecoder::new(reader()?)?;
+                if !decoder.has_animation() {
+                    return Ok(None);
+                }
+                decoder.into_frames()
+            }
+            _ => return Ok(None),
+        };
+
+        let frames = frames
+            .map(|frame| {
+                let frame = frame?;
+                let (numerator, denominator) = frame.delay().numer_denom_ms();
+                // Browsers treat tiny delays as 100ms, and so do we.
+                let delay = match numerator / denominator.max(1) {
+                    0..=10 => Duration::from_millis(100),
+                    ms => Duration::from_millis(ms as u64),
+                };
+                Ok((frame.into_buffer(), delay))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        match frames.len() {
+            0 => Err(anyhow!("Animated image {path} has no frames")),
+            1 => Ok(None),
+            _ => Ok(Some(frames)),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn from_rgba(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        rgba: &[u8],
+        dimensions: PhysicalSize<u32>,
+        placement: Placement,
+        window_size_buffer: &wgpu::Buffer,
+        window_pos_buffer: &wgpu::Buffer,
+        intensity_buffer: &wgpu::Buffer,
+    ) -> anyhow::Result<Self> {
+        let max_dimension = device.limits().max_texture_dimension_2d;
+        if dimensions.width > max_dimension || dimensions.height > max_dimension {
+            return Err(anyhow!(
+                "Background of {}x{} exceeds the maximum texture size of {max_dimension}, \
+                 use a scaling background mode or a smaller image",
+                dimensions.width,
+                dimensions.height
+            ));
+        }
+
+        let size = wgpu::Extent3d {
+            width: dimensions.width,
+            height: dimensions.height,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("Background Image Texture"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            rgba,
+        );
+
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let address_mode = if placement.tile != 0 {
+            wgpu::AddressMode::Repeat
+        } else {
+            wgpu::AddressMode::ClampToEdge
+        };
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: address_mode,
+            address_mode_v: address_mode,
+            address_mode_w: address_mode,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Background Image Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let placement_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Background Image Placement Buffer"),
+            contents: bytemuck::bytes_of(&placement),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Background Image Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: window_size_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: window_pos_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: placement_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: intensity_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let pipeline = Renderer::create_fullscreen_pipeline(
+            device,
+            format,
+            sample_count,
+            wgpu::BlendState::REPLACE,
+            "Background Image",
+            include_str!("shaders/background_image_shader.wgsl"),
+            &bind_group_layout,
+        );
+
+        Ok(Self {
+            pipeline,
+            bind_group_layout,
+            bind_group,
+            texture,
+            size,
+            animation: None,
+            #[cfg(feature = "video")]
+            video: None,
+        })
+    }
+
+    /// Uploads the next frame of an animated background, if one is due.
+    pub fn update(&mut self, queue: &wgpu::Queue, delta_time: Duration) {
+        #[cfg(feature = "video")]
+        if let Some(frame) = self.video.as_ref().and_then(VideoDecoder::next_frame) {
+            Self::write_texture(queue, &self.texture, self.size, &frame);
+        }
+
+        if let Some(animation) = &mut self.animation {
+            animation.elapsed += delta_time;
+
+            let previous = animation.current;
+            while animation.elapsed >= animation.frames[animation.current].1 {
+                animation.elapsed -= animation.frames[animation.current].1;
+                animation.current = (animation.current + 1) % animation.frames.len();
+            }
+
+            if animation.current != previous {
+                let frame = &animation.frames[animation.current].0;
+                Self::write_texture(queue, &self.texture, self.size, frame);
+            }
+        }
+    }
+
+    fn write_texture(
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+        size: wgpu::Extent3d,
+        rgba: &[u8],
+    ) {
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            rgba,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * size.width),
+                rows_per_image: Some(size.height),
+            },
+            size,
+        );
+    }
+
+    /// The layout of `bind_group`, for pipelines that sample the background elsewhere.
+    pub fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    /// Binds the texture, sampler, window position and placement of the background.
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..4, 0..1);
+    }
+}
@@ -0,0 +1,28 @@
+/// How long the running average of the intensity remembers, in seconds.
+const AVERAGE_TIME: f32 = 0.5;
+/// A rise above the running average this large counts as the strongest possible hit.
+const ONSET_RANGE: f32 = 0.25;
+
+/// Hits weaker than this do not count as a beat.
+pub const BEAT_THRESHOLD: f32 = 0.5;
+/// Shortest time in seconds between two beats, as a hit stays above the threshold for a
+/// few frames.
+pub const BEAT_COOLDOWN: f32 = 0.15;
+
+/// Detects hits in the music as sudden rises of the intensity above its recent average.
+///
+/// The volume providers only report the peak level, so this stands in for low-band
+/// energy: kicks and other transients are what make the peak jump.
+#[derive(Default)]
+pub struct BeatDetector {
+    average: f32,
+}
+
+impl BeatDetector {
+    /// Feeds the current intensity and returns the strength of the hit, from 0 to 1.
+    pub fn update(&mut self, intensity: f32, delta_time: f32) -> f32 {
+        let onset = ((intensity - self.average) / ONSET_RANGE).clamp(0.0, 1.0);
+        self.average += (intensity - self.average) * (1.0 - (-delta_time / AVERAGE_TIME).exp());
+        onset
+    }
+}
@@ -0,0 +1,135 @@
+use std::{
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use winit::{
+    application::ApplicationHandler,
+    dpi::PhysicalSize,
+    event::WindowEvent,
+    event_loop::{ActiveEventLoop, EventLoop},
+    window::{Window, WindowId},
+};
+
+use crate::{
+    canvas::Canvas, config::Config, hooks::Hooks, state::State,
+    volume_providers::synthetic_volume_provider::SyntheticVolumeProvider,
+};
+
+/// Frames drawn before measuring, while pipelines and caches settle.
+const WARMUP_FRAMES: u32 = 60;
+/// Time every frame advances the simulation by, as at 60 frames per second.
+const FRAME_TIME: Duration = Duration::from_nanos(16_666_667);
+/// Size of the window when --width and --height are not given.
+const DEFAULT_SIZE: PhysicalSize<u32> = PhysicalSize::new(1280, 720);
+
+/// Measures how long frames take for each count of `points`, drawing `frames` frames with
+/// `config` to a window's GPU but offscreen, so the display does not hold frames back. The
+/// music is replaced by a steady beat and the seed is fixed, so runs are comparable.
+pub fn run(mut config: Config, points: &[u32], frames: u32) -> anyhow::Result<()> {
+    crate::init_logging(&config)?;
+
+    config.seed = config.seed.or(Some(0));
+    let mut bench = Bench {
+        config,
+        points: points.to_vec(),
+        frames: frames.max(1),
+        result: Ok(()),
+    };
+    EventLoop::new()?.run_app(&mut bench)?;
+
+    bench.result
+}
+
+struct Bench {
+    config: Config,
+    points: Vec<u32>,
+    frames: u32,
+    result: anyhow::Result<()>,
+}
+
+impl Bench {
+    fn measure(&mut self, event_loop: &ActiveEventLoop) -> anyhow::Result<()> {
+        let size = match (self.config.width, self.config.height) {
+            (Some(width), Some(height)) => PhysicalSize::new(width, height),
+            _ => DEFAULT_SIZE,
+        };
+        let window = Arc::new(
+            event_loop.create_window(
+                Window::default_attributes()
+                    .with_title("connecting-dots bench")
+                    .with_inner_size(size)
+                    .with_resizable(false),
+            )?,
+        );
+
+        println!(
+            "{} frames per run at {}x{}",
+            self.frames,
+            window.inner_size().width,
+            window.inner_size().height
+        );
+        println!(
+            "{:>8} {:>10} {:>10} {:>10} {:>10} {:>10}",
+            "Points", "Average", "p50", "p95", "p99", "Max"
+        );
+
+        for &points in &self.points {
+            let config = Config {
+                points,
+                ..self.config.clone()
+            };
+            let hooks = Hooks {
+                volume_provider: Some(Rc::new(SyntheticVolumeProvider::default())),
+                ..Hooks::default()
+            };
+            let mut state = pollster::block_on(State::with_canvas(
+                Canvas::Window(window.clone()),
+                &config,
+                hooks,
+            ))?;
+            let view = state.create_offscreen_view();
+
+            for _ in 0..WARMUP_FRAMES {
+                state.draw_offscreen(FRAME_TIME, &view)?;
+            }
+
+            let mut frame_times = Vec::with_capacity(self.frames as usize);
+            for _ in 0..self.frames {
+                let start = Instant::now();
+                state.draw_offscreen(FRAME_TIME, &view)?;
+                frame_times.push(start.elapsed());
+            }
+            frame_times.sort();
+
+            let average = frame_times.iter().sum::<Duration>() / self.frames;
+            let percentile = |percent: usize| {
+                frame_times[(frame_times.len() * percent / 100).min(frame_times.len() - 1)]
+            };
+            println!(
+                "{points:>8} {:>10} {:>10} {:>10} {:>10} {:>10}",
+                milliseconds(average),
+                milliseconds(percentile(50)),
+                milliseconds(percentile(95)),
+                milliseconds(percentile(99)),
+                milliseconds(frame_times[frame_times.len() - 1]),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl ApplicationHandler for Bench {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        self.result = self.measure(event_loop);
+        event_loop.exit();
+    }
+
+    fn window_event(&mut self, _: &ActiveEventLoop, _: WindowId, _: WindowEvent) {}
+}
+
+fn milliseconds(duration: Duration) -> String {
+    format!("{:.2} ms", duration.as_secs_f64() * 1000.0)
+}
@@ -0,0 +1,234 @@
+use std::sync::Arc;
+#[cfg(target_os = "linux")]
+use std::{ffi::c_void, ptr::NonNull};
+
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    window::{Window, WindowId},
+};
+
+/// What the visualization is drawn into.
+#[derive(Clone)]
+pub enum Canvas {
+    /// A winit window, or the canvas element on the web.
+    Window(Arc<Window>),
+    /// A Wayland surface managed outside of winit, like a layer-shell surface. The connection
+    /// and surface behind the pointers must outlive the state drawing into them.
+    #[cfg(target_os = "linux")]
+    Wayland {
+        display: NonNull<c_void>,
+        surface: NonNull<c_void>,
+        size: PhysicalSize<u32>,
+        /// Size of the output the surface is shown on, when known.
+        monitor_size: Option<PhysicalSize<u32>>,
+        /// Name of the output the surface is shown on, when known.
+        monitor_name: Option<String>,
+        /// Physical pixels per logical pixel of the surface.
+        scale_factor: f64,
+    },
+    /// A window on every monitor, together showing one field covering the whole desktop.
+    #[cfg(not(target_arch = "wasm32"))]
+    Span(Vec<Arc<Window>>),
+}
+
+impl Canvas {
+    pub fn size(&self) -> PhysicalSize<u32> {
+        match self {
+            Canvas::Window(window) => window.inner_size(),
+            #[cfg(target_os = "linux")]
+            Canvas::Wayland { size, .. } => *size,
+            #[cfg(not(target_arch = "wasm32"))]
+            Canvas::Span(windows) => windows.iter().zip(span_origins(windows)).fold(
+                PhysicalSize::new(0, 0),
+                |size, (window, origin)| {
+                    let window_size = window.inner_size();
+                    PhysicalSize::new(
+                        size.width.max(origin.x as u32 + window_size.width),
+                        size.height.max(origin.y as u32 + window_size.height),
+                    )
+                },
+            ),
+        }
+    }
+
+    /// Physical pixels per logical pixel, e.g. 1.5 on a display scaled to 150%.
+    pub fn scale_factor(&self) -> f64 {
+        match self {
+            Canvas::Window(window) => window.scale_factor(),
+            #[cfg(target_os = "linux")]
+            Canvas::Wayland { scale_factor, .. } => *scale_factor,
+            // The field is shared across the monitors, so it follows the first one.
+            #[cfg(not(target_arch = "wasm32"))]
+            Canvas::Span(windows) => windows.first().map_or(1.0, |window| window.scale_factor()),
+        }
+    }
+
+    /// Position of the top left corner of the window with `id` in the canvas, which is only
+    /// away from the origin for the windows of a span.
+    #[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+    pub fn origin(&self, id: WindowId) -> PhysicalPosition<i32> {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            Canvas::Span(windows) => windows
+                .iter()
+                .zip(span_origins(windows))
+                .find(|(window, _)| window.id() == id)
+                .map_or(PhysicalPosition::new(0, 0), |(_, origin)| origin),
+            _ => PhysicalPosition::new(0, 0),
+        }
+    }
+
+    /// Whether redraws of the window with `id` draw the frames. A span draws every window
+    /// when the first one is redrawn.
+    pub fn draws_on(&self, id: WindowId) -> bool {
+        match self {
+            Canvas::Window(window) => window.id() == id,
+            #[cfg(target_os = "linux")]
+            Canvas::Wayland { .. } => true,
+            #[cfg(not(target_arch = "wasm32"))]
+            Canvas::Span(windows) => windows.first().is_some_and(|window| window.id() == id),
+        }
+    }
+
+    /// Position of the top left corner on the monitor.
+    pub fn position(&self) -> PhysicalPosition<i32> {
+        match self {
+            // Not every platform tells windows where they are, like Wayland, so assume the top
+            // left corner there.
+            Canvas::Window(window) => {
+                let Ok(position) = window.inner_position() else {
+                    return PhysicalPosition { x: 0, y: 0 };
+                };
+                let monitor = window
+                    .current_monitor()
+                    .map_or(PhysicalPosition { x: 0, y: 0 }, |monitor| {
+                        monitor.position()
+                    });
+
+                PhysicalPosition {
+                    x: position.x - monitor.x,
+                    y: position.y - monitor.y,
+                }
+            }
+            #[cfg(target_os = "linux")]
+            Canvas::Wayland { .. } => PhysicalPosition { x: 0, y: 0 },
+            // The span covers the desktop it is positioned on.
+            #[cfg(not(target_arch = "wasm32"))]
+            Canvas::Span(_) => PhysicalPosition { x: 0, y: 0 },
+        }
+    }
+
+    pub fn monitor_size(&self) -> Option<PhysicalSize<u32>> {
+        match self {
+            Canvas::Window(window) => window
+                .current_monitor()
+                .or_else(|| window.available_monitors().next())
+                .map(|monitor| monitor.size()),
+            #[cfg(target_os = "linux")]
+            Canvas::Wayland {
+                size, monitor_size, ..
+            } => Some(monitor_size.unwrap_or(*size)),
+            #[cfg(not(target_arch = "wasm32"))]
+            Canvas::Span(_) => Some(self.size()),
+        }
+    }
+
+    /// Name of the monitor, like DP-1, when the platform reports one.
+    pub fn monitor_name(&self) -> Option<String> {
+        match self {
+            Canvas::Window(window) => window
+                .current_monitor()
+                .or_else(|| window.available_monitors().next())
+                .and_then(|monitor| monitor.name()),
+            #[cfg(target_os = "linux")]
+            Canvas::Wayland { monitor_name, .. } => monitor_name.clone(),
+            #[cfg(not(target_arch = "wasm32"))]
+            Canvas::Span(_) => None,
+        }
+    }
+
+    /// Creates the surface to draw into. A span gets the surface of its first window, the
+    /// others get theirs when the span is set up.
+    pub fn create_surface(
+        &self,
+        instance: &wgpu::Instance,
+    ) -> anyhow::Result<wgpu::Surface<'static>> {
+        match self {
+            Canvas::Window(window) => Ok(instance.create_surface(window.clone())?),
+            #[cfg(not(target_arch = "wasm32"))]
+            Canvas::Span(windows) => {
+                let window = windows
+                    .first()
+                    .ok_or_else(|| anyhow::anyhow!("No monitors to span"))?;
+                Ok(instance.create_surface(window.clone())?)
+            }
+            #[cfg(target_os = "linux")]
+            Canvas::Wayland {
+                display, surface, ..
+            } => {
+                use winit::raw_window_handle::{
+                    RawDisplayHandle, RawWindowHandle, WaylandDisplayHandle, WaylandWindowHandle,
+                };
+
+                // SAFETY: Whoever creates a Wayland canvas keeps the display and surface alive
+                // for as long as the state drawing into them.
+                Ok(unsafe {
+                    instance.create_surface_unsafe(wgpu::SurfaceTargetUnsafe::RawHandle {
+                        raw_display_handle: RawDisplayHandle::Wayland(WaylandDisplayHandle::new(
+                            *display,
+                        )),
+                        raw_window_handle: RawWindowHandle::Wayland(WaylandWindowHandle::new(
+                            *surface,
+                        )),
+                    })?
+                })
+            }
+        }
+    }
+
+    /// Asks for another frame. Wayland canvases are redrawn on every frame callback anyway.
+    pub fn request_redraw(&self) {
+        match self {
+            Canvas::Window(window) => window.request_redraw(),
+            #[cfg(target_os = "linux")]
+            Canvas::Wayland { .. } => {}
+            #[cfg(not(target_arch = "wasm32"))]
+            Canvas::Span(windows) => {
+                if let Some(window) = windows.first() {
+                    window.request_redraw();
+                }
+            }
+        }
+    }
+}
+
+/// Positions of the top left corners of `windows` relative to the top left corner of the
+/// area they cover together. Every window is assumed to fill its monitor.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn span_origins(windows: &[Arc<Window>]) -> Vec<PhysicalPosition<i32>> {
+    let positions: Vec<_> = windows
+        .iter()
+        .map(|window| {
+            window
+                .current_monitor()
+                .map(|monitor| monitor.position())
+                .or_else(|| window.outer_position().ok())
+                .unwrap_or(PhysicalPosition::new(0, 0))
+        })
+        .collect();
+    let left = positions
+        .iter()
+        .map(|position| position.x)
+        .min()
+        .unwrap_or(0);
+    let top = positions
+        .iter()
+        .map(|position| position.y)
+        .min()
+        .unwrap_or(0);
+
+    positions
+        .iter()
+        .map(|position| PhysicalPosition::new(position.x - left, position.y - top))
+        .collect()
+}
@@ -0,0 +1,264 @@
+use std::{fmt::Display, path::Path, process::Command};
+
+use global_hotkey::hotkey::HotKey;
+
+use crate::{
+    config::Config, config_file::ConfigFile, control::override_args, formation::Formation,
+    point_data::PointData, volume_providers::file_volume_provider::FileVolumeProvider,
+};
+
+/// Checks `config`, the config file it names and the files the options point at without
+/// opening a window, printing a line for each, and fails when anything would keep it from
+/// starting.
+pub fn run(config: &Config) -> anyhow::Result<()> {
+    let mut report = Report::default();
+    report.ok("Options parse");
+
+    let config = match &config.config_file {
+        Some(path) => report
+            .check(format!("Config file {}", path.display()), config_file(path))
+            .unwrap_or_else(|| config.clone()),
+        None => config.clone(),
+    };
+
+    let problems = range_problems(&config);
+    if problems.is_empty() {
+        report.ok("Option values are in range");
+    }
+    for problem in problems {
+        report.error("Option values", problem);
+    }
+
+    if let Some(images) = &config.background_image {
+        let paths = images
+            .default
+            .iter()
+            .map(|path| ("Background image".to_owned(), path))
+            .chain(
+                images
+                    .monitors
+                    .iter()
+                    .map(|(monitor, path)| (format!("Background image of {monitor}"), path)),
+            );
+        for (what, path) in paths {
+            let path = Path::new(path);
+
+            // Videos are probed with ffprobe, which comes with the ffmpeg that decodes them.
+            #[cfg(feature = "video")]
+            if crate::video::is_video(path) {
+                report.check_size(
+                    format!("{what} {}", path.display()),
+                    crate::video::probe_size(path),
+                );
+                continue;
+            }
+
+            report.check_size(
+                format!("{what} {}", path.display()),
+                image::ImageReader::open(path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|reader| Ok(reader.decode()?))
+                    .map(|image| (image.width(), image.height())),
+            );
+        }
+    }
+
+    if let Some(path) = &config.mask {
+        report.check_size(
+            format!("Mask {}", path.display()),
+            Formation::load_mask(path, config.mask_invert).map(|mask| mask.dimensions()),
+        );
+    }
+    if let Some(path) = &config.point_data
+        && let Some(values) = report.check(
+            format!("Point data {}", path.display()),
+            PointData::load(path),
+        )
+    {
+        println!("        {} values", values.len());
+    }
+    if let Some(path) = &config.intensity_file {
+        report.check(
+            format!("Intensity file {}", path.display()),
+            FileVolumeProvider::new(path),
+        );
+    }
+
+    if config.hotkeys {
+        for (name, keys) in [
+            ("--hotkey-pause", &config.hotkey_pause),
+            ("--hotkey-next-preset", &config.hotkey_next_preset),
+            ("--hotkey-boost", &config.hotkey_boost),
+        ] {
+            report.check(
+                format!("{name} {keys}"),
+                keys.parse::<HotKey>().map_err(anyhow::Error::from),
+            );
+        }
+    }
+
+    if config.record.is_some() {
+        report.check(
+            "ffmpeg, for --record",
+            Command::new("ffmpeg")
+                .arg("-version")
+                .output()
+                .map_err(|e| anyhow::anyhow!("Unable to run ffmpeg: {e}")),
+        );
+    }
+
+    match report.problems {
+        0 => {
+            println!("No problems found");
+            Ok(())
+        }
+        1 => anyhow::bail!("1 problem found"),
+        problems => anyhow::bail!("{problems} problems found"),
+    }
+}
+
+#[derive(Default)]
+struct Report {
+    problems: usize,
+}
+
+impl Report {
+    fn ok(&mut self, what: impl Display) {
+        println!("ok      {what}");
+    }
+
+    fn error(&mut self, what: impl Display, error: impl Display) {
+        println!("error   {what}: {error}");
+        self.problems += 1;
+    }
+
+    /// Reports `result` of checking `what`, returning its value when it succeeded.
+    fn check<T>(&mut self, what: impl Display, result: anyhow::Result<T>) -> Option<T> {
+        match result {
+            Ok(value) => {
+                self.ok(what);
+                Some(value)
+            }
+            Err(e) => {
+                self.error(what, format!("{e:#}"));
+                None
+            }
+        }
+    }
+
+    /// Reports `result` of decoding `what`, with its size when it decoded.
+    fn check_size(&mut self, what: impl Display, result: anyhow::Result<(u32, u32)>) {
+        if let Some((width, height)) = self.check(what, result) {
+            println!("        {width}x{height}");
+        }
+    }
+}
+
+/// The config with the options of the config file at `path` applied, after checking that
+/// every preset in it parses too.
+fn config_file(path: &Path) -> anyhow::Result<Config> {
+    let args = Config::process_args();
+    let file = ConfigFile::open(path, &args)?;
+    let file_args = file.args(&args)?;
+    let config = Config::try_parse_from(file_args.clone()).map_err(clap_message)?;
+
+    for name in file.preset_names() {
+        let options = file
+            .preset(name)
+            .expect("Presets are named by preset_names");
+        Config::try_parse_from(override_args(&file_args, options)?)
+            .map_err(|e| anyhow::anyhow!("The preset {name}: {}", clap_message(e)))?;
+    }
+
+    Ok(config)
+}
+
+/// The first line of a clap error, without its prefix and the hint to try --help.
+fn clap_message(error: clap::Error) -> anyhow::Error {
+    let message = error.to_string();
+    let line = message.lines().next().unwrap_or_default();
+    anyhow::anyhow!("{}", line.trim_start_matches("error: "))
+}
+
+/// Values clap accepts on their own, but which do not make sense, or not together.
+fn range_problems(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    for (min_name, min, max_name, max) in [
+        (
+            "--min-point-size",
+            config.min_point_size,
+            "--max-point-size",
+            config.max_point_size,
+        ),
+        (
+            "--min-speed",
+            config.min_speed,
+            "--max-speed",
+            config.max_speed,
+        ),
+        (
+            "--min-points",
+            config.min_points as f32,
+            "--max-points",
+            config.max_points as f32,
+        ),
+        (
+            "--point-data-min-scale",
+            config.point_data_min_scale,
+            "--point-data-max-scale",
+            config.point_data_max_scale,
+        ),
+    ] {
+        if min > max {
+            problems.push(format!("{min_name} {min} is larger than {max_name} {max}"));
+        }
+    }
+
+    for (name, value) in [
+        ("--point-size-variation", config.point_size_variation),
+        ("--low-power-points", config.low_power_points),
+        ("--oled-max-brightness", config.oled_max_brightness),
+        ("--oled-dim-brightness", config.oled_dim_brightness),
+    ] {
+        if !(0.0..=1.0).contains(&value) {
+            problems.push(format!("{name} {value} is not between 0 and 1"));
+        }
+    }
+
+    if !(0.0..=100.0).contains(&config.low_power_threshold) {
+        problems.push(format!(
+            "--low-power-threshold {} is not a percentage",
+            config.low_power_threshold
+        ));
+    }
+
+    for (name, value) in [
+        ("--preset-transition", config.preset_transition),
+        ("--background-zoom", config.background_zoom),
+        ("--background-blur", config.background_blur),
+        ("--connection-distance", config.connection_distance),
+        ("--cursor-radius", config.cursor_radius),
+        ("--metaball-radius", config.metaball_radius),
+        ("--spark-lifetime", config.spark_lifetime),
+        ("--point-lifetime", config.point_lifetime),
+        ("--collision-radius", config.collision_radius),
+        ("--spacing", config.spacing),
+        ("--drag", config.drag),
+        ("--oled-dim-after", config.oled_dim_after),
+        ("--intro-duration", config.intro_duration),
+    ] {
+        if !(0.0..).contains(&value) {
+            problems.push(format!("{name} {value} is negative"));
+        }
+    }
+
+    if config.time_scale.is_nan() || config.time_scale <= 0.0 {
+        problems.push(format!(
+            "--time-scale {} stops or reverses time",
+            config.time_scale
+        ));
+    }
+
+    problems
+}
@@ -0,0 +1,102 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{Result, anyhow};
+
+/// An sRGB color parsed from a `#rrggbb` or `#rrggbbaa` hex string.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const BLACK: Color = Color {
+        r: 0,
+        g: 0,
+        b: 0,
+        a: 255,
+    };
+
+    /// Converts to linear RGBA, which is what shaders writing to an sRGB surface expect.
+    pub fn to_linear(self) -> [f32; 4] {
+        [
+            srgb_to_linear(self.r),
+            srgb_to_linear(self.g),
+            srgb_to_linear(self.b),
+            self.a as f32 / 255.0,
+        ]
+    }
+
+    /// The color `t` of the way from `self` to `other`, mixed in sRGB.
+    pub fn lerp(self, other: Color, t: f32) -> Color {
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Color {
+            r: mix(self.r, other.r),
+            g: mix(self.g, other.g),
+            b: mix(self.b, other.b),
+            a: mix(self.a, other.a),
+        }
+    }
+
+    pub fn to_wgpu(self) -> wgpu::Color {
+        let [r, g, b, a] = self.to_linear();
+        wgpu::Color {
+            r: r as f64,
+            g: g as f64,
+            b: b as f64,
+            a: a as f64,
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+                .ok_or_else(|| anyhow!("Invalid color '{s}', expected #rrggbb or #rrggbbaa"))
+        };
+
+        match hex.len() {
+            6 => Ok(Color {
+                r: channel(0)?,
+                g: channel(2)?,
+                b: channel(4)?,
+                a: 255,
+            }),
+            8 => Ok(Color {
+                r: channel(0)?,
+                g: channel(2)?,
+                b: channel(4)?,
+                a: channel(6)?,
+            }),
+            _ => Err(anyhow!(
+                "Invalid color '{s}', expected #rrggbb or #rrggbbaa"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)?;
+        if self.a != 255 {
+            write!(f, "{:02x}", self.a)?;
+        }
+        Ok(())
+    }
+}
+
+fn srgb_to_linear(channel: u8) -> f32 {
+    let c = channel as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
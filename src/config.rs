@@ -0,0 +1,1571 @@
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use clap::{ArgAction, Args, Command, FromArgMatches, ValueEnum, parser::ValueSource};
+
+use crate::color::Color;
+use crate::lines::MAX_CONNECTIONS_LIMIT;
+
+/// Prefix of the environment variables options can be set in, like CONNECTING_DOTS_POINTS
+/// for --points.
+pub const ENV_PREFIX: &str = "CONNECTING_DOTS_";
+
+#[derive(Args, Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Start from a bundle of options that look good together. Options given on the command
+    /// line override the preset's
+    #[arg(long, value_enum)]
+    pub preset: Option<Preset>,
+
+    /// Trade looks for speed with a bundle of the point count, anti-aliasing, bloom and
+    /// connection distance, from low for old laptops to ultra for desktop GPUs. Wins over
+    /// the preset, and options given on the command line override it
+    #[arg(long, value_enum)]
+    pub quality: Option<Quality>,
+
+    /// Read options from this TOML file, like `points = 300`, and apply changes to it while
+    /// running. Options given on the command line or in the environment win over the file's
+    #[arg(long = "config", value_name = "PATH")]
+    pub config_file: Option<PathBuf>,
+
+    /// Seconds numbers and colors take to glide over when switching between the named presets
+    /// of the config file
+    #[arg(long, value_name = "SECONDS", default_value_t = 1.0)]
+    pub preset_transition: f32,
+
+    /// Path to background image (or video, when built with the video feature). Give each
+    /// monitor its own as MONITOR=PATH,MONITOR=PATH, with an optional plain PATH for the rest
+    #[arg(
+        short,
+        long,
+        value_name = "[MONITOR=]PATH",
+        conflicts_with = "background"
+    )]
+    pub background_image: Option<BackgroundImages>,
+
+    /// How the background image is scaled to the monitor
+    #[arg(long, value_enum, default_value_t = BackgroundMode::Fill)]
+    pub background_mode: BackgroundMode,
+
+    /// Where the background image is anchored when it does not exactly cover the monitor
+    #[arg(long, value_enum, default_value_t = BackgroundAlign::Center)]
+    pub background_align: BackgroundAlign,
+
+    /// Zoom the background image in by up to this fraction as the music gets louder, e.g. 0.03
+    #[arg(long, value_name = "AMOUNT", default_value_t = 0.0)]
+    pub background_zoom: f32,
+
+    /// Color every point with the background image underneath it
+    #[arg(long, requires = "background_image")]
+    pub point_color_from_background: bool,
+
+    /// Blur the background image with a gaussian of this radius in pixels
+    #[arg(long, value_name = "RADIUS", default_value_t = 0.0)]
+    pub background_blur: f32,
+
+    /// Advance video backgrounds one frame per rendered frame instead of in real time
+    #[cfg(feature = "video")]
+    #[arg(long)]
+    pub video_sync_render: bool,
+
+    /// Built-in animated background, e.g. procedural:plasma or procedural:nebula
+    #[arg(long, value_name = "BACKGROUND")]
+    pub background: Option<Background>,
+
+    /// Background color as #rrggbb or #rrggbbaa
+    #[arg(long, value_name = "COLOR", default_value = "#000000")]
+    pub background_color: Color,
+
+    /// Draw a gradient from the background color to this color
+    #[arg(long, value_name = "COLOR")]
+    pub background_gradient: Option<Color>,
+
+    /// Shape of the background gradient
+    #[arg(long, value_enum, default_value_t = GradientShape::Vertical)]
+    pub background_gradient_shape: GradientShape,
+
+    /// Post-processing effects applied in order, e.g. bloom,vignette
+    #[arg(
+        long = "effect",
+        value_name = "EFFECT",
+        value_enum,
+        value_delimiter = ','
+    )]
+    pub effects: Vec<Effect>,
+
+    /// Largest offset between color channels of the chromatic-aberration effect, in pixels
+    #[arg(long, value_name = "PIXELS", default_value_t = 8.0)]
+    pub aberration_max_offset: f32,
+
+    /// Seconds for the chromatic-aberration effect to fade to about a third after a hit
+    #[arg(long, value_name = "SECONDS", default_value_t = 0.15)]
+    pub aberration_decay: f32,
+
+    /// How the points are drawn
+    #[arg(long, value_enum, default_value_t = RenderMode::Dots)]
+    pub mode: RenderMode,
+
+    /// Radius in pixels of each point's influence in metaballs mode
+    #[arg(long, value_name = "PIXELS", default_value_t = 30.0)]
+    pub metaball_radius: f32,
+
+    /// Seed of the random numbers, so runs with the same seed and options start the same
+    #[arg(long, value_name = "SEED")]
+    pub seed: Option<u64>,
+
+    /// Number of points, or of points per layer without a count of its own
+    #[arg(long, value_name = "N", default_value_t = 1000, value_parser = clap::value_parser!(u32).range(1..))]
+    pub points: u32,
+
+    /// Scale the number of points in the front layer to hold this frame rate
+    #[arg(long, value_name = "FPS")]
+    pub target_fps: Option<f32>,
+
+    /// Scale the number of points in the front layer with the intensity, from a sparse field
+    /// when quiet to a dense web when loud
+    #[arg(long, conflicts_with = "target_fps")]
+    pub points_follow_intensity: bool,
+
+    /// Fewest points the front layer is scaled down to with --target-fps or
+    /// --points-follow-intensity
+    #[arg(long, value_name = "N", default_value_t = 100)]
+    pub min_points: usize,
+
+    /// Most points the front layer is scaled up to with --target-fps or
+    /// --points-follow-intensity
+    #[arg(long, value_name = "N", default_value_t = 5000)]
+    pub max_points: usize,
+
+    /// Connect points closer than this many pixels with lines, 0 to disable
+    #[arg(long, value_name = "PIXELS", default_value_t = 100.0)]
+    pub connection_distance: f32,
+
+    /// Connect each point to at most this many of its nearest neighbours (up to 16), 0 for no limit
+    #[arg(long, value_name = "COUNT", default_value_t = 0, value_parser = clap::value_parser!(u32).range(0..=MAX_CONNECTIONS_LIMIT as i64))]
+    pub max_connections: u32,
+
+    /// Emit bursts of sparks from random spots on hits in the music
+    #[arg(long)]
+    pub sparks: bool,
+
+    /// Most sparks alive at once
+    #[arg(long, value_name = "COUNT", default_value_t = 512, value_parser = clap::value_parser!(u32).range(1..))]
+    pub spark_pool: u32,
+
+    /// Average seconds a spark lives
+    #[arg(long, value_name = "SECONDS", default_value_t = 0.8)]
+    pub spark_lifetime: f32,
+
+    /// Acceleration in pixels per second squared pulling points toward the cursor, negative to
+    /// push them away, 0 to ignore the cursor
+    #[arg(
+        long,
+        value_name = "FORCE",
+        default_value_t = 0.0,
+        allow_negative_numbers = true
+    )]
+    pub cursor_force: f32,
+
+    /// Distance in pixels within which the cursor affects points
+    #[arg(long, value_name = "PIXELS", default_value_t = 150.0)]
+    pub cursor_radius: f32,
+
+    /// Connect the cursor to nearby points with lines, as if it were a point of its own
+    #[arg(long)]
+    pub cursor_lines: bool,
+
+    /// Constant acceleration of every point in pixels per second squared as X,Y, e.g. 0,40
+    /// to let the points fall like snow
+    #[arg(long, value_name = "X,Y", default_value = "0,0", value_parser = parse_vector, allow_hyphen_values = true)]
+    pub gravity: (f32, f32),
+
+    /// How the points move
+    #[arg(long, value_enum, default_value_t = Motion::Straight)]
+    pub motion: Motion,
+
+    /// Size in pixels of the swirls in flow motion
+    #[arg(long, value_name = "PIXELS", default_value_t = 400.0)]
+    pub flow_scale: f32,
+
+    /// Speed in pixels per second of the points in flow motion
+    #[arg(long, value_name = "SPEED", default_value_t = 40.0)]
+    pub flow_speed: f32,
+
+    /// Multiplier of the point speed when silent
+    #[arg(long, value_name = "FACTOR", default_value_t = 1.0)]
+    pub silent_speed: f32,
+
+    /// Multiplier of the point speed at full intensity, e.g. 4 to make the points rush along
+    /// with loud music
+    #[arg(long, value_name = "FACTOR", default_value_t = 1.0)]
+    pub loud_speed: f32,
+
+    /// Speed of the simulation, e.g. 0.25 for slow motion. Adjust at runtime with + and -
+    #[arg(long, value_name = "FACTOR", default_value_t = 1.0)]
+    pub time_scale: f32,
+
+    /// Slowest speed of new points in pixels per second, per axis with diagonal directions
+    #[arg(long, value_name = "SPEED", default_value_t = 1.0)]
+    pub min_speed: f32,
+
+    /// Fastest speed of new points in pixels per second, per axis with diagonal directions
+    #[arg(long, value_name = "SPEED", default_value_t = 3.0)]
+    pub max_speed: f32,
+
+    /// Directions new points start moving in
+    #[arg(long, value_enum, default_value_t = Direction::Diagonal)]
+    pub direction: Direction,
+
+    /// Average seconds a point lives before it starts over at a random spot, 0 to live forever
+    #[arg(long, value_name = "SECONDS", default_value_t = 0.0)]
+    pub point_lifetime: f32,
+
+    /// What happens to points reaching the edge of the window
+    #[arg(long, value_enum, default_value_t = EdgeBehavior::Bounce)]
+    pub edge: EdgeBehavior,
+
+    /// Points closer than this many pixels push each other apart instead of passing through
+    /// each other, 0 to turn collisions off
+    #[arg(long, value_name = "PIXELS", default_value_t = 0.0)]
+    pub collision_radius: f32,
+
+    /// Acceleration in pixels per second squared between two points on top of each other
+    #[arg(long, value_name = "FORCE", default_value_t = 200.0)]
+    pub collision_strength: f32,
+
+    /// Points closer than this many pixels gently push each other apart, so the field stays
+    /// even instead of forming dense knots and empty regions over time. 0 to turn it off
+    #[arg(long, value_name = "PIXELS", default_value_t = 0.0)]
+    pub spacing: f32,
+
+    /// Acceleration in pixels per second squared between two points on top of each other
+    /// from --spacing
+    #[arg(long, value_name = "FORCE", default_value_t = 20.0)]
+    pub spacing_strength: f32,
+
+    /// Points spawned at the cursor by a left click, or removed nearest to it by a right click
+    #[arg(long, value_name = "COUNT", default_value_t = 10)]
+    pub click_points: usize,
+
+    /// Room for points spawned by clicks, on top of the points of the front layer
+    #[arg(long, value_name = "COUNT", default_value_t = 1000)]
+    pub click_capacity: usize,
+
+    /// Image the points gather into, e.g. a logo. Bright pixels form the shape
+    #[arg(long, value_name = "PATH")]
+    pub mask: Option<PathBuf>,
+
+    /// Text the points gather into, e.g. "HELLO". Line breaks in the text start new lines
+    #[arg(long, value_name = "TEXT", conflicts_with = "mask")]
+    pub text: Option<String>,
+
+    /// Gather the points on the dark pixels of the mask instead of the bright ones, or around
+    /// the text instead of on it
+    #[arg(long)]
+    pub mask_invert: bool,
+
+    /// How strongly the points are pulled into the mask or text
+    #[arg(long, value_name = "STRENGTH", default_value_t = 1.0)]
+    pub mask_strength: f32,
+
+    /// Add a layer of points, e.g. count=600,size=0.6,speed=0.5,distance=60. Layers are
+    /// drawn back to front in the order given. Omitted fields use the defaults of a single
+    /// layer. Without any --layer there is one layer of --points points
+    #[arg(long = "layer", value_name = "LAYER")]
+    pub layers: Vec<LayerConfig>,
+
+    /// Add a point that pulls the points around it, e.g. x=0.5,y=0.5,strength=40,radius=300.
+    /// x and y go from 0 to 1 across the window, a negative strength pushes the points away
+    /// and orbit=PIXELS,period=SECONDS moves it in a circle around that spot
+    #[arg(long = "attractor", value_name = "ATTRACTOR")]
+    pub attractors: Vec<AttractorConfig>,
+
+    /// Swirl the points around a random spot on hits in the music, with this acceleration in
+    /// pixels per second squared at the strongest hit. 0 to turn swirls off
+    #[arg(long, value_name = "FORCE", default_value_t = 0.0)]
+    pub vortex_strength: f32,
+
+    /// Distance in pixels from the center of a swirl within which it turns the points
+    #[arg(long, value_name = "PIXELS", default_value_t = 400.0)]
+    pub vortex_radius: f32,
+
+    /// Seconds for a swirl to fade to about a third after a hit
+    #[arg(long, value_name = "SECONDS", default_value_t = 0.5)]
+    pub vortex_decay: f32,
+
+    /// Scatter the points from the center of the window with this speed in pixels per second
+    /// when the intensity jumps up, as on a drop. 0 to turn explosions off
+    #[arg(long, value_name = "SPEED", default_value_t = 0.0)]
+    pub explosion_speed: f32,
+
+    /// Rise of the intensity (0-1) within one frame that sets off an explosion
+    #[arg(long, value_name = "AMOUNT", default_value_t = 0.3)]
+    pub explosion_threshold: f32,
+
+    /// Seconds for an explosion to fade to about a third, after which the points drift on as
+    /// before
+    #[arg(long, value_name = "SECONDS", default_value_t = 0.4)]
+    pub explosion_decay: f32,
+
+    /// Point diameter in pixels when silent
+    #[arg(long, value_name = "PIXELS", default_value_t = 5.0)]
+    pub min_point_size: f32,
+
+    /// Point diameter in pixels at full intensity
+    #[arg(long, value_name = "PIXELS", default_value_t = 5.0)]
+    pub max_point_size: f32,
+
+    /// How much point sizes vary, as a fraction of the point size
+    #[arg(long, value_name = "AMOUNT", default_value_t = 0.0)]
+    pub point_size_variation: f32,
+
+    /// Distribution point sizes are drawn from
+    #[arg(long, value_enum, default_value_t = SizeDistribution::Uniform)]
+    pub point_size_distribution: SizeDistribution,
+
+    /// How much the masses of the points vary, as a fraction of the average mass. Heavy
+    /// points are pushed around less by gravity, attractors, the cursor and the flow field
+    #[arg(long, value_name = "AMOUNT", default_value_t = 0.0)]
+    pub mass_variation: f32,
+
+    /// Distribution point masses are drawn from
+    #[arg(long, value_enum, default_value_t = SizeDistribution::Uniform)]
+    pub mass_distribution: SizeDistribution,
+
+    /// Average rate per second the points lose their velocity at, e.g. 0.5. Points with drag
+    /// come to a stop unless a force keeps them moving
+    #[arg(long, value_name = "RATE", default_value_t = 0.0)]
+    pub drag: f32,
+
+    /// How much the drag of the points varies, as a fraction of --drag
+    #[arg(long, value_name = "AMOUNT", default_value_t = 0.0)]
+    pub drag_variation: f32,
+
+    /// Distribution the drag of the points is drawn from
+    #[arg(long, value_enum, default_value_t = SizeDistribution::Uniform)]
+    pub drag_distribution: SizeDistribution,
+
+    /// Take sizes, distances and speeds in physical pixels instead of logical ones, so they
+    /// shrink on HiDPI displays rather than scaling with them
+    #[arg(long)]
+    pub physical_pixels: bool,
+
+    /// Anti-aliasing method
+    #[arg(long, value_enum, default_value_t = AntiAliasing::Msaa)]
+    pub aa: AntiAliasing,
+
+    /// How finished frames are shown, trading tearing for latency
+    #[arg(long, value_enum, default_value_t = PresentMode::Auto)]
+    pub present_mode: PresentMode,
+
+    /// Draw at most this many frames per second, e.g. 60 for a wallpaper on a 240 Hz monitor
+    #[arg(long, value_name = "FPS", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_fps: Option<u32>,
+
+    /// Graphics adapter to draw with, by its index in the list of the info command or a part
+    /// of its name, like nvidia. Picked by the system when not given
+    #[arg(long, value_name = "INDEX|NAME")]
+    pub gpu: Option<String>,
+
+    /// MSAA samples per pixel (1, 2, 4 or 8), lowered when the GPU does not support it
+    #[arg(long, value_name = "SAMPLES", default_value_t = 4, value_parser = parse_sample_count)]
+    pub msaa: u32,
+
+    /// OLED friendly mode: pure black background, capped brightness, slow drift and idle dimming
+    #[arg(long)]
+    pub oled: bool,
+
+    /// Maximum dot brightness in OLED mode (0-1)
+    #[arg(long, value_name = "BRIGHTNESS", default_value_t = 0.6)]
+    pub oled_max_brightness: f32,
+
+    /// Seconds without input before OLED mode dims the dots
+    #[arg(long, value_name = "SECONDS", default_value_t = 600.0)]
+    pub oled_dim_after: f32,
+
+    /// Dot brightness once OLED mode has dimmed (0-1)
+    #[arg(long, value_name = "BRIGHTNESS", default_value_t = 0.2)]
+    pub oled_dim_brightness: f32,
+
+    /// Play generative ambient audio that follows the simulation
+    #[cfg(feature = "ambient-audio")]
+    #[arg(long)]
+    pub ambient_audio: bool,
+
+    /// Volume of the ambient audio (0-1)
+    #[cfg(feature = "ambient-audio")]
+    #[arg(long, value_name = "VOLUME", default_value_t = 0.3)]
+    pub ambient_audio_volume: f32,
+
+    /// CSV or JSON file with one value per point, mapped to point color and size.
+    /// The file is reloaded whenever it changes
+    #[arg(long, value_name = "PATH")]
+    pub point_data: Option<PathBuf>,
+
+    /// Color of points with the lowest data value
+    #[arg(long, value_name = "COLOR", default_value = "#3050ff")]
+    pub point_data_low_color: Color,
+
+    /// Color of points with the highest data value
+    #[arg(long, value_name = "COLOR", default_value = "#ff4030")]
+    pub point_data_high_color: Color,
+
+    /// Point size multiplier for the lowest data value
+    #[arg(long, value_name = "SCALE", default_value_t = 0.5)]
+    pub point_data_min_scale: f32,
+
+    /// Point size multiplier for the highest data value
+    #[arg(long, value_name = "SCALE", default_value_t = 2.0)]
+    pub point_data_max_scale: f32,
+
+    /// Show the frame rate in the top left corner
+    #[arg(long)]
+    pub show_fps: bool,
+
+    /// Show the current intensity in the top left corner
+    #[arg(long)]
+    pub show_intensity: bool,
+
+    /// Show the track playing in any MPRIS media player, using playerctl
+    #[arg(long)]
+    pub show_track: bool,
+
+    /// Keep rendering while the session is locked or the displays are off
+    #[arg(long)]
+    pub keep_running_when_locked: bool,
+
+    /// Keep rendering while the window is minimized or covered by other windows
+    #[arg(long)]
+    pub keep_running_when_hidden: bool,
+
+    /// When to switch to a lighter profile that saves power
+    #[arg(long, value_enum, default_value_t = PowerProfile::Auto)]
+    pub power_profile: PowerProfile,
+
+    /// Save power on battery once the charge drops to this percentage, 100 to save power as
+    /// soon as the machine is unplugged
+    #[arg(long, value_name = "PERCENT", default_value_t = 100.0)]
+    pub low_power_threshold: f32,
+
+    /// Frame rate cap while saving power
+    #[arg(long, value_name = "FPS", default_value_t = 30, value_parser = clap::value_parser!(u32).range(1..))]
+    pub low_power_fps: u32,
+
+    /// Fraction of the points kept while saving power
+    #[arg(long, value_name = "FRACTION", default_value_t = 0.5)]
+    pub low_power_points: f32,
+
+    /// Anti-aliasing method while saving power
+    #[arg(long, value_enum, default_value_t = AntiAliasing::Off)]
+    pub low_power_aa: AntiAliasing,
+
+    /// Register global hotkeys, which work while other windows have focus, to pause, switch
+    /// to the next preset and boost the intensity. Wayland asks through the global shortcuts
+    /// portal, where the desktop may let you pick other keys
+    #[arg(long)]
+    pub hotkeys: bool,
+
+    /// Hotkey that pauses or resumes, like ctrl+alt+p
+    #[arg(long, value_name = "KEYS", default_value = "ctrl+alt+p")]
+    pub hotkey_pause: String,
+
+    /// Hotkey that switches to the next preset
+    #[arg(long, value_name = "KEYS", default_value = "ctrl+alt+n")]
+    pub hotkey_next_preset: String,
+
+    /// Hotkey that briefly drives the intensity up to its maximum
+    #[arg(long, value_name = "KEYS", default_value = "ctrl+alt+b")]
+    pub hotkey_boost: String,
+
+    /// Animation played once at startup
+    #[arg(long, value_enum, default_value_t = IntroAnimation::None)]
+    pub intro: IntroAnimation,
+
+    /// Length of the startup animation in seconds
+    #[arg(long, value_name = "SECONDS", default_value_t = 2.0)]
+    pub intro_duration: f32,
+
+    /// Window class
+    #[arg(short, long, default_value = "connecting-dots")]
+    pub class: String,
+
+    /// Width in pixels of the window
+    #[arg(long, value_name = "PIXELS", requires = "height")]
+    pub width: Option<u32>,
+
+    /// Height in pixels of the window
+    #[arg(long, value_name = "PIXELS", requires = "width")]
+    pub height: Option<u32>,
+
+    /// Horizontal position in pixels of the window on the desktop, where the platform allows
+    /// windows to place themselves
+    #[arg(
+        long,
+        value_name = "PIXELS",
+        requires = "y",
+        allow_negative_numbers = true
+    )]
+    pub x: Option<i32>,
+
+    /// Vertical position in pixels of the window on the desktop
+    #[arg(
+        long,
+        value_name = "PIXELS",
+        requires = "x",
+        allow_negative_numbers = true
+    )]
+    pub y: Option<i32>,
+
+    /// Open borderless fullscreen, on the monitor with this name (like DP-1) when given and
+    /// otherwise on the current one
+    #[arg(long, value_name = "MONITOR", num_args = 0..=1, conflicts_with = "wallpaper")]
+    pub fullscreen: Option<Option<String>>,
+
+    /// Open a borderless fullscreen window on every monitor, all showing one field spanning
+    /// the whole desktop, so points and lines flow from one monitor onto the next
+    #[arg(
+        long,
+        conflicts_with_all = ["fullscreen", "wallpaper", "layer_shell", "record", "export_gif"]
+    )]
+    pub span: bool,
+
+    /// Run as a screensaver. On Wayland this locks the session through ext-session-lock,
+    /// covering every output until a key or button is pressed, without asking for a password.
+    /// On X11 it draws into the window XScreenSaver hands over in $XSCREENSAVER_WINDOW
+    #[arg(
+        long,
+        conflicts_with_all = ["fullscreen", "span", "wallpaper", "layer_shell", "record", "export_gif"]
+    )]
+    pub screensaver: bool,
+
+    /// Keep the window below other windows. Wayland has no way to ask for this, use
+    /// --layer-shell there instead
+    #[arg(long)]
+    pub below: bool,
+
+    /// Run as an animated wallpaper behind the desktop icons (Windows and macOS)
+    #[arg(long)]
+    pub wallpaper: bool,
+
+    /// Run as an animated wallpaper on this wlr-layer-shell layer instead of in a window, on
+    /// Wayland compositors like Hyprland, sway and wayfire
+    #[arg(long, value_enum, value_name = "LAYER")]
+    pub layer_shell: Option<ShellLayer>,
+
+    /// Periodically write the velocity field as a Middlebury .flo image to this path
+    #[arg(long, value_name = "PATH")]
+    pub export_flow: Option<PathBuf>,
+
+    /// Seconds between velocity field exports
+    #[arg(long, value_name = "SECONDS", default_value_t = 1.0)]
+    pub export_flow_interval: f32,
+
+    /// Size in pixels of each grid cell the velocity field is sampled on
+    #[arg(long, value_name = "PIXELS", default_value_t = 32)]
+    pub export_flow_cell_size: u32,
+
+    /// Render offscreen at a fixed timestep and encode the frames to this video file with ffmpeg,
+    /// then exit
+    #[arg(long, value_name = "PATH")]
+    pub record: Option<PathBuf>,
+
+    /// Render offscreen like --record, but write a small looping animated GIF to this path
+    #[arg(long, value_name = "PATH", conflicts_with = "record")]
+    pub export_gif: Option<PathBuf>,
+
+    /// Length of the recording or GIF, e.g. 30s, 2m or 500ms
+    #[arg(long, value_name = "DURATION", default_value = "30s", value_parser = parse_duration)]
+    pub duration: Duration,
+
+    /// Frames per second of the recording
+    #[arg(long, value_name = "FPS", default_value_t = 60, value_parser = clap::value_parser!(u32).range(1..))]
+    pub record_fps: u32,
+
+    /// Size of the recording in pixels
+    #[arg(long, value_name = "WIDTHxHEIGHT", default_value = "1920x1080", value_parser = parse_size)]
+    pub record_size: (u32, u32),
+
+    /// Frames per second of the GIF
+    #[arg(long, value_name = "FPS", default_value_t = 15, value_parser = clap::value_parser!(u32).range(1..))]
+    pub gif_fps: u32,
+
+    /// Size of the GIF in pixels
+    #[arg(long, value_name = "WIDTHxHEIGHT", default_value = "480x270", value_parser = parse_size)]
+    pub gif_size: (u32, u32),
+
+    /// Read the intensity from a file with one value per line, one line per rendered frame,
+    /// instead of capturing audio. Useful with --record
+    #[arg(long, value_name = "PATH")]
+    pub intensity_file: Option<PathBuf>,
+
+    /// Log more, repeat for even more: -v for warnings, -vv for info, -vvv for debug and
+    /// -vvvv for everything. Only errors are logged by default
+    #[arg(short, long, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Log nothing, not even errors
+    #[arg(short, long, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Append the log to this file instead of writing it to stderr, for running without a
+    /// terminal, like as a wallpaper daemon
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Log levels per module on top of -v and -q, like
+    /// wgpu_core=warn,connecting_dots_rs::state=debug. Also read from RUST_LOG, which this
+    /// wins over
+    #[arg(long, value_name = "FILTER")]
+    pub log_filter: Option<String>,
+
+    /// Check the options, the config file and the files they name, print a report and exit
+    /// without opening a window, to find out why an autostarted wallpaper does not come up
+    #[arg(long)]
+    pub check: bool,
+}
+
+/// Every option at its default, as with no arguments at all.
+impl Default for Config {
+    fn default() -> Self {
+        Config::from_arg_matches(&Config::command().get_matches_from(["connecting-dots"]))
+            .expect("The defaults of the options parse")
+    }
+}
+
+impl Config {
+    /// The options as a clap command on their own.
+    pub fn command() -> Command {
+        Config::augment_args(Command::new(env!("CARGO_PKG_NAME")))
+    }
+
+    /// The config with the low power profile applied: fewer points and cheaper
+    /// anti-aliasing.
+    pub fn low_power(&self) -> Config {
+        let fraction = self.low_power_points.clamp(0.0, 1.0);
+        let scale = |count: usize| ((count as f32 * fraction).round() as usize).max(1);
+
+        let mut config = self.clone();
+        config.points = scale(self.points as usize) as u32;
+        config.max_points = scale(self.max_points).max(self.min_points);
+        for layer in &mut config.layers {
+            layer.count = layer.count.map(scale);
+        }
+        config.aa = self.low_power_aa;
+
+        config
+    }
+
+    /// Shortest time between two frames with --max-fps.
+    pub fn frame_interval(&self) -> Option<Duration> {
+        self.max_fps
+            .map(|max_fps| Duration::from_secs_f64(1.0 / max_fps as f64))
+    }
+
+    /// The level logged at unless --log-filter or RUST_LOG say otherwise.
+    pub fn log_level(&self) -> log::LevelFilter {
+        if self.quiet {
+            return log::LevelFilter::Off;
+        }
+        match self.verbose {
+            0 => log::LevelFilter::Error,
+            1 => log::LevelFilter::Warn,
+            2 => log::LevelFilter::Info,
+            3 => log::LevelFilter::Debug,
+            _ => log::LevelFilter::Trace,
+        }
+    }
+
+    /// The config with sizes, distances and speeds given in logical pixels turned into
+    /// physical pixels for a display scaled by `scale_factor`.
+    pub fn scaled(&self, scale_factor: f32) -> Config {
+        let mut config = self.clone();
+        for pixels in [
+            &mut config.background_blur,
+            &mut config.aberration_max_offset,
+            &mut config.metaball_radius,
+            &mut config.connection_distance,
+            &mut config.cursor_force,
+            &mut config.cursor_radius,
+            &mut config.gravity.0,
+            &mut config.gravity.1,
+            &mut config.flow_scale,
+            &mut config.flow_speed,
+            &mut config.min_speed,
+            &mut config.max_speed,
+            &mut config.collision_radius,
+            &mut config.collision_strength,
+            &mut config.spacing,
+            &mut config.spacing_strength,
+            &mut config.vortex_strength,
+            &mut config.vortex_radius,
+            &mut config.explosion_speed,
+            &mut config.min_point_size,
+            &mut config.max_point_size,
+        ] {
+            *pixels *= scale_factor;
+        }
+        for layer in &mut config.layers {
+            layer.distance = layer.distance.map(|distance| distance * scale_factor);
+        }
+        for attractor in &mut config.attractors {
+            attractor.strength *= scale_factor;
+            attractor.radius *= scale_factor;
+            attractor.orbit *= scale_factor;
+        }
+
+        config
+    }
+
+    /// The options on the command line of the process, leaving out a subcommand like bench
+    /// and its own options, with the options set in environment variables in front of them
+    /// so the ones given on the command line win. Flags are on unless the variable is empty,
+    /// 0, false, no or off, and repeatable options like --layer take several values
+    /// separated by semicolons.
+    pub fn process_args() -> Vec<OsString> {
+        Config::args_with_env(std::env::args_os().collect(), |name| std::env::var_os(name))
+    }
+
+    /// `args` with the options set in the variables `var` looks up in front of them, as
+    /// described for [`Config::process_args`].
+    fn args_with_env(
+        mut args: Vec<OsString>,
+        var: impl Fn(&str) -> Option<OsString>,
+    ) -> Vec<OsString> {
+        if let Ok(matches) = Config::command()
+            .allow_external_subcommands(true)
+            .try_get_matches_from(&args)
+            && let Some((_, subcommand)) = matches.subcommand()
+        {
+            let subcommand_args = subcommand
+                .get_many::<OsString>("")
+                .map_or(0, |values| values.len());
+            args.truncate(args.len() - subcommand_args - 1);
+        }
+
+        // Invalid command lines, or ones asking for --help, are left for clap to report.
+        let Ok(matches) = Config::command().try_get_matches_from(&args) else {
+            return args;
+        };
+
+        let mut command = Config::command();
+        command.build();
+        let mut env_args = Vec::new();
+        for arg in command.get_arguments() {
+            let Some(long) = arg.get_long() else {
+                continue;
+            };
+            let name = format!("{ENV_PREFIX}{}", long.to_uppercase().replace('-', "_"));
+            let Some(value) = var(&name) else {
+                continue;
+            };
+            if matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine) {
+                continue;
+            }
+
+            let value = value.to_string_lossy();
+            let on = !matches!(
+                value.trim().to_lowercase().as_str(),
+                "" | "0" | "false" | "no" | "off"
+            );
+            let value_optional = arg
+                .get_num_args()
+                .is_some_and(|num_args| num_args.min_values() == 0);
+            if !arg.get_action().takes_values() {
+                if on {
+                    env_args.push(OsString::from(format!("--{long}")));
+                }
+            } else if value_optional && (value.is_empty() || value == "1" || value == "true") {
+                env_args.push(OsString::from(format!("--{long}")));
+            } else if matches!(arg.get_action(), ArgAction::Append) {
+                for value in value.split(';').filter(|value| !value.is_empty()) {
+                    env_args.push(OsString::from(format!("--{long}={value}")));
+                }
+            } else {
+                env_args.push(OsString::from(format!("--{long}={value}")));
+            }
+        }
+
+        let mut args = args.into_iter();
+        args.next()
+            .into_iter()
+            .chain(env_args)
+            .chain(args)
+            .collect()
+    }
+
+    /// Parses command line arguments, which start with the program name, and expands the
+    /// quality profile and preset among them.
+    pub fn try_parse_from(args: Vec<OsString>) -> Result<Self, clap::Error> {
+        let config = Config::from_arg_matches(&Config::command().try_get_matches_from(&args)?)?;
+
+        match config.quality.is_some() || config.preset.is_some() {
+            true => Config::from_arg_matches(
+                &Config::command().try_get_matches_from(config.expand(args))?,
+            ),
+            false => Ok(config),
+        }
+    }
+
+    /// The command line arguments `args`, which start with the program name and were parsed
+    /// into this config, with the options of its quality profile and then its preset added,
+    /// so the profile wins over the preset and the options given explicitly over both.
+    pub fn expand(&self, mut args: Vec<OsString>) -> Vec<OsString> {
+        if let Some(quality) = self.quality {
+            args = quality.expand(args);
+        }
+        if let Some(preset) = self.preset {
+            args = preset.expand(args);
+        }
+
+        args
+    }
+}
+
+/// Parses a duration given in hours, minutes, seconds or milliseconds, defaulting to seconds.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (value, unit_seconds) = if let Some(value) = s.strip_suffix("ms") {
+        (value, 0.001)
+    } else if let Some(value) = s.strip_suffix('s') {
+        (value, 1.0)
+    } else if let Some(value) = s.strip_suffix('m') {
+        (value, 60.0)
+    } else if let Some(value) = s.strip_suffix('h') {
+        (value, 3600.0)
+    } else {
+        (s, 1.0)
+    };
+
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .and_then(|value| Duration::try_from_secs_f64(value * unit_seconds).ok())
+        .ok_or_else(|| format!("expected a duration like 30s, 2m or 500ms, got '{s}'"))
+}
+
+fn parse_size(s: &str) -> Result<(u32, u32), String> {
+    s.split_once('x')
+        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+        .filter(|&(width, height)| width > 0 && height > 0)
+        .ok_or_else(|| format!("expected a size like 1920x1080, got '{s}'"))
+}
+
+fn parse_vector(s: &str) -> Result<(f32, f32), String> {
+    s.split_once(',')
+        .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)))
+        .ok_or_else(|| format!("expected a vector like 0,40, got '{s}'"))
+}
+
+fn parse_sample_count(s: &str) -> Result<u32, String> {
+    match s.parse() {
+        Ok(count @ (1 | 2 | 4 | 8)) => Ok(count),
+        _ => Err(format!("expected 1, 2, 4 or 8, got '{s}'")),
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GradientShape {
+    /// From the top edge to the bottom edge
+    Vertical,
+    /// From the center to the corners
+    Radial,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackgroundMode {
+    /// Scale to cover the monitor, cropping what does not fit
+    Fill,
+    /// Scale to fit inside the monitor, leaving bars of background color
+    Fit,
+    /// Scale to the monitor size, ignoring the aspect ratio
+    Stretch,
+    /// Repeat the image at its native size
+    Tile,
+    /// Show the image at its native size
+    Center,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackgroundAlign {
+    Center,
+    Top,
+    Bottom,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl BackgroundAlign {
+    /// Returns the anchor as fractions of the free space, from 0 (left/top) to 1 (right/bottom).
+    pub fn anchor(self) -> (f32, f32) {
+        match self {
+            BackgroundAlign::Center => (0.5, 0.5),
+            BackgroundAlign::Top => (0.5, 0.0),
+            BackgroundAlign::Bottom => (0.5, 1.0),
+            BackgroundAlign::Left => (0.0, 0.5),
+            BackgroundAlign::Right => (1.0, 0.5),
+            BackgroundAlign::TopLeft => (0.0, 0.0),
+            BackgroundAlign::TopRight => (1.0, 0.0),
+            BackgroundAlign::BottomLeft => (0.0, 1.0),
+            BackgroundAlign::BottomRight => (1.0, 1.0),
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    /// Make bright areas glow
+    Bloom,
+    /// Soften the whole frame
+    Blur,
+    /// Darken the corners
+    Vignette,
+    /// Split the color channels on hits in the music
+    ChromaticAberration,
+    /// Scanlines, screen curvature and a phosphor mask like an old CRT monitor
+    Crt,
+    /// Added at the end of the chain by --aa fxaa
+    #[value(skip)]
+    Fxaa,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderMode {
+    /// A round dot per point
+    Dots,
+    /// Gooey blobs that merge when points get close
+    Metaballs,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerProfile {
+    /// Save power while on battery, as reported by UPower on Linux
+    Auto,
+    /// Always render at full quality
+    Performance,
+    /// Always save power
+    LowPower,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// Few slow points in a long-reaching web on deep blue
+    Calm,
+    /// A dense field that rushes along with loud music
+    Energetic,
+    /// Many small points swirling through a turbulent flow field
+    Chaotic,
+    /// A handful of points and faint lines on black
+    Minimal,
+}
+
+impl Preset {
+    /// The options the preset stands for, exactly as they would be written on the command
+    /// line, so they can be copied and tweaked.
+    pub fn args(self) -> &'static [&'static str] {
+        match self {
+            Preset::Calm => &[
+                "--points=400",
+                "--silent-speed=0.5",
+                "--loud-speed=1.5",
+                "--connection-distance=150",
+                "--background-color=#0b1a2a",
+                "--background-gradient=#000000",
+            ],
+            Preset::Energetic => &[
+                "--points=1500",
+                "--silent-speed=1",
+                "--loud-speed=4",
+                "--connection-distance=90",
+                "--max-point-size=9",
+                "--background-color=#1a0b2a",
+                "--background-gradient=#000000",
+            ],
+            Preset::Chaotic => &[
+                "--points=2500",
+                "--motion=flow",
+                "--flow-speed=80",
+                "--loud-speed=3",
+                "--connection-distance=60",
+                "--max-connections=4",
+                "--min-point-size=2",
+                "--max-point-size=5",
+                "--background-color=#2a0b0b",
+            ],
+            Preset::Minimal => &[
+                "--points=150",
+                "--connection-distance=220",
+                "--min-point-size=3",
+                "--max-point-size=4",
+                "--background-color=#000000",
+            ],
+        }
+    }
+
+    /// The preset after `current` in the list, or the first without one.
+    pub fn next(current: Option<Preset>) -> Preset {
+        let presets = Preset::value_variants();
+        let index = current
+            .and_then(|current| presets.iter().position(|&preset| preset == current))
+            .map_or(0, |index| (index + 1) % presets.len());
+
+        presets[index]
+    }
+
+    /// Inserts the preset's options in front of the command line arguments `args`, which
+    /// start with the program name, leaving out the ones given explicitly.
+    pub fn expand(self, args: Vec<OsString>) -> Vec<OsString> {
+        insert_defaults(args, self.args())
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quality {
+    /// Few points, short lines and no anti-aliasing, for integrated graphics and old laptops
+    Low,
+    /// Fewer points with cheap FXAA anti-aliasing
+    Medium,
+    /// The default point count with 4x MSAA and bloom
+    High,
+    /// Many points with long lines, 8x MSAA and bloom, for desktop GPUs
+    Ultra,
+}
+
+impl Quality {
+    /// The options the profile stands for, exactly as they would be written on the command
+    /// line.
+    pub fn args(self) -> &'static [&'static str] {
+        match self {
+            Quality::Low => &["--points=300", "--aa=off", "--connection-distance=70"],
+            Quality::Medium => &["--points=600", "--aa=fxaa", "--connection-distance=85"],
+            Quality::High => &[
+                "--points=1000",
+                "--aa=msaa",
+                "--msaa=4",
+                "--effect=bloom",
+                "--connection-distance=100",
+            ],
+            Quality::Ultra => &[
+                "--points=3000",
+                "--aa=msaa",
+                "--msaa=8",
+                "--effect=bloom",
+                "--connection-distance=130",
+            ],
+        }
+    }
+
+    /// Inserts the profile's options in front of the command line arguments `args`, which
+    /// start with the program name, leaving out the ones given explicitly.
+    pub fn expand(self, args: Vec<OsString>) -> Vec<OsString> {
+        insert_defaults(args, self.args())
+    }
+}
+
+/// Inserts `defaults`, written as `--name=value`, after the program name at the start of
+/// `args`, except for the options `args` already give.
+fn insert_defaults(args: Vec<OsString>, defaults: &[&str]) -> Vec<OsString> {
+    let command = Config::command();
+    // Errors are reported when the expanded arguments are parsed.
+    let Ok(matches) = command.clone().try_get_matches_from(&args) else {
+        return args;
+    };
+    let given = |default: &&str| {
+        let name = default.trim_start_matches('-').split('=').next();
+        command
+            .get_arguments()
+            .find(|arg| arg.get_long() == name)
+            .is_some_and(|arg| {
+                matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+            })
+    };
+
+    let mut args = args.into_iter();
+    args.next()
+        .into_iter()
+        .chain(
+            defaults
+                .iter()
+                .filter(|default| !given(default))
+                .map(OsString::from),
+        )
+        .chain(args)
+        .collect()
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Motion {
+    /// Straight lines at a constant speed
+    Straight,
+    /// Swirl along a slowly changing curl-noise flow field, more turbulent when it is loud
+    Flow,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeBehavior {
+    /// Reflect off the edge
+    Bounce,
+    /// Leave through one edge and come back through the opposite one
+    Wrap,
+    /// Start over at a random spot, or at the edge gravity pulls away from
+    Respawn,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellLayer {
+    /// Below everything, where wallpapers go
+    Background,
+    /// Above the wallpaper but below windows
+    Bottom,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Up or down and left or right, with a speed drawn for each axis
+    Diagonal,
+    /// Any angle, all equally likely
+    Any,
+    /// Left or right
+    Horizontal,
+    /// Up or down
+    Vertical,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeDistribution {
+    /// Every size in the range is equally likely
+    Uniform,
+    /// Most points are close to the average size
+    Normal,
+    /// Mostly small points with a few large ones
+    Skewed,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AntiAliasing {
+    /// Multisampling with --msaa samples per pixel
+    Msaa,
+    /// A cheaper FXAA post-processing pass
+    Fxaa,
+    /// No anti-aliasing
+    Off,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresentMode {
+    /// Wait for the vertical blank, without tearing
+    Fifo,
+    /// Wait for the vertical blank, but replace the waiting frame with newer ones, for lower
+    /// latency without tearing
+    Mailbox,
+    /// Show frames right away, for the lowest latency at the cost of tearing
+    Immediate,
+    /// Vsync, letting late frames tear where the platform supports it
+    Auto,
+}
+
+impl PresentMode {
+    pub fn to_wgpu(self) -> wgpu::PresentMode {
+        match self {
+            PresentMode::Fifo => wgpu::PresentMode::Fifo,
+            PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+            PresentMode::Immediate => wgpu::PresentMode::Immediate,
+            PresentMode::Auto => wgpu::PresentMode::AutoVsync,
+        }
+    }
+}
+
+/// One independent field of points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayerConfig {
+    /// Number of points, `--points` when unset.
+    pub count: Option<usize>,
+    /// Multiplier of the point size.
+    pub size: f32,
+    /// Multiplier of the point speed.
+    pub speed: f32,
+    /// Connection distance in pixels, `--connection-distance` when unset.
+    pub distance: Option<f32>,
+}
+
+impl Default for LayerConfig {
+    fn default() -> Self {
+        Self {
+            count: None,
+            size: 1.0,
+            speed: 1.0,
+            distance: None,
+        }
+    }
+}
+
+impl FromStr for LayerConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut layer = LayerConfig::default();
+
+        for field in s.split(',').filter(|field| !field.is_empty()) {
+            let (key, value) = field
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Invalid layer field '{field}', expected key=value"))?;
+
+            match key.trim() {
+                "count" => layer.count = Some(value.trim().parse()?),
+                "size" => layer.size = value.trim().parse()?,
+                "speed" => layer.speed = value.trim().parse()?,
+                "distance" => layer.distance = Some(value.trim().parse()?),
+                _ => {
+                    return Err(anyhow!(
+                        "Unknown layer field '{key}', expected count, size, speed or distance"
+                    ));
+                }
+            }
+        }
+
+        if layer.count == Some(0) {
+            return Err(anyhow!("A layer needs at least one point"));
+        }
+
+        Ok(layer)
+    }
+}
+
+/// A spot pulling nearby points toward it, or pushing them away.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AttractorConfig {
+    /// Horizontal position from 0 to 1 across the window.
+    pub x: f32,
+    /// Vertical position from 0 to 1 down the window.
+    pub y: f32,
+    /// Acceleration in pixels per second squared next to the attractor, negative to repel.
+    pub strength: f32,
+    /// Distance in pixels beyond which the attractor has no effect.
+    pub radius: f32,
+    /// Radius in pixels of the circle the attractor moves along, 0 to stay in place.
+    pub orbit: f32,
+    /// Seconds per orbit.
+    pub period: f32,
+}
+
+impl Default for AttractorConfig {
+    fn default() -> Self {
+        Self {
+            x: 0.5,
+            y: 0.5,
+            strength: 40.0,
+            radius: 300.0,
+            orbit: 0.0,
+            period: 10.0,
+        }
+    }
+}
+
+impl FromStr for AttractorConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut attractor = AttractorConfig::default();
+
+        for field in s.split(',').filter(|field| !field.is_empty()) {
+            let (key, value) = field
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Invalid attractor field '{field}', expected key=value"))?;
+
+            match key.trim() {
+                "x" => attractor.x = value.trim().parse()?,
+                "y" => attractor.y = value.trim().parse()?,
+                "strength" => attractor.strength = value.trim().parse()?,
+                "radius" => attractor.radius = value.trim().parse()?,
+                "orbit" => attractor.orbit = value.trim().parse()?,
+                "period" => attractor.period = value.trim().parse()?,
+                _ => {
+                    return Err(anyhow!(
+                        "Unknown attractor field '{key}', expected x, y, strength, radius, \
+                         orbit or period"
+                    ));
+                }
+            }
+        }
+
+        if attractor.radius <= 0.0 {
+            return Err(anyhow!("An attractor needs a positive radius"));
+        }
+
+        Ok(attractor)
+    }
+}
+
+/// Background images, either one for every monitor or one per monitor by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackgroundImages {
+    /// Used on monitors without an image of their own.
+    pub default: Option<String>,
+    /// Monitor names, like DP-1, and their images.
+    pub monitors: Vec<(String, String)>,
+}
+
+impl BackgroundImages {
+    /// Returns the image for the monitor with the given name.
+    pub fn for_monitor(&self, name: Option<&str>) -> Option<&str> {
+        name.and_then(|name| {
+            self.monitors
+                .iter()
+                .find(|(monitor, _)| monitor == name)
+                .map(|(_, path)| path.as_str())
+        })
+        .or(self.default.as_deref())
+    }
+}
+
+impl FromStr for BackgroundImages {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        // A plain path may contain commas and =, so only split when monitors are named.
+        if !s.contains('=') || Path::new(s).exists() {
+            return Ok(BackgroundImages {
+                default: Some(s.to_owned()),
+                monitors: Vec::new(),
+            });
+        }
+
+        let mut images = BackgroundImages {
+            default: None,
+            monitors: Vec::new(),
+        };
+
+        for entry in s.split(',').filter(|entry| !entry.is_empty()) {
+            match split_monitor(entry) {
+                Some((monitor, path)) => {
+                    images
+                        .monitors
+                        .push((monitor.trim().to_owned(), path.trim().to_owned()));
+                }
+                None if images.default.is_none() => images.default = Some(entry.to_owned()),
+                None => {
+                    return Err(anyhow!(
+                        "Only one background image can be used for the remaining monitors"
+                    ));
+                }
+            }
+        }
+
+        Ok(images)
+    }
+}
+
+/// Splits `MONITOR=PATH` into the monitor and path, unless the = is part of a path, which is
+/// the case when the text names a file, whole or before the =.
+fn split_monitor(entry: &str) -> Option<(&str, &str)> {
+    entry
+        .split_once('=')
+        .filter(|(monitor, _)| !Path::new(entry).exists() && !Path::new(monitor).exists())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Background {
+    Procedural(ProceduralPreset),
+}
+
+impl FromStr for Background {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.split_once(':') {
+            Some(("procedural", preset)) => Ok(Background::Procedural(
+                ProceduralPreset::from_str(preset, true).map_err(|e| anyhow!(e))?,
+            )),
+            _ => Err(anyhow!(
+                "Invalid background '{s}', expected procedural:<preset>"
+            )),
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProceduralPreset {
+    /// Slowly shifting rainbow plasma
+    Plasma,
+    /// Drifting clouds of interstellar dust
+    Nebula,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntroAnimation {
+    /// Start with the simulation already running
+    None,
+    /// Fade the points in
+    Fade,
+    /// Fade the points in while they fly in from the nearest window edge
+    Converge,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        ["connecting-dots-rs"]
+            .iter()
+            .chain(args)
+            .map(OsString::from)
+            .collect()
+    }
+
+    fn parse(command_line: &[&str]) -> Config {
+        Config::try_parse_from(args(command_line)).unwrap()
+    }
+
+    #[test]
+    fn explicit_options_win_over_preset_and_quality() {
+        for points in [&["--points", "500"][..], &["--points=500"]] {
+            let config = parse(&[&["--preset", "calm"], points].concat());
+            assert_eq!(config.points, 500);
+            assert_eq!(config.silent_speed, 0.5);
+
+            let config = parse(&[&["--quality", "ultra"], points].concat());
+            assert_eq!(config.points, 500);
+            assert_eq!(config.connection_distance, 130.0);
+        }
+
+        let expanded = Preset::Calm.expand(args(&["--points", "500"]));
+        assert!(!expanded.contains(&OsString::from("--points=400")));
+    }
+
+    #[test]
+    fn preset_fills_unset_options() {
+        let config = parse(&["--preset", "minimal"]);
+        assert_eq!(config.points, 150);
+        assert_eq!(config.connection_distance, 220.0);
+        assert_eq!(config.min_point_size, 3.0);
+        assert_eq!(config.silent_speed, Config::default().silent_speed);
+
+        // The quality profile wins over the preset.
+        let config = parse(&["--preset", "minimal", "--quality", "low"]);
+        assert_eq!(config.points, 300);
+        assert_eq!(config.connection_distance, 70.0);
+        assert_eq!(config.min_point_size, 3.0);
+    }
+
+    #[test]
+    fn insert_defaults_leaves_invalid_args_alone() {
+        let invalid = args(&["--points", "many"]);
+        assert_eq!(Preset::Calm.expand(invalid.clone()), invalid);
+    }
+
+    #[test]
+    fn options_from_environment() {
+        let env = |name: &str| {
+            let value = match name.strip_prefix(ENV_PREFIX)? {
+                "POINTS" => "300",
+                "CONNECTION_DISTANCE" => "80",
+                "QUIET" => "yes",
+                "SPARKS" => "off",
+                "FULLSCREEN" => "",
+                "LAYER" => "count=10;;count=20",
+                _ => return None,
+            };
+            Some(OsString::from(value))
+        };
+
+        let config =
+            Config::try_parse_from(Config::args_with_env(args(&["--points", "500"]), env)).unwrap();
+        assert_eq!(config.points, 500);
+        assert_eq!(config.connection_distance, 80.0);
+        assert!(config.quiet);
+        assert!(!config.sparks);
+        assert_eq!(config.fullscreen, Some(None));
+        let counts: Vec<_> = config.layers.iter().map(|layer| layer.count).collect();
+        assert_eq!(counts, [Some(10), Some(20)]);
+
+        let env = |name: &str| (name == "CONNECTING_DOTS_FULLSCREEN").then(|| "DP-1".into());
+        let config = Config::try_parse_from(Config::args_with_env(args(&[]), env)).unwrap();
+        assert_eq!(config.fullscreen, Some(Some("DP-1".to_owned())));
+    }
+
+    #[test]
+    fn environment_leaves_out_subcommand() {
+        let command_line = args(&["--points", "5", "bench", "--points", "1,2"]);
+        assert_eq!(
+            Config::args_with_env(command_line, |_| None),
+            args(&["--points", "5"])
+        );
+    }
+
+    #[test]
+    fn durations() {
+        for (text, seconds) in [
+            ("30", 30.0),
+            ("30s", 30.0),
+            ("2m", 120.0),
+            ("1.5h", 5400.0),
+            ("500ms", 0.5),
+            ("2 m", 120.0),
+        ] {
+            assert_eq!(parse_duration(text), Ok(Duration::from_secs_f64(seconds)));
+        }
+        for text in ["", "m", "soon", "-1s", "10d"] {
+            assert!(parse_duration(text).is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn background_images_per_monitor() {
+        let images: BackgroundImages = "DP-1=/left.png, HDMI-1 = /right.png".parse().unwrap();
+        assert_eq!(images.default, None);
+        assert_eq!(
+            images.monitors,
+            [
+                ("DP-1".to_owned(), "/left.png".to_owned()),
+                ("HDMI-1".to_owned(), "/right.png".to_owned()),
+            ]
+        );
+        assert_eq!(images.for_monitor(Some("HDMI-1")), Some("/right.png"));
+    }
+
+    #[test]
+    fn background_image_path_with_equals() {
+        let path = std::env::temp_dir().join(format!("a=b-{}.png", std::process::id()));
+        std::fs::write(&path, []).unwrap();
+        let path = path.to_str().unwrap();
+
+        let images = path.parse::<BackgroundImages>();
+        let with_monitor = format!("DP-1={path}").parse::<BackgroundImages>();
+        let split = split_monitor(path);
+        std::fs::remove_file(path).unwrap();
+
+        let images = images.unwrap();
+        assert_eq!(images.default.as_deref(), Some(path));
+        assert!(images.monitors.is_empty());
+        assert_eq!(split, None);
+        assert_eq!(
+            with_monitor.unwrap().monitors,
+            [("DP-1".to_owned(), path.to_owned())]
+        );
+    }
+
+    #[test]
+    fn background_image_for_missing_monitor() {
+        let images: BackgroundImages = "DP-1=/left.png,/other.png".parse().unwrap();
+        assert_eq!(images.for_monitor(Some("DP-2")), Some("/other.png"));
+        assert_eq!(images.for_monitor(None), Some("/other.png"));
+
+        let images: BackgroundImages = "DP-1=/left.png".parse().unwrap();
+        assert_eq!(images.for_monitor(Some("DP-2")), None);
+
+        assert!(
+            "/one.png,/two.png=x,/three.png"
+                .parse::<BackgroundImages>()
+                .is_err()
+        );
+    }
+}
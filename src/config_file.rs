@@ -0,0 +1,421 @@
+use std::{
+    any::TypeId,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::{Context, anyhow, bail};
+use clap::{Arg, ArgAction, parser::ValueSource};
+use serde_json::{Map, Value, json};
+
+use crate::{config::Config, control::override_args};
+
+/// How often the file is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Coarsest modification times of common filesystems, like FAT's two seconds. A write this
+/// soon after the last one can leave the time as it was.
+const MODIFIED_RESOLUTION: Duration = Duration::from_secs(2);
+
+/// A TOML file of options, like `points = 300`, taken as if they had been given on the
+/// command line, and read again whenever it changes. Tables like `[presets.calm]` hold named
+/// presets of options to switch between at runtime.
+pub struct ConfigFile {
+    path: PathBuf,
+    /// Modification time and length of the file when last read.
+    stamp: Option<(SystemTime, u64)>,
+    checked: Instant,
+    /// Options given on the command line, which win over the file's.
+    explicit: Vec<String>,
+    /// What the file held when its options were last put in effect.
+    applied: Contents,
+    /// What the file held when last read, when its options are not in effect yet.
+    read: Option<Contents>,
+}
+
+/// Options of a config file, leaving out the ones given on the command line.
+#[derive(Default)]
+struct Contents {
+    options: Map<String, Value>,
+    /// Named presets, in the order of the file.
+    presets: Vec<(String, Map<String, Value>)>,
+}
+
+impl ConfigFile {
+    /// Reads the file at `path` for the command line `args`, which start with the program
+    /// name.
+    pub fn open(path: &Path, args: &[OsString]) -> anyhow::Result<Self> {
+        let matches = Config::command().try_get_matches_from(args)?;
+        let explicit = Config::command()
+            .get_arguments()
+            .filter(|arg| {
+                matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+            })
+            .filter_map(|arg| arg.get_long().map(str::to_owned))
+            .collect();
+
+        let mut file = Self {
+            path: path.to_owned(),
+            stamp: stamp(path),
+            checked: Instant::now(),
+            explicit,
+            applied: Contents::default(),
+            read: None,
+        };
+        file.applied = file.read()?;
+
+        Ok(file)
+    }
+
+    /// Reads the file again once it was modified, and returns the options that changed since
+    /// the ones in effect, with the ones taken out of the file as null so they drop back to
+    /// their defaults.
+    pub fn changes(&mut self) -> Option<Map<String, Value>> {
+        if self.checked.elapsed() < POLL_INTERVAL {
+            return None;
+        }
+        self.checked = Instant::now();
+
+        let stamp = stamp(&self.path);
+        // A recent modification time may not have moved on with a later write, so such a
+        // file is read again to be sure.
+        let recent = stamp.is_some_and(|(modified, _)| {
+            modified
+                .elapsed()
+                .is_ok_and(|age| age < MODIFIED_RESOLUTION + POLL_INTERVAL)
+        });
+        if stamp == self.stamp && !recent {
+            return None;
+        }
+        self.stamp = stamp;
+
+        let contents = self
+            .read()
+            .inspect_err(|e| log::error!("Unable to read {}: {e}", self.path.display()))
+            .ok()?;
+        let changes = option_changes(&self.applied.options, &contents.options);
+
+        // Changed presets take effect the next time they are switched to.
+        if changes.is_empty() {
+            self.applied = contents;
+            return None;
+        }
+        self.read = Some(contents);
+
+        Some(changes)
+    }
+
+    /// Marks the options last read as in effect.
+    pub fn apply(&mut self) {
+        if let Some(contents) = self.read.take() {
+            self.applied = contents;
+        }
+    }
+
+    /// The command line `args` with the options of the file in effect added.
+    pub fn args(&self, args: &[OsString]) -> anyhow::Result<Vec<OsString>> {
+        override_args(args, &self.applied.options)
+    }
+
+    /// Names of the presets in the file, in its order.
+    pub fn preset_names(&self) -> impl Iterator<Item = &str> {
+        self.applied.presets.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Options of the preset called `name`.
+    pub fn preset(&self, name: &str) -> Option<&Map<String, Value>> {
+        self.applied
+            .presets
+            .iter()
+            .find(|(preset, _)| preset == name)
+            .map(|(_, options)| options)
+    }
+
+    /// Value of the option `name` while the preset `preset`, if any, is in effect, when the
+    /// file sets it.
+    pub fn value(&self, preset: Option<&str>, name: &str) -> Option<&Value> {
+        preset
+            .and_then(|preset| self.preset(preset))
+            .and_then(|options| options.get(name))
+            .or_else(|| self.applied.options.get(name))
+    }
+
+    /// The options that switch from the preset `from`, or none, to the preset `to`: the ones
+    /// `to` sets, and the ones only `from` sets going back to the file's, or to their
+    /// defaults as null.
+    pub fn preset_switch(
+        &self,
+        from: Option<&str>,
+        to: &str,
+    ) -> anyhow::Result<Map<String, Value>> {
+        let mut options = self
+            .preset(to)
+            .ok_or_else(|| anyhow!("There is no preset called {to} in the config file"))?
+            .clone();
+        if let Some(from) = from.and_then(|from| self.preset(from)) {
+            for name in from.keys() {
+                if !options.contains_key(name) {
+                    let value = self.applied.options.get(name).cloned();
+                    options.insert(name.clone(), value.unwrap_or(Value::Null));
+                }
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// A JSON Schema of the file, for editors to complete and check it with. Its options are
+    /// the ones of the command line, so the schema is derived from their clap definitions.
+    pub fn schema() -> Value {
+        let mut command = Config::command();
+        command.build();
+
+        let options: Map<String, Value> = command
+            .get_arguments()
+            .filter(|arg| !arg.is_hide_set())
+            .filter(|arg| !matches!(arg.get_action(), ArgAction::Help | ArgAction::Version))
+            .filter_map(|arg| Some((arg.get_long()?, arg)))
+            // Neither can be set from the file itself.
+            .filter(|(long, _)| !["config", "check"].contains(long))
+            .map(|(long, arg)| (long.to_owned(), option_schema(arg)))
+            .collect();
+
+        let mut properties = options.clone();
+        properties.insert(
+            "presets".to_owned(),
+            json!({
+                "description": "Named presets of options to switch between at runtime",
+                "type": "object",
+                "additionalProperties": { "$ref": "#/definitions/options" },
+            }),
+        );
+
+        json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": "connecting-dots-rs config file",
+            "type": "object",
+            "properties": properties,
+            "additionalProperties": false,
+            "definitions": {
+                "options": {
+                    "type": "object",
+                    "properties": options,
+                    "additionalProperties": false,
+                },
+            },
+        })
+    }
+
+    fn read(&self) -> anyhow::Result<Contents> {
+        let text = fs::read_to_string(&self.path)
+            .with_context(|| format!("Unable to read {}", self.path.display()))?;
+        let document: toml_edit::DocumentMut = text.parse()?;
+
+        let mut contents = Contents::default();
+        for (name, item) in document.iter() {
+            if name != "presets" {
+                if let Some(value) = self.option(name, item)? {
+                    contents.options.insert(name.to_owned(), value);
+                }
+                continue;
+            }
+
+            let presets = item
+                .as_table_like()
+                .ok_or_else(|| anyhow!("presets has to be a table of presets"))?;
+            for (preset, item) in presets.iter() {
+                let table = item
+                    .as_table_like()
+                    .ok_or_else(|| anyhow!("The preset {preset} has to be a table of options"))?;
+                let mut options = Map::new();
+                for (name, item) in table.iter() {
+                    if let Some(value) = self.option(name, item)? {
+                        options.insert(name.to_owned(), value);
+                    }
+                }
+                contents.presets.push((preset.to_owned(), options));
+            }
+        }
+
+        Ok(contents)
+    }
+
+    /// The value of the option `name`, unless it was given on the command line.
+    fn option(&self, name: &str, item: &toml_edit::Item) -> anyhow::Result<Option<Value>> {
+        if self.explicit.iter().any(|explicit| explicit == name) {
+            return Ok(None);
+        }
+        let value = item
+            .as_value()
+            .ok_or_else(|| anyhow!("{name} has to be a value, not a table"))?;
+
+        Ok(Some(
+            to_json(value).with_context(|| format!("Invalid value of {name}"))?,
+        ))
+    }
+}
+
+/// The options of `read` that differ from `applied`, with the ones only `applied` has as
+/// null.
+fn option_changes(applied: &Map<String, Value>, read: &Map<String, Value>) -> Map<String, Value> {
+    let mut changes: Map<String, Value> = read
+        .iter()
+        .filter(|(name, value)| applied.get(*name) != Some(value))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    for name in applied.keys() {
+        if !read.contains_key(name) {
+            changes.insert(name.clone(), Value::Null);
+        }
+    }
+
+    changes
+}
+
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// The schema of the values `arg` takes, with its help as the description.
+fn option_schema(arg: &Arg) -> Value {
+    let type_id = arg.get_value_parser().type_id();
+    let (kind, value) = if !arg.get_action().takes_values() {
+        ("boolean", json!({ "type": "boolean" }))
+    } else if [
+        TypeId::of::<u8>(),
+        TypeId::of::<u32>(),
+        TypeId::of::<u64>(),
+        TypeId::of::<usize>(),
+    ]
+    .iter()
+    .any(|id| type_id == *id)
+    {
+        ("integer", json!({ "type": "integer", "minimum": 0 }))
+    } else if type_id == TypeId::of::<i32>() {
+        ("integer", json!({ "type": "integer" }))
+    } else if type_id == TypeId::of::<f32>() {
+        ("number", json!({ "type": "number" }))
+    } else {
+        let possible: Vec<_> = arg
+            .get_possible_values()
+            .iter()
+            .filter(|value| !value.is_hide_set())
+            .map(|value| value.get_name().to_owned())
+            .collect();
+        match possible.is_empty() {
+            true => ("string", json!({ "type": "string" })),
+            false => ("string", json!({ "type": "string", "enum": possible })),
+        }
+    };
+
+    let value_optional = arg.get_action().takes_values()
+        && arg
+            .get_num_args()
+            .is_some_and(|num_args| num_args.min_values() == 0);
+    let mut schema = match arg.get_action() {
+        // Values can also be given together as a string, split at the delimiter.
+        ArgAction::Append if arg.get_value_delimiter().is_some() => json!({
+            "anyOf": [{ "type": "string" }, { "type": "array", "items": value }],
+        }),
+        ArgAction::Append => json!({
+            "anyOf": [value, { "type": "array", "items": value }],
+        }),
+        _ if value_optional => json!({ "anyOf": [{ "type": "boolean" }, value] }),
+        _ => value,
+    };
+
+    if let Some(help) = arg.get_help() {
+        schema["description"] = Value::String(help.to_string());
+    }
+    let defaults: Vec<Value> = arg
+        .get_default_values()
+        .iter()
+        .map(|value| {
+            let value = value.to_string_lossy();
+            match kind {
+                "boolean" => Value::Bool(value == "true"),
+                "integer" | "number" => {
+                    serde_json::from_str(&value).unwrap_or(Value::String(value.into_owned()))
+                }
+                _ => Value::String(value.into_owned()),
+            }
+        })
+        .collect();
+    match defaults.as_slice() {
+        [] => {}
+        [default] => schema["default"] = default.clone(),
+        _ => schema["default"] = Value::Array(defaults),
+    }
+
+    schema
+}
+
+/// Turns a TOML value into the JSON the control socket takes options as.
+fn to_json(value: &toml_edit::Value) -> anyhow::Result<Value> {
+    Ok(match value {
+        toml_edit::Value::String(value) => Value::String(value.value().clone()),
+        toml_edit::Value::Integer(value) => Value::from(*value.value()),
+        toml_edit::Value::Float(value) => Value::from(*value.value()),
+        toml_edit::Value::Boolean(value) => Value::Bool(*value.value()),
+        toml_edit::Value::Array(values) => {
+            Value::Array(values.iter().map(to_json).collect::<anyhow::Result<_>>()?)
+        }
+        toml_edit::Value::Datetime(_) => bail!("Dates are not options"),
+        toml_edit::Value::InlineTable(_) => bail!("Tables are not options"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(options) => options,
+            _ => panic!("options must be an object"),
+        }
+    }
+
+    #[test]
+    fn changes_between_options() {
+        let applied = options(json!({"points": 300, "sparks": true, "aa": "fxaa"}));
+        let read = options(json!({"points": 500, "aa": "fxaa", "msaa": 8}));
+
+        assert_eq!(
+            option_changes(&applied, &read),
+            options(json!({"points": 500, "sparks": null, "msaa": 8}))
+        );
+        assert!(option_changes(&read, &read).is_empty());
+    }
+
+    #[test]
+    fn changes_of_edited_file() {
+        let path = std::env::temp_dir().join(format!("config-{}.toml", std::process::id()));
+        fs::write(&path, "points = 300\nsparks = true\nmsaa = 4\n").unwrap();
+        let args: Vec<OsString> = ["connecting-dots-rs", "--msaa", "2"]
+            .iter()
+            .map(OsString::from)
+            .collect();
+        let mut file = ConfigFile::open(&path, &args).unwrap();
+        let mut changes = |contents: &str| {
+            fs::write(&path, contents).unwrap();
+            file.checked -= POLL_INTERVAL;
+            let changes = file.changes();
+            file.apply();
+            changes
+        };
+
+        let edited = changes("points = 500\nmsaa = 8\n");
+        let unchanged = changes("points = 500\nmsaa = 8\n");
+        fs::remove_file(&path).unwrap();
+
+        // --msaa was given on the command line, so the file's is left out.
+        assert_eq!(
+            edited,
+            Some(options(json!({"points": 500, "sparks": null})))
+        );
+        assert_eq!(unchanged, None);
+    }
+}
@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::renderer::Renderer;
+use crate::simulation::Simulation;
+
+/// The visualization as a part of another program's frames: a [`Simulation`] and a
+/// [`Renderer`] drawing it into texture views the program hands over, on its own device.
+/// The program decides when frames are drawn, what intensity they show and where they end
+/// up, so there is no window, surface or audio capture involved.
+pub struct ConnectingDots {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    simulation: Simulation,
+    renderer: Renderer,
+}
+
+impl ConnectingDots {
+    /// Sets up drawing with `config` into texture views of `format`, `width` by `height`
+    /// pixels, on `device`.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        config: &Config,
+    ) -> anyhow::Result<Self> {
+        let simulation = Simulation::new(device, queue, width, height, config)?;
+        let renderer = Renderer::new(device, queue, format, width, height, config, &simulation)?;
+
+        Ok(Self {
+            device: device.clone(),
+            queue: queue.clone(),
+            simulation,
+            renderer,
+        })
+    }
+
+    /// Advances the visualization by `dt` at `intensity`, from 0 to 1, and draws the frame
+    /// into `view`, which has to be of the format and size it was set up with. The commands
+    /// are submitted to the queue before returning, so anything submitted afterwards sees
+    /// the frame.
+    pub fn render_to_texture(&mut self, view: &wgpu::TextureView, dt: Duration, intensity: f32) {
+        let intensity = intensity.clamp(0.0, 1.0);
+        self.simulation.update(dt, intensity);
+        self.renderer.update(&self.simulation, dt, intensity);
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Connecting Dots Encoder"),
+            });
+        self.simulation.encode(&mut encoder);
+        self.renderer.encode(&self.simulation, &mut encoder, view);
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Makes the frames `width` by `height` pixels, stretching the field of points along.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.simulation.resize(width, height);
+        self.renderer.resize(width, height);
+    }
+
+    /// The points and the forces moving them, e.g. to move the cursor force with the
+    /// program's own input.
+    pub fn simulation(&mut self) -> &mut Simulation {
+        &mut self.simulation
+    }
+
+    /// What draws the points, e.g. to show a notice on top of them.
+    pub fn renderer(&mut self) -> &mut Renderer {
+        &mut self.renderer
+    }
+}
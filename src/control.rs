@@ -0,0 +1,877 @@
+#[cfg(unix)]
+use std::{
+    env, fs,
+    io::{BufRead, BufReader, Write},
+    os::unix::{
+        fs::{DirBuilderExt, MetadataExt},
+        net::{UnixListener, UnixStream},
+    },
+    thread,
+};
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+use anyhow::Context;
+use anyhow::{anyhow, bail};
+#[cfg(unix)]
+use clap::Subcommand;
+use clap::{Arg, ValueEnum};
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    color::Color,
+    config::{Config, Preset},
+    config_file::ConfigFile,
+    state::State,
+};
+
+/// Name of the control socket in the runtime directory.
+#[cfg(unix)]
+const SOCKET_NAME: &str = "connecting-dots.sock";
+
+/// A command for a running instance, sent over the control socket as one line of JSON like
+/// `{"command": "set", "options": {"points": 300}}`.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum Request {
+    /// Changes options as if they had been given on the command line instead. `true` turns
+    /// a flag on, `false` or `null` drops the option again and an array gives a repeatable
+    /// option once per item. Window options, like --fullscreen, only apply at startup.
+    Set {
+        options: Map<String, Value>,
+    },
+    Pause,
+    Resume,
+    TogglePause,
+    /// Switches to the preset after the current one.
+    NextPreset,
+    /// Switches to the named preset of the config file, or to the one after the current one
+    /// when no name is given.
+    SwitchPreset {
+        #[serde(default)]
+        name: Option<String>,
+    },
+    /// Briefly drives the intensity up to its maximum.
+    Boost,
+    /// Asks for the command line the instance runs with now.
+    Options,
+    /// Writes the velocity field of the current frame as a .flo image to `path`, or to the
+    /// path of --export-flow when none is given.
+    ExportFlow {
+        #[serde(default)]
+        path: Option<PathBuf>,
+    },
+}
+
+/// The answer to a request, also one line of JSON.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<Vec<String>>,
+}
+
+/// Commands of `connecting-dots ctl`.
+#[cfg(unix)]
+#[derive(Subcommand, Debug)]
+pub enum CtlCommand {
+    /// Change options of the running instance, like points=300 or oled=true
+    Set {
+        #[arg(value_name = "OPTION[=VALUE]", required = true)]
+        options: Vec<String>,
+    },
+    /// Pause the running instance
+    Pause,
+    /// Resume the running instance
+    Resume,
+    /// Pause the running instance, or resume it when paused
+    TogglePause,
+    /// Switch the running instance to the next preset
+    NextPreset,
+    /// Switch the running instance to a named preset of its config file, or the next one
+    SwitchPreset { name: Option<String> },
+    /// Briefly drive the intensity of the running instance up to its maximum
+    Boost,
+    /// Print the options the running instance uses
+    Options,
+    /// Write the velocity field of the running instance as a .flo image, to PATH or the path
+    /// of its --export-flow
+    ExportFlow { path: Option<PathBuf> },
+}
+
+#[cfg(unix)]
+impl CtlCommand {
+    fn into_request(self) -> Request {
+        match self {
+            CtlCommand::Set { options } => Request::Set {
+                options: options
+                    .iter()
+                    .map(|option| {
+                        let option = option.trim_start_matches('-');
+                        match option.split_once('=') {
+                            // Numbers and booleans are sent as such, anything else as a string.
+                            Some((name, value)) => (
+                                name.to_owned(),
+                                serde_json::from_str(value)
+                                    .unwrap_or_else(|_| Value::String(value.to_owned())),
+                            ),
+                            None => (option.to_owned(), Value::Bool(true)),
+                        }
+                    })
+                    .collect(),
+            },
+            CtlCommand::Pause => Request::Pause,
+            CtlCommand::Resume => Request::Resume,
+            CtlCommand::TogglePause => Request::TogglePause,
+            CtlCommand::NextPreset => Request::NextPreset,
+            CtlCommand::SwitchPreset { name } => Request::SwitchPreset { name },
+            CtlCommand::Boost => Request::Boost,
+            CtlCommand::Options => Request::Options,
+            // The instance may run in another directory.
+            CtlCommand::ExportFlow { path } => Request::ExportFlow {
+                path: path.map(|path| std::path::absolute(&path).unwrap_or(path)),
+            },
+        }
+    }
+}
+
+/// Sends a command to the running instance and prints its answer.
+#[cfg(unix)]
+pub fn ctl(command: CtlCommand) -> anyhow::Result<()> {
+    let response = send(&command.into_request())?;
+
+    if let Some(error) = response.error {
+        bail!(error);
+    }
+    for option in response.options.unwrap_or_default() {
+        println!("{option}");
+    }
+
+    Ok(())
+}
+
+/// Sends a request to the running instance and waits for its response.
+#[cfg(unix)]
+pub fn send(request: &Request) -> anyhow::Result<Response> {
+    let path = socket_path();
+    let mut stream = UnixStream::connect(&path).with_context(|| {
+        format!(
+            "Unable to connect to {}, is connecting-dots running?",
+            path.display()
+        )
+    })?;
+
+    serde_json::to_writer(&mut stream, request)?;
+    stream.write_all(b"\n")?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+
+    Ok(serde_json::from_str(&line)?)
+}
+
+/// `$XDG_RUNTIME_DIR/connecting-dots.sock`, or else the same in a directory of the user's
+/// own in the temporary directory, which other users share.
+#[cfg(unix)]
+pub fn socket_path() -> PathBuf {
+    match env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join(SOCKET_NAME),
+        None => env::temp_dir()
+            .join(format!("connecting-dots-{}", uid()))
+            .join(SOCKET_NAME),
+    }
+}
+
+#[cfg(unix)]
+fn uid() -> u32 {
+    // getuid cannot fail.
+    unsafe { libc::getuid() }
+}
+
+/// Creates `dir` for the socket when it is missing, making sure only the user can get into
+/// it so no one else can send requests or listen in their place.
+#[cfg(unix)]
+fn create_private_dir(dir: &Path) -> anyhow::Result<()> {
+    match fs::DirBuilder::new().mode(0o700).create(dir) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(e).with_context(|| format!("Unable to create {}", dir.display())),
+    }
+
+    let metadata = fs::symlink_metadata(dir)?;
+    if !metadata.is_dir() || metadata.uid() != uid() || metadata.mode() & 0o077 != 0 {
+        bail!(
+            "{} is not a directory only the user can get into, set XDG_RUNTIME_DIR to one",
+            dir.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// A request waiting for the frame loop, with where to send the response.
+type Pending = (Request, Option<mpsc::Sender<Response>>);
+
+/// Hands requests to the frame loop from elsewhere, like global hotkeys.
+#[derive(Clone)]
+pub struct RequestSender(mpsc::Sender<Pending>);
+
+impl RequestSender {
+    pub fn send(&self, request: Request) {
+        let _ = self.0.send((request, None));
+    }
+}
+
+/// Applies requests from the control socket and hotkeys to the running instance between
+/// frames.
+pub struct Controller {
+    sender: mpsc::Sender<Pending>,
+    receiver: mpsc::Receiver<Pending>,
+    #[cfg(unix)]
+    path: Option<PathBuf>,
+    /// Command line the current config was parsed from, with the preset not yet expanded.
+    args: Vec<OsString>,
+    config_file: Option<ConfigFile>,
+    /// Named preset of the config file in effect.
+    preset: Option<String>,
+    transition: Option<Transition>,
+    paused: bool,
+    /// Whether applying the last requests started the state over.
+    restarted: bool,
+}
+
+impl Controller {
+    pub fn new(args: Vec<OsString>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        Self {
+            sender,
+            receiver,
+            #[cfg(unix)]
+            path: None,
+            args,
+            config_file: None,
+            preset: None,
+            transition: None,
+            paused: false,
+            restarted: false,
+        }
+    }
+
+    /// Takes requests for an instance started with the command line `args`, which starts
+    /// with the program name, on the control socket and from changes to the config file of
+    /// `config`. What cannot be opened is only warned about.
+    pub fn start(args: Vec<OsString>, config: &Config) -> Self {
+        #[allow(unused_mut)]
+        let mut controller = Self::new(args);
+        #[cfg(unix)]
+        if let Err(e) = controller.listen() {
+            log::warn!("Unable to open the control socket: {e}");
+        }
+        if let Some(path) = &config.config_file
+            && let Err(e) = controller.watch(path)
+        {
+            log::warn!("Unable to watch the config file: {e}");
+        }
+
+        controller
+    }
+
+    /// Takes the options in the config file at `path` and applies changes to it from now on.
+    pub fn watch(&mut self, path: &Path) -> anyhow::Result<()> {
+        let config_file = ConfigFile::open(path, &self.args)?;
+        self.args = config_file.args(&self.args)?;
+        info!("Watching {} for changes", path.display());
+        self.config_file = Some(config_file);
+
+        Ok(())
+    }
+
+    pub fn sender(&self) -> RequestSender {
+        RequestSender(self.sender.clone())
+    }
+
+    /// Starts taking requests on the control socket.
+    #[cfg(unix)]
+    pub fn listen(&mut self) -> anyhow::Result<()> {
+        let path = socket_path();
+        if env::var_os("XDG_RUNTIME_DIR").is_none()
+            && let Some(dir) = path.parent()
+        {
+            create_private_dir(dir)?;
+        }
+        if UnixStream::connect(&path).is_ok() {
+            bail!("{} is in use by another instance", path.display());
+        }
+        // Left behind by an instance that did not exit cleanly.
+        let _ = fs::remove_file(&path);
+
+        let listener = UnixListener::bind(&path)
+            .with_context(|| format!("Unable to listen on {}", path.display()))?;
+        let sender = self.sender.clone();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Err(e) = serve(stream, &sender) {
+                    log::warn!("Unable to answer a control request: {e}");
+                }
+            }
+        });
+
+        info!("Listening for commands on {}", path.display());
+        self.path = Some(path);
+
+        Ok(())
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Applies the requests that came in since the last call, and changes to the config
+    /// file. Returns true when changing options started the state over with the new config.
+    pub fn poll(&mut self, state: &mut Option<State>, config: &mut Config) -> bool {
+        self.restarted = false;
+
+        while let Ok((request, reply)) = self.receiver.try_recv() {
+            let response = match self.handle(request, state, config) {
+                Ok(options) => Response {
+                    ok: true,
+                    error: None,
+                    options,
+                },
+                Err(e) => Response {
+                    ok: false,
+                    error: Some(e.to_string()),
+                    options: None,
+                },
+            };
+            if let Some(reply) = reply {
+                let _ = reply.send(response);
+            }
+        }
+
+        // The frame loop stops while paused, so exports asked for then are finished here.
+        if let Some(state) = state {
+            state.finish_flow_exports();
+        }
+
+        if let Some(transition) = &self.transition {
+            let options = transition.options();
+            if transition.is_done() {
+                self.transition = None;
+            }
+            if let Err(e) = self.apply(&options, state, config) {
+                log::error!("Unable to switch presets: {e}");
+                self.transition = None;
+            }
+        }
+
+        if let Some(mut changes) = self.config_file.as_mut().and_then(ConfigFile::changes) {
+            // The named preset in effect wins over the rest of the file.
+            if let (Some(config_file), Some(preset)) = (&self.config_file, &self.preset)
+                && let Some(preset) = config_file.preset(preset)
+            {
+                changes.retain(|name, _| !preset.contains_key(name));
+            }
+            match self.set(&changes, state, config) {
+                Ok(()) => {
+                    if let Some(config_file) = &mut self.config_file {
+                        config_file.apply();
+                    }
+                }
+                Err(e) => log::error!("Unable to apply the changed config file: {e}"),
+            }
+        }
+
+        self.restarted
+    }
+
+    fn handle(
+        &mut self,
+        request: Request,
+        state: &mut Option<State>,
+        config: &mut Config,
+    ) -> anyhow::Result<Option<Vec<String>>> {
+        match request {
+            Request::Set { options } => self.set(&options, state, config)?,
+            Request::Pause => self.paused = true,
+            Request::Resume => self.paused = false,
+            Request::TogglePause => self.paused = !self.paused,
+            Request::NextPreset => {
+                let preset = Preset::next(config.preset);
+                let name = preset
+                    .to_possible_value()
+                    .map(|value| value.get_name().to_owned())
+                    .ok_or_else(|| anyhow!("The preset {preset:?} has no name"))?;
+                let options = Map::from_iter([("preset".to_owned(), Value::String(name))]);
+                self.set(&options, state, config)?;
+            }
+            Request::SwitchPreset { name } => self.switch_preset(name, state, config)?,
+            Request::Boost => {
+                if let Some(state) = state {
+                    state.boost_intensity();
+                }
+            }
+            Request::ExportFlow { path } => {
+                let Some(state) = state else {
+                    bail!("Nothing is drawn yet");
+                };
+                state.export_flow(path)?;
+            }
+            Request::Options => {
+                return Ok(Some(
+                    self.args
+                        .iter()
+                        .skip(1)
+                        .map(|arg| arg.to_string_lossy().into_owned())
+                        .collect(),
+                ));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn set(
+        &mut self,
+        options: &Map<String, Value>,
+        state: &mut Option<State>,
+        config: &mut Config,
+    ) -> anyhow::Result<()> {
+        self.apply(options, state, config)?;
+
+        info!(
+            "Options changed to: {}",
+            self.args
+                .iter()
+                .skip(1)
+                .map(|arg| arg.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+
+        Ok(())
+    }
+
+    /// Switches to the named preset of the config file, or to the one after the current one.
+    /// Numbers and colors that can change in place glide over to the preset's, anything else
+    /// switches right away.
+    fn switch_preset(
+        &mut self,
+        name: Option<String>,
+        state: &mut Option<State>,
+        config: &mut Config,
+    ) -> anyhow::Result<()> {
+        let config_file = self
+            .config_file
+            .as_ref()
+            .ok_or_else(|| anyhow!("There is no config file to take named presets from"))?;
+        let name = match name {
+            Some(name) => name,
+            None => {
+                let names: Vec<_> = config_file.preset_names().collect();
+                let next = self
+                    .preset
+                    .as_deref()
+                    .and_then(|preset| names.iter().position(|name| *name == preset))
+                    .map_or(0, |index| (index + 1) % names.len().max(1));
+                names
+                    .get(next)
+                    .ok_or_else(|| anyhow!("The config file has no named presets"))?
+                    .to_string()
+            }
+        };
+
+        let mut from = Map::new();
+        let mut to = Map::new();
+        let mut now = Map::new();
+        for (option, value) in config_file.preset_switch(self.preset.as_deref(), &name)? {
+            let current = config_file.value(self.preset.as_deref(), &option);
+            if current == Some(&value) {
+                continue;
+            }
+            match current {
+                Some(current)
+                    if interpolate(current, &value, 0.0).is_some()
+                        && self.changes_in_place(&option, &value, config) =>
+                {
+                    from.insert(option.clone(), current.clone());
+                    to.insert(option, value);
+                }
+                _ => {
+                    now.insert(option, value);
+                }
+            }
+        }
+
+        if !now.is_empty() {
+            self.apply(&now, state, config)?;
+        }
+        info!("Switching to the preset {name}");
+        self.preset = Some(name);
+        self.transition = Some(Transition {
+            from,
+            to,
+            started: Instant::now(),
+            duration: Duration::from_secs_f32(config.preset_transition.max(0.0)),
+        });
+
+        Ok(())
+    }
+
+    /// Whether setting the option `name` to `value` can change the state in place.
+    fn changes_in_place(&self, name: &str, value: &Value, config: &Config) -> bool {
+        let options = Map::from_iter([(name.to_owned(), value.clone())]);
+        override_args(&self.args, &options)
+            .ok()
+            .and_then(|args| Config::try_parse_from(args).ok())
+            .is_some_and(|new_config| State::can_reconfigure(config, &new_config))
+    }
+
+    /// Changes `options` as if they had been given on the command line instead.
+    fn apply(
+        &mut self,
+        options: &Map<String, Value>,
+        state: &mut Option<State>,
+        config: &mut Config,
+    ) -> anyhow::Result<()> {
+        let args = override_args(&self.args, options)?;
+        let new_config = Config::try_parse_from(args.clone())?;
+
+        // Options that only feed uniforms change in place, anything else starts over.
+        let reconfigured = state
+            .as_mut()
+            .is_some_and(|state| state.reconfigure(config, &new_config));
+        if !reconfigured {
+            State::restart(state, &new_config, config)?;
+            self.restarted = true;
+        }
+
+        *config = new_config;
+        self.args = args;
+
+        Ok(())
+    }
+}
+
+/// Options gliding from one named preset over to the next.
+struct Transition {
+    from: Map<String, Value>,
+    to: Map<String, Value>,
+    started: Instant,
+    duration: Duration,
+}
+
+impl Transition {
+    fn progress(&self) -> f32 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+        (self.started.elapsed().as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+    }
+
+    fn is_done(&self) -> bool {
+        self.progress() >= 1.0
+    }
+
+    /// The options on their way over, easing in and out.
+    fn options(&self) -> Map<String, Value> {
+        let t = self.progress();
+        let t = t * t * (3.0 - 2.0 * t);
+
+        self.to
+            .iter()
+            .map(|(name, to)| {
+                let value = self
+                    .from
+                    .get(name)
+                    .and_then(|from| interpolate(from, to, t))
+                    .unwrap_or_else(|| to.clone());
+                (name.clone(), value)
+            })
+            .collect()
+    }
+}
+
+/// The value `t` of the way from `from` to `to`, for numbers and colors.
+fn interpolate(from: &Value, to: &Value, t: f32) -> Option<Value> {
+    match (from, to) {
+        (Value::Number(from), Value::Number(to)) => {
+            let value = from.as_f64()? + (to.as_f64()? - from.as_f64()?) * t as f64;
+            if from.is_f64() || to.is_f64() {
+                Some(Value::from(value))
+            } else {
+                Some(Value::from(value.round() as i64))
+            }
+        }
+        (Value::String(from), Value::String(to)) => {
+            let from: Color = from.parse().ok()?;
+            let to: Color = to.parse().ok()?;
+            Some(Value::String(from.lerp(to, t).to_string()))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Controller {
+    fn drop(&mut self) {
+        if let Some(path) = &self.path {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Reads one request from `stream`, waits for the frame loop to apply it and writes back
+/// the response.
+#[cfg(unix)]
+fn serve(stream: UnixStream, sender: &mpsc::Sender<Pending>) -> anyhow::Result<()> {
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+
+    let response = match serde_json::from_str(&line) {
+        Ok(request) => {
+            let (reply, response) = mpsc::channel();
+            sender
+                .send((request, Some(reply)))
+                .map_err(|_| anyhow!("The frame loop has stopped"))?;
+            response.recv()?
+        }
+        Err(e) => Response {
+            ok: false,
+            error: Some(format!("Invalid request: {e}")),
+            options: None,
+        },
+    };
+
+    let mut stream = &stream;
+    serde_json::to_writer(&mut stream, &response)?;
+    stream.write_all(b"\n")?;
+
+    Ok(())
+}
+
+/// Rewrites the command line `args` so `options` replace whatever was given for them.
+pub(crate) fn override_args(
+    args: &[OsString],
+    options: &Map<String, Value>,
+) -> anyhow::Result<Vec<OsString>> {
+    let mut command = Config::command();
+    command.build();
+    let mut args = args.to_vec();
+
+    for (name, value) in options {
+        let name = name.trim_start_matches('-');
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(name))
+            .ok_or_else(|| anyhow!("Unknown option --{name}"))?;
+        let takes_value = arg.get_action().takes_values();
+        let value_optional = arg
+            .get_num_args()
+            .is_some_and(|num_args| num_args.min_values() == 0);
+
+        remove_arg(&mut args, arg, takes_value);
+
+        let values = match value {
+            Value::Array(values) => values.iter().collect(),
+            Value::Null => Vec::new(),
+            value => vec![value],
+        };
+        for value in values {
+            match value {
+                Value::Bool(true) if !takes_value || value_optional => {
+                    args.push(format!("--{name}").into())
+                }
+                Value::Bool(false) if !takes_value => {}
+                Value::String(value) => args.push(format!("--{name}={value}").into()),
+                value => args.push(format!("--{name}={value}").into()),
+            }
+        }
+    }
+
+    Ok(args)
+}
+
+/// Drops every occurrence of `arg` from `args`, along with its value.
+fn remove_arg(args: &mut Vec<OsString>, arg: &Arg, takes_value: bool) {
+    let long = arg.get_long().map(|long| format!("--{long}"));
+    let short = arg.get_short().map(|short| format!("-{short}"));
+
+    let mut remaining = args.drain(..).peekable();
+    // The program name.
+    let mut kept: Vec<OsString> = remaining.next().into_iter().collect();
+
+    while let Some(current) = remaining.next() {
+        let current_str = current.to_string_lossy();
+
+        if long.as_deref() == Some(&*current_str) || short.as_deref() == Some(&*current_str) {
+            if takes_value
+                && remaining
+                    .peek()
+                    .is_some_and(|next| !next.to_string_lossy().starts_with("--"))
+            {
+                remaining.next();
+            }
+            continue;
+        }
+
+        let joined = long
+            .as_ref()
+            .is_some_and(|long| current_str.starts_with(&format!("{long}=")))
+            || short.as_ref().is_some_and(|short| {
+                !current_str.starts_with("--") && current_str.starts_with(short.as_str())
+            });
+        if takes_value && joined {
+            continue;
+        }
+
+        kept.push(current);
+    }
+
+    drop(remaining);
+    *args = kept;
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        ["connecting-dots-rs"]
+            .iter()
+            .chain(args)
+            .map(OsString::from)
+            .collect()
+    }
+
+    fn override_with(command_line: &[&str], options: Value) -> Vec<OsString> {
+        let Value::Object(options) = options else {
+            panic!("options must be an object");
+        };
+        override_args(&args(command_line), &options).unwrap()
+    }
+
+    #[test]
+    fn override_replaces_every_form() {
+        assert_eq!(
+            override_with(
+                &["--points", "100", "--class", "x", "--points=200"],
+                json!({"points": 300})
+            ),
+            args(&["--class", "x", "--points=300"])
+        );
+        assert_eq!(
+            override_with(&["--points", "100"], json!({"--points": 300})),
+            args(&["--points=300"])
+        );
+    }
+
+    #[test]
+    fn override_short_flags() {
+        assert_eq!(
+            override_with(
+                &["-c", "foo", "-q", "-b/image.png", "--points", "5"],
+                json!({"class": "bar", "quiet": false, "background-image": null})
+            ),
+            args(&["--points", "5", "--class=bar"])
+        );
+    }
+
+    #[test]
+    fn override_flags() {
+        assert_eq!(
+            override_with(&[], json!({"sparks": true})),
+            args(&["--sparks"])
+        );
+        assert_eq!(
+            override_with(&["--sparks", "--points", "5"], json!({"sparks": false})),
+            args(&["--points", "5"])
+        );
+    }
+
+    #[test]
+    fn override_optional_values() {
+        assert_eq!(
+            override_with(&["--fullscreen", "DP-2"], json!({"fullscreen": true})),
+            args(&["--fullscreen"])
+        );
+        assert_eq!(
+            override_with(
+                &["--fullscreen", "--points=5"],
+                json!({"fullscreen": "DP-1"})
+            ),
+            args(&["--points=5", "--fullscreen=DP-1"])
+        );
+    }
+
+    #[test]
+    fn override_null_and_arrays() {
+        assert_eq!(
+            override_with(&["--points", "5", "--sparks"], json!({"points": null})),
+            args(&["--sparks"])
+        );
+        assert_eq!(
+            override_with(
+                &["--layer", "count=5", "--layer=count=6"],
+                json!({"layer": ["count=10", "count=20"], "connection-distance": 80.5})
+            ),
+            args(&[
+                "--connection-distance=80.5",
+                "--layer=count=10",
+                "--layer=count=20"
+            ])
+        );
+    }
+
+    #[test]
+    fn override_unknown_option() {
+        let Value::Object(options) = json!({"no-such-option": 1}) else {
+            unreachable!()
+        };
+        assert!(override_args(&args(&[]), &options).is_err());
+    }
+
+    #[test]
+    fn remove_flag_keeps_next_arg() {
+        let mut command = Config::command();
+        command.build();
+        let quiet = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some("quiet"))
+            .unwrap();
+
+        let mut command_line = args(&["-q", "foo", "--quiet"]);
+        remove_arg(&mut command_line, quiet, false);
+        assert_eq!(command_line, args(&["foo"]));
+    }
+
+    #[test]
+    fn interpolate_numbers_and_colors() {
+        assert_eq!(
+            interpolate(&json!(100), &json!(200), 0.25),
+            Some(json!(125))
+        );
+        assert_eq!(interpolate(&json!(1.0), &json!(2.0), 0.5), Some(json!(1.5)));
+        assert_eq!(interpolate(&json!(1), &json!(2.0), 0.5), Some(json!(1.5)));
+        assert_eq!(
+            interpolate(&json!("#000000"), &json!("#ffffff"), 0.5),
+            Some(json!("#808080"))
+        );
+        assert_eq!(interpolate(&json!("bloom"), &json!("fxaa"), 0.5), None);
+        assert_eq!(interpolate(&json!(true), &json!(false), 0.5), None);
+        assert_eq!(interpolate(&json!(1), &json!("#ffffff"), 0.5), None);
+    }
+}
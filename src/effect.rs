@@ -0,0 +1,54 @@
+/// What every effect is updated with before a frame is drawn.
+#[derive(Debug, Clone, Copy)]
+pub struct EffectUniforms {
+    /// Seconds the simulation has advanced by, following the time scale.
+    pub time: f32,
+    /// Seconds of real time since the previous frame.
+    pub delta_time: f32,
+    /// Intensity of the music from 0 to 1, as the points show it.
+    pub intensity: f32,
+    /// Size in pixels of the frame.
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Where in the frame an effect is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EffectPlacement {
+    /// On top of the points, sparks and overlay, before any post-processing.
+    Layer,
+    /// After the built-in post-processing effects, in the order they were added.
+    PostProcess,
+}
+
+/// An effect drawn with its own pipelines, e.g. an overlay of a game or a fullscreen shader.
+/// It is set up once for the format and size of the frames, updated every frame and then
+/// encodes its passes into the frame's command encoder.
+pub trait Effect {
+    /// Creates the pipelines and buffers for frames of `format`, `width` by `height` pixels.
+    fn setup(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> anyhow::Result<()>;
+
+    /// Recreates what depends on the size when the frames become `width` by `height` pixels.
+    fn resize(&mut self, _device: &wgpu::Device, _width: u32, _height: u32) {}
+
+    /// Writes the uniforms for the next frame.
+    fn update(&mut self, _queue: &wgpu::Queue, _uniforms: &EffectUniforms) {}
+
+    /// Records the passes drawing the effect into `output`, a single sampled view of the
+    /// format it was set up with. Post-processing effects sample the frame so far from
+    /// `input` and replace `output`, layers get no input and have to load what `output`
+    /// already holds.
+    fn encode(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        input: Option<&wgpu::TextureView>,
+        output: &wgpu::TextureView,
+    );
+}
@@ -0,0 +1,114 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Result;
+
+use crate::simulation::Point;
+
+/// Magic number at the start of every Middlebury .flo file ("PIEH" in ASCII).
+const FLO_TAG: f32 = 202021.25;
+
+/// Times the periodic exports of the velocity field to a path.
+pub struct FlowExporter {
+    path: PathBuf,
+    interval: Duration,
+    elapsed: Duration,
+}
+
+impl FlowExporter {
+    pub fn new(path: PathBuf, interval: Duration) -> Self {
+        Self {
+            path,
+            interval,
+            // Export on the first frame so the file exists right away.
+            elapsed: interval,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Advances the export timer, returning true when an export is due.
+    pub fn tick(&mut self, delta_time: Duration) -> bool {
+        self.elapsed += delta_time;
+
+        if self.elapsed >= self.interval {
+            self.elapsed = Duration::ZERO;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Samples the velocities of `points` onto a grid of `cell_size` pixels and writes them to
+/// `path` as a Middlebury .flo optical flow image, for reuse in compositing tools.
+pub fn export(
+    path: &Path,
+    points: &[Point],
+    width: u32,
+    height: u32,
+    cell_size: u32,
+) -> Result<()> {
+    let cell_size = cell_size.max(1);
+    let columns = width.div_ceil(cell_size).max(1);
+    let rows = height.div_ceil(cell_size).max(1);
+
+    let field = sample_velocity_field(points, columns, rows, cell_size);
+
+    // Write to a temporary file first so readers never observe a partial image.
+    let temporary_path = path.with_extension("flo.tmp");
+    {
+        let mut writer = BufWriter::new(File::create(&temporary_path)?);
+        writer.write_all(&FLO_TAG.to_le_bytes())?;
+        writer.write_all(&(columns as i32).to_le_bytes())?;
+        writer.write_all(&(rows as i32).to_le_bytes())?;
+        for velocity in field {
+            writer.write_all(&velocity[0].to_le_bytes())?;
+            writer.write_all(&velocity[1].to_le_bytes())?;
+        }
+        writer.flush()?;
+    }
+    std::fs::rename(temporary_path, path)?;
+
+    Ok(())
+}
+
+/// Averages the velocity (in pixels per second) of the points inside each grid cell.
+/// Cells without any points have zero flow.
+fn sample_velocity_field(
+    points: &[Point],
+    columns: u32,
+    rows: u32,
+    cell_size: u32,
+) -> Vec<[f32; 2]> {
+    let cell_count = (columns * rows) as usize;
+    let mut sums = vec![[0f32; 2]; cell_count];
+    let mut counts = vec![0u32; cell_count];
+
+    for point in points {
+        let column = (point.position[0] as u32 / cell_size).min(columns - 1);
+        let row = (point.position[1] as u32 / cell_size).min(rows - 1);
+        let index = (row * columns + column) as usize;
+
+        sums[index][0] += point.velocity[0];
+        sums[index][1] += point.velocity[1];
+        counts[index] += 1;
+    }
+
+    sums.iter()
+        .zip(counts)
+        .map(|(sum, count)| {
+            if count == 0 {
+                [0.0, 0.0]
+            } else {
+                [sum[0] / count as f32, sum[1] / count as f32]
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,279 @@
+use std::path::Path;
+
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont, point};
+use anyhow::{Result, anyhow};
+use bytemuck::{Pod, Zeroable};
+use image::GrayImage;
+use rand::prelude::*;
+use wgpu::util::DeviceExt;
+
+/// Mask pixels at least this bright are part of the shape.
+const MASK_THRESHOLD: u8 = 128;
+/// Height in pixels of the text rasterized for text masks. Only the shape matters, as it is
+/// scaled to the window.
+const TEXT_SIZE: f32 = 128.0;
+
+pub struct FormationOptions {
+    /// How strongly the points are pulled toward the shape.
+    pub strength: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct FormationUniform {
+    /// Width divided by height of the shape.
+    aspect: f32,
+    strength: f32,
+}
+
+/// Pulls every point toward its own target inside a shape, so the swarm forms a logo or
+/// other shape. The louder the music, the tighter the points snap to their targets.
+///
+/// The targets are picked once on the CPU from the pixels of a mask and stored relative to
+/// the shape, which the compute pass fits into the window.
+pub struct Formation {
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    points_count: usize,
+}
+
+impl Formation {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &wgpu::Device,
+        points_buffer: &wgpu::Buffer,
+        points_count: usize,
+        window_size_buffer: &wgpu::Buffer,
+        delta_time_buffer: &wgpu::Buffer,
+        intensity_buffer: &wgpu::Buffer,
+        mask: &GrayImage,
+        rng: &mut impl Rng,
+        options: FormationOptions,
+    ) -> Result<Self> {
+        let targets = Self::pick_targets(mask, points_count, rng)?;
+
+        let targets_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Formation Targets Buffer"),
+            contents: bytemuck::cast_slice(&targets),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        let formation = FormationUniform {
+            aspect: mask.width() as f32 / mask.height() as f32,
+            strength: options.strength,
+        };
+
+        let formation_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Formation Buffer"),
+            contents: bytemuck::bytes_of(&formation),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let storage_entry = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let uniform_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Formation Bind Group Layout"),
+            entries: &[
+                storage_entry(0, false),
+                storage_entry(1, true),
+                uniform_entry(2),
+                uniform_entry(3),
+                uniform_entry(4),
+                uniform_entry(5),
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Formation Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: points_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: targets_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: window_size_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: delta_time_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: intensity_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: formation_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Formation Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/formation.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Formation Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Formation Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        Ok(Self {
+            pipeline,
+            bind_group,
+            points_count,
+        })
+    }
+
+    /// Loads a mask image. Bright pixels form the shape, or dark ones when `invert` is set.
+    /// Transparent pixels are never part of the shape.
+    pub fn load_mask(path: &Path, invert: bool) -> Result<GrayImage> {
+        let image = image::open(path)
+            .map_err(|e| anyhow!("Failed to load mask {}: {e}", path.display()))?
+            .into_luma_alpha8();
+
+        Ok(GrayImage::from_fn(image.width(), image.height(), |x, y| {
+            let [luma, alpha] = image.get_pixel(x, y).0;
+            let value = if invert { u8::MAX - luma } else { luma };
+            image::Luma([(value as u16 * alpha as u16 / u8::MAX as u16) as u8])
+        }))
+    }
+
+    /// Rasterizes `text` into a mask, one line per line of text. The glyphs form the shape,
+    /// or the space around them when `invert` is set.
+    pub fn text_mask(text: &str, invert: bool) -> Result<GrayImage> {
+        let font = FontRef::try_from_slice(epaint_default_fonts::HACK_REGULAR)?;
+        let scaled_font = font.as_scaled(PxScale::from(TEXT_SIZE));
+
+        let lines: Vec<&str> = text.lines().collect();
+        let line_width = |line: &str| -> f32 {
+            line.chars()
+                .map(|c| scaled_font.h_advance(font.glyph_id(c)))
+                .sum()
+        };
+        let width = lines
+            .iter()
+            .map(|line| line_width(line))
+            .fold(0.0, f32::max);
+        let height = scaled_font.height() * lines.len() as f32;
+
+        if width < 1.0 {
+            return Err(anyhow!("The formation text is empty"));
+        }
+
+        let mut mask = GrayImage::new(width.ceil() as u32, height.ceil() as u32);
+
+        for (row, line) in lines.iter().enumerate() {
+            // Center every line.
+            let mut x = (width - line_width(line)) * 0.5;
+            let baseline = scaled_font.height() * row as f32 + scaled_font.ascent();
+
+            for c in line.chars() {
+                let glyph = scaled_font.scaled_glyph(c);
+                let advance = scaled_font.h_advance(glyph.id);
+                let glyph = glyph
+                    .id
+                    .with_scale_and_position(TEXT_SIZE, point(x, baseline));
+                x += advance;
+
+                let Some(outline) = font.outline_glyph(glyph) else {
+                    continue;
+                };
+
+                let bounds = outline.px_bounds();
+                outline.draw(|gx, gy, coverage| {
+                    let px = bounds.min.x as i32 + gx as i32;
+                    let py = bounds.min.y as i32 + gy as i32;
+                    if px >= 0
+                        && py >= 0
+                        && (px as u32) < mask.width()
+                        && (py as u32) < mask.height()
+                    {
+                        mask.put_pixel(
+                            px as u32,
+                            py as u32,
+                            image::Luma([(coverage * 255.0) as u8]),
+                        );
+                    }
+                });
+            }
+        }
+
+        if invert {
+            image::imageops::invert(&mut mask);
+        }
+
+        Ok(mask)
+    }
+
+    /// Picks a random spot inside the shape for every point, relative to the mask size.
+    fn pick_targets(
+        mask: &GrayImage,
+        points_count: usize,
+        rng: &mut impl Rng,
+    ) -> Result<Vec<[f32; 2]>> {
+        let pixels: Vec<(u32, u32)> = mask
+            .enumerate_pixels()
+            .filter(|(_, _, pixel)| pixel.0[0] >= MASK_THRESHOLD)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+
+        if pixels.is_empty() {
+            return Err(anyhow!("The formation mask has no pixels to form"));
+        }
+
+        let size = [mask.width() as f32, mask.height() as f32];
+
+        Ok((0..points_count)
+            .map(|_| {
+                let (x, y) = pixels[rng.random_range(0..pixels.len())];
+                [
+                    (x as f32 + rng.random::<f32>()) / size[0],
+                    (y as f32 + rng.random::<f32>()) / size[1],
+                ]
+            })
+            .collect())
+    }
+
+    /// Steers the points toward their targets. Must run before the points move for this frame.
+    pub fn steer(&self, compute_pass: &mut wgpu::ComputePass) {
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        compute_pass.dispatch_workgroups((self.points_count as u32).div_ceil(64), 1, 1);
+    }
+}
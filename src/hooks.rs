@@ -0,0 +1,82 @@
+use std::{cell::RefCell, fmt, rc::Rc, time::Duration};
+
+use crate::beat::{BEAT_COOLDOWN, BEAT_THRESHOLD, BeatDetector};
+use crate::effect::{Effect, EffectPlacement};
+use crate::volume_providers::volume_provider::VolumeProvider;
+
+/// A callback shared by every state, which calls it in turn.
+pub(crate) type Callback<F> = Rc<RefCell<F>>;
+
+/// Creates an effect for every state, as each one sets effects up on its own device.
+pub(crate) type EffectFactory = Rc<dyn Fn() -> Box<dyn Effect>>;
+
+/// What library code hooks into the states it runs, shared by every state so the hooks
+/// outlive states started over with new options.
+#[derive(Clone, Default)]
+pub(crate) struct Hooks {
+    pub on_frame: Option<Callback<dyn FnMut(f32, Duration)>>,
+    pub on_beat: Option<Callback<dyn FnMut()>>,
+    pub on_resize: Option<Callback<dyn FnMut(u32, u32)>>,
+    pub effects: Vec<(EffectPlacement, EffectFactory)>,
+    pub volume_provider: Option<Rc<dyn VolumeProvider>>,
+}
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .field("on_frame", &self.on_frame.is_some())
+            .field("on_beat", &self.on_beat.is_some())
+            .field("on_resize", &self.on_resize.is_some())
+            .field("effects", &self.effects.len())
+            .field("volume_provider", &self.volume_provider.is_some())
+            .finish()
+    }
+}
+
+/// Calls the hooks of one state from the frames it draws.
+#[derive(Default)]
+pub(crate) struct HookRunner {
+    hooks: Hooks,
+    beat_detector: BeatDetector,
+    since_beat: f32,
+}
+
+impl HookRunner {
+    pub fn new(hooks: Hooks) -> Self {
+        Self {
+            hooks,
+            ..Self::default()
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn hooks(&self) -> &Hooks {
+        &self.hooks
+    }
+
+    /// Reports a frame shown at `intensity` after `delta_time`, and a beat when the
+    /// intensity jumped.
+    pub fn frame(&mut self, intensity: f32, delta_time: Duration) {
+        if let Some(on_frame) = &self.hooks.on_frame {
+            (on_frame.borrow_mut())(intensity, delta_time);
+        }
+
+        let Some(on_beat) = &self.hooks.on_beat else {
+            return;
+        };
+        let delta_time = delta_time.as_secs_f32();
+        let hit = self.beat_detector.update(intensity, delta_time);
+        self.since_beat += delta_time;
+        if hit >= BEAT_THRESHOLD && self.since_beat >= BEAT_COOLDOWN {
+            self.since_beat = 0.0;
+            (on_beat.borrow_mut())();
+        }
+    }
+
+    /// Reports that the frames are `width` by `height` pixels from now on.
+    pub fn resize(&self, width: u32, height: u32) {
+        if let Some(on_resize) = &self.hooks.on_resize {
+            (on_resize.borrow_mut())(width, height);
+        }
+    }
+}
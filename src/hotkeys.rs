@@ -0,0 +1,245 @@
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState, hotkey::HotKey};
+use log::info;
+
+use crate::{
+    config::Config,
+    control::{Request, RequestSender},
+};
+
+/// A global hotkey and the request it sends.
+struct Binding {
+    /// Identifies the shortcut to the global shortcuts portal.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    id: &'static str,
+    description: &'static str,
+    keys: String,
+    request: fn() -> Request,
+}
+
+/// Keeps the global hotkeys registered until dropped.
+pub struct Hotkeys {
+    _manager: Option<GlobalHotKeyManager>,
+}
+
+/// Registers the hotkeys of `config`, which send their requests through `sender`. Has to be
+/// called on the main thread.
+pub fn register(config: &Config, sender: RequestSender) -> anyhow::Result<Hotkeys> {
+    let bindings = vec![
+        Binding {
+            id: "pause",
+            description: "Pause or resume",
+            keys: config.hotkey_pause.clone(),
+            request: || Request::TogglePause,
+        },
+        Binding {
+            id: "next-preset",
+            description: "Switch to the next preset",
+            keys: config.hotkey_next_preset.clone(),
+            request: || Request::NextPreset,
+        },
+        Binding {
+            id: "boost",
+            description: "Boost the intensity",
+            keys: config.hotkey_boost.clone(),
+            request: || Request::Boost,
+        },
+    ];
+
+    // Wayland does not let clients grab keys, the desktop hands them out through a portal.
+    #[cfg(target_os = "linux")]
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        std::thread::spawn(move || {
+            if let Err(e) = portal::bind(&bindings, &sender) {
+                log::warn!("Unable to bind global shortcuts through the portal: {e}");
+            }
+        });
+        return Ok(Hotkeys { _manager: None });
+    }
+
+    let manager = GlobalHotKeyManager::new()?;
+    let mut registered = Vec::new();
+    for binding in bindings {
+        let hotkey: HotKey = binding.keys.parse()?;
+        manager.register(hotkey)?;
+        info!("{}: {}", binding.description, binding.keys);
+        registered.push((hotkey.id(), binding.request));
+    }
+
+    GlobalHotKeyEvent::set_event_handler(Some(move |event: GlobalHotKeyEvent| {
+        if event.state() != HotKeyState::Pressed {
+            return;
+        }
+        if let Some((_, request)) = registered.iter().find(|(id, _)| *id == event.id()) {
+            sender.send(request());
+        }
+    }));
+
+    Ok(Hotkeys {
+        _manager: Some(manager),
+    })
+}
+
+#[cfg(target_os = "linux")]
+mod portal {
+    use std::collections::HashMap;
+
+    use anyhow::{anyhow, bail};
+    use global_hotkey::hotkey::{HotKey, Modifiers};
+    use log::info;
+    use zbus::{
+        MatchRule,
+        blocking::{Connection, MessageIterator, Proxy},
+        message::Type,
+        zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value},
+    };
+
+    use super::Binding;
+    use crate::control::RequestSender;
+
+    const PORTAL: &str = "org.freedesktop.portal.Desktop";
+    const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+    const GLOBAL_SHORTCUTS: &str = "org.freedesktop.portal.GlobalShortcuts";
+
+    /// Binds the shortcuts in a global shortcuts portal session and sends their requests as
+    /// they are activated. Only returns on errors.
+    pub fn bind(bindings: &[Binding], sender: &RequestSender) -> anyhow::Result<()> {
+        let connection = Connection::session()?;
+        let portal = Proxy::new(&connection, PORTAL, PORTAL_PATH, GLOBAL_SHORTCUTS)?;
+
+        let token = "connecting_dots_session";
+        let options = HashMap::from([
+            ("handle_token", Value::from(token)),
+            ("session_handle_token", Value::from(token)),
+        ]);
+        let results = call(&connection, &portal, "CreateSession", token, &(options,))?;
+        let session_handle = results
+            .get("session_handle")
+            .ok_or_else(|| anyhow!("The portal did not create a session"))?;
+        // Older portals hand the session out as an object path rather than a string.
+        let session_handle = match session_handle.downcast_ref::<&str>() {
+            Ok(handle) => OwnedObjectPath::try_from(handle)?,
+            Err(_) => session_handle.downcast_ref::<ObjectPath>()?.into(),
+        };
+
+        let shortcuts = bindings
+            .iter()
+            .map(|binding| {
+                let trigger = portal_trigger(&binding.keys)?;
+                let properties = HashMap::from([
+                    ("description", Value::from(binding.description)),
+                    ("preferred_trigger", Value::from(trigger)),
+                ]);
+                Ok((binding.id, properties))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let activations = MessageIterator::for_match_rule(
+            MatchRule::builder()
+                .msg_type(Type::Signal)
+                .sender(PORTAL)?
+                .interface(GLOBAL_SHORTCUTS)?
+                .member("Activated")?
+                .build(),
+            &connection,
+            None,
+        )?;
+
+        let token = "connecting_dots_bind";
+        let options = HashMap::from([("handle_token", Value::from(token))]);
+        call(
+            &connection,
+            &portal,
+            "BindShortcuts",
+            token,
+            &(&session_handle, &shortcuts, "", options),
+        )?;
+        info!("Bound global shortcuts through the portal");
+
+        for message in activations {
+            let message = message?;
+            let (session, id, _, _): (OwnedObjectPath, String, u64, HashMap<String, OwnedValue>) =
+                message.body().deserialize()?;
+            if session != session_handle {
+                continue;
+            }
+
+            if let Some(binding) = bindings.iter().find(|binding| binding.id == id) {
+                sender.send((binding.request)());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Calls a portal method that answers through the Response signal of a request object,
+    /// and returns the results of that response. `token` is the handle token in the options
+    /// of `body`, which the request object is named after.
+    fn call<B: serde::Serialize + zbus::zvariant::DynamicType>(
+        connection: &Connection,
+        portal: &Proxy,
+        method: &str,
+        token: &str,
+        body: &B,
+    ) -> anyhow::Result<HashMap<String, OwnedValue>> {
+        let unique_name = connection
+            .unique_name()
+            .ok_or_else(|| anyhow!("Not connected to the session bus"))?;
+        let request_path = format!(
+            "{PORTAL_PATH}/request/{}/{token}",
+            unique_name.trim_start_matches(':').replace('.', "_")
+        );
+
+        // Subscribe before calling so the response cannot come in before we listen.
+        let mut responses = MessageIterator::for_match_rule(
+            MatchRule::builder()
+                .msg_type(Type::Signal)
+                .sender(PORTAL)?
+                .interface("org.freedesktop.portal.Request")?
+                .member("Response")?
+                .path(request_path.as_str())?
+                .build(),
+            connection,
+            None,
+        )?;
+
+        let _: OwnedObjectPath = portal.call(method, body)?;
+
+        let message = responses
+            .next()
+            .ok_or_else(|| anyhow!("The portal did not answer {method}"))??;
+        let (code, results): (u32, HashMap<String, OwnedValue>) = message.body().deserialize()?;
+        match code {
+            0 => Ok(results),
+            1 => bail!("{method} was cancelled"),
+            _ => bail!("{method} failed"),
+        }
+    }
+
+    /// Turns keys like ctrl+alt+p into the trigger format of the global shortcuts portal, like
+    /// CTRL+ALT+p.
+    fn portal_trigger(keys: &str) -> anyhow::Result<String> {
+        let hotkey: HotKey = keys.parse()?;
+
+        let mut trigger = String::new();
+        for (modifier, name) in [
+            (Modifiers::CONTROL, "CTRL"),
+            (Modifiers::ALT, "ALT"),
+            (Modifiers::SHIFT, "SHIFT"),
+            (Modifiers::SUPER, "LOGO"),
+        ] {
+            if hotkey.mods.contains(modifier) {
+                trigger.push_str(name);
+                trigger.push('+');
+            }
+        }
+        // The key is the last part, named as an xkb keysym.
+        let key = keys.rsplit('+').next().unwrap_or(keys).trim();
+        if key.chars().count() == 1 {
+            trigger.push_str(&key.to_lowercase());
+        } else {
+            trigger.push_str(key);
+        }
+
+        Ok(trigger)
+    }
+}
@@ -0,0 +1,158 @@
+use std::{
+    env,
+    io::{BufRead, BufReader, Read, Write},
+    net::Shutdown,
+    os::unix::net::UnixStream,
+    path::PathBuf,
+    process,
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+};
+
+use anyhow::{Context, anyhow};
+use serde::Deserialize;
+
+/// Events after which our window may have ended up somewhere else.
+const MOVE_EVENTS: &[&str] = &[
+    "movewindow",
+    "movewindowv2",
+    "workspace",
+    "workspacev2",
+    "focusedmon",
+    "changefloatingmode",
+    "fullscreen",
+    "openwindow",
+    "closewindow",
+    "monitoradded",
+    "monitorremoved",
+];
+
+#[derive(Deserialize, Debug)]
+struct Client {
+    pid: u32,
+    at: [f32; 2],
+}
+
+/// Directory holding the sockets of the running Hyprland instance, if there is one.
+fn socket_dir() -> Option<PathBuf> {
+    let signature = env::var_os("HYPRLAND_INSTANCE_SIGNATURE")?;
+
+    // Hyprland moved its sockets from /tmp to the runtime directory in 0.40.
+    env::var_os("XDG_RUNTIME_DIR")
+        .map(|runtime_dir| PathBuf::from(runtime_dir).join("hypr").join(&signature))
+        .filter(|dir| dir.exists())
+        .or_else(|| Some(PathBuf::from("/tmp/hypr").join(&signature)))
+}
+
+/// Whether we are running under Hyprland.
+pub fn is_running() -> bool {
+    socket_dir().is_some()
+}
+
+/// Sends a command to Hyprland, like `j/clients`, and returns its reply.
+pub fn request(command: &str) -> anyhow::Result<Vec<u8>> {
+    let socket_dir = socket_dir().ok_or_else(|| anyhow!("Not running under Hyprland"))?;
+    let mut stream = UnixStream::connect(socket_dir.join(".socket.sock"))
+        .context("Unable to connect to Hyprland")?;
+
+    stream.write_all(command.as_bytes())?;
+    let mut reply = Vec::new();
+    stream.read_to_end(&mut reply)?;
+
+    Ok(reply)
+}
+
+/// Asks Hyprland where the top left corner of our window is in the layout.
+pub fn window_position() -> anyhow::Result<[f32; 2]> {
+    let pid = process::id();
+    let clients: Vec<Client> = serde_json::from_slice(&request("j/clients")?)?;
+
+    clients
+        .iter()
+        .find(|client| client.pid == pid)
+        .map(|client| client.at)
+        .ok_or_else(|| anyhow!("No client found for pid {pid}"))
+}
+
+/// Follows the position of our window through the Hyprland event socket, so it is only
+/// queried again after something may have moved it.
+pub struct WindowTracker {
+    position: Arc<Mutex<Option<[f32; 2]>>>,
+    moved: Arc<AtomicBool>,
+    /// The event socket, shut down on drop to end the thread reading from it.
+    stream: UnixStream,
+}
+
+impl WindowTracker {
+    /// Starts listening for events, or returns `None` when not running under Hyprland.
+    pub fn spawn() -> Option<Self> {
+        let socket_dir = socket_dir()?;
+        let stream = UnixStream::connect(socket_dir.join(".socket2.sock"))
+            .inspect_err(|e| log::warn!("Unable to subscribe to Hyprland events: {e}"))
+            .ok()?;
+        let reader = stream
+            .try_clone()
+            .inspect_err(|e| log::warn!("Unable to read Hyprland events: {e}"))
+            .ok()?;
+
+        let position = Arc::new(Mutex::new(None));
+        let moved = Arc::new(AtomicBool::new(false));
+
+        {
+            let position = Arc::clone(&position);
+            let moved = Arc::clone(&moved);
+            thread::spawn(move || {
+                for line in BufReader::new(reader).lines() {
+                    let Ok(line) = line else {
+                        break;
+                    };
+                    let event = line
+                        .split_once(">>")
+                        .map_or(line.as_str(), |(event, _)| event);
+                    if !MOVE_EVENTS.contains(&event) {
+                        continue;
+                    }
+
+                    match window_position() {
+                        Ok(at) => {
+                            let mut position =
+                                position.lock().unwrap_or_else(PoisonError::into_inner);
+                            if *position != Some(at) {
+                                *position = Some(at);
+                                moved.store(true, Ordering::Relaxed);
+                            }
+                        }
+                        Err(e) => log::debug!("Unable to find our window: {e}"),
+                    }
+                }
+                log::debug!("Stopped reading Hyprland events");
+            });
+        }
+
+        Some(Self {
+            position,
+            moved,
+            stream,
+        })
+    }
+
+    /// Returns the new position of the window if it has moved since the last call.
+    pub fn take_moved(&self) -> Option<[f32; 2]> {
+        if self.moved.swap(false, Ordering::Relaxed) {
+            *self.position.lock().unwrap_or_else(PoisonError::into_inner)
+        } else {
+            None
+        }
+    }
+}
+
+impl Drop for WindowTracker {
+    fn drop(&mut self) {
+        if let Err(e) = self.stream.shutdown(Shutdown::Both) {
+            log::debug!("Unable to close the Hyprland event socket: {e}");
+        }
+    }
+}
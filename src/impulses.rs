@@ -0,0 +1,159 @@
+use bytemuck::{Pod, Zeroable};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use wgpu::util::DeviceExt;
+
+use crate::beat::BeatDetector;
+
+/// Hits weaker than this do not start a swirl.
+const VORTEX_THRESHOLD: f32 = 0.5;
+/// Shortest time in seconds between two swirls, as a hit stays above the threshold for a
+/// few frames.
+const VORTEX_COOLDOWN: f32 = 0.15;
+/// Shortest time in seconds between two explosions, so a drop scatters the points once.
+const EXPLOSION_COOLDOWN: f32 = 0.5;
+
+pub struct ImpulseOptions {
+    /// Acceleration in pixels per second squared of a swirl started by the strongest hit,
+    /// 0 to turn swirls off.
+    pub vortex_strength: f32,
+    pub vortex_radius: f32,
+    /// Seconds for a swirl to fade to about a third.
+    pub vortex_decay: f32,
+    /// Speed in pixels per second the points fly outward with on an explosion, 0 to turn
+    /// explosions off.
+    pub explosion_speed: f32,
+    /// Rise of the intensity within one frame that sets off an explosion.
+    pub explosion_threshold: f32,
+    /// Seconds for an explosion to fade to about a third.
+    pub explosion_decay: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct ImpulsesUniform {
+    vortex_center: [f32; 2],
+    /// Tangential acceleration at the center, negative to turn clockwise.
+    vortex_strength: f32,
+    vortex_radius: f32,
+    explosion_center: [f32; 2],
+    /// Outward speed at the center, added on top of the velocity of the points so they
+    /// continue as before once it has faded.
+    explosion_speed: f32,
+    /// Distance at which the explosion has faded out completely.
+    explosion_radius: f32,
+}
+
+/// Brief kicks to the motion of every point on hits in the music, applied by the compute
+/// shader of the points.
+pub struct Impulses {
+    options: ImpulseOptions,
+    uniform: ImpulsesUniform,
+    buffer: wgpu::Buffer,
+    beat_detector: BeatDetector,
+    rng: StdRng,
+    since_vortex: f32,
+    last_intensity: f32,
+    since_explosion: f32,
+}
+
+impl Impulses {
+    pub fn new(device: &wgpu::Device, rng: StdRng, options: ImpulseOptions) -> Self {
+        let uniform = ImpulsesUniform {
+            vortex_radius: options.vortex_radius.max(1.0),
+            ..ImpulsesUniform::zeroed()
+        };
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Impulses Buffer"),
+            contents: bytemuck::bytes_of(&uniform),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        Self {
+            options,
+            uniform,
+            buffer,
+            beat_detector: BeatDetector::default(),
+            rng,
+            since_vortex: VORTEX_COOLDOWN,
+            last_intensity: 0.0,
+            since_explosion: EXPLOSION_COOLDOWN,
+        }
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    /// Starts new impulses when the intensity hits hard enough and fades out the current ones.
+    pub fn update(
+        &mut self,
+        queue: &wgpu::Queue,
+        delta_time: f32,
+        intensity: f32,
+        window_size: [f32; 2],
+    ) {
+        if self.options.vortex_strength == 0.0 && self.options.explosion_speed == 0.0 {
+            return;
+        }
+
+        if self.options.vortex_strength != 0.0 {
+            self.update_vortex(delta_time, intensity, window_size);
+        }
+        if self.options.explosion_speed != 0.0 {
+            self.update_explosion(delta_time, intensity, window_size);
+        }
+
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&self.uniform));
+    }
+
+    /// Starts a swirl around a random spot on a hit in the music.
+    fn update_vortex(&mut self, delta_time: f32, intensity: f32, window_size: [f32; 2]) {
+        let hit = self.beat_detector.update(intensity, delta_time);
+        self.since_vortex += delta_time;
+
+        let uniform = &mut self.uniform;
+        if self.options.vortex_decay > 0.0 {
+            uniform.vortex_strength *= (-delta_time / self.options.vortex_decay).exp();
+        } else {
+            uniform.vortex_strength = 0.0;
+        }
+
+        if hit >= VORTEX_THRESHOLD && self.since_vortex >= VORTEX_COOLDOWN {
+            self.since_vortex = 0.0;
+            let rng = &mut self.rng;
+            // Scaled rather than drawn from a range, which would be empty for a minimized
+            // window.
+            uniform.vortex_center = [
+                rng.random::<f32>() * window_size[0],
+                rng.random::<f32>() * window_size[1],
+            ];
+            let direction = if rng.random_bool(0.5) { 1.0 } else { -1.0 };
+            uniform.vortex_strength = self.options.vortex_strength * hit * direction;
+        }
+    }
+
+    /// Scatters the points from the center of the window when the intensity jumps up within
+    /// a single frame, as on a drop.
+    fn update_explosion(&mut self, delta_time: f32, intensity: f32, window_size: [f32; 2]) {
+        let jump = intensity - self.last_intensity;
+        self.last_intensity = intensity;
+        self.since_explosion += delta_time;
+
+        let uniform = &mut self.uniform;
+        if self.options.explosion_decay > 0.0 {
+            uniform.explosion_speed *= (-delta_time / self.options.explosion_decay).exp();
+        } else {
+            uniform.explosion_speed = 0.0;
+        }
+
+        if jump >= self.options.explosion_threshold && self.since_explosion >= EXPLOSION_COOLDOWN {
+            self.since_explosion = 0.0;
+            let [width, height] = window_size;
+            uniform.explosion_center = [width / 2.0, height / 2.0];
+            uniform.explosion_radius = (width.hypot(height) / 2.0).max(1.0);
+            uniform.explosion_speed = self.options.explosion_speed;
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use std::fmt::Write;
+
+use winit::{
+    application::ApplicationHandler,
+    event::WindowEvent,
+    event_loop::{ActiveEventLoop, EventLoop},
+    window::{Window, WindowId},
+};
+
+use crate::{state::State, volume_providers::volume_provider::volume_provider_names};
+
+/// Format MSAA support is listed for when there is no surface to take the format of.
+const FALLBACK_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8UnormSrgb;
+
+/// Prints what the system supports: the graphics adapters to pick from with --gpu, the surface
+/// formats and present modes each of them offers for a window, the MSAA sample counts and
+/// the volume providers built in.
+pub fn print() -> anyhow::Result<()> {
+    let instance = State::create_instance();
+
+    println!(
+        "Backends built in: {}",
+        backend_names(wgpu::Instance::enabled_backend_features())
+    );
+    println!();
+
+    // Surface capabilities need a window, which is kept hidden and closed right away.
+    let mut probe = Probe {
+        instance,
+        report: None,
+    };
+    let report = match EventLoop::new() {
+        Ok(event_loop) => {
+            event_loop.run_app(&mut probe)?;
+            probe
+                .report
+                .unwrap_or_else(|| adapters(&probe.instance, None))
+        }
+        Err(e) => {
+            println!("No display to open a window on, leaving out surface support: {e}");
+            println!();
+            adapters(&probe.instance, None)
+        }
+    };
+    print!("{report}");
+
+    println!("Volume providers:");
+    for name in volume_provider_names() {
+        println!("  {name}");
+    }
+
+    Ok(())
+}
+
+/// Opens a hidden window once the event loop starts, to describe the adapters with the
+/// surface of it.
+struct Probe {
+    instance: wgpu::Instance,
+    report: Option<String>,
+}
+
+impl ApplicationHandler for Probe {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let surface = event_loop
+            .create_window(Window::default_attributes().with_visible(false))
+            .map_err(anyhow::Error::from)
+            .and_then(|window| Ok(self.instance.create_surface(window)?));
+        let report = match surface {
+            Ok(surface) => adapters(&self.instance, Some(&surface)),
+            Err(e) => {
+                let report = adapters(&self.instance, None);
+                format!("Unable to create a surface, leaving out surface support: {e}\n\n{report}")
+            }
+        };
+
+        self.report = Some(report);
+        event_loop.exit();
+    }
+
+    fn window_event(&mut self, _: &ActiveEventLoop, _: WindowId, _: WindowEvent) {}
+}
+
+/// Describes every adapter, with what it supports for `surface` when there is one.
+fn adapters(instance: &wgpu::Instance, surface: Option<&wgpu::Surface>) -> String {
+    let adapters = State::adapters(instance);
+    if adapters.is_empty() {
+        return "No adapters found\n\n".to_owned();
+    }
+
+    let mut report =
+        "Pick an adapter with --gpu and its index or a part of its name\n\n".to_owned();
+    for (index, adapter) in adapters.iter().enumerate() {
+        let info = adapter.get_info();
+        let _ = writeln!(
+            report,
+            "Adapter {index}: {} ({:?}, {:?})",
+            info.name, info.backend, info.device_type
+        );
+        if !info.driver.is_empty() {
+            let _ = writeln!(report, "  Driver: {} {}", info.driver, info.driver_info);
+        }
+
+        let mut format = FALLBACK_FORMAT;
+        if let Some(surface) = surface {
+            let capabilities = surface.get_capabilities(adapter);
+            if capabilities.formats.is_empty() {
+                let _ = writeln!(report, "  Cannot present to the window");
+            } else {
+                format = capabilities.formats[0];
+                let _ = writeln!(report, "  Surface formats: {:?}", capabilities.formats);
+                let _ = writeln!(report, "  Present modes: {:?}", capabilities.present_modes);
+                let _ = writeln!(report, "  Alpha modes: {:?}", capabilities.alpha_modes);
+            }
+        }
+
+        let _ = writeln!(
+            report,
+            "  MSAA sample counts for {format:?}: {:?}",
+            State::supported_sample_counts(adapter, format)
+        );
+        let limits = adapter.limits();
+        let _ = writeln!(
+            report,
+            "  Largest texture: {0}x{0}, largest storage buffer: {1} MiB",
+            limits.max_texture_dimension_2d,
+            limits.max_storage_buffer_binding_size / (1024 * 1024)
+        );
+        report.push('\n');
+    }
+
+    report
+}
+
+fn backend_names(backends: wgpu::Backends) -> String {
+    let names: Vec<_> = [
+        (wgpu::Backends::VULKAN, "Vulkan"),
+        (wgpu::Backends::METAL, "Metal"),
+        (wgpu::Backends::DX12, "DirectX 12"),
+        (wgpu::Backends::GL, "OpenGL"),
+        (wgpu::Backends::BROWSER_WEBGPU, "WebGPU"),
+    ]
+    .into_iter()
+    .filter(|(backend, _)| backends.contains(*backend))
+    .map(|(_, name)| name)
+    .collect();
+
+    match names.is_empty() {
+        true => "none".to_owned(),
+        false => names.join(", "),
+    }
+}
@@ -0,0 +1,414 @@
+use std::{
+    ffi::{OsString, c_void},
+    ptr::NonNull,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, anyhow};
+use log::info;
+use smithay_client_toolkit::{
+    compositor::{CompositorHandler, CompositorState, Region},
+    delegate_compositor, delegate_layer, delegate_output, delegate_registry,
+    output::{OutputHandler, OutputState},
+    reexports::client::{
+        Connection, Proxy, QueueHandle,
+        globals::registry_queue_init,
+        protocol::{wl_output, wl_surface},
+    },
+    registry::{ProvidesRegistryState, RegistryState},
+    registry_handlers,
+    shell::{
+        WaylandSurface,
+        wlr_layer::{
+            Anchor, KeyboardInteractivity, Layer, LayerShell, LayerShellHandler, LayerSurface,
+            LayerSurfaceConfigure,
+        },
+    },
+};
+use winit::dpi::{LogicalSize, PhysicalSize};
+
+use crate::{
+    canvas::Canvas,
+    config::{Config, ShellLayer},
+    control::Controller,
+    hooks::Hooks,
+    hotkeys::{self, Hotkeys},
+    state::State,
+    wayland_scale::{ScaleHandler, Scaling, SurfaceScale, delegate_scaling},
+};
+
+/// Runs the visualization as a wallpaper on a wlr-layer-shell surface covering the first
+/// output, instead of in a winit window that has to be pinned below everything else.
+pub(crate) fn run(
+    config: Config,
+    layer: ShellLayer,
+    hooks: Hooks,
+    command_line: Option<Vec<OsString>>,
+) -> anyhow::Result<()> {
+    let connection =
+        Connection::connect_to_env().context("Unable to connect to the Wayland compositor")?;
+    let (globals, mut event_queue) = registry_queue_init(&connection)?;
+    let qh = event_queue.handle();
+
+    let compositor = CompositorState::bind(&globals, &qh)?;
+    let layer_shell = LayerShell::bind(&globals, &qh)
+        .map_err(|_| anyhow!("The compositor does not support wlr-layer-shell"))?;
+
+    let surface = compositor.create_surface(&qh);
+    let layer_surface = layer_shell.create_layer_surface(
+        &qh,
+        surface,
+        match layer {
+            ShellLayer::Background => Layer::Background,
+            ShellLayer::Bottom => Layer::Bottom,
+        },
+        Some(config.class.clone()),
+        None,
+    );
+    // Cover the whole output, including the area under panels, and let input through to
+    // whatever else is on the desktop.
+    layer_surface.set_anchor(Anchor::all());
+    layer_surface.set_exclusive_zone(-1);
+    layer_surface.set_keyboard_interactivity(KeyboardInteractivity::None);
+    layer_surface.set_size(0, 0);
+    let input_region = Region::new(&compositor)?;
+    layer_surface.set_input_region(Some(input_region.wl_region()));
+    let surface_scale = Scaling::bind(&globals, &qh).scale_surface(layer_surface.wl_surface(), &qh);
+    layer_surface.commit();
+
+    let controller = command_line.map(|args| Controller::start(args, &config));
+    let hotkeys = controller
+        .as_ref()
+        .filter(|_| config.hotkeys)
+        .map(|controller| hotkeys::register(&config, controller.sender()))
+        .transpose()
+        .unwrap_or_else(|e| {
+            log::warn!("Unable to register the hotkeys: {e}");
+            None
+        });
+
+    let mut wallpaper = Wallpaper {
+        state: None,
+        layer_surface,
+        surface_scale,
+        logical_size: None,
+        registry_state: RegistryState::new(&globals),
+        output_state: OutputState::new(&globals, &qh),
+        connection: connection.clone(),
+        controller,
+        _hotkeys: hotkeys,
+        config,
+        hooks,
+        last_update: Instant::now(),
+        closed: false,
+    };
+
+    while !wallpaper.closed {
+        event_queue.blocking_dispatch(&mut wallpaper)?;
+    }
+
+    Ok(())
+}
+
+struct Wallpaper {
+    /// Dropped before the layer surface it draws into.
+    state: Option<State>,
+    layer_surface: LayerSurface,
+    surface_scale: SurfaceScale,
+    /// Size of the layer surface in logical pixels, once configured.
+    logical_size: Option<LogicalSize<u32>>,
+    registry_state: RegistryState,
+    output_state: OutputState,
+    connection: Connection,
+    /// Takes requests when the wallpaper was started from a command line to apply them to.
+    controller: Option<Controller>,
+    _hotkeys: Option<Hotkeys>,
+    config: Config,
+    hooks: Hooks,
+    last_update: Instant,
+    closed: bool,
+}
+
+impl Wallpaper {
+    fn canvas(&self, size: PhysicalSize<u32>) -> anyhow::Result<Canvas> {
+        let display = NonNull::new(self.connection.backend().display_ptr() as *mut c_void)
+            .ok_or_else(|| anyhow!("No Wayland display"))?;
+        let surface = NonNull::new(self.layer_surface.wl_surface().id().as_ptr() as *mut c_void)
+            .ok_or_else(|| anyhow!("No Wayland surface"))?;
+
+        let (monitor_size, monitor_name) = self.monitor();
+
+        Ok(Canvas::Wayland {
+            display,
+            surface,
+            size,
+            monitor_size,
+            monitor_name,
+            scale_factor: self.surface_scale.scale(),
+        })
+    }
+
+    /// Size and name of the output the surface is shown on, when known.
+    fn monitor(&self) -> (Option<PhysicalSize<u32>>, Option<String>) {
+        let info = self
+            .output_state
+            .outputs()
+            .find_map(|output| self.output_state.info(&output));
+        let monitor_size = info
+            .as_ref()
+            .and_then(|info| info.modes.iter().find(|mode| mode.current))
+            .map(|mode| PhysicalSize::new(mode.dimensions.0 as u32, mode.dimensions.1 as u32));
+
+        (monitor_size, info.and_then(|info| info.name))
+    }
+
+    /// Follows outputs being plugged in, unplugged or changing mode.
+    fn refresh_monitor(&mut self) {
+        let (size, name) = self.monitor();
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        if let Canvas::Wayland {
+            monitor_size,
+            monitor_name,
+            ..
+        } = &mut state.canvas
+        {
+            *monitor_size = size;
+            *monitor_name = name;
+        }
+        state.refresh_monitor();
+    }
+
+    /// Draws at `scale` from now on, starting over when the sizes in the config have to
+    /// follow.
+    fn rescale(&mut self, scale: f64) {
+        if !self.surface_scale.set_scale(scale) {
+            return;
+        }
+        info!("Drawing at a scale of {}", self.surface_scale.scale());
+
+        let (Some(logical_size), Some(state)) = (self.logical_size, &mut self.state) else {
+            return;
+        };
+        let size = self.surface_scale.apply(logical_size);
+        if let Canvas::Wayland {
+            size: canvas_size,
+            scale_factor,
+            ..
+        } = &mut state.canvas
+        {
+            *canvas_size = size;
+            *scale_factor = self.surface_scale.scale();
+        }
+        state.resize(size.width, size.height);
+
+        if state.needs_rescale()
+            && let Err(e) = State::restart(&mut self.state, &self.config, &self.config)
+        {
+            log::error!("Unable to follow the scale: {e}");
+        }
+    }
+
+    /// Advances and renders a frame, asking for a frame callback to draw the next one.
+    fn draw(&mut self, qh: &QueueHandle<Self>) {
+        if let Some(controller) = &mut self.controller {
+            controller.poll(&mut self.state, &mut self.config);
+        }
+
+        let Some(state) = &mut self.state else {
+            return;
+        };
+
+        let surface = self.layer_surface.wl_surface();
+        surface.frame(qh, surface.clone());
+
+        // Frame callbacks come at the refresh rate of the output, so with a cap the rest of
+        // the frame is waited out here.
+        if let Some(frame_interval) = self.config.frame_interval() {
+            std::thread::sleep(
+                (self.last_update + frame_interval).saturating_duration_since(Instant::now()),
+            );
+        }
+
+        // Frame callbacks stop coming once nothing is drawn, so a paused wallpaper keeps
+        // drawing the same frame.
+        let now = Instant::now();
+        let paused = self.controller.as_ref().is_some_and(Controller::is_paused);
+        state.update(if paused {
+            Duration::ZERO
+        } else {
+            now - self.last_update
+        });
+        self.last_update = now;
+
+        match state.render() {
+            Ok(_) => {}
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                let size = state.canvas.size();
+                state.resize(size.width, size.height);
+            }
+            Err(e) => log::error!("Unable to render {e}"),
+        }
+    }
+}
+
+impl LayerShellHandler for Wallpaper {
+    fn closed(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _layer: &LayerSurface) {
+        info!("The compositor closed the layer surface");
+        self.closed = true;
+    }
+
+    fn configure(
+        &mut self,
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+        _layer: &LayerSurface,
+        configure: LayerSurfaceConfigure,
+        _serial: u32,
+    ) {
+        // The compositor sizes the surface in logical pixels, the buffer is drawn at the scale.
+        let (width, height) = configure.new_size;
+        let logical_size = LogicalSize::new(width.max(1), height.max(1));
+        self.logical_size = Some(logical_size);
+        let size = self.surface_scale.apply(logical_size);
+
+        if let Some(state) = &mut self.state {
+            if let Canvas::Wayland {
+                size: canvas_size, ..
+            } = &mut state.canvas
+            {
+                *canvas_size = size;
+            }
+            state.resize(size.width, size.height);
+            return;
+        }
+
+        let state = self.canvas(size).and_then(|canvas| {
+            pollster::block_on(State::with_canvas(canvas, &self.config, self.hooks.clone()))
+        });
+        match state {
+            Ok(mut state) => {
+                state.resize(size.width, size.height);
+                self.state = Some(state);
+                self.last_update = Instant::now();
+                self.draw(qh);
+            }
+            Err(e) => {
+                log::error!("Unable to start drawing the wallpaper: {e}");
+                self.closed = true;
+            }
+        }
+    }
+}
+
+impl CompositorHandler for Wallpaper {
+    fn scale_factor_changed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        new_factor: i32,
+    ) {
+        if !self.surface_scale.is_fractional() {
+            self.rescale(new_factor as f64);
+        }
+    }
+
+    fn transform_changed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _new_transform: wl_output::Transform,
+    ) {
+    }
+
+    fn frame(
+        &mut self,
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _time: u32,
+    ) {
+        self.draw(qh);
+    }
+
+    fn surface_enter(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _output: &wl_output::WlOutput,
+    ) {
+    }
+
+    fn surface_leave(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _output: &wl_output::WlOutput,
+    ) {
+    }
+}
+
+impl OutputHandler for Wallpaper {
+    fn output_state(&mut self) -> &mut OutputState {
+        &mut self.output_state
+    }
+
+    fn new_output(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _output: wl_output::WlOutput,
+    ) {
+        self.refresh_monitor();
+    }
+
+    fn update_output(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _output: wl_output::WlOutput,
+    ) {
+        self.refresh_monitor();
+    }
+
+    fn output_destroyed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _output: wl_output::WlOutput,
+    ) {
+        self.refresh_monitor();
+    }
+}
+
+impl ScaleHandler for Wallpaper {
+    fn scale_changed(
+        &mut self,
+        _qh: &QueueHandle<Self>,
+        surface: &wl_surface::WlSurface,
+        scale: f64,
+    ) {
+        if surface == self.layer_surface.wl_surface() {
+            self.rescale(scale);
+        }
+    }
+}
+
+impl ProvidesRegistryState for Wallpaper {
+    fn registry(&mut self) -> &mut RegistryState {
+        &mut self.registry_state
+    }
+
+    registry_handlers![OutputState];
+}
+
+delegate_compositor!(Wallpaper);
+delegate_output!(Wallpaper);
+delegate_layer!(Wallpaper);
+delegate_registry!(Wallpaper);
+delegate_scaling!(Wallpaper);
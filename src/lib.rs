@@ -1,32 +1,197 @@
 use winit::event_loop::EventLoop;
 
+mod adaptive;
+#[cfg(feature = "ambient-audio")]
+mod ambient_audio;
 mod app;
+mod background_image;
+mod beat;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod bench;
+mod canvas;
+#[cfg(not(target_arch = "wasm32"))]
+mod check;
+pub mod color;
+pub mod config;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod config_file;
+mod connecting_dots;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod control;
+pub mod effect;
+mod flow_export;
+mod formation;
+mod hooks;
+#[cfg(not(target_arch = "wasm32"))]
+mod hotkeys;
+#[cfg(target_os = "linux")]
+mod hyprland;
+mod impulses;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod info;
+#[cfg(target_os = "linux")]
+mod layer_shell;
+mod lines;
+mod metaballs;
+mod oled;
+mod overlay;
+mod point_data;
+mod post_process;
+#[cfg(target_os = "linux")]
+mod power;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod preview;
+mod recorder;
+pub mod renderer;
+mod run_options;
+#[cfg(target_os = "linux")]
+mod session;
+#[cfg(target_os = "linux")]
+mod session_lock;
+pub mod simulation;
+#[cfg(not(target_arch = "wasm32"))]
+mod span;
+mod sparks;
+mod spatial_grid;
 mod state;
+#[cfg(target_os = "linux")]
+mod track;
 
+#[cfg(feature = "video")]
+mod video;
 mod volume_providers;
+mod wallpaper;
+#[cfg(target_os = "linux")]
+mod wayland_scale;
+#[cfg(target_arch = "wasm32")]
+mod web;
+#[cfg(target_os = "linux")]
+mod xscreensaver;
 
 use app::App;
+#[cfg(not(target_arch = "wasm32"))]
+use config::Config;
+
+pub use connecting_dots::ConnectingDots;
+pub use run_options::RunOptions;
+pub use volume_providers::volume_provider::VolumeProvider;
+
+/// Runs the visualization with `options` until its window is closed.
+pub fn run(options: RunOptions) -> anyhow::Result<()> {
+    let RunOptions {
+        config,
+        hooks,
+        #[cfg(not(target_arch = "wasm32"))]
+        command_line,
+        #[cfg(target_arch = "wasm32")]
+        page,
+    } = options;
 
-pub fn run(
-    background_image: Option<String>,
-    #[cfg(not(target_arch = "wasm32"))] class: String,
-) -> anyhow::Result<()> {
     #[cfg(not(target_arch = "wasm32"))]
-    env_logger::init();
+    init_logging(&config)?;
 
+    // The logger is set up already when the page starts again after an error.
+    #[cfg(target_arch = "wasm32")]
+    let _ = console_log::init_with_level(log::Level::Info);
     #[cfg(target_arch = "wasm32")]
-    console_log::init_with_level(log::Level::Info).unwrap_throw();
+    console_error_panic_hook::set_once();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if config.check {
+        return check::run(&config);
+    }
+
+    if let Some(quality) = config.quality {
+        log::info!(
+            "Using the {quality:?} quality: {}",
+            quality.args().join(" ")
+        );
+    }
+    if let Some(preset) = config.preset {
+        log::info!("Using the {preset:?} preset: {}", preset.args().join(" "));
+    }
+
+    if let Some(layer) = config.layer_shell {
+        #[cfg(target_os = "linux")]
+        return layer_shell::run(config, layer, hooks, command_line);
+
+        #[cfg(not(target_os = "linux"))]
+        anyhow::bail!("--layer-shell {layer:?} needs Wayland, which is only supported on Linux");
+    }
+
+    // XScreenSaver hands X11 screensavers a window, Wayland has them lock the session.
+    if config.screensaver {
+        #[cfg(target_os = "linux")]
+        if std::env::var_os("WAYLAND_DISPLAY").is_some()
+            && std::env::var_os("XSCREENSAVER_WINDOW").is_none()
+        {
+            return session_lock::run(config, hooks);
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        anyhow::bail!("--screensaver is only supported on Linux");
+    }
 
     let event_loop = EventLoop::with_user_event().build()?;
+    #[allow(unused_mut)]
     let mut app = App::new(
         #[cfg(target_arch = "wasm32")]
         &event_loop,
-        background_image,
+        config,
+        hooks,
         #[cfg(not(target_arch = "wasm32"))]
-        class,
+        command_line,
+        #[cfg(target_arch = "wasm32")]
+        page,
     );
 
-    event_loop.run_app(&mut app)?;
+    // The page's scripts go on running while the app draws, so they can control it.
+    #[cfg(target_arch = "wasm32")]
+    {
+        use winit::platform::web::EventLoopExtWebSys;
+
+        event_loop.spawn_app(app);
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        event_loop.run_app(&mut app)?;
+        app.into_result()
+    }
+}
+
+/// Logs at the level of -v and -q, refined per module by RUST_LOG and then --log-filter,
+/// to stderr or the --log-file.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn init_logging(config: &Config) -> anyhow::Result<()> {
+    use anyhow::Context;
+
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(config.log_level());
+    if let Ok(filters) = std::env::var("RUST_LOG") {
+        builder.parse_filters(&filters);
+    }
+    if let Some(filters) = &config.log_filter {
+        builder.parse_filters(filters);
+    }
+
+    if let Some(path) = &config.log_file {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Unable to open the log file {}", path.display()))?;
+        builder
+            .target(env_logger::Target::Pipe(Box::new(file)))
+            .write_style(env_logger::WriteStyle::Never);
+    }
+
+    // An embedding app, or an earlier run in the same process, may have set a logger up
+    // already, which then keeps logging.
+    if let Err(e) = builder.try_init() {
+        log::debug!("Keeping the logger already set up: {e}");
+    }
 
     Ok(())
 }
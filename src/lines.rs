@@ -0,0 +1,452 @@
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+use crate::spatial_grid::SpatialGrid;
+
+/// Room in the line buffer per point. Pairs found beyond that are dropped.
+const MAX_LINES_PER_POINT: usize = 16;
+
+/// Largest supported `LineOptions::max_connections`, bounded by the size of the array the
+/// shader keeps the nearest distances in.
+pub const MAX_CONNECTIONS_LIMIT: u32 = 16;
+
+pub struct LineOptions {
+    /// Points further apart than this are not connected.
+    pub distance: f32,
+    /// How many of its nearest neighbours each point may connect to, 0 for no limit.
+    pub max_connections: u32,
+    /// Whether the cursor connects to nearby points like a point of its own.
+    pub cursor: bool,
+}
+
+/// Size in bytes of the line buffer for `points_count` points, the largest buffer allocated
+/// per point.
+pub fn lines_buffer_size(points_count: usize) -> wgpu::BufferAddress {
+    (points_count * MAX_LINES_PER_POINT * 2 * size_of::<u32>()) as wgpu::BufferAddress
+}
+
+/// Lines connecting points closer than the connection distance.
+///
+/// A compute pass finds the close pairs and appends them to a storage buffer, counting
+/// them in the instance count of an indirect draw, so the line list never has to be read
+/// back to the CPU.
+pub struct Lines {
+    nearest_pipeline: Option<wgpu::ComputePipeline>,
+    compute_pipeline: wgpu::ComputePipeline,
+    compute_bind_group: wgpu::BindGroup,
+    render_pipeline: wgpu::RenderPipeline,
+    render_bind_group: wgpu::BindGroup,
+    indirect_buffer: wgpu::Buffer,
+    style_buffer: wgpu::Buffer,
+    style: LineStyle,
+    points_count: usize,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct LineStyle {
+    distance: f32,
+    width: f32,
+    max_connections: u32,
+    cursor: u32,
+}
+
+/// Line width in pixels.
+const LINE_WIDTH: f32 = 1.5;
+
+impl Lines {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        points_buffer: &wgpu::Buffer,
+        points_count: usize,
+        window_size_buffer: &wgpu::Buffer,
+        intensity_buffer: &wgpu::Buffer,
+        intro_buffer: &wgpu::Buffer,
+        cursor_buffer: &wgpu::Buffer,
+        grid_bind_group_layout: &wgpu::BindGroupLayout,
+        options: LineOptions,
+    ) -> Self {
+        let style = LineStyle {
+            distance: options.distance,
+            width: LINE_WIDTH,
+            max_connections: options.max_connections.min(MAX_CONNECTIONS_LIMIT),
+            cursor: options.cursor as u32,
+        };
+
+        let style_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Line Style Buffer"),
+            contents: bytemuck::bytes_of(&style),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // Each line is a pair of point indices.
+        let lines_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Lines Buffer"),
+            size: lines_buffer_size(points_count),
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        // Squared distance to the furthest neighbour each point may connect to.
+        let nearest_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Nearest Distances Buffer"),
+            size: (points_count * size_of::<f32>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let indirect_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Lines Indirect Buffer"),
+            contents: wgpu::util::DrawIndirectArgs::default().as_bytes(),
+            usage: wgpu::BufferUsages::INDIRECT
+                | wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let compute_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Find Lines Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 5,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let compute_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Find Lines Bind Group"),
+            layout: &compute_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: points_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: lines_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: indirect_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: style_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: nearest_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: cursor_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let compute_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Find Lines Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/find_lines.wgsl").into()),
+        });
+
+        let compute_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Find Lines Pipeline Layout"),
+                bind_group_layouts: &[&compute_bind_group_layout, grid_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Find Lines Pipeline"),
+            layout: Some(&compute_pipeline_layout),
+            module: &compute_shader,
+            entry_point: Some("main"),
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            cache: None,
+        });
+
+        let nearest_pipeline = (style.max_connections > 0).then(|| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Find Nearest Pipeline"),
+                layout: Some(&compute_pipeline_layout),
+                module: &compute_shader,
+                entry_point: Some("find_nearest"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: None,
+            })
+        });
+
+        let render_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Lines Render Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 5,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 6,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let render_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Lines Render Bind Group"),
+            layout: &render_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: points_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: lines_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: window_size_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: intensity_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: style_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: intro_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: cursor_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let render_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Lines Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/lines.wgsl").into()),
+        });
+
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Lines Render Pipeline Layout"),
+                bind_group_layouts: &[&render_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Lines Render Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &render_shader,
+                entry_point: Some("vs_main"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &render_shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            nearest_pipeline,
+            compute_pipeline,
+            compute_bind_group,
+            render_pipeline,
+            render_bind_group,
+            indirect_buffer,
+            style_buffer,
+            style,
+            points_count,
+        }
+    }
+
+    /// Points closer than this are connected.
+    pub fn distance(&self) -> f32 {
+        self.style.distance
+    }
+
+    /// Connects points closer than `distance` from now on. The cells of the grid the lines
+    /// are found in must keep up with it.
+    pub fn set_distance(&mut self, queue: &wgpu::Queue, distance: f32) {
+        self.style.distance = distance;
+        queue.write_buffer(&self.style_buffer, 0, bytemuck::bytes_of(&self.style));
+    }
+
+    /// Empties the line list before `find` runs again.
+    pub fn reset(&self, queue: &wgpu::Queue) {
+        let args = wgpu::util::DrawIndirectArgs {
+            vertex_count: 4,
+            ..Default::default()
+        };
+        queue.write_buffer(&self.indirect_buffer, 0, args.as_bytes());
+    }
+
+    /// Finds the close pairs among the neighbours in `grid`, whose cells must be at least the
+    /// connection distance. Must run after the grid has been built from the points moved for
+    /// this frame.
+    pub fn find(&self, compute_pass: &mut wgpu::ComputePass, grid: &SpatialGrid) {
+        let num_dispatches = (self.points_count as u32).div_ceil(64);
+        compute_pass.set_bind_group(0, &self.compute_bind_group, &[]);
+        compute_pass.set_bind_group(1, grid.query_bind_group(), &[]);
+
+        if let Some(nearest_pipeline) = &self.nearest_pipeline {
+            compute_pass.set_pipeline(nearest_pipeline);
+            compute_pass.dispatch_workgroups(num_dispatches, 1, 1);
+        }
+
+        compute_pass.set_pipeline(&self.compute_pipeline);
+        compute_pass.dispatch_workgroups(num_dispatches, 1, 1);
+    }
+
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.render_bind_group, &[]);
+        render_pass.draw_indirect(&self.indirect_buffer, 0);
+    }
+}
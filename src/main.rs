@@ -1,22 +1,114 @@
-use clap::Parser;
-use connecting_dots_rs::run;
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+#[cfg(unix)]
+use connecting_dots_rs::control::{self, CtlCommand};
+use connecting_dots_rs::{
+    RunOptions, bench, config::Config, config_file::ConfigFile, info, preview, run,
+};
 
 #[derive(Parser, Debug)]
-#[command(version, about)]
+#[command(
+    version,
+    about,
+    after_help = "Every option can also be set in an environment variable named after it, like \
+                  CONNECTING_DOTS_POINTS=300 for --points 300. The command line wins over the \
+                  environment, which wins over --config."
+)]
 struct Args {
-    /// Path to background image
-    #[arg(short, long)]
-    background_image: Option<String>,
+    #[command(subcommand)]
+    command: Option<Command>,
 
-    /// Window class
-    #[arg(short, long, default_value = "connecting-dots")]
-    class: String,
+    #[command(flatten)]
+    config: Config,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Control a running instance through its control socket
+    #[cfg(unix)]
+    Ctl {
+        #[command(subcommand)]
+        command: CtlCommand,
+    },
+    /// Show the graphics adapters, surface formats, present modes, MSAA sample counts and
+    /// volume providers this system supports
+    Info,
+    /// Measure frame times with the other options and a steady beat instead of the music,
+    /// once for every number of points
+    Bench {
+        /// Numbers of points to measure with, separated by commas
+        #[arg(
+            long,
+            value_name = "N,N,...",
+            value_delimiter = ',',
+            value_parser = clap::value_parser!(u32).range(1..),
+            default_value = "1000,5000,20000"
+        )]
+        points: Vec<u32>,
+
+        /// Frames measured for each number of points
+        #[arg(long, value_name = "COUNT", default_value_t = 600)]
+        frames: u32,
+    },
+    /// Open a grid of every preset at once, drawn with the other options and following the
+    /// same music, and print the options of the one clicked
+    Preview,
+    /// Print a JSON Schema of the --config file, for editors to complete and check it with
+    Schema,
+    /// Print completions for a shell, e.g. to source from ~/.bashrc or save in a
+    /// fish/completions directory
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
 }
 
 fn main() -> anyhow::Result<()> {
-    let args = Args::try_parse()?;
+    let mut args = Args::try_parse()?;
+
+    // Bench and preview run with the options, which are parsed below.
+    let command = match args.command.take() {
+        #[cfg(unix)]
+        Some(Command::Ctl { command }) => return control::ctl(command),
+        Some(Command::Info) => return info::print(),
+        Some(Command::Schema) => {
+            println!("{}", serde_json::to_string_pretty(&ConfigFile::schema())?);
+            return Ok(());
+        }
+        Some(Command::Completions { shell }) => {
+            let mut command = Args::command();
+            clap_complete::generate(
+                shell,
+                &mut command,
+                env!("CARGO_BIN_NAME"),
+                &mut std::io::stdout(),
+            );
+            return Ok(());
+        }
+        command => command,
+    };
+
+    // The control socket, hotkeys and config file apply their changes to the command line.
+    let command_line = Config::process_args();
+    let mut arg_list = command_line.clone();
+    args = Args::try_parse_from(&arg_list)?;
+    // --check reads the config file itself, to report what is wrong with it.
+    if let Some(path) = &args.config.config_file
+        && !args.config.check
+    {
+        arg_list = ConfigFile::open(path, &arg_list)?.args(&arg_list)?;
+        args = Args::try_parse_from(&arg_list)?;
+    }
+
+    if args.config.quality.is_some() || args.config.preset.is_some() {
+        args = Args::try_parse_from(args.config.expand(arg_list.clone()))?;
+    }
 
-    run(args.background_image, args.class)?;
+    match command {
+        Some(Command::Bench { points, frames }) => bench::run(args.config, &points, frames)?,
+        Some(Command::Preview) => preview::run(args.config, &arg_list)?,
+        _ => run(RunOptions::from(args.config).command_line(command_line))?,
+    }
 
     Ok(())
 }
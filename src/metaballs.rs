@@ -0,0 +1,285 @@
+use wgpu::util::DeviceExt;
+
+use crate::renderer::Renderer;
+
+const FIELD_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R16Float;
+
+/// Draws the points as metaballs: every point splats a smooth falloff into a field texture,
+/// and a fullscreen pass turns the field into shapes by thresholding it with soft edges, so
+/// nearby points merge into blobs.
+pub struct Metaballs {
+    field_pipeline: wgpu::RenderPipeline,
+    field_bind_group: wgpu::BindGroup,
+    field_view: wgpu::TextureView,
+    composite_pipeline: wgpu::RenderPipeline,
+    composite_bind_group_layout: wgpu::BindGroupLayout,
+    composite_bind_group: wgpu::BindGroup,
+    intensity_buffer: wgpu::Buffer,
+    points_count: usize,
+}
+
+impl Metaballs {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        width: u32,
+        height: u32,
+        points_buffer: &wgpu::Buffer,
+        points_count: usize,
+        window_size_buffer: &wgpu::Buffer,
+        intensity_buffer: &wgpu::Buffer,
+        radius: f32,
+    ) -> Self {
+        let radius_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Metaball Radius Buffer"),
+            contents: bytemuck::bytes_of(&radius),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let field_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Metaball Field Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let field_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Metaball Field Bind Group"),
+            layout: &field_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: points_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: window_size_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: radius_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let field_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Metaball Field Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/metaball_field.wgsl").into()),
+        });
+
+        let field_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Metaball Field Pipeline Layout"),
+                bind_group_layouts: &[&field_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        // The contributions of overlapping points add up.
+        let additive = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::REPLACE,
+        };
+
+        let field_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Metaball Field Pipeline"),
+            layout: Some(&field_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &field_shader,
+                entry_point: Some("vs_main"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &field_shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: FIELD_FORMAT,
+                    blend: Some(additive),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let composite_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Metaball Composite Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let composite_pipeline = Renderer::create_fullscreen_pipeline(
+            device,
+            format,
+            sample_count,
+            wgpu::BlendState::ALPHA_BLENDING,
+            "Metaball Composite",
+            include_str!("shaders/metaball_composite.wgsl"),
+            &composite_bind_group_layout,
+        );
+
+        let field_view = Self::create_field_texture(device, width, height);
+        let composite_bind_group = Self::create_composite_bind_group(
+            device,
+            &composite_bind_group_layout,
+            &field_view,
+            intensity_buffer,
+        );
+
+        Self {
+            field_pipeline,
+            field_bind_group,
+            field_view,
+            composite_pipeline,
+            composite_bind_group_layout,
+            composite_bind_group,
+            intensity_buffer: intensity_buffer.clone(),
+            points_count,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.field_view = Self::create_field_texture(device, width, height);
+        self.composite_bind_group = Self::create_composite_bind_group(
+            device,
+            &self.composite_bind_group_layout,
+            &self.field_view,
+            &self.intensity_buffer,
+        );
+    }
+
+    /// Renders the field texture. Must run before the pass `draw` is called in.
+    pub fn render_field(&self, encoder: &mut wgpu::CommandEncoder) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Metaball Field Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.field_view,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&self.field_pipeline);
+        render_pass.set_bind_group(0, &self.field_bind_group, &[]);
+        render_pass.draw(0..4, 0..self.points_count as u32);
+    }
+
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.set_pipeline(&self.composite_pipeline);
+        render_pass.set_bind_group(0, &self.composite_bind_group, &[]);
+        render_pass.draw(0..4, 0..1);
+    }
+
+    fn create_field_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::TextureView {
+        device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Metaball Field Texture"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: FIELD_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    fn create_composite_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        field_view: &wgpu::TextureView,
+        intensity_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Metaball Composite Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(field_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: intensity_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+}
@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+/// Speed in pixels per second of the forced drift that keeps pixels from staying lit.
+/// Kept below the slowest point speed so points can still bounce away from the edges.
+const DRIFT_SPEED: f32 = 0.5;
+/// Radians per second the drift direction rotates, so the field never settles.
+const DRIFT_ROTATION_SPEED: f32 = 0.01;
+/// How long the transition into the dimmed state takes.
+const DIM_TRANSITION: Duration = Duration::from_secs(30);
+
+/// Burn-in mitigations for OLED panels: a brightness cap, a slow drift of the
+/// whole field, and dimming after a period without user input.
+pub struct OledMode {
+    max_brightness: f32,
+    dim_after: Duration,
+    dim_brightness: f32,
+    idle: Duration,
+    elapsed: Duration,
+}
+
+impl OledMode {
+    pub fn new(max_brightness: f32, dim_after: Duration, dim_brightness: f32) -> Self {
+        Self {
+            max_brightness: max_brightness.clamp(0.0, 1.0),
+            dim_after,
+            dim_brightness: dim_brightness.clamp(0.0, 1.0),
+            idle: Duration::ZERO,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    pub fn update(&mut self, delta_time: Duration) {
+        self.idle += delta_time;
+        self.elapsed += delta_time;
+    }
+
+    /// Restarts the dimming schedule, called on user input.
+    pub fn register_activity(&mut self) {
+        self.idle = Duration::ZERO;
+    }
+
+    pub fn brightness_cap(&self) -> f32 {
+        let dimmed = self.max_brightness.min(self.dim_brightness);
+        let progress = (self.idle.saturating_sub(self.dim_after).as_secs_f32()
+            / DIM_TRANSITION.as_secs_f32())
+        .min(1.0);
+
+        self.max_brightness + (dimmed - self.max_brightness) * progress
+    }
+
+    pub fn drift(&self) -> [f32; 2] {
+        let angle = self.elapsed.as_secs_f32() * DRIFT_ROTATION_SPEED;
+        [angle.cos() * DRIFT_SPEED, angle.sin() * DRIFT_SPEED]
+    }
+}
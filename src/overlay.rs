@@ -0,0 +1,405 @@
+use std::time::Duration;
+
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont};
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+#[cfg(target_os = "linux")]
+use crate::track::TrackMonitor;
+
+const FONT_SIZE: f32 = 16.0;
+/// Distance in pixels from the top left corner of the window to the text.
+const MARGIN: f32 = 8.0;
+/// Most characters drawn at once, the rest is cut off.
+const MAX_CHARS: usize = 512;
+const FIRST_CHAR: char = ' ';
+const LAST_CHAR: char = '~';
+const ATLAS_COLUMNS: u32 = 16;
+/// How often the FPS counter is refreshed.
+const FPS_INTERVAL: Duration = Duration::from_millis(500);
+/// How long a notice stays on screen.
+const NOTICE_DURATION: Duration = Duration::from_millis(1500);
+
+pub struct OverlayOptions {
+    pub fps: bool,
+    pub intensity: bool,
+    pub track: bool,
+}
+
+/// Text drawn on top of the visualization, such as the FPS, the playing track and brief
+/// notices of values changed with the keyboard.
+///
+/// The printable ASCII characters of a built-in font are rasterized into an atlas once,
+/// and the text is redrawn as one textured quad per character whenever it changes.
+pub struct Overlay {
+    options: OverlayOptions,
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    vertex_buffer: wgpu::Buffer,
+    vertex_count: u32,
+    glyphs: Vec<Glyph>,
+    line_height: f32,
+    ascent: f32,
+    text: String,
+    frames: u32,
+    since_fps: Duration,
+    fps: f32,
+    /// Shown below the rest until its time runs out.
+    notice: Option<(String, Duration)>,
+    #[cfg(target_os = "linux")]
+    track: Option<TrackMonitor>,
+}
+
+/// Where a character is in the atlas and how to place it relative to the pen position.
+struct Glyph {
+    uv_min: [f32; 2],
+    uv_max: [f32; 2],
+    offset: [f32; 2],
+    size: [f32; 2],
+    advance: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct GlyphVertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+}
+
+impl Overlay {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        window_size_buffer: &wgpu::Buffer,
+        options: OverlayOptions,
+    ) -> anyhow::Result<Self> {
+        let font = FontRef::try_from_slice(epaint_default_fonts::HACK_REGULAR)?;
+        let scaled_font = font.as_scaled(PxScale::from(FONT_SIZE));
+
+        // Every cell fits the largest glyph, plus a pixel of padding against bleeding.
+        let cell_width = (scaled_font.h_advance(font.glyph_id('W')).ceil() as u32) + 2;
+        let cell_height = (scaled_font.height().ceil() as u32) + 2;
+        let chars = FIRST_CHAR..=LAST_CHAR;
+        let rows = (chars.clone().count() as u32).div_ceil(ATLAS_COLUMNS);
+        let atlas_width = cell_width * ATLAS_COLUMNS;
+        let atlas_height = cell_height * rows;
+
+        let mut atlas = vec![0u8; (atlas_width * atlas_height) as usize];
+        let mut glyphs = Vec::new();
+
+        for (i, c) in chars.enumerate() {
+            let cell_x = (i as u32 % ATLAS_COLUMNS) * cell_width + 1;
+            let cell_y = (i as u32 / ATLAS_COLUMNS) * cell_height + 1;
+
+            // Positioned with the pen on the baseline at the origin.
+            let glyph = scaled_font.scaled_glyph(c);
+            let advance = scaled_font.h_advance(glyph.id);
+
+            let Some(outline) = font.outline_glyph(glyph) else {
+                // Nothing to draw, e.g. a space.
+                glyphs.push(Glyph {
+                    uv_min: [0.0, 0.0],
+                    uv_max: [0.0, 0.0],
+                    offset: [0.0, 0.0],
+                    size: [0.0, 0.0],
+                    advance,
+                });
+                continue;
+            };
+
+            let bounds = outline.px_bounds();
+            let width = (bounds.width() as u32).min(cell_width - 2);
+            let height = (bounds.height() as u32).min(cell_height - 2);
+
+            outline.draw(|x, y, coverage| {
+                if x < width && y < height {
+                    let index = (cell_y + y) * atlas_width + cell_x + x;
+                    atlas[index as usize] = (coverage * 255.0) as u8;
+                }
+            });
+
+            glyphs.push(Glyph {
+                uv_min: [
+                    cell_x as f32 / atlas_width as f32,
+                    cell_y as f32 / atlas_height as f32,
+                ],
+                uv_max: [
+                    (cell_x + width) as f32 / atlas_width as f32,
+                    (cell_y + height) as f32 / atlas_height as f32,
+                ],
+                offset: [bounds.min.x, bounds.min.y],
+                size: [width as f32, height as f32],
+                advance,
+            });
+        }
+
+        let atlas_texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("Overlay Font Atlas"),
+                size: wgpu::Extent3d {
+                    width: atlas_width,
+                    height: atlas_height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::R8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            &atlas,
+        );
+
+        let atlas_view = atlas_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Overlay Vertex Buffer"),
+            size: (MAX_CHARS * 6 * size_of::<GlyphVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Overlay Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Overlay Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&atlas_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: window_size_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Overlay Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/overlay.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Overlay Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Overlay Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: size_of::<GlyphVertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        #[cfg(target_os = "linux")]
+        let track = options.track.then(TrackMonitor::spawn);
+        #[cfg(not(target_os = "linux"))]
+        if options.track {
+            log::warn!("Showing the playing track is only supported on Linux");
+        }
+
+        Ok(Self {
+            options,
+            pipeline,
+            bind_group,
+            vertex_buffer,
+            vertex_count: 0,
+            glyphs,
+            line_height: scaled_font.height() + scaled_font.line_gap(),
+            ascent: scaled_font.ascent(),
+            text: String::new(),
+            frames: 0,
+            since_fps: Duration::ZERO,
+            fps: 0.0,
+            notice: None,
+            #[cfg(target_os = "linux")]
+            track,
+        })
+    }
+
+    /// Shows `text` for a moment, replacing the notice shown before.
+    pub fn notify(&mut self, text: String) {
+        self.notice = Some((text, NOTICE_DURATION));
+    }
+
+    /// Refreshes the text, uploading it again if it changed.
+    pub fn update(&mut self, queue: &wgpu::Queue, delta_time: Duration, intensity: f32) {
+        self.frames += 1;
+        self.since_fps += delta_time;
+        if self.since_fps >= FPS_INTERVAL {
+            self.fps = self.frames as f32 / self.since_fps.as_secs_f32();
+            self.frames = 0;
+            self.since_fps = Duration::ZERO;
+        }
+
+        let mut lines = Vec::new();
+        if self.options.fps {
+            lines.push(format!("FPS {:.0}", self.fps));
+        }
+        if self.options.intensity {
+            lines.push(format!("Intensity {intensity:.2}"));
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(track) = self.track.as_ref().and_then(TrackMonitor::track) {
+            lines.push(track);
+        }
+        if let Some((notice, remaining)) = &mut self.notice {
+            lines.push(notice.clone());
+            *remaining = remaining.saturating_sub(delta_time);
+            if remaining.is_zero() {
+                self.notice = None;
+            }
+        }
+
+        let text = lines.join("\n");
+        if text != self.text {
+            let vertices = self.layout(&text);
+            queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+            self.vertex_count = vertices.len() as u32;
+            self.text = text;
+        }
+    }
+
+    /// Builds two triangles per visible character, in window pixels.
+    fn layout(&self, text: &str) -> Vec<GlyphVertex> {
+        let mut vertices = Vec::new();
+
+        for (line_index, line) in text.lines().enumerate() {
+            let baseline = MARGIN + self.ascent + line_index as f32 * self.line_height;
+            let mut pen = MARGIN;
+
+            for c in line.chars() {
+                let c = if (FIRST_CHAR..=LAST_CHAR).contains(&c) {
+                    c
+                } else {
+                    '?'
+                };
+                let glyph = &self.glyphs[c as usize - FIRST_CHAR as usize];
+
+                if glyph.size[0] > 0.0 && vertices.len() < MAX_CHARS * 6 {
+                    let x0 = pen + glyph.offset[0];
+                    let y0 = baseline + glyph.offset[1];
+                    let x1 = x0 + glyph.size[0];
+                    let y1 = y0 + glyph.size[1];
+                    let [u0, v0] = glyph.uv_min;
+                    let [u1, v1] = glyph.uv_max;
+
+                    let top_left = GlyphVertex {
+                        position: [x0, y0],
+                        uv: [u0, v0],
+                    };
+                    let top_right = GlyphVertex {
+                        position: [x1, y0],
+                        uv: [u1, v0],
+                    };
+                    let bottom_left = GlyphVertex {
+                        position: [x0, y1],
+                        uv: [u0, v1],
+                    };
+                    let bottom_right = GlyphVertex {
+                        position: [x1, y1],
+                        uv: [u1, v1],
+                    };
+                    vertices.extend([
+                        top_left,
+                        bottom_left,
+                        top_right,
+                        top_right,
+                        bottom_left,
+                        bottom_right,
+                    ]);
+                }
+
+                pen += glyph.advance;
+            }
+        }
+
+        vertices
+    }
+
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        if self.vertex_count == 0 {
+            return;
+        }
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.vertex_count, 0..1);
+    }
+}
@@ -0,0 +1,102 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Result, anyhow};
+
+/// How often the data file is checked for modifications.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Value written for points without data, which the shader draws with the default style.
+pub const NO_DATA: f32 = -1.0;
+
+/// External per-point scalar data read from a CSV or JSON file, reloaded whenever the file
+/// changes so the points can visualize live data.
+pub struct PointData {
+    path: PathBuf,
+    last_modified: Option<SystemTime>,
+    since_poll: Duration,
+}
+
+impl PointData {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            last_modified: None,
+            // Load on the first update.
+            since_poll: POLL_INTERVAL,
+        }
+    }
+
+    /// Returns the normalized values when the file has changed since the last call.
+    pub fn poll(&mut self, delta_time: Duration, points_count: usize) -> Option<Vec<f32>> {
+        self.since_poll += delta_time;
+        if self.since_poll < POLL_INTERVAL {
+            return None;
+        }
+        self.since_poll = Duration::ZERO;
+
+        let modified = fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+        if modified.is_some() && modified == self.last_modified {
+            return None;
+        }
+        self.last_modified = modified;
+
+        match Self::load(&self.path) {
+            Ok(values) => Some(Self::normalize(values, points_count)),
+            Err(e) => {
+                log::error!("Unable to load point data from {:?}: {e}", self.path);
+                None
+            }
+        }
+    }
+
+    /// Reads one value per point. JSON files hold an array of numbers, CSV files one row per
+    /// point using the last column, so an index or label column can precede the value.
+    pub(crate) fn load(path: &Path) -> Result<Vec<f32>> {
+        let contents = fs::read_to_string(path)?;
+
+        let is_json = path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+
+        if is_json {
+            return Ok(serde_json::from_str(&contents)?);
+        }
+
+        let values = contents
+            .lines()
+            .filter_map(|line| line.rsplit(',').next())
+            .filter_map(|column| column.trim().parse::<f32>().ok())
+            .collect::<Vec<_>>();
+
+        if values.is_empty() && !contents.trim().is_empty() {
+            return Err(anyhow!("No numeric values found"));
+        }
+
+        Ok(values)
+    }
+
+    /// Rescales the values to 0-1 and pads or truncates them to one value per point.
+    fn normalize(values: Vec<f32>, points_count: usize) -> Vec<f32> {
+        let finite = values.iter().copied().filter(|v| v.is_finite());
+        let min = finite.clone().fold(f32::INFINITY, f32::min);
+        let max = finite.fold(f32::NEG_INFINITY, f32::max);
+        let range = max - min;
+
+        let mut normalized = values
+            .into_iter()
+            .take(points_count)
+            .map(|v| match v {
+                v if !v.is_finite() => NO_DATA,
+                _ if range <= f32::EPSILON => 1.0,
+                v => (v - min) / range,
+            })
+            .collect::<Vec<_>>();
+        normalized.resize(points_count, NO_DATA);
+
+        normalized
+    }
+}
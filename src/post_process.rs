@@ -0,0 +1,322 @@
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+use crate::beat::BeatDetector;
+use crate::config::Effect;
+use crate::effect::{self, EffectUniforms};
+use crate::renderer::Renderer;
+
+/// Uniforms shared by every post-processing pass.
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct PostProcessGlobals {
+    window_size: [f32; 2],
+    time: f32,
+    intensity: f32,
+    aberration: f32,
+    _padding: [f32; 3],
+}
+
+pub struct PostProcessOptions {
+    /// Channel offset in pixels of the chromatic aberration at the strongest hit.
+    pub aberration_max_offset: f32,
+    /// Time constant in seconds of the chromatic aberration's decay.
+    pub aberration_decay: f32,
+}
+
+/// One pass of the chain.
+enum Pass {
+    BuiltIn(wgpu::RenderPipeline),
+    /// An effect added by library code, which draws its passes on its own.
+    Custom(Box<dyn effect::Effect>),
+}
+
+/// An ordered chain of fullscreen effects applied to the rendered frame.
+///
+/// The scene is resolved into one of two offscreen textures. Every pass samples one of
+/// them and renders into the other, except for the last pass which renders to the surface.
+pub struct PostProcessChain {
+    passes: Vec<Pass>,
+    format: wgpu::TextureFormat,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    globals_buffer: wgpu::Buffer,
+    globals: PostProcessGlobals,
+    options: PostProcessOptions,
+    beat_detector: BeatDetector,
+    /// Strength of the chromatic aberration from 0 to 1, jumping up on hits and decaying.
+    aberration: f32,
+    targets: [wgpu::TextureView; 2],
+    /// `bind_groups[i]` samples `targets[i]`.
+    bind_groups: [wgpu::BindGroup; 2],
+}
+
+impl PostProcessChain {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        effects: &[Effect],
+        options: PostProcessOptions,
+    ) -> Self {
+        let globals = PostProcessGlobals {
+            window_size: [width as f32, height as f32],
+            time: 0.0,
+            intensity: 0.0,
+            aberration: 0.0,
+            _padding: [0.0; 3],
+        };
+
+        let globals_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Post Process Globals Buffer"),
+            contents: bytemuck::bytes_of(&globals),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Post Process Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let passes = effects
+            .iter()
+            .map(|effect| {
+                let (label, shader_source) = Self::effect_shader(*effect);
+                Pass::BuiltIn(Renderer::create_fullscreen_pipeline(
+                    device,
+                    format,
+                    1,
+                    wgpu::BlendState::REPLACE,
+                    label,
+                    shader_source,
+                    &bind_group_layout,
+                ))
+            })
+            .collect();
+
+        let targets = Self::create_targets(device, format, width, height);
+        let bind_groups = Self::create_bind_groups(
+            device,
+            &bind_group_layout,
+            &targets,
+            &sampler,
+            &globals_buffer,
+        );
+
+        Self {
+            passes,
+            format,
+            bind_group_layout,
+            sampler,
+            globals_buffer,
+            globals,
+            options,
+            beat_detector: BeatDetector::default(),
+            aberration: 0.0,
+            targets,
+            bind_groups,
+        }
+    }
+
+    fn effect_shader(effect: Effect) -> (&'static str, &'static str) {
+        match effect {
+            Effect::Bloom => ("Bloom Effect", include_str!("shaders/effect_bloom.wgsl")),
+            Effect::Blur => ("Blur Effect", include_str!("shaders/effect_blur.wgsl")),
+            Effect::Vignette => (
+                "Vignette Effect",
+                include_str!("shaders/effect_vignette.wgsl"),
+            ),
+            Effect::ChromaticAberration => (
+                "Chromatic Aberration Effect",
+                include_str!("shaders/effect_chromatic_aberration.wgsl"),
+            ),
+            Effect::Crt => ("CRT Effect", include_str!("shaders/effect_crt.wgsl")),
+            Effect::Fxaa => ("FXAA Effect", include_str!("shaders/effect_fxaa.wgsl")),
+        }
+    }
+
+    /// The texture the scene should be resolved into instead of the surface.
+    pub fn scene_target(&self) -> &wgpu::TextureView {
+        &self.targets[0]
+    }
+
+    /// Runs `effect`, which has been set up, after the passes there are.
+    pub fn push(&mut self, effect: Box<dyn effect::Effect>) {
+        self.passes.push(Pass::Custom(effect));
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        for pass in &mut self.passes {
+            if let Pass::Custom(effect) = pass {
+                effect.resize(device, width, height);
+            }
+        }
+
+        self.globals.window_size = [width as f32, height as f32];
+        self.targets = Self::create_targets(device, self.format, width, height);
+        self.bind_groups = Self::create_bind_groups(
+            device,
+            &self.bind_group_layout,
+            &self.targets,
+            &self.sampler,
+            &self.globals_buffer,
+        );
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, uniforms: &EffectUniforms) {
+        for pass in &mut self.passes {
+            if let Pass::Custom(effect) = pass {
+                effect.update(queue, uniforms);
+            }
+        }
+
+        let EffectUniforms {
+            time,
+            delta_time,
+            intensity,
+            ..
+        } = *uniforms;
+        let hit = self.beat_detector.update(intensity, delta_time);
+        let decay = (-delta_time / self.options.aberration_decay.max(f32::EPSILON)).exp();
+        self.aberration = (self.aberration * decay).max(hit);
+
+        self.globals.time = time;
+        self.globals.intensity = intensity;
+        self.globals.aberration = self.aberration * self.options.aberration_max_offset;
+        queue.write_buffer(&self.globals_buffer, 0, bytemuck::bytes_of(&self.globals));
+    }
+
+    /// Runs every pass of the chain, starting from `scene_target` and ending in `output`.
+    pub fn apply(&self, encoder: &mut wgpu::CommandEncoder, output: &wgpu::TextureView) {
+        for (i, pass) in self.passes.iter().enumerate() {
+            let input = i % 2;
+            let target = if i + 1 == self.passes.len() {
+                output
+            } else {
+                &self.targets[1 - input]
+            };
+
+            let pipeline = match pass {
+                Pass::BuiltIn(pipeline) => pipeline,
+                Pass::Custom(effect) => {
+                    effect.encode(encoder, Some(&self.targets[input]), target);
+                    continue;
+                }
+            };
+
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Post Process Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    depth_slice: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, &self.bind_groups[input], &[]);
+            render_pass.draw(0..4, 0..1);
+        }
+    }
+
+    fn create_targets(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> [wgpu::TextureView; 2] {
+        [0, 1].map(|i| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(&format!("Post Process Texture {i}")),
+                    size: wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        })
+    }
+
+    fn create_bind_groups(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        targets: &[wgpu::TextureView; 2],
+        sampler: &wgpu::Sampler,
+        globals_buffer: &wgpu::Buffer,
+    ) -> [wgpu::BindGroup; 2] {
+        [0, 1].map(|i| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(&format!("Post Process Bind Group {i}")),
+                layout: bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&targets[i]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: globals_buffer.as_entire_binding(),
+                    },
+                ],
+            })
+        })
+    }
+}
@@ -0,0 +1,106 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+};
+
+use zbus::{
+    MatchRule,
+    blocking::{Connection, MessageIterator, Proxy},
+    message::Type,
+    zvariant::OwnedValue,
+};
+
+const UPOWER: &str = "org.freedesktop.UPower";
+const UPOWER_PATH: &str = "/org/freedesktop/UPower";
+/// The combined battery UPower shows in panels.
+const DISPLAY_DEVICE_PATH: &str = "/org/freedesktop/UPower/devices/DisplayDevice";
+
+/// Watches UPower for the machine running on a battery that is low enough to save power.
+pub struct PowerMonitor {
+    low_power: Arc<AtomicBool>,
+}
+
+impl PowerMonitor {
+    /// Starts watching, with `threshold` the battery percentage at or below which power is
+    /// saved.
+    pub fn spawn(threshold: f32) -> Self {
+        let low_power = Arc::new(AtomicBool::new(false));
+
+        {
+            let low_power = Arc::clone(&low_power);
+            thread::spawn(move || {
+                if let Err(e) = watch_upower(&low_power, threshold) {
+                    log::warn!("Unable to follow the battery through UPower: {e}");
+                }
+            });
+        }
+
+        Self { low_power }
+    }
+
+    pub fn is_low_power(&self) -> bool {
+        self.low_power.load(Ordering::Relaxed)
+    }
+}
+
+/// Follows the property changes of UPower on the system bus. Only returns on errors.
+fn watch_upower(low_power: &AtomicBool, threshold: f32) -> anyhow::Result<()> {
+    let connection = Connection::system()?;
+    let upower = Proxy::new(&connection, UPOWER, UPOWER_PATH, UPOWER)?;
+    let battery = Proxy::new(
+        &connection,
+        UPOWER,
+        DISPLAY_DEVICE_PATH,
+        "org.freedesktop.UPower.Device",
+    )?;
+
+    // Subscribe before reading the properties so no change falls in between.
+    let rule = MatchRule::builder()
+        .msg_type(Type::Signal)
+        .sender(UPOWER)?
+        .member("PropertiesChanged")?
+        .build();
+    let messages = MessageIterator::for_match_rule(rule, &connection, None)?;
+
+    let mut on_battery: bool = upower.get_property("OnBattery")?;
+    let mut percentage: f64 = battery.get_property("Percentage")?;
+    low_power.store(
+        on_battery && percentage <= threshold as f64,
+        Ordering::Relaxed,
+    );
+
+    for message in messages {
+        let message = message?;
+        let header = message.header();
+        let Some(path) = header.path() else {
+            continue;
+        };
+
+        let (_, changed, _): (String, HashMap<String, OwnedValue>, Vec<String>) =
+            message.body().deserialize()?;
+        match path.as_str() {
+            UPOWER_PATH => {
+                if let Some(value) = changed.get("OnBattery") {
+                    on_battery = value.downcast_ref()?;
+                }
+            }
+            DISPLAY_DEVICE_PATH => {
+                if let Some(value) = changed.get("Percentage") {
+                    percentage = value.downcast_ref()?;
+                }
+            }
+            _ => continue,
+        }
+
+        let is_low_power = on_battery && percentage <= threshold as f64;
+        if low_power.swap(is_low_power, Ordering::Relaxed) != is_low_power {
+            log::info!("On battery: {on_battery}, charge: {percentage:.0}%");
+        }
+    }
+
+    Ok(())
+}
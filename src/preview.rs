@@ -0,0 +1,395 @@
+use std::{
+    ffi::OsString,
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+use winit::{
+    application::ApplicationHandler,
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{ElementState, MouseButton, WindowEvent},
+    event_loop::{ActiveEventLoop, EventLoop},
+    keyboard::{Key, NamedKey},
+    window::{Window, WindowId},
+};
+
+use crate::{
+    ConnectingDots,
+    config::{Config, Preset},
+    control::override_args,
+    state::State,
+    volume_providers::volume_provider::{VolumeProvider, get_volume_provider},
+};
+
+const TITLE: &str = "connecting-dots preview: click a preset to pick it";
+/// Size of the window when --width and --height are not given.
+const DEFAULT_SIZE: PhysicalSize<u32> = PhysicalSize::new(1280, 720);
+/// Seconds the loudest volume heard takes to fade to a third, so quiet songs move the points
+/// too.
+const LOUDEST_DECAY: f32 = 20.0;
+/// Volume below which nothing is scaled up, so silence stays still.
+const MIN_LOUDEST: f32 = 0.01;
+
+/// Opens a window showing every preset side by side in a grid, each with the options of
+/// `args` it does not set itself, all following the same music. Clicking one prints its
+/// options and closes the window.
+pub fn run(config: Config, args: &[OsString]) -> anyhow::Result<()> {
+    crate::init_logging(&config)?;
+
+    let presets = Preset::value_variants()
+        .iter()
+        .map(|&preset| {
+            let name = preset
+                .to_possible_value()
+                .map(|value| value.get_name().to_owned())
+                .ok_or_else(|| anyhow::anyhow!("The preset {preset:?} has no name"))?;
+            let options = Map::from_iter([("preset".to_owned(), Value::String(name))]);
+            let config = Config::try_parse_from(override_args(args, &options)?)?;
+            Ok((preset, config))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut preview = Preview {
+        size: match (config.width, config.height) {
+            (Some(width), Some(height)) => PhysicalSize::new(width, height),
+            _ => DEFAULT_SIZE,
+        },
+        presets,
+        window: None,
+        gpu: None,
+        cells: Vec::new(),
+        volume_provider: get_volume_provider(),
+        loudest: MIN_LOUDEST,
+        intensity: 0.0,
+        last_frame: Instant::now(),
+        cursor: None,
+        result: Ok(()),
+    };
+    EventLoop::new()?.run_app(&mut preview)?;
+
+    preview.result
+}
+
+/// The device all presets draw with and the surface of the window they end up in.
+struct Gpu {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    surface: wgpu::Surface<'static>,
+    config: wgpu::SurfaceConfiguration,
+}
+
+/// A preset drawing into its own texture, which is copied into its place in the grid.
+struct Cell {
+    preset: Preset,
+    dots: ConnectingDots,
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    origin: PhysicalPosition<u32>,
+}
+
+struct Preview {
+    size: PhysicalSize<u32>,
+    presets: Vec<(Preset, Config)>,
+    window: Option<Arc<Window>>,
+    gpu: Option<Gpu>,
+    cells: Vec<Cell>,
+    volume_provider: Rc<dyn VolumeProvider>,
+    /// The loudest volume heard lately, which moves the points the most.
+    loudest: f32,
+    intensity: f32,
+    last_frame: Instant,
+    cursor: Option<PhysicalPosition<f64>>,
+    result: anyhow::Result<()>,
+}
+
+impl Preview {
+    fn open(&mut self, event_loop: &ActiveEventLoop) -> anyhow::Result<()> {
+        let window = Arc::new(
+            event_loop.create_window(
+                Window::default_attributes()
+                    .with_title(TITLE)
+                    .with_inner_size(self.size),
+            )?,
+        );
+
+        let instance = State::create_instance();
+        let surface = instance.create_surface(window.clone())?;
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            force_fallback_adapter: false,
+            compatible_surface: Some(&surface),
+        }))?;
+        let (device, queue) =
+            pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default()))?;
+
+        let caps = surface.get_capabilities(&adapter);
+        if !caps.usages.contains(wgpu::TextureUsages::COPY_DST) {
+            anyhow::bail!("The surface of the window cannot be copied into");
+        }
+        let format = caps
+            .formats
+            .iter()
+            .find(|format| format.is_srgb())
+            .copied()
+            .unwrap_or(caps.formats[0]);
+        let size = window.inner_size();
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_DST,
+            format,
+            width: size.width.max(1),
+            height: size.height.max(1),
+            present_mode: wgpu::PresentMode::AutoVsync,
+            alpha_mode: caps.alpha_modes[0],
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+        surface.configure(&device, &config);
+
+        let (width, height) = self.cell_size(config.width, config.height);
+        self.cells = self
+            .presets
+            .iter()
+            .enumerate()
+            .map(|(index, (preset, preset_config))| {
+                let dots =
+                    ConnectingDots::new(&device, &queue, format, width, height, preset_config)?;
+                let (texture, view) = create_texture(&device, format, width, height);
+                Ok(Cell {
+                    preset: *preset,
+                    dots,
+                    texture,
+                    view,
+                    origin: self.cell_origin(index, width, height),
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        window.request_redraw();
+        self.window = Some(window);
+        self.gpu = Some(Gpu {
+            device,
+            queue,
+            surface,
+            config,
+        });
+
+        Ok(())
+    }
+
+    /// Columns and rows of the grid, as close to square as the number of presets allows.
+    fn grid(&self) -> (u32, u32) {
+        let count = self.presets.len() as u32;
+        let columns = (count as f32).sqrt().ceil() as u32;
+        (columns, count.div_ceil(columns))
+    }
+
+    fn cell_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let (columns, rows) = self.grid();
+        ((width / columns).max(1), (height / rows).max(1))
+    }
+
+    fn cell_origin(&self, index: usize, width: u32, height: u32) -> PhysicalPosition<u32> {
+        let (columns, _) = self.grid();
+        let index = index as u32;
+        PhysicalPosition::new(index % columns * width, index / columns * height)
+    }
+
+    fn resize(&mut self, size: PhysicalSize<u32>) {
+        if size.width == 0 || size.height == 0 {
+            return;
+        }
+        let (width, height) = self.cell_size(size.width, size.height);
+        let origins: Vec<_> = (0..self.cells.len())
+            .map(|index| self.cell_origin(index, width, height))
+            .collect();
+        let Some(gpu) = &mut self.gpu else {
+            return;
+        };
+
+        gpu.config.width = size.width;
+        gpu.config.height = size.height;
+        gpu.surface.configure(&gpu.device, &gpu.config);
+        for (cell, origin) in self.cells.iter_mut().zip(origins) {
+            cell.dots.resize(width, height);
+            (cell.texture, cell.view) =
+                create_texture(&gpu.device, gpu.config.format, width, height);
+            cell.origin = origin;
+        }
+    }
+
+    /// Follows the music the way the visualization does on its own, scaled to the loudest
+    /// it has been lately and fading out when the volume provider has nothing new.
+    fn update_intensity(&mut self, dt: f32) {
+        let volume = self.volume_provider.poll_volume().unwrap_or_else(|e| {
+            log::warn!("Lost the audio: {e}");
+            None
+        });
+        self.intensity = match volume {
+            Some(volume) => {
+                let decay = (-dt / LOUDEST_DECAY).exp();
+                self.loudest = (self.loudest * decay).max(volume).max(MIN_LOUDEST);
+                volume / self.loudest
+            }
+            None => (self.intensity - dt / LOUDEST_DECAY).max(0.0),
+        };
+    }
+
+    fn draw(&mut self) -> anyhow::Result<()> {
+        let dt = self.last_frame.elapsed();
+        self.last_frame = Instant::now();
+        self.update_intensity(dt.as_secs_f32());
+
+        let Some(gpu) = &self.gpu else {
+            return Ok(());
+        };
+        let output = match gpu.surface.get_current_texture() {
+            Ok(output) => output,
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                gpu.surface.configure(&gpu.device, &gpu.config);
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Preview Encoder"),
+            });
+        for cell in &mut self.cells {
+            cell.dots.render_to_texture(
+                &cell.view,
+                dt.min(Duration::from_millis(100)),
+                self.intensity,
+            );
+            encoder.copy_texture_to_texture(
+                cell.texture.as_image_copy(),
+                wgpu::TexelCopyTextureInfo {
+                    texture: &output.texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: cell.origin.x,
+                        y: cell.origin.y,
+                        z: 0,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                cell.texture.size(),
+            );
+        }
+        gpu.queue.submit(std::iter::once(encoder.finish()));
+        output.present();
+
+        Ok(())
+    }
+
+    /// The preset under the cursor, if any.
+    fn hovered(&self) -> Option<Preset> {
+        let cursor = self.cursor?;
+        self.cells
+            .iter()
+            .find(|cell| {
+                let size = cell.texture.size();
+                (cell.origin.x as f64..(cell.origin.x + size.width) as f64).contains(&cursor.x)
+                    && (cell.origin.y as f64..(cell.origin.y + size.height) as f64)
+                        .contains(&cursor.y)
+            })
+            .map(|cell| cell.preset)
+    }
+
+    /// Names the preset under the cursor in the title, as the cells have no labels.
+    fn update_title(&self) {
+        if let Some(window) = &self.window {
+            window.set_title(&match self.hovered() {
+                Some(preset) => format!("{TITLE} ({preset:?})"),
+                None => TITLE.to_owned(),
+            });
+        }
+    }
+}
+
+impl ApplicationHandler for Preview {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.window.is_some() {
+            return;
+        }
+        if let Err(e) = self.open(event_loop) {
+            self.result = Err(e);
+            event_loop.exit();
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::KeyboardInput { event, .. }
+                if event.state == ElementState::Pressed
+                    && event.logical_key == Key::Named(NamedKey::Escape) =>
+            {
+                event_loop.exit();
+            }
+            WindowEvent::Resized(size) => self.resize(size),
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = Some(position);
+                self.update_title();
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor = None;
+                self.update_title();
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => {
+                // Prints the options to add to the command line or config file.
+                if let Some(preset) = self.hovered() {
+                    if let Some(value) = preset.to_possible_value() {
+                        println!("--preset={}", value.get_name());
+                    }
+                    println!("{}", preset.args().join(" "));
+                    event_loop.exit();
+                }
+            }
+            WindowEvent::RedrawRequested => {
+                if let Err(e) = self.draw() {
+                    self.result = Err(e);
+                    event_loop.exit();
+                    return;
+                }
+                if let Some(window) = &self.window {
+                    window.request_redraw();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn create_texture(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+) -> (wgpu::Texture, wgpu::TextureView) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Preview Cell Texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    (texture, view)
+}
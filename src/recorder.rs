@@ -0,0 +1,311 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, Command, Stdio},
+    time::Duration,
+};
+
+use anyhow::{Result, anyhow};
+use image::{
+    Delay, Frame, RgbaImage,
+    codecs::gif::{GifEncoder, Repeat},
+};
+
+use crate::config::Config;
+
+/// Speed of the GIF color quantization from 1 (best) to 30 (fastest).
+const GIF_SPEED: i32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingFormat {
+    /// Any video format ffmpeg can write, picked from the file extension.
+    Video,
+    /// A looping animated GIF, encoded in-process.
+    Gif,
+}
+
+pub struct RecordingOptions {
+    pub format: RecordingFormat,
+    pub path: PathBuf,
+    pub size: (u32, u32),
+    pub fps: u32,
+    pub duration: Duration,
+}
+
+impl RecordingOptions {
+    /// Returns the recording requested by `--record` or `--export-gif`, if any.
+    pub fn new(config: &Config) -> Option<Self> {
+        if let Some(path) = &config.record {
+            Some(Self {
+                format: RecordingFormat::Video,
+                path: path.clone(),
+                size: config.record_size,
+                fps: config.record_fps,
+                duration: config.duration,
+            })
+        } else {
+            config.export_gif.as_ref().map(|path| Self {
+                format: RecordingFormat::Gif,
+                path: path.clone(),
+                size: config.gif_size,
+                fps: config.gif_fps,
+                duration: config.duration,
+            })
+        }
+    }
+}
+
+/// Where the frames read back from the GPU end up.
+enum Output {
+    Ffmpeg {
+        child: Child,
+        stdin: Option<ChildStdin>,
+    },
+    Gif(Option<GifEncoder<BufWriter<File>>>),
+}
+
+/// Renders frames into an offscreen texture, reads them back and encodes them, either into a
+/// video file by piping the raw pixels through an `ffmpeg` subprocess or into a GIF.
+pub struct Recorder {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    readback_buffer: wgpu::Buffer,
+    /// Bytes per row of the readback buffer, padded to the copy alignment.
+    padded_bytes_per_row: u32,
+    width: u32,
+    height: u32,
+    /// Whether the texture stores blue before red.
+    bgra: bool,
+    output: Output,
+    fps: u32,
+    frame_time: Duration,
+    frames_written: u32,
+    frames_total: u32,
+}
+
+impl Recorder {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        options: RecordingOptions,
+    ) -> Result<Self> {
+        let (width, height) = options.size;
+        let bgra = match format {
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            _ => {
+                return Err(anyhow!(
+                    "Recording from a {format:?} surface is not supported"
+                ));
+            }
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Recording Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let padded_bytes_per_row = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Recording Readback Buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let output = match options.format {
+            RecordingFormat::Video => {
+                Self::spawn_ffmpeg(&options.path, bgra, width, height, options.fps)?
+            }
+            RecordingFormat::Gif => {
+                let mut encoder = GifEncoder::new_with_speed(
+                    BufWriter::new(File::create(&options.path)?),
+                    GIF_SPEED,
+                );
+                encoder.set_repeat(Repeat::Infinite)?;
+                Output::Gif(Some(encoder))
+            }
+        };
+
+        Ok(Self {
+            texture,
+            view,
+            readback_buffer,
+            padded_bytes_per_row,
+            width,
+            height,
+            bgra,
+            output,
+            fps: options.fps,
+            frame_time: Duration::from_secs_f64(1.0 / options.fps as f64),
+            frames_written: 0,
+            frames_total: (options.duration.as_secs_f64() * options.fps as f64)
+                .round()
+                .max(1.0) as u32,
+        })
+    }
+
+    fn spawn_ffmpeg(path: &Path, bgra: bool, width: u32, height: u32, fps: u32) -> Result<Output> {
+        let pixel_format = if bgra { "bgra" } else { "rgba" };
+
+        let mut child = Command::new("ffmpeg")
+            .args(["-loglevel", "error", "-nostdin", "-y"])
+            .args(["-f", "rawvideo", "-pix_fmt", pixel_format])
+            .args(["-s", &format!("{width}x{height}"), "-r", &fps.to_string()])
+            .args(["-i", "-", "-pix_fmt", "yuv420p"])
+            .arg(path)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| anyhow!("Failed to start ffmpeg: {e}"))?;
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or(anyhow!("Failed to open ffmpeg input"))?;
+
+        Ok(Output::Ffmpeg {
+            child,
+            stdin: Some(stdin),
+        })
+    }
+
+    /// The texture frames are rendered into instead of the surface.
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    /// How far the simulation advances between two frames of the recording.
+    pub fn frame_time(&self) -> Duration {
+        self.frame_time
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frames_written >= self.frames_total
+    }
+
+    /// Copies the rendered frame into the readback buffer. Must be submitted before
+    /// `write_frame` is called.
+    pub fn copy_frame(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.copy_texture_to_buffer(
+            self.texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &self.readback_buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// Waits for the copied frame and encodes it.
+    pub fn write_frame(&mut self, device: &wgpu::Device) -> Result<()> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.readback_buffer
+            .map_async(wgpu::MapMode::Read, .., move |result| {
+                let _ = sender.send(result);
+            });
+        device.poll(wgpu::PollType::wait_indefinitely())?;
+        receiver.recv()??;
+
+        // Strip the row padding.
+        let row_size = (self.width * 4) as usize;
+        let pixels: Vec<u8> = self
+            .readback_buffer
+            .get_mapped_range(..)
+            .chunks_exact(self.padded_bytes_per_row as usize)
+            .flat_map(|row| &row[..row_size])
+            .copied()
+            .collect();
+        self.readback_buffer.unmap();
+
+        match &mut self.output {
+            Output::Ffmpeg {
+                stdin: Some(stdin), ..
+            } => stdin
+                .write_all(&pixels)
+                .map_err(|e| anyhow!("Failed to send a frame to ffmpeg: {e}"))?,
+            Output::Gif(Some(encoder)) => {
+                let mut pixels = pixels;
+                for pixel in pixels.chunks_exact_mut(4) {
+                    if self.bgra {
+                        pixel.swap(0, 2);
+                    }
+                    // GIFs only have on/off transparency, so keep the frame opaque.
+                    pixel[3] = u8::MAX;
+                }
+                let image = RgbaImage::from_raw(self.width, self.height, pixels)
+                    .ok_or(anyhow!("Frame does not match the recording size"))?;
+                encoder.encode_frame(Frame::from_parts(
+                    image,
+                    0,
+                    0,
+                    Delay::from_numer_denom_ms(1000, self.fps),
+                ))?;
+            }
+            _ => return Err(anyhow!("The recording has already finished")),
+        }
+
+        self.frames_written += 1;
+        if self.frames_written.is_multiple_of(60) || self.is_finished() {
+            log::info!(
+                "Recorded {} of {} frames",
+                self.frames_written,
+                self.frames_total
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Closes the stream and waits for the file to be completely written.
+    pub fn finish(&mut self) -> Result<()> {
+        match &mut self.output {
+            Output::Ffmpeg { child, stdin } => {
+                // Dropping the input signals the end of the video to ffmpeg.
+                *stdin = None;
+
+                let status = child.wait()?;
+                if !status.success() {
+                    return Err(anyhow!("ffmpeg exited with {status}"));
+                }
+            }
+            Output::Gif(encoder) => {
+                // The encoder writes the end of the GIF when it is dropped.
+                drop(encoder.take());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Output::Ffmpeg {
+            child,
+            stdin: Some(_),
+        } = &mut self.output
+        {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
@@ -0,0 +1,1369 @@
+use std::time::Duration;
+
+use bytemuck::{Pod, Zeroable};
+use log::info;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use wgpu::util::DeviceExt;
+use winit::dpi::PhysicalSize;
+
+use crate::background_image::{BackgroundImage, BackgroundImageOptions};
+use crate::color::Color;
+use crate::config::{
+    AntiAliasing, Background, BackgroundImages, Config, Effect, GradientShape, IntroAnimation,
+    ProceduralPreset, RenderMode,
+};
+use crate::effect::{self, EffectPlacement, EffectUniforms};
+use crate::lines::{LineOptions, Lines};
+use crate::metaballs::Metaballs;
+use crate::overlay::{Overlay, OverlayOptions};
+use crate::point_data::{NO_DATA, PointData};
+use crate::post_process::{PostProcessChain, PostProcessOptions};
+use crate::simulation::{Simulation, layer_configs};
+use crate::sparks::{SparkOptions, Sparks};
+
+const INTENSITY_HISTORY_LENGTH: u32 = 256;
+/// Shortest connection distance the keyboard goes down to, in pixels.
+const MIN_CONNECTION_DISTANCE: f32 = 5.0;
+/// Sample count WebGPU guarantees for every renderable format, the one MSAA uses when the
+/// adapter is not at hand to ask.
+const GUARANTEED_SAMPLE_COUNT: u32 = 4;
+
+/// Draws a [`Simulation`] into any texture view of one format and size: the backgrounds,
+/// the lines, the points and the effects on top. It knows nothing of windows or surfaces,
+/// so the frames can end up wherever the texture views come from.
+pub struct Renderer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+    sample_count: u32,
+    /// Multisampled render target, absent when rendering without MSAA.
+    msaa_texture_view: Option<wgpu::TextureView>,
+
+    window_pos_buffer: wgpu::Buffer,
+    point_size_buffer: wgpu::Buffer,
+    min_point_size: f32,
+    max_point_size: f32,
+    intensity_history_texture: wgpu::Texture,
+    intensity_history_head_buffer: wgpu::Buffer,
+    intensity_history_head: u32,
+    intro_buffer: wgpu::Buffer,
+    intro: IntroUniform,
+    intro_duration: f32,
+
+    render_pipeline: wgpu::RenderPipeline,
+    /// What is drawn of every layer of the simulation, in the same order.
+    layers: Vec<LayerDrawing>,
+
+    /// Whether the points are tinted by the background image, which is then bound as the
+    /// second bind group of the render pipeline.
+    point_color_from_background: bool,
+
+    clear_color: wgpu::Color,
+    background_gradient_state: Option<(wgpu::RenderPipeline, wgpu::BindGroup)>,
+    background_image: Option<BackgroundImage>,
+    /// Kept to load the background image again when the monitor changes.
+    background_images: Option<BackgroundImages>,
+    background_options: BackgroundImageOptions,
+    /// Name and size of the monitor the target covers.
+    monitor: (Option<String>, Option<PhysicalSize<u32>>),
+    post_process: Option<PostProcessChain>,
+    sparks: Option<Sparks>,
+    overlay: Option<Overlay>,
+    procedural_background_state: Option<(wgpu::RenderPipeline, wgpu::BindGroup)>,
+    point_data: Option<PointData>,
+    /// Effects added by library code, drawn on top of everything before post-processing.
+    layer_effects: Vec<Box<dyn effect::Effect>>,
+}
+
+/// Where and how a [`Renderer`] draws, beyond what the config says.
+pub(crate) struct RendererOptions {
+    pub format: wgpu::TextureFormat,
+    pub width: u32,
+    pub height: u32,
+    pub sample_count: u32,
+    /// Name and size of the monitor the target covers, which picks the background image.
+    pub monitor: (Option<String>, Option<PhysicalSize<u32>>),
+    /// Offset of the bottom left corner of the target from the bottom left of the monitor.
+    pub window_pos: [f32; 2],
+    /// Whether the overlay is there for notices even without options to show.
+    pub notices: bool,
+}
+
+impl Renderer {
+    /// Sets up drawing `simulation` with `config` into texture views of `format`, `width` by
+    /// `height` pixels, on `device`. MSAA uses 4 samples, the count every adapter supports.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        config: &Config,
+        simulation: &Simulation,
+    ) -> anyhow::Result<Self> {
+        let sample_count = match config.aa {
+            AntiAliasing::Msaa if config.msaa >= GUARANTEED_SAMPLE_COUNT => {
+                if config.msaa != GUARANTEED_SAMPLE_COUNT {
+                    log::warn!(
+                        "{}x MSAA is not known to be supported, using {GUARANTEED_SAMPLE_COUNT}x \
+                         instead",
+                        config.msaa
+                    );
+                }
+                GUARANTEED_SAMPLE_COUNT
+            }
+            AntiAliasing::Msaa | AntiAliasing::Fxaa | AntiAliasing::Off => 1,
+        };
+
+        Self::with_options(
+            device,
+            queue,
+            config,
+            simulation,
+            RendererOptions {
+                format,
+                width,
+                height,
+                sample_count,
+                monitor: (None, Some(PhysicalSize::new(width, height))),
+                window_pos: [0.0, 0.0],
+                notices: false,
+            },
+        )
+    }
+
+    pub(crate) fn with_options(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        config: &Config,
+        simulation: &Simulation,
+        options: RendererOptions,
+    ) -> anyhow::Result<Self> {
+        let RendererOptions {
+            format,
+            width,
+            height,
+            sample_count,
+            monitor,
+            window_pos,
+            notices,
+        } = options;
+
+        let mut rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed.wrapping_add(1)),
+            None => StdRng::from_os_rng(),
+        };
+
+        let msaa_texture_view =
+            Self::create_msaa_texture(device, format, width, height, sample_count);
+
+        let window_size_buffer = simulation.window_size_buffer();
+        let intensity_buffer = simulation.intensity_buffer();
+
+        let window_pos_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Window Position Buffer"),
+            contents: bytemuck::bytes_of(&window_pos),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let point_size = config.min_point_size;
+
+        let point_size_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Point Size Buffer"),
+            contents: bytemuck::bytes_of(&point_size),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let (intensity_history_texture, intensity_history_texture_view) =
+            Self::create_intensity_history_texture(device);
+
+        let intensity_history_head = 0u32;
+
+        let intensity_history_head_buffer =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Intensity History Head Buffer"),
+                contents: bytemuck::bytes_of(&intensity_history_head),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+
+        let point_data_style = PointDataStyle {
+            low_color: config.point_data_low_color.to_linear(),
+            high_color: config.point_data_high_color.to_linear(),
+            min_scale: config.point_data_min_scale,
+            max_scale: config.point_data_max_scale,
+            _padding: [0.0; 2],
+        };
+
+        let point_data_style_buffer =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Point Data Style Buffer"),
+                contents: bytemuck::bytes_of(&point_data_style),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+
+        let intro = IntroUniform {
+            progress: if config.intro_duration > 0.0 {
+                0.0
+            } else {
+                1.0
+            },
+            mode: match config.intro {
+                IntroAnimation::None => 0,
+                IntroAnimation::Fade => 1,
+                IntroAnimation::Converge => 2,
+            },
+        };
+
+        let intro_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Intro Buffer"),
+            contents: bytemuck::bytes_of(&intro),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        if config.oled
+            && (config.background_gradient.is_some()
+                || config.background_image.is_some()
+                || config.background.is_some())
+        {
+            log::warn!("OLED mode enforces a black background, ignoring configured backgrounds");
+        }
+
+        let procedural_background_state =
+            config
+                .background
+                .filter(|_| !config.oled)
+                .map(|Background::Procedural(preset)| {
+                    Self::create_procedural_background_pipeline(
+                        device,
+                        format,
+                        sample_count,
+                        preset,
+                        simulation.time_buffer(),
+                        intensity_buffer,
+                        window_size_buffer,
+                    )
+                });
+
+        let background_gradient_state =
+            config
+                .background_gradient
+                .filter(|_| !config.oled)
+                .map(|end| {
+                    Self::create_background_gradient_pipeline(
+                        device,
+                        format,
+                        sample_count,
+                        window_size_buffer,
+                        config.background_color,
+                        end,
+                        config.background_gradient_shape,
+                    )
+                });
+
+        let background_images = config.background_image.clone().filter(|_| !config.oled);
+        let background_options = BackgroundImageOptions {
+            mode: config.background_mode,
+            align: config.background_align,
+            blur: config.background_blur,
+            zoom: config.background_zoom,
+            #[cfg(feature = "video")]
+            video_sync_to_render: config.video_sync_render,
+        };
+        let background_image = Self::load_background_image(
+            device,
+            queue,
+            format,
+            sample_count,
+            background_images.as_ref(),
+            background_options,
+            &monitor,
+            [window_size_buffer, &window_pos_buffer, intensity_buffer],
+        )?;
+
+        let sparks = config.sparks.then(|| {
+            Sparks::new(
+                device,
+                format,
+                sample_count,
+                window_size_buffer,
+                simulation.delta_time_buffer(),
+                StdRng::from_rng(&mut rng),
+                SparkOptions {
+                    pool_size: config.spark_pool as usize,
+                    lifetime: config.spark_lifetime,
+                },
+            )
+        });
+
+        // Without options to show the overlay is still there for notices, when asked for.
+        let overlay = if notices || config.show_fps || config.show_intensity || config.show_track {
+            Some(Overlay::new(
+                device,
+                queue,
+                format,
+                sample_count,
+                window_size_buffer,
+                OverlayOptions {
+                    fps: config.show_fps,
+                    intensity: config.show_intensity,
+                    track: config.show_track,
+                },
+            )?)
+        } else {
+            None
+        };
+
+        let mut effects = config.effects.clone();
+        if config.aa == AntiAliasing::Fxaa {
+            effects.push(Effect::Fxaa);
+        }
+
+        let post_process = (!effects.is_empty()).then(|| {
+            PostProcessChain::new(
+                device,
+                format,
+                width,
+                height,
+                &effects,
+                PostProcessOptions {
+                    aberration_max_offset: config.aberration_max_offset,
+                    aberration_decay: config.aberration_decay,
+                },
+            )
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/shader.wgsl").into()),
+        });
+
+        let render_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Render Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D1,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 5,
+                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 6,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 7,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 8,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        // Sampling the background binds its bind group as the second group.
+        let sampled_background = background_image
+            .as_ref()
+            .filter(|_| config.point_color_from_background);
+        let mut render_bind_group_layouts = vec![&render_bind_group_layout];
+        render_bind_group_layouts
+            .extend(sampled_background.map(BackgroundImage::bind_group_layout));
+
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &render_bind_group_layouts,
+                push_constant_ranges: &[],
+            });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some(if sampled_background.is_some() {
+                    "fs_background"
+                } else {
+                    "fs_main"
+                }),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        let layer_configs = layer_configs(config);
+        let layers = simulation
+            .layers()
+            .iter()
+            .zip(&layer_configs)
+            .enumerate()
+            .map(|(index, (layer, layer_config))| {
+                let points_buffer = &layer.points_buffer;
+                let points_count = layer.points_count;
+
+                let point_values_buffer =
+                    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some(&format!("Point Values Buffer {index}")),
+                        contents: bytemuck::cast_slice(&vec![NO_DATA; points_count]),
+                        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                    });
+
+                let render_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some(&format!("Render Bind Group {index}")),
+                    layout: &render_bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: points_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: window_size_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: point_size_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 3,
+                            resource: intensity_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 4,
+                            resource: wgpu::BindingResource::TextureView(
+                                &intensity_history_texture_view,
+                            ),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 5,
+                            resource: intensity_history_head_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 6,
+                            resource: point_values_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 7,
+                            resource: point_data_style_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 8,
+                            resource: intro_buffer.as_entire_binding(),
+                        },
+                    ],
+                });
+
+                let connection_distance =
+                    layer_config.distance.unwrap_or(config.connection_distance);
+
+                // The simulation keeps a neighbour grid wherever there are lines to find.
+                let lines = (connection_distance > 0.0).then(|| {
+                    Lines::new(
+                        device,
+                        format,
+                        sample_count,
+                        points_buffer,
+                        points_count,
+                        window_size_buffer,
+                        intensity_buffer,
+                        &intro_buffer,
+                        simulation.cursor_buffer(),
+                        simulation.grid_bind_group_layout(),
+                        LineOptions {
+                            distance: connection_distance,
+                            max_connections: config.max_connections,
+                            cursor: config.cursor_lines,
+                        },
+                    )
+                });
+
+                let metaballs = (config.mode == RenderMode::Metaballs).then(|| {
+                    Metaballs::new(
+                        device,
+                        format,
+                        sample_count,
+                        width,
+                        height,
+                        points_buffer,
+                        points_count,
+                        window_size_buffer,
+                        intensity_buffer,
+                        config.metaball_radius,
+                    )
+                });
+
+                LayerDrawing {
+                    points_count,
+                    point_values_buffer,
+                    render_bind_group,
+                    lines,
+                    metaballs,
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            device: device.clone(),
+            queue: queue.clone(),
+            format,
+            width,
+            height,
+            sample_count,
+            msaa_texture_view,
+            window_pos_buffer,
+            point_size_buffer,
+            min_point_size: config.min_point_size,
+            max_point_size: config.max_point_size,
+            intensity_history_texture,
+            intensity_history_head_buffer,
+            intensity_history_head,
+            intro_buffer,
+            intro,
+            intro_duration: config.intro_duration,
+            render_pipeline,
+            layers,
+            point_color_from_background: sampled_background.is_some(),
+            clear_color: if config.oled {
+                Color::BLACK
+            } else {
+                config.background_color
+            }
+            .to_wgpu(),
+            background_gradient_state,
+            background_image,
+            background_images,
+            background_options,
+            monitor,
+            procedural_background_state,
+            post_process,
+            sparks,
+            overlay,
+            point_data: config.point_data.clone().map(PointData::new),
+            layer_effects: Vec::new(),
+        })
+    }
+
+    /// Format of the texture views the renderer draws into.
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    /// Size in pixels of the texture views the renderer draws into.
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Makes the renderer draw into texture views of `width` by `height` pixels.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.width = width;
+        self.height = height;
+
+        self.msaa_texture_view =
+            Self::create_msaa_texture(&self.device, self.format, width, height, self.sample_count);
+
+        if let Some(post_process) = &mut self.post_process {
+            post_process.resize(&self.device, width, height);
+        }
+
+        for layer in &mut self.layers {
+            if let Some(metaballs) = &mut layer.metaballs {
+                metaballs.resize(&self.device, width, height);
+            }
+        }
+
+        for effect in &mut self.layer_effects {
+            effect.resize(&self.device, width, height);
+        }
+    }
+
+    /// Sets `effect` up and draws it from the next frame on, at `placement`.
+    pub fn add_effect(
+        &mut self,
+        mut effect: Box<dyn effect::Effect>,
+        placement: EffectPlacement,
+    ) -> anyhow::Result<()> {
+        effect.setup(
+            &self.device,
+            &self.queue,
+            self.format,
+            self.width,
+            self.height,
+        )?;
+
+        match placement {
+            EffectPlacement::Layer => self.layer_effects.push(effect),
+            EffectPlacement::PostProcess => self
+                .post_process
+                .get_or_insert_with(|| {
+                    PostProcessChain::new(
+                        &self.device,
+                        self.format,
+                        self.width,
+                        self.height,
+                        &[],
+                        PostProcessOptions {
+                            aberration_max_offset: 0.0,
+                            aberration_decay: 1.0,
+                        },
+                    )
+                })
+                .push(effect),
+        }
+
+        Ok(())
+    }
+
+    /// Lines the background up with the monitor for a target whose bottom left corner is
+    /// `window_pos` pixels from the bottom left of the monitor.
+    pub(crate) fn set_window_pos(&self, window_pos: [f32; 2]) {
+        self.queue
+            .write_buffer(&self.window_pos_buffer, 0, bytemuck::bytes_of(&window_pos));
+    }
+
+    /// Name and size of the monitor the target covers.
+    pub(crate) fn monitor(&self) -> &(Option<String>, Option<PhysicalSize<u32>>) {
+        &self.monitor
+    }
+
+    /// Loads the background image again for `monitor`, so it keeps covering the monitor
+    /// exactly.
+    pub(crate) fn set_monitor(
+        &mut self,
+        simulation: &Simulation,
+        monitor: (Option<String>, Option<PhysicalSize<u32>>),
+    ) {
+        self.monitor = monitor;
+
+        // Whether there is a background at all is baked into the render pipeline, so only an
+        // existing one is swapped out.
+        if self.background_image.is_none() {
+            return;
+        }
+        match Self::load_background_image(
+            &self.device,
+            &self.queue,
+            self.format,
+            self.sample_count,
+            self.background_images.as_ref(),
+            self.background_options,
+            &self.monitor,
+            [
+                simulation.window_size_buffer(),
+                &self.window_pos_buffer,
+                simulation.intensity_buffer(),
+            ],
+        ) {
+            Ok(Some(background_image)) => self.background_image = Some(background_image),
+            Ok(None) => {}
+            Err(e) => log::error!("Unable to load the background image again: {e}"),
+        }
+    }
+
+    /// Advances what is animated apart from the points by `delta_time` at `intensity`, from
+    /// 0 to 1, after `simulation` has been updated for the frame.
+    pub fn update(&mut self, simulation: &Simulation, delta_time: Duration, intensity: f32) {
+        if let Some(background_image) = &mut self.background_image {
+            background_image.update(&self.queue, delta_time);
+        }
+
+        let points_count = self.layers.iter().map(|layer| layer.points_count).sum();
+        if let Some(values) = self
+            .point_data
+            .as_mut()
+            .and_then(|point_data| point_data.poll(delta_time, points_count))
+        {
+            // The values continue from one layer to the next.
+            let mut values = values.as_slice();
+            for layer in &self.layers {
+                let (layer_values, rest) = values.split_at(layer.points_count);
+                self.queue.write_buffer(
+                    &layer.point_values_buffer,
+                    0,
+                    bytemuck::cast_slice(layer_values),
+                );
+                values = rest;
+            }
+        }
+
+        if self.intro.progress < 1.0 {
+            self.intro.progress = (simulation.elapsed_time() / self.intro_duration).min(1.0);
+            self.queue
+                .write_buffer(&self.intro_buffer, 0, bytemuck::bytes_of(&self.intro));
+        }
+
+        let point_size =
+            self.min_point_size + (self.max_point_size - self.min_point_size) * intensity;
+        self.queue
+            .write_buffer(&self.point_size_buffer, 0, bytemuck::bytes_of(&point_size));
+
+        let delta_time = delta_time.as_secs_f32();
+        let window_size = [self.width as f32, self.height as f32];
+        if let Some(sparks) = &mut self.sparks {
+            sparks.update(&self.queue, delta_time, intensity, window_size);
+        }
+
+        if let Some(overlay) = &mut self.overlay {
+            overlay.update(&self.queue, Duration::from_secs_f32(delta_time), intensity);
+        }
+
+        let uniforms = EffectUniforms {
+            time: simulation.elapsed_time(),
+            delta_time,
+            intensity,
+            width: self.width,
+            height: self.height,
+        };
+        for effect in &mut self.layer_effects {
+            effect.update(&self.queue, &uniforms);
+        }
+        if let Some(post_process) = &mut self.post_process {
+            post_process.update(&self.queue, &uniforms);
+        }
+
+        self.push_intensity_history(intensity);
+    }
+
+    /// Records the commands drawing `simulation` into `view` after the passes of
+    /// [`Simulation::encode`].
+    pub fn encode(
+        &self,
+        simulation: &Simulation,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        for lines in self.layers.iter().filter_map(|layer| layer.lines.as_ref()) {
+            lines.reset(&self.queue);
+        }
+
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Find Lines Pass"),
+                timestamp_writes: None,
+            });
+
+            for (layer, simulated) in self.layers.iter().zip(simulation.layers()) {
+                if let Some(lines) = &layer.lines
+                    && let Some(grid) = &simulated.grid
+                {
+                    lines.find(&mut compute_pass, grid);
+                }
+            }
+
+            if let Some(sparks) = &self.sparks {
+                sparks.simulate(&mut compute_pass);
+            }
+        }
+
+        for metaballs in self
+            .layers
+            .iter()
+            .filter_map(|layer| layer.metaballs.as_ref())
+        {
+            metaballs.render_field(encoder);
+        }
+
+        let target = self
+            .post_process
+            .as_ref()
+            .map_or(view, PostProcessChain::scene_target);
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: self.msaa_texture_view.as_ref().unwrap_or(target),
+                    resolve_target: self.msaa_texture_view.as_ref().map(|_| target),
+                    depth_slice: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.clear_color),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+            if let Some((background_gradient_render_pipeline, background_gradient_bind_group)) =
+                &self.background_gradient_state
+            {
+                render_pass.set_pipeline(background_gradient_render_pipeline);
+                render_pass.set_bind_group(0, background_gradient_bind_group, &[]);
+                render_pass.draw(0..4, 0..1);
+            }
+
+            if let Some((procedural_background_pipeline, procedural_background_bind_group)) =
+                &self.procedural_background_state
+            {
+                render_pass.set_pipeline(procedural_background_pipeline);
+                render_pass.set_bind_group(0, procedural_background_bind_group, &[]);
+                render_pass.draw(0..4, 0..1);
+            }
+
+            if let Some(background_image) = &self.background_image {
+                background_image.draw(&mut render_pass);
+            }
+
+            for layer in &self.layers {
+                if let Some(lines) = &layer.lines {
+                    lines.draw(&mut render_pass);
+                }
+
+                if let Some(metaballs) = &layer.metaballs {
+                    metaballs.draw(&mut render_pass);
+                } else {
+                    render_pass.set_pipeline(&self.render_pipeline);
+                    render_pass.set_bind_group(0, &layer.render_bind_group, &[]);
+                    if self.point_color_from_background
+                        && let Some(background_image) = &self.background_image
+                    {
+                        render_pass.set_bind_group(1, background_image.bind_group(), &[]);
+                    }
+                    render_pass.draw(0..4, 0..layer.points_count as u32);
+                }
+            }
+
+            if let Some(sparks) = &self.sparks {
+                sparks.draw(&mut render_pass);
+            }
+
+            if let Some(overlay) = &self.overlay {
+                overlay.draw(&mut render_pass);
+            }
+        }
+
+        for effect in &self.layer_effects {
+            effect.encode(encoder, None, target);
+        }
+
+        if let Some(post_process) = &self.post_process {
+            post_process.apply(encoder, view);
+        }
+    }
+
+    /// Applies the options of `config` that feed uniforms, leaving the rest as it is.
+    pub(crate) fn reconfigure(&mut self, config: &Config) {
+        self.min_point_size = config.min_point_size;
+        self.max_point_size = config.max_point_size;
+        self.clear_color = if config.oled {
+            Color::BLACK
+        } else {
+            config.background_color
+        }
+        .to_wgpu();
+
+        for (layer, layer_config) in self.layers.iter_mut().zip(layer_configs(config)) {
+            let connection_distance = layer_config.distance.unwrap_or(config.connection_distance);
+            if let Some(lines) = &mut layer.lines {
+                lines.set_distance(&self.queue, connection_distance);
+            }
+        }
+    }
+
+    /// Multiplies the connection distance of every layer that draws lines by `factor`,
+    /// widening the neighbour grids of `simulation` along. Returns the distance of the front
+    /// layer that draws lines, if any does.
+    pub fn scale_connection_distance(
+        &mut self,
+        simulation: &mut Simulation,
+        factor: f32,
+    ) -> Option<f32> {
+        let mut front_distance = None;
+        for (index, layer) in self.layers.iter_mut().enumerate() {
+            let Some(lines) = &mut layer.lines else {
+                continue;
+            };
+            let distance = (lines.distance() * factor).max(MIN_CONNECTION_DISTANCE);
+            lines.set_distance(&self.queue, distance);
+            simulation.set_connection_distance(index, distance);
+            front_distance = Some(distance);
+        }
+
+        front_distance
+    }
+
+    /// Logs `text` and shows it on top of the visualization for a moment.
+    pub fn notify(&mut self, text: String) {
+        info!("{text}");
+        if let Some(overlay) = &mut self.overlay {
+            overlay.notify(text);
+        }
+    }
+
+    fn push_intensity_history(&mut self, intensity: f32) {
+        self.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.intensity_history_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: self.intensity_history_head,
+                    y: 0,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            bytemuck::bytes_of(&intensity),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: None,
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        // The head points at the most recently written sample.
+        self.queue.write_buffer(
+            &self.intensity_history_head_buffer,
+            0,
+            bytemuck::bytes_of(&self.intensity_history_head),
+        );
+        self.intensity_history_head = (self.intensity_history_head + 1) % INTENSITY_HISTORY_LENGTH;
+    }
+
+    /// Loads the background image given for `monitor`, the name and size of the monitor the
+    /// canvas is on, if there is one. `buffers` are the window size, window position and
+    /// intensity buffers it reads.
+    #[allow(clippy::too_many_arguments)]
+    fn load_background_image(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        images: Option<&BackgroundImages>,
+        options: BackgroundImageOptions,
+        monitor: &(Option<String>, Option<PhysicalSize<u32>>),
+        buffers: [&wgpu::Buffer; 3],
+    ) -> anyhow::Result<Option<BackgroundImage>> {
+        let (monitor_name, monitor_size) = monitor;
+        let Some(path) = images.and_then(|images| {
+            let path = images.for_monitor(monitor_name.as_deref());
+            if path.is_none() {
+                log::warn!("No background image given for monitor {monitor_name:?}");
+            }
+            path
+        }) else {
+            return Ok(None);
+        };
+
+        let monitor_size = monitor_size.ok_or_else(|| anyhow::anyhow!("No monitor found"))?;
+        info!("Monitor {monitor_name:?} size: {monitor_size:?}");
+
+        let [window_size_buffer, window_pos_buffer, intensity_buffer] = buffers;
+        Ok(Some(BackgroundImage::new(
+            device,
+            queue,
+            format,
+            sample_count,
+            path,
+            monitor_size,
+            window_size_buffer,
+            window_pos_buffer,
+            intensity_buffer,
+            options,
+        )?))
+    }
+
+    fn create_background_gradient_pipeline(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        window_size_buffer: &wgpu::Buffer,
+        start: Color,
+        end: Color,
+        shape: GradientShape,
+    ) -> (wgpu::RenderPipeline, wgpu::BindGroup) {
+        let gradient = BackgroundGradient {
+            start: start.to_linear(),
+            end: end.to_linear(),
+            shape: match shape {
+                GradientShape::Vertical => 0,
+                GradientShape::Radial => 1,
+            },
+            _padding: [0; 3],
+        };
+
+        let gradient_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Background Gradient Buffer"),
+            contents: bytemuck::bytes_of(&gradient),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Background Gradient Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Background Gradient Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: gradient_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: window_size_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let pipeline = Self::create_fullscreen_pipeline(
+            device,
+            format,
+            sample_count,
+            wgpu::BlendState::REPLACE,
+            "Background Gradient",
+            include_str!("shaders/background_gradient_shader.wgsl"),
+            &bind_group_layout,
+        );
+
+        (pipeline, bind_group)
+    }
+
+    fn create_procedural_background_pipeline(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        preset: ProceduralPreset,
+        time_buffer: &wgpu::Buffer,
+        intensity_buffer: &wgpu::Buffer,
+        window_size_buffer: &wgpu::Buffer,
+    ) -> (wgpu::RenderPipeline, wgpu::BindGroup) {
+        let preset: u32 = match preset {
+            ProceduralPreset::Plasma => 0,
+            ProceduralPreset::Nebula => 1,
+        };
+
+        let preset_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Procedural Background Preset Buffer"),
+            contents: bytemuck::bytes_of(&preset),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let uniform_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Procedural Background Bind Group Layout"),
+            entries: &[
+                uniform_entry(0),
+                uniform_entry(1),
+                uniform_entry(2),
+                uniform_entry(3),
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Procedural Background Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: preset_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: time_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: intensity_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: window_size_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let pipeline = Self::create_fullscreen_pipeline(
+            device,
+            format,
+            sample_count,
+            wgpu::BlendState::REPLACE,
+            "Procedural Background",
+            include_str!("shaders/procedural_background_shader.wgsl"),
+            &bind_group_layout,
+        );
+
+        (pipeline, bind_group)
+    }
+
+    /// Creates a pipeline drawing a fullscreen triangle strip of 4 vertices. Used for the
+    /// background and post-processing passes.
+    pub(crate) fn create_fullscreen_pipeline(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        blend: wgpu::BlendState,
+        label: &str,
+        shader_source: &str,
+        bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(&format!("{label} Shader")),
+            source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(&format!("{label} Render Pipeline Layout")),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(&format!("{label} Render Pipeline")),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(blend),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    fn create_intensity_history_texture(
+        device: &wgpu::Device,
+    ) -> (wgpu::Texture, wgpu::TextureView) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Intensity History Texture"),
+            size: wgpu::Extent3d {
+                width: INTENSITY_HISTORY_LENGTH,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D1,
+            format: wgpu::TextureFormat::R32Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        (texture, view)
+    }
+
+    fn create_msaa_texture(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        sample_count: u32,
+    ) -> Option<wgpu::TextureView> {
+        if sample_count == 1 {
+            return None;
+        }
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("MSAA Color Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+
+        Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+    }
+}
+
+/// What is drawn of one layer of the simulation.
+struct LayerDrawing {
+    points_count: usize,
+    point_values_buffer: wgpu::Buffer,
+    render_bind_group: wgpu::BindGroup,
+    lines: Option<Lines>,
+    metaballs: Option<Metaballs>,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct BackgroundGradient {
+    start: [f32; 4],
+    end: [f32; 4],
+    shape: u32,
+    _padding: [u32; 3],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct PointDataStyle {
+    low_color: [f32; 4],
+    high_color: [f32; 4],
+    min_scale: f32,
+    max_scale: f32,
+    _padding: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct IntroUniform {
+    progress: f32,
+    mode: u32,
+}
@@ -0,0 +1,112 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::ffi::OsString;
+use std::{cell::RefCell, path::PathBuf, rc::Rc, time::Duration};
+
+use crate::config::{BackgroundImages, Config};
+use crate::effect::{Effect, EffectPlacement};
+use crate::hooks::Hooks;
+use crate::volume_providers::volume_provider::VolumeProvider;
+
+/// What [`run`](crate::run) starts with, built up one option at a time from the defaults,
+/// like `RunOptions::new().points(2000).background(images)`, so options can be added
+/// without changing the signature of `run`. Anything without a method of its own is set on
+/// the [`Config`] given to [`RunOptions::config`].
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    pub(crate) config: Config,
+    pub(crate) hooks: Hooks,
+    /// The command line requests are applied to, when they are taken.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) command_line: Option<Vec<OsString>>,
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) page: crate::web::Page,
+}
+
+impl RunOptions {
+    /// Every option at its default.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts from `config`, e.g. one parsed from the command line.
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Takes requests from the control socket, the hotkeys and changes to the config file,
+    /// like the connecting-dots program does, applying them to `args`, the command line the
+    /// config was parsed from, which starts with the program name. Without one the config
+    /// stays as given.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn command_line(mut self, args: Vec<OsString>) -> Self {
+        self.command_line = Some(args);
+        self
+    }
+
+    /// Shows `images` behind the points, one for every monitor or one per monitor by name.
+    pub fn background(mut self, images: BackgroundImages) -> Self {
+        self.config.background = None;
+        self.config.background_image = Some(images);
+        self
+    }
+
+    /// Number of points, or of points per layer without a count of its own.
+    pub fn points(mut self, points: u32) -> Self {
+        self.config.points = points.max(1);
+        self
+    }
+
+    /// Takes the intensity from a file with one intensity per frame instead of the music.
+    pub fn intensity_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.intensity_file = Some(path.into());
+        self
+    }
+
+    /// Takes the intensity from `provider` instead of the music or `intensity_file`, e.g. to
+    /// follow game events. Every window polls the same provider.
+    pub fn volume_provider(mut self, provider: impl VolumeProvider + 'static) -> Self {
+        self.hooks.volume_provider = Some(Rc::new(provider));
+        self
+    }
+
+    /// Calls `callback` with the intensity, from 0 to 1, and the time since the previous
+    /// frame for every frame drawn, e.g. to light LED strips along with the points.
+    pub fn on_frame(mut self, callback: impl FnMut(f32, Duration) + 'static) -> Self {
+        self.hooks.on_frame = Some(Rc::new(RefCell::new(callback)));
+        self
+    }
+
+    /// Calls `callback` on every hit in the music, the same ones sparks and impulses follow.
+    pub fn on_beat(mut self, callback: impl FnMut() + 'static) -> Self {
+        self.hooks.on_beat = Some(Rc::new(RefCell::new(callback)));
+        self
+    }
+
+    /// Calls `callback` with the width and height in physical pixels whenever the window
+    /// the frames are drawn in changes size.
+    pub fn on_resize(mut self, callback: impl FnMut(u32, u32) + 'static) -> Self {
+        self.hooks.on_resize = Some(Rc::new(RefCell::new(callback)));
+        self
+    }
+
+    /// Draws an effect made by `create` at `placement`. Every window, monitor and restart
+    /// with new options gets an effect of its own, set up for its device.
+    pub fn effect(
+        mut self,
+        placement: EffectPlacement,
+        create: impl Fn() -> Box<dyn Effect> + 'static,
+    ) -> Self {
+        self.hooks.effects.push((placement, Rc::new(create)));
+        self
+    }
+}
+
+impl From<Config> for RunOptions {
+    fn from(config: Config) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+}
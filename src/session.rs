@@ -0,0 +1,172 @@
+use std::{
+    collections::HashMap,
+    env,
+    process::Command,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use serde::Deserialize;
+use zbus::{
+    MatchRule,
+    blocking::{Connection, MessageIterator, Proxy},
+    message::Type,
+    zvariant::{OwnedObjectPath, OwnedValue},
+};
+
+use crate::hyprland;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const LOGIND: &str = "org.freedesktop.login1";
+
+/// Watches for the session being locked or idle, the machine going to sleep and the displays
+/// being blanked, during which there is nobody to look at the visualization.
+pub struct SessionMonitor {
+    /// Set while logind reports the session as locked, idle or about to sleep.
+    session_inactive: Arc<AtomicBool>,
+    displays_blanked: Arc<AtomicBool>,
+}
+
+impl SessionMonitor {
+    pub fn spawn() -> Self {
+        let session_inactive = Arc::new(AtomicBool::new(false));
+        let displays_blanked = Arc::new(AtomicBool::new(false));
+
+        {
+            let session_inactive = Arc::clone(&session_inactive);
+            thread::spawn(move || {
+                if let Err(e) = watch_logind(&session_inactive) {
+                    log::warn!("Unable to follow logind, asking it every few seconds instead: {e}");
+                }
+                loop {
+                    session_inactive.store(session_inactive_hint(), Ordering::Relaxed);
+                    thread::sleep(POLL_INTERVAL);
+                }
+            });
+        }
+
+        {
+            let displays_blanked = Arc::clone(&displays_blanked);
+            thread::spawn(move || {
+                loop {
+                    displays_blanked.store(self::displays_blanked(), Ordering::Relaxed);
+                    thread::sleep(POLL_INTERVAL);
+                }
+            });
+        }
+
+        Self {
+            session_inactive,
+            displays_blanked,
+        }
+    }
+
+    pub fn is_inactive(&self) -> bool {
+        self.session_inactive.load(Ordering::Relaxed)
+            || self.displays_blanked.load(Ordering::Relaxed)
+    }
+}
+
+/// Follows the lock, idle and sleep signals of logind on the system bus, so the
+/// visualization pauses as soon as the screen locks or the machine suspends. Only returns on
+/// errors.
+fn watch_logind(inactive: &AtomicBool) -> anyhow::Result<()> {
+    let connection = Connection::system()?;
+    let manager = Proxy::new(
+        &connection,
+        LOGIND,
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager",
+    )?;
+    let session_id = env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".to_string());
+    let session_path: OwnedObjectPath = manager.call("GetSession", &(session_id,))?;
+    let session = Proxy::new(
+        &connection,
+        LOGIND,
+        &session_path,
+        "org.freedesktop.login1.Session",
+    )?;
+
+    // Subscribe before reading the hints so no change falls in between.
+    let rule = MatchRule::builder()
+        .msg_type(Type::Signal)
+        .sender(LOGIND)?
+        .build();
+    let messages = MessageIterator::for_match_rule(rule, &connection, None)?;
+
+    let mut locked: bool = session.get_property("LockedHint")?;
+    let mut idle: bool = session.get_property("IdleHint")?;
+    let mut asleep = false;
+    inactive.store(locked || idle, Ordering::Relaxed);
+
+    for message in messages {
+        let message = message?;
+        let header = message.header();
+        let on_session = header.path().is_some_and(|path| *path == *session_path);
+
+        match header.member().map(|member| member.as_str()) {
+            Some("PrepareForSleep") => asleep = message.body().deserialize()?,
+            Some("Lock") if on_session => locked = true,
+            Some("Unlock") if on_session => locked = false,
+            Some("PropertiesChanged") if on_session => {
+                let (_, changed, _): (String, HashMap<String, OwnedValue>, Vec<String>) =
+                    message.body().deserialize()?;
+                if let Some(value) = changed.get("LockedHint") {
+                    locked = value.downcast_ref()?;
+                }
+                if let Some(value) = changed.get("IdleHint") {
+                    idle = value.downcast_ref()?;
+                }
+            }
+            _ => continue,
+        }
+
+        inactive.store(locked || idle || asleep, Ordering::Relaxed);
+    }
+
+    Ok(())
+}
+
+/// Asks logind through loginctl whether the current session is locked or idle.
+fn session_inactive_hint() -> bool {
+    let session = env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".to_string());
+
+    let Ok(output) = Command::new("loginctl")
+        .args([
+            "show-session",
+            &session,
+            "--property=LockedHint",
+            "--property=IdleHint",
+        ])
+        .output()
+    else {
+        return false;
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .any(|line| line == "LockedHint=yes" || line == "IdleHint=yes")
+}
+
+#[derive(Deserialize, Debug)]
+struct HyprMonitor {
+    #[serde(rename = "dpmsStatus")]
+    dpms_status: bool,
+}
+
+/// Asks Hyprland whether every monitor has been turned off through DPMS.
+fn displays_blanked() -> bool {
+    let Ok(reply) = hyprland::request("j/monitors") else {
+        return false;
+    };
+
+    let Ok(monitors) = serde_json::from_slice::<Vec<HyprMonitor>>(&reply) else {
+        return false;
+    };
+
+    !monitors.is_empty() && monitors.iter().all(|m| !m.dpms_status)
+}
@@ -0,0 +1,502 @@
+use std::{
+    ffi::c_void,
+    ptr::NonNull,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, anyhow};
+use log::info;
+use smithay_client_toolkit::{
+    compositor::{CompositorHandler, CompositorState},
+    delegate_compositor, delegate_output, delegate_registry, delegate_seat, delegate_session_lock,
+    output::{OutputHandler, OutputState},
+    reexports::client::{
+        Connection, Dispatch, Proxy, QueueHandle, WEnum,
+        globals::registry_queue_init,
+        protocol::{wl_keyboard, wl_output, wl_pointer, wl_seat, wl_surface},
+    },
+    registry::{ProvidesRegistryState, RegistryState},
+    registry_handlers,
+    seat::{Capability, SeatHandler, SeatState},
+    session_lock::{
+        SessionLock, SessionLockHandler, SessionLockState, SessionLockSurface,
+        SessionLockSurfaceConfigure,
+    },
+};
+use winit::dpi::{LogicalSize, PhysicalSize};
+
+use crate::{
+    canvas::Canvas,
+    config::Config,
+    hooks::Hooks,
+    state::State,
+    wayland_scale::{ScaleHandler, Scaling, SurfaceScale, delegate_scaling},
+};
+
+/// Runs the visualization as a screensaver on ext-session-lock surfaces covering every
+/// output, which nothing else can draw over, until a key or button is pressed.
+pub(crate) fn run(config: Config, hooks: Hooks) -> anyhow::Result<()> {
+    let connection =
+        Connection::connect_to_env().context("Unable to connect to the Wayland compositor")?;
+    let (globals, mut event_queue) = registry_queue_init(&connection)?;
+    let qh = event_queue.handle();
+
+    let compositor = CompositorState::bind(&globals, &qh)?;
+    let session_lock_state = SessionLockState::new(&globals, &qh);
+    let session_lock = session_lock_state
+        .lock(&qh)
+        .map_err(|_| anyhow!("The compositor does not support ext-session-lock"))?;
+
+    let mut screensaver = Screensaver {
+        screens: Vec::new(),
+        session_lock,
+        scaling: Scaling::bind(&globals, &qh),
+        compositor,
+        registry_state: RegistryState::new(&globals),
+        output_state: OutputState::new(&globals, &qh),
+        seat_state: SeatState::new(&globals, &qh),
+        connection: connection.clone(),
+        config,
+        hooks,
+        done: false,
+        denied: false,
+    };
+
+    while !screensaver.done {
+        event_queue.blocking_dispatch(&mut screensaver)?;
+    }
+    // Make sure the compositor hears about the unlock before the connection goes away.
+    event_queue.roundtrip(&mut screensaver)?;
+
+    if screensaver.denied {
+        anyhow::bail!("The compositor refused to lock the session, is another locker running?");
+    }
+
+    Ok(())
+}
+
+/// The lock surface on one output.
+struct Screen {
+    /// Dropped before the lock surface it draws into.
+    state: Option<State>,
+    scale: SurfaceScale,
+    surface: SessionLockSurface,
+    /// Size of the lock surface in logical pixels, once configured.
+    logical_size: Option<LogicalSize<u32>>,
+    output: wl_output::WlOutput,
+    last_update: Instant,
+}
+
+struct Screensaver {
+    screens: Vec<Screen>,
+    session_lock: SessionLock,
+    scaling: Scaling,
+    compositor: CompositorState,
+    registry_state: RegistryState,
+    output_state: OutputState,
+    seat_state: SeatState,
+    connection: Connection,
+    config: Config,
+    hooks: Hooks,
+    done: bool,
+    /// Whether the compositor refused the lock.
+    denied: bool,
+}
+
+impl Screensaver {
+    /// Covers `output` with a lock surface.
+    fn add_screen(&mut self, qh: &QueueHandle<Self>, output: wl_output::WlOutput) {
+        let surface = self.compositor.create_surface(qh);
+        let surface = self.session_lock.create_lock_surface(surface, &output, qh);
+        let scale = self.scaling.scale_surface(surface.wl_surface(), qh);
+        self.screens.push(Screen {
+            state: None,
+            scale,
+            surface,
+            logical_size: None,
+            output,
+            last_update: Instant::now(),
+        });
+    }
+
+    fn canvas(&self, screen: &Screen, size: PhysicalSize<u32>) -> anyhow::Result<Canvas> {
+        let display = NonNull::new(self.connection.backend().display_ptr() as *mut c_void)
+            .ok_or_else(|| anyhow!("No Wayland display"))?;
+        let surface = NonNull::new(screen.surface.wl_surface().id().as_ptr() as *mut c_void)
+            .ok_or_else(|| anyhow!("No Wayland surface"))?;
+
+        let info = self.output_state.info(&screen.output);
+
+        Ok(Canvas::Wayland {
+            display,
+            surface,
+            size,
+            monitor_size: Some(size),
+            monitor_name: info.and_then(|info| info.name),
+            scale_factor: screen.scale.scale(),
+        })
+    }
+
+    /// Draws the screen showing `surface` at `scale` from now on, starting it over when the
+    /// sizes in the config have to follow.
+    fn rescale(&mut self, surface: &wl_surface::WlSurface, scale: f64) {
+        let Some(screen) = self
+            .screens
+            .iter_mut()
+            .find(|screen| screen.surface.wl_surface() == surface)
+        else {
+            return;
+        };
+        if !screen.scale.set_scale(scale) {
+            return;
+        }
+
+        let (Some(logical_size), Some(state)) = (screen.logical_size, &mut screen.state) else {
+            return;
+        };
+        let size = screen.scale.apply(logical_size);
+        if let Canvas::Wayland {
+            size: canvas_size,
+            monitor_size,
+            scale_factor,
+            ..
+        } = &mut state.canvas
+        {
+            *canvas_size = size;
+            *monitor_size = Some(size);
+            *scale_factor = screen.scale.scale();
+        }
+        state.resize(size.width, size.height);
+
+        if state.needs_rescale()
+            && let Err(e) = State::restart(&mut screen.state, &self.config, &self.config)
+        {
+            log::error!("Unable to follow the scale: {e}");
+        }
+    }
+
+    /// Ends the screensaver, giving the session back.
+    fn unlock(&mut self) {
+        if self.done {
+            return;
+        }
+
+        info!("Unlocking the session");
+        // The surfaces have to go before the lock does.
+        self.screens.clear();
+        self.session_lock.unlock();
+        self.done = true;
+    }
+}
+
+/// Advances and renders a frame of `screen`, asking for a frame callback to draw the next one.
+/// Waits until `frame_interval` has passed since the last frame, when given.
+fn draw(screen: &mut Screen, frame_interval: Option<Duration>, qh: &QueueHandle<Screensaver>) {
+    let Some(state) = &mut screen.state else {
+        return;
+    };
+
+    let surface = screen.surface.wl_surface();
+    surface.frame(qh, surface.clone());
+
+    if let Some(frame_interval) = frame_interval {
+        std::thread::sleep(
+            (screen.last_update + frame_interval).saturating_duration_since(Instant::now()),
+        );
+    }
+
+    let now = Instant::now();
+    state.update(now - screen.last_update);
+    screen.last_update = now;
+
+    match state.render() {
+        Ok(_) => {}
+        Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+            let size = state.canvas.size();
+            state.resize(size.width, size.height);
+        }
+        Err(e) => log::error!("Unable to render {e}"),
+    }
+}
+
+impl SessionLockHandler for Screensaver {
+    fn locked(&mut self, _conn: &Connection, qh: &QueueHandle<Self>, _session_lock: SessionLock) {
+        info!("Locked the session");
+        for output in self.output_state.outputs().collect::<Vec<_>>() {
+            self.add_screen(qh, output);
+        }
+    }
+
+    fn finished(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, session_lock: SessionLock) {
+        if !session_lock.is_locked() {
+            self.denied = true;
+        }
+        self.screens.clear();
+        self.done = true;
+    }
+
+    fn configure(
+        &mut self,
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+        surface: SessionLockSurface,
+        configure: SessionLockSurfaceConfigure,
+        _serial: u32,
+    ) {
+        let Some(index) = self
+            .screens
+            .iter()
+            .position(|screen| screen.surface.wl_surface() == surface.wl_surface())
+        else {
+            return;
+        };
+
+        // The compositor sizes the surface in logical pixels, the buffer is drawn at the scale.
+        let (width, height) = configure.new_size;
+        let logical_size = LogicalSize::new(width.max(1), height.max(1));
+        self.screens[index].logical_size = Some(logical_size);
+        let size = self.screens[index].scale.apply(logical_size);
+
+        if let Some(state) = &mut self.screens[index].state {
+            if let Canvas::Wayland {
+                size: canvas_size,
+                monitor_size,
+                ..
+            } = &mut state.canvas
+            {
+                *canvas_size = size;
+                *monitor_size = Some(size);
+            }
+            state.resize(size.width, size.height);
+            state.refresh_monitor();
+            return;
+        }
+
+        let state = self.canvas(&self.screens[index], size).and_then(|canvas| {
+            pollster::block_on(State::with_canvas(canvas, &self.config, self.hooks.clone()))
+        });
+        match state {
+            Ok(mut state) => {
+                state.resize(size.width, size.height);
+                let screen = &mut self.screens[index];
+                screen.state = Some(state);
+                screen.last_update = Instant::now();
+                draw(screen, self.config.frame_interval(), qh);
+            }
+            // The compositor keeps showing its own lock color on this output.
+            Err(e) => log::error!("Unable to start drawing the screensaver: {e}"),
+        }
+    }
+}
+
+impl CompositorHandler for Screensaver {
+    fn scale_factor_changed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        surface: &wl_surface::WlSurface,
+        new_factor: i32,
+    ) {
+        let fractional = self
+            .screens
+            .iter()
+            .any(|screen| screen.surface.wl_surface() == surface && screen.scale.is_fractional());
+        if !fractional {
+            self.rescale(surface, new_factor as f64);
+        }
+    }
+
+    fn transform_changed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _new_transform: wl_output::Transform,
+    ) {
+    }
+
+    fn frame(
+        &mut self,
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+        surface: &wl_surface::WlSurface,
+        _time: u32,
+    ) {
+        if let Some(screen) = self
+            .screens
+            .iter_mut()
+            .find(|screen| screen.surface.wl_surface() == surface)
+        {
+            draw(screen, self.config.frame_interval(), qh);
+        }
+    }
+
+    fn surface_enter(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _output: &wl_output::WlOutput,
+    ) {
+    }
+
+    fn surface_leave(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _output: &wl_output::WlOutput,
+    ) {
+    }
+}
+
+impl OutputHandler for Screensaver {
+    fn output_state(&mut self) -> &mut OutputState {
+        &mut self.output_state
+    }
+
+    fn new_output(
+        &mut self,
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+        output: wl_output::WlOutput,
+    ) {
+        // Outputs plugged in while locked have to be covered too.
+        if self.session_lock.is_locked() && !self.done {
+            self.add_screen(qh, output);
+        }
+    }
+
+    fn update_output(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        output: wl_output::WlOutput,
+    ) {
+        // The size follows through configure, only the name is picked up here.
+        let name = self.output_state.info(&output).and_then(|info| info.name);
+        for screen in &mut self.screens {
+            if screen.output != output {
+                continue;
+            }
+            if let Some(state) = &mut screen.state {
+                if let Canvas::Wayland { monitor_name, .. } = &mut state.canvas {
+                    *monitor_name = name.clone();
+                }
+                state.refresh_monitor();
+            }
+        }
+    }
+
+    fn output_destroyed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        output: wl_output::WlOutput,
+    ) {
+        self.screens.retain(|screen| screen.output != output);
+    }
+}
+
+impl SeatHandler for Screensaver {
+    fn seat_state(&mut self) -> &mut SeatState {
+        &mut self.seat_state
+    }
+
+    fn new_seat(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _seat: wl_seat::WlSeat) {}
+
+    fn new_capability(
+        &mut self,
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+        seat: wl_seat::WlSeat,
+        capability: Capability,
+    ) {
+        match capability {
+            Capability::Keyboard => {
+                seat.get_keyboard(qh, ());
+            }
+            Capability::Pointer => {
+                seat.get_pointer(qh, ());
+            }
+            _ => {}
+        }
+    }
+
+    fn remove_capability(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _seat: wl_seat::WlSeat,
+        _capability: Capability,
+    ) {
+    }
+
+    fn remove_seat(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _seat: wl_seat::WlSeat) {
+    }
+}
+
+// Only presses matter, so the raw events are enough without a keymap or cursor theme.
+impl Dispatch<wl_keyboard::WlKeyboard, ()> for Screensaver {
+    fn event(
+        state: &mut Self,
+        _keyboard: &wl_keyboard::WlKeyboard,
+        event: wl_keyboard::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let wl_keyboard::Event::Key {
+            state: WEnum::Value(wl_keyboard::KeyState::Pressed),
+            ..
+        } = event
+        {
+            state.unlock();
+        }
+    }
+}
+
+impl Dispatch<wl_pointer::WlPointer, ()> for Screensaver {
+    fn event(
+        state: &mut Self,
+        _pointer: &wl_pointer::WlPointer,
+        event: wl_pointer::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let wl_pointer::Event::Button {
+            state: WEnum::Value(wl_pointer::ButtonState::Pressed),
+            ..
+        } = event
+        {
+            state.unlock();
+        }
+    }
+}
+
+impl ScaleHandler for Screensaver {
+    fn scale_changed(
+        &mut self,
+        _qh: &QueueHandle<Self>,
+        surface: &wl_surface::WlSurface,
+        scale: f64,
+    ) {
+        self.rescale(surface, scale);
+    }
+}
+
+impl ProvidesRegistryState for Screensaver {
+    fn registry(&mut self) -> &mut RegistryState {
+        &mut self.registry_state
+    }
+
+    registry_handlers![OutputState, SeatState];
+}
+
+delegate_compositor!(Screensaver);
+delegate_output!(Screensaver);
+delegate_seat!(Screensaver);
+delegate_session_lock!(Screensaver);
+delegate_registry!(Screensaver);
+delegate_scaling!(Screensaver);
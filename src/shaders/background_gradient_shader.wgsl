@@ -0,0 +1,50 @@
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) uv: vec2<f32>,
+};
+
+struct Gradient {
+  start: vec4<f32>,
+  end: vec4<f32>,
+  // 0 = vertical (start at the top), 1 = radial (start at the center)
+  shape: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> gradient: Gradient;
+
+@group(0) @binding(1)
+var<uniform> windowSize: vec2<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+  let quad = array<vec2<f32>, 4>(
+    vec2(-1.0, -1.0),
+    vec2( 1.0, -1.0),
+    vec2(-1.0,  1.0),
+    vec2( 1.0,  1.0),
+  );
+
+  let pos = quad[vertex_index];
+
+  var out: VertexOutput;
+  out.clip_position = vec4(pos, 0.0, 1.0);
+  out.uv = vec2(pos.x * 0.5 + 0.5, 0.5 - pos.y * 0.5);
+
+  return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  var t: f32;
+
+  if (gradient.shape == 1u) {
+    // Measure the distance in pixels so the gradient stays circular on non-square windows.
+    let offset = (in.uv - vec2(0.5)) * windowSize;
+    t = length(offset) / length(windowSize * 0.5);
+  } else {
+    t = in.uv.y;
+  }
+
+  return mix(gradient.start, gradient.end, clamp(t, 0.0, 1.0));
+}
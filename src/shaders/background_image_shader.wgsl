@@ -14,10 +14,23 @@ var<uniform> windowSize: vec2<f32>;
 @group(0) @binding(3)
 var<uniform> windowPos: vec2<f32>;
 
+// The rectangle the image covers on the monitor, in monitor pixels.
+struct Placement {
+  origin: vec2<f32>,
+  size: vec2<f32>,
+  tile: u32,
+  // How far the image zooms in at full intensity.
+  zoom: f32,
+};
+
+@group(0) @binding(4)
+var<uniform> placement: Placement;
+
+@group(0) @binding(5)
+var<uniform> intensity: f32;
+
 @vertex
 fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
-  let imageSize = vec2(1920.0, 1080.0);
-
   let quad = array<vec2<f32>, 4>(
     vec2(-1.0, -1.0),
     vec2( 1.0, -1.0),
@@ -33,14 +46,18 @@ fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
   );
 
   let pos = quad[vertex_index];
-  var uv = uvs[vertex_index];
+  let uv = uvs[vertex_index];
+
+  // Position on the monitor, with y pointing down.
+  var pixel = windowPos + vec2(uv.x, 1.0 - uv.y) * windowSize;
 
-  uv = uv * windowSize;
-  uv = (windowPos + uv) / imageSize;
+  // Zoom towards the center of the image so the background breathes with the music.
+  let center = placement.origin + placement.size * 0.5;
+  pixel = center + (pixel - center) / (1.0 + placement.zoom * intensity);
 
   var out: VertexOutput;
   out.clip_position = vec4(pos, 0.0, 1.0);
-  out.uv = vec2(uv.x, 1.0 - uv.y);
+  out.uv = (pixel - placement.origin) / placement.size;
 
   return out;
 }
@@ -48,5 +65,12 @@ fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-  return textureSample(t_diffuse, s_diffuse, in.uv);
+  let color = textureSample(t_diffuse, s_diffuse, in.uv);
+
+  // Let the background color show around images that do not cover the monitor.
+  if (placement.tile == 0u && (any(in.uv < vec2(0.0)) || any(in.uv > vec2(1.0)))) {
+    discard;
+  }
+
+  return color;
 }
@@ -1,6 +1,14 @@
 struct Point {
   position: vec2<f32>,
   velocity: vec2<f32>,
+  // Multiplier of the point size.
+  size: f32,
+  // How strongly forces are resisted, 1 for an average point.
+  mass: f32,
+  // Rate per second the velocity decays at.
+  drag: f32,
+  // Seconds since the point was spawned.
+  age: f32,
 }
 
 struct Points {
@@ -14,6 +22,334 @@ struct Points {
 
 @group(0) @binding(2) var<uniform> deltaTime : f32;
 
+// Velocity added to every point, used by OLED mode to keep pixels from staying lit.
+@group(0) @binding(3) var<uniform> drift : vec2<f32>;
+
+struct Cursor {
+  position: vec2<f32>,
+  // 1 while the cursor is inside the window.
+  inside: u32,
+  // Acceleration toward the cursor, negative to repel.
+  force: f32,
+  radius: f32,
+}
+
+@group(0) @binding(4) var<uniform> cursor : Cursor;
+
+struct Grid {
+  cellSize: f32,
+  // Number of buckets the cells are hashed into, a power of two.
+  tableSize: u32,
+}
+
+// The distinct buckets of the 3x3 cells around a point. Neighbouring cells can share a
+// bucket, which must only be walked once.
+struct Neighbourhood {
+  buckets: array<u32, 9>,
+  count: u32,
+}
+
+struct Forces {
+  // Constant acceleration in pixels per second squared.
+  gravity: vec2<f32>,
+  // Points closer than this push each other apart.
+  collisionRadius: f32,
+  collisionStrength: f32,
+  // Points closer than this gently push each other apart to keep the field even.
+  spacing: f32,
+  spacingStrength: f32,
+  // 0 to bounce off the window edges, 1 to wrap around and 2 to respawn.
+  edge: u32,
+  // 0 to move in straight lines, 1 to follow the flow field.
+  motion: u32,
+  // Size in pixels of the swirls of the flow field.
+  flowScale: f32,
+  flowSpeed: f32,
+  // Multipliers of the velocity at zero and full intensity.
+  silentSpeed: f32,
+  loudSpeed: f32,
+  // Average seconds a point lives before it respawns, 0 to live forever.
+  lifetime: f32,
+  // Seed of the random numbers, from --seed.
+  seed: u32,
+}
+
+const EDGE_WRAP = 1u;
+const EDGE_RESPAWN = 2u;
+
+const MOTION_FLOW = 1u;
+// How quickly the velocity of a point turns toward the flow, per second.
+const FLOW_RESPONSE = 2.0;
+// How quickly the flow field changes, in noise cells per second.
+const FLOW_EVOLUTION = 0.05;
+
+fn hash(value: u32) -> u32 {
+  var x = value * 747796405u + 2891336453u;
+  x = ((x >> ((x >> 28u) + 4u)) ^ x) * 277803737u;
+  return (x >> 22u) ^ x;
+}
+
+// A pseudo-random number from 0 to 1, advancing the state.
+fn random(state: ptr<function, u32>) -> f32 {
+  *state = hash(*state);
+  return f32(*state) / 4294967295.0;
+}
+
+// Gradient of a lattice corner, pointing in one of 12 directions.
+fn corner_gradient(corner: vec3<f32>) -> vec3<f32> {
+  let c = vec3<i32>(corner);
+  let h = hash(bitcast<u32>(c.x) ^ hash(bitcast<u32>(c.y) ^ hash(bitcast<u32>(c.z)))) % 12u;
+  let gradients = array(
+    vec3(1.0, 1.0, 0.0), vec3(-1.0, 1.0, 0.0), vec3(1.0, -1.0, 0.0), vec3(-1.0, -1.0, 0.0),
+    vec3(1.0, 0.0, 1.0), vec3(-1.0, 0.0, 1.0), vec3(1.0, 0.0, -1.0), vec3(-1.0, 0.0, -1.0),
+    vec3(0.0, 1.0, 1.0), vec3(0.0, -1.0, 1.0), vec3(0.0, 1.0, -1.0), vec3(0.0, -1.0, -1.0),
+  );
+  return gradients[h];
+}
+
+// 3D Perlin noise from about -1 to 1.
+fn perlin(position: vec3<f32>) -> f32 {
+  let cell = floor(position);
+  let f = position - cell;
+  let u = f * f * f * (f * (f * 6.0 - 15.0) + 10.0);
+
+  var corners: array<f32, 8>;
+  for (var k = 0u; k < 8u; k++) {
+    let offset = vec3(f32(k & 1u), f32((k >> 1u) & 1u), f32(k >> 2u));
+    corners[k] = dot(corner_gradient(cell + offset), f - offset);
+  }
+
+  let x0 = mix(mix(corners[0], corners[1], u.x), mix(corners[2], corners[3], u.x), u.y);
+  let x1 = mix(mix(corners[4], corners[5], u.x), mix(corners[6], corners[7], u.x), u.y);
+  return mix(x0, x1, u.z);
+}
+
+// The potential the flow field is the curl of. A finer octave adds turbulence with the
+// intensity.
+fn potential(position: vec2<f32>) -> f32 {
+  let t = time * FLOW_EVOLUTION;
+  let fine = perlin(vec3(position * 2.0 + 17.0, t * 2.0));
+  return perlin(vec3(position, t)) + intensity * 0.5 * fine;
+}
+
+// Velocity of the flow field, which is divergence-free so the points neither bunch up nor
+// spread out.
+fn flow_velocity(position: vec2<f32>) -> vec2<f32> {
+  let q = position / forces.flowScale;
+  let e = 0.01;
+  let dx = potential(q + vec2(e, 0.0)) - potential(q - vec2(e, 0.0));
+  let dy = potential(q + vec2(0.0, e)) - potential(q - vec2(0.0, e));
+  return vec2(dy, -dx) / (2.0 * e) * forces.flowSpeed;
+}
+
+// Acceleration of a point at `position` from all attractors.
+fn attraction(position: vec2<f32>) -> vec2<f32> {
+  var acceleration = vec2(0.0);
+  for (var k = 0u; k < arrayLength(&attractors); k++) {
+    let attractor = attractors[k];
+    if (attractor.strength == 0.0) {
+      continue;
+    }
+
+    var center = attractor.center * windowSize;
+    if (attractor.orbit > 0.0 && attractor.period > 0.0) {
+      let angle = time / attractor.period * 6.2831853;
+      center += vec2(cos(angle), sin(angle)) * attractor.orbit;
+    }
+
+    let offset = center - position;
+    let gap = length(offset);
+    if (gap < attractor.radius && gap > 0.001) {
+      acceleration += offset / gap * attractor.strength * (1.0 - gap / attractor.radius);
+    }
+  }
+  return acceleration;
+}
+
+// Moves a point that left the window or outlived its lifetime to a random spot inside the
+// window, heading in a random direction at the same speed. Under gravity it enters from the
+// edge gravity pulls away from instead, like a new snowflake.
+fn respawn(p: Point, i: u32) -> Point {
+  var state = hash(hash(i ^ forces.seed) ^ bitcast<u32>(p.position.x))
+    ^ bitcast<u32>(p.position.y);
+  var out = p;
+  out.age = 0.0;
+
+  out.position = vec2(random(&state), random(&state)) * windowSize;
+  let angle = random(&state) * 6.2831853;
+  out.velocity = vec2(cos(angle), sin(angle)) * length(p.velocity);
+
+  let gravity = forces.gravity;
+  if (any(gravity != vec2(0.0))) {
+    out.velocity = vec2(0.0);
+    if (abs(gravity.y) >= abs(gravity.x)) {
+      out.position.y = select(windowSize.y, 0.0, gravity.y > 0.0);
+    } else {
+      out.position.x = select(windowSize.x, 0.0, gravity.x > 0.0);
+    }
+  }
+
+  return out;
+}
+
+@group(0) @binding(5) var<uniform> forces : Forces;
+
+// Seconds since the start.
+@group(0) @binding(6) var<uniform> time : f32;
+
+@group(0) @binding(7) var<uniform> intensity : f32;
+
+struct Attractor {
+  // Center from 0 to 1 across the window.
+  center: vec2<f32>,
+  // Acceleration toward the attractor, negative to repel.
+  strength: f32,
+  radius: f32,
+  // Radius in pixels of the circle the attractor moves along around its center.
+  orbit: f32,
+  // Seconds per orbit.
+  period: f32,
+}
+
+@group(0) @binding(8) var<storage, read> attractors : array<Attractor>;
+
+struct Impulses {
+  vortexCenter: vec2<f32>,
+  // Tangential acceleration at the center of the swirl, negative to turn clockwise.
+  vortexStrength: f32,
+  vortexRadius: f32,
+  explosionCenter: vec2<f32>,
+  // Outward speed at the center, added to the velocity of the points while it lasts.
+  explosionSpeed: f32,
+  explosionRadius: f32,
+}
+
+// Brief kicks on hits in the music, updated every frame.
+@group(0) @binding(9) var<uniform> impulses : Impulses;
+
+// The points as they were at the start of the frame. Passes looking at the neighbours of a
+// point read them from here, as the points buffer changes under them.
+@group(0) @binding(10) var<storage, read> previousPoints : array<Point>;
+
+// Acceleration of a point at `position` from the current swirl, fading out toward its edge.
+fn swirl(position: vec2<f32>) -> vec2<f32> {
+  if (impulses.vortexStrength == 0.0) {
+    return vec2(0.0);
+  }
+
+  let offset = position - impulses.vortexCenter;
+  let gap = length(offset);
+  if (gap >= impulses.vortexRadius || gap < 0.001) {
+    return vec2(0.0);
+  }
+
+  let tangent = vec2(-offset.y, offset.x) / gap;
+  return tangent * impulses.vortexStrength * (1.0 - gap / impulses.vortexRadius);
+}
+
+// Velocity of a point at `position` flying away from the current explosion.
+fn explosion(position: vec2<f32>) -> vec2<f32> {
+  if (impulses.explosionSpeed == 0.0) {
+    return vec2(0.0);
+  }
+
+  let offset = position - impulses.explosionCenter;
+  let gap = length(offset);
+  if (gap >= impulses.explosionRadius || gap < 0.001) {
+    return vec2(0.0);
+  }
+
+  return offset / gap * impulses.explosionSpeed * (1.0 - gap / impulses.explosionRadius);
+}
+
+// Spatial grid of the points, built by spatial_grid.wgsl.
+@group(1) @binding(0) var<uniform> grid: Grid;
+
+@group(1) @binding(1) var<storage, read> gridStarts: array<u32>;
+
+@group(1) @binding(2) var<storage, read> gridIndices: array<u32>;
+
+
+// Must match bucket in spatial_grid.wgsl.
+fn grid_bucket(cell: vec2<i32>) -> u32 {
+  return ((bitcast<u32>(cell.x) * 73856093u) ^ (bitcast<u32>(cell.y) * 19349663u))
+    & (grid.tableSize - 1u);
+}
+
+fn neighbourhood(position: vec2<f32>) -> Neighbourhood {
+  let cell = vec2<i32>(floor(position / grid.cellSize));
+
+  var hood: Neighbourhood;
+  hood.count = 0u;
+  for (var y = -1; y <= 1; y++) {
+    for (var x = -1; x <= 1; x++) {
+      let bucket = grid_bucket(cell + vec2(x, y));
+
+      var seen = false;
+      for (var k = 0u; k < hood.count; k++) {
+        seen = seen || hood.buckets[k] == bucket;
+      }
+      if (!seen) {
+        hood.buckets[hood.count] = bucket;
+        hood.count++;
+      }
+    }
+  }
+  return hood;
+}
+
+
+// Pushes every point away from the points overlapping it, harder the deeper they overlap,
+// and gently away from the points within the spacing. Only reads the positions of other
+// points, so it can run before they move.
+@compute
+@workgroup_size(64)
+fn collide(
+  @builtin(global_invocation_id) id: vec3<u32>,
+  ) {
+  let i = id.x;
+
+  let count = arrayLength(&points.data);
+
+  if (i >= count || points.data[i].size <= 0.0) {
+    return;
+  }
+
+  let p = previousPoints[i].position;
+  let radius = forces.collisionRadius;
+  let spacing = forces.spacing;
+
+  var push = vec2(0.0);
+  var spread = vec2(0.0);
+  let hood = neighbourhood(p);
+  for (var b = 0u; b < hood.count; b++) {
+    let bucket = hood.buckets[b];
+    for (var g = gridStarts[bucket]; g < gridStarts[bucket + 1u]; g++) {
+      let j = gridIndices[g];
+      if (j == i) {
+        continue;
+      }
+
+      let offset = p - previousPoints[j].position;
+      let distance_squared = dot(offset, offset);
+      if (distance_squared <= 0.000001) {
+        continue;
+      }
+
+      let gap = sqrt(distance_squared);
+      if (gap < radius) {
+        push += offset / gap * (1.0 - gap / radius);
+      }
+      if (gap < spacing) {
+        spread += offset / gap * (1.0 - gap / spacing);
+      }
+    }
+  }
+
+  let acceleration = push * forces.collisionStrength + spread * forces.spacingStrength;
+  points.data[i].velocity += acceleration / points.data[i].mass * deltaTime;
+}
+
 
 @compute
 @workgroup_size(64)
@@ -24,23 +360,56 @@ fn main(
 
   let count = arrayLength(&points.data);
 
-  if (i >= count) {
+  // Unused slots stay where they are.
+  if (i >= count || points.data[i].size <= 0.0) {
     return;
   }
 
   var p = points.data[i];
 
-  p.position += p.velocity * deltaTime;
+  // Pull toward or push away from the cursor, fading out toward the edge of the radius.
+  if (cursor.inside != 0u && cursor.force != 0.0) {
+    let offset = cursor.position - p.position;
+    let gap = length(offset);
+    if (gap < cursor.radius && gap > 0.001) {
+      let falloff = 1.0 - gap / cursor.radius;
+      p.velocity += offset / gap * cursor.force * falloff * falloff / p.mass * deltaTime;
+    }
+  }
 
-  if (p.position.x < 0.0 || p.position.x > windowSize.x) {
-    p.velocity.x = -p.velocity.x;
+  if (forces.motion == MOTION_FLOW) {
+    let flow = flow_velocity(p.position);
+    p.velocity = mix(p.velocity, flow, 1.0 - exp(-FLOW_RESPONSE / p.mass * deltaTime));
   }
 
-  if (p.position.y < 0.0 || p.position.y > windowSize.y) {
-    p.velocity.y = -p.velocity.y;
+  p.velocity += (forces.gravity + attraction(p.position) + swirl(p.position)) / p.mass * deltaTime;
+  p.velocity *= exp(-p.drag * deltaTime);
+
+  let speed = mix(forces.silentSpeed, forces.loudSpeed, intensity);
+  p.position += (p.velocity * speed + explosion(p.position) / p.mass + drift) * deltaTime;
+  p.age += deltaTime;
+
+  // Every slot gets its own lifetime from half to one and a half times the average, so the
+  // points do not all respawn at once.
+  if (forces.lifetime > 0.0) {
+    var state = hash(i ^ forces.seed);
+    if (p.age >= forces.lifetime * (0.5 + random(&state))) {
+      p = respawn(p, i);
+    }
   }
 
-  p.position = clamp(p.position, vec2(0.0), windowSize);
+  let outside = (p.position < vec2(0.0)) | (p.position > windowSize);
+
+  if (forces.edge == EDGE_WRAP) {
+    p.position -= floor(p.position / windowSize) * windowSize;
+  } else if (forces.edge == EDGE_RESPAWN) {
+    if (any(outside)) {
+      p = respawn(p, i);
+    }
+  } else {
+    p.velocity = select(p.velocity, -p.velocity, outside);
+    p.position = clamp(p.position, vec2(0.0), windowSize);
+  }
 
   points.data[i] = p;
 }
@@ -0,0 +1,67 @@
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) uv: vec2<f32>,
+};
+
+struct Globals {
+  windowSize: vec2<f32>,
+  time: f32,
+  intensity: f32,
+  // Offset between the color channels at the edges, in pixels.
+  aberration: f32,
+};
+
+@group(0) @binding(0)
+var t_input: texture_2d<f32>;
+@group(0) @binding(1)
+var s_input: sampler;
+
+@group(0) @binding(2)
+var<uniform> globals: Globals;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+  let quad = array<vec2<f32>, 4>(
+    vec2(-1.0, -1.0),
+    vec2( 1.0, -1.0),
+    vec2(-1.0,  1.0),
+    vec2( 1.0,  1.0),
+  );
+
+  let pos = quad[vertex_index];
+
+  var out: VertexOutput;
+  out.clip_position = vec4(pos, 0.0, 1.0);
+  out.uv = vec2(pos.x * 0.5 + 0.5, 0.5 - pos.y * 0.5);
+
+  return out;
+}
+
+// Brightness above which pixels start to glow.
+const THRESHOLD = 0.6;
+const RADIUS = 3.0;
+const STRENGTH = 0.8;
+
+fn bright(uv: vec2<f32>) -> vec3<f32> {
+  let color = textureSample(t_input, s_input, uv).rgb;
+  let luminance = dot(color, vec3(0.2126, 0.7152, 0.0722));
+  return color * smoothstep(THRESHOLD, 1.0, luminance);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  let texel = RADIUS / globals.windowSize;
+  let base = textureSample(t_input, s_input, in.uv);
+
+  // Sample the bright parts on two rings around the pixel.
+  var glow = vec3(0.0);
+  for (var i = 0; i < 12; i++) {
+    let angle = f32(i) * 0.5235988;
+    let direction = vec2(cos(angle), sin(angle));
+    glow += bright(in.uv + direction * texel * 2.0) * 0.6;
+    glow += bright(in.uv + direction * texel * 5.0) * 0.4;
+  }
+  glow /= 12.0;
+
+  return vec4(base.rgb + glow * STRENGTH, base.a);
+}
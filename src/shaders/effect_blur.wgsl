@@ -0,0 +1,57 @@
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) uv: vec2<f32>,
+};
+
+struct Globals {
+  windowSize: vec2<f32>,
+  time: f32,
+  intensity: f32,
+  // Offset between the color channels at the edges, in pixels.
+  aberration: f32,
+};
+
+@group(0) @binding(0)
+var t_input: texture_2d<f32>;
+@group(0) @binding(1)
+var s_input: sampler;
+
+@group(0) @binding(2)
+var<uniform> globals: Globals;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+  let quad = array<vec2<f32>, 4>(
+    vec2(-1.0, -1.0),
+    vec2( 1.0, -1.0),
+    vec2(-1.0,  1.0),
+    vec2( 1.0,  1.0),
+  );
+
+  let pos = quad[vertex_index];
+
+  var out: VertexOutput;
+  out.clip_position = vec4(pos, 0.0, 1.0);
+  out.uv = vec2(pos.x * 0.5 + 0.5, 0.5 - pos.y * 0.5);
+
+  return out;
+}
+
+// Gaussian weights for offsets -2 to 2.
+const WEIGHTS = array<f32, 5>(0.0545, 0.2442, 0.4026, 0.2442, 0.0545);
+const RADIUS = 2.0;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  let texel = RADIUS / globals.windowSize;
+
+  var color = vec4(0.0);
+  for (var y = 0; y < 5; y++) {
+    for (var x = 0; x < 5; x++) {
+      let offset = vec2(f32(x - 2), f32(y - 2)) * texel;
+      color += textureSample(t_input, s_input, in.uv + offset) * WEIGHTS[x] * WEIGHTS[y];
+    }
+  }
+
+  return color;
+}
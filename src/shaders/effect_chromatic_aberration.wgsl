@@ -0,0 +1,50 @@
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) uv: vec2<f32>,
+};
+
+struct Globals {
+  windowSize: vec2<f32>,
+  time: f32,
+  intensity: f32,
+  // Offset between the color channels at the edges, in pixels.
+  aberration: f32,
+};
+
+@group(0) @binding(0)
+var t_input: texture_2d<f32>;
+@group(0) @binding(1)
+var s_input: sampler;
+
+@group(0) @binding(2)
+var<uniform> globals: Globals;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+  let quad = array<vec2<f32>, 4>(
+    vec2(-1.0, -1.0),
+    vec2( 1.0, -1.0),
+    vec2(-1.0,  1.0),
+    vec2( 1.0,  1.0),
+  );
+
+  let pos = quad[vertex_index];
+
+  var out: VertexOutput;
+  out.clip_position = vec4(pos, 0.0, 1.0);
+  out.uv = vec2(pos.x * 0.5 + 0.5, 0.5 - pos.y * 0.5);
+
+  return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  // Split the channels outwards from the center, more towards the edges.
+  let offset = (in.uv - 0.5) * 2.0 * globals.aberration / globals.windowSize;
+
+  let red = textureSample(t_input, s_input, in.uv + offset);
+  let center = textureSample(t_input, s_input, in.uv);
+  let blue = textureSample(t_input, s_input, in.uv - offset);
+
+  return vec4(red.r, center.g, blue.b, max(center.a, max(red.a, blue.a)));
+}
@@ -0,0 +1,74 @@
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) uv: vec2<f32>,
+};
+
+struct Globals {
+  windowSize: vec2<f32>,
+  time: f32,
+  intensity: f32,
+  // Offset between the color channels at the edges, in pixels.
+  aberration: f32,
+};
+
+@group(0) @binding(0)
+var t_input: texture_2d<f32>;
+@group(0) @binding(1)
+var s_input: sampler;
+
+@group(0) @binding(2)
+var<uniform> globals: Globals;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+  let quad = array<vec2<f32>, 4>(
+    vec2(-1.0, -1.0),
+    vec2( 1.0, -1.0),
+    vec2(-1.0,  1.0),
+    vec2( 1.0,  1.0),
+  );
+
+  let pos = quad[vertex_index];
+
+  var out: VertexOutput;
+  out.clip_position = vec4(pos, 0.0, 1.0);
+  out.uv = vec2(pos.x * 0.5 + 0.5, 0.5 - pos.y * 0.5);
+
+  return out;
+}
+
+// How strongly the screen bulges outwards.
+const CURVATURE = 0.08;
+// Height of one scanline in pixels.
+const SCANLINE_HEIGHT = 3.0;
+const SCANLINE_STRENGTH = 0.35;
+const MASK_STRENGTH = 0.2;
+
+// Bends the coordinates like the glass of a CRT, pushing them outwards towards the corners.
+fn barrel(uv: vec2<f32>) -> vec2<f32> {
+  let centered = uv * 2.0 - 1.0;
+  let distorted = centered * (1.0 + CURVATURE * dot(centered, centered));
+  return distorted * 0.5 + 0.5;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  let uv = barrel(in.uv);
+  let color = textureSample(t_input, s_input, uv);
+
+  // Black outside the curved screen.
+  if (any(uv < vec2(0.0)) || any(uv > vec2(1.0))) {
+    return vec4(0.0, 0.0, 0.0, color.a);
+  }
+
+  let pixel = uv * globals.windowSize;
+
+  let scanline = 1.0 - SCANLINE_STRENGTH * (0.5 - 0.5 * cos(pixel.y / SCANLINE_HEIGHT * 6.2831853));
+
+  // Aperture grille: every column of pixels favours one of red, green or blue.
+  let column = u32(pixel.x) % 3u;
+  var mask = vec3(1.0 - MASK_STRENGTH);
+  mask[column] = 1.0;
+
+  return vec4(color.rgb * scanline * mask, color.a);
+}
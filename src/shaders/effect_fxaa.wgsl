@@ -0,0 +1,87 @@
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) uv: vec2<f32>,
+};
+
+struct Globals {
+  windowSize: vec2<f32>,
+  time: f32,
+  intensity: f32,
+  // Offset between the color channels at the edges, in pixels.
+  aberration: f32,
+};
+
+@group(0) @binding(0)
+var t_input: texture_2d<f32>;
+@group(0) @binding(1)
+var s_input: sampler;
+
+@group(0) @binding(2)
+var<uniform> globals: Globals;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+  let quad = array<vec2<f32>, 4>(
+    vec2(-1.0, -1.0),
+    vec2( 1.0, -1.0),
+    vec2(-1.0,  1.0),
+    vec2( 1.0,  1.0),
+  );
+
+  let pos = quad[vertex_index];
+
+  var out: VertexOutput;
+  out.clip_position = vec4(pos, 0.0, 1.0);
+  out.uv = vec2(pos.x * 0.5 + 0.5, 0.5 - pos.y * 0.5);
+
+  return out;
+}
+
+// Fast approximate anti-aliasing, after Timothy Lottes' FXAA.
+const REDUCE_MIN = 1.0 / 128.0;
+const REDUCE_MUL = 1.0 / 8.0;
+// Longest edge search in pixels.
+const SPAN_MAX = 8.0;
+
+fn luma(color: vec3<f32>) -> f32 {
+  return dot(color, vec3(0.299, 0.587, 0.114));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  let texel = 1.0 / globals.windowSize;
+
+  let center = textureSample(t_input, s_input, in.uv);
+  let luma_nw = luma(textureSample(t_input, s_input, in.uv + vec2(-1.0, -1.0) * texel).rgb);
+  let luma_ne = luma(textureSample(t_input, s_input, in.uv + vec2( 1.0, -1.0) * texel).rgb);
+  let luma_sw = luma(textureSample(t_input, s_input, in.uv + vec2(-1.0,  1.0) * texel).rgb);
+  let luma_se = luma(textureSample(t_input, s_input, in.uv + vec2( 1.0,  1.0) * texel).rgb);
+  let luma_m = luma(center.rgb);
+
+  let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+  let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+  // Blur along the edge, perpendicular to the luma gradient.
+  var direction = vec2(
+    -((luma_nw + luma_ne) - (luma_sw + luma_se)),
+    (luma_nw + luma_sw) - (luma_ne + luma_se),
+  );
+  let direction_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+  let inverse_direction_min = 1.0 / (min(abs(direction.x), abs(direction.y)) + direction_reduce);
+  direction = clamp(direction * inverse_direction_min, vec2(-SPAN_MAX), vec2(SPAN_MAX)) * texel;
+
+  let near = 0.5 * (
+    textureSample(t_input, s_input, in.uv + direction * (1.0 / 3.0 - 0.5)).rgb +
+    textureSample(t_input, s_input, in.uv + direction * (2.0 / 3.0 - 0.5)).rgb
+  );
+  let far = near * 0.5 + 0.25 * (
+    textureSample(t_input, s_input, in.uv - direction * 0.5).rgb +
+    textureSample(t_input, s_input, in.uv + direction * 0.5).rgb
+  );
+
+  // The wide blur crossed another edge, so stick with the narrow one.
+  let luma_far = luma(far);
+  let crossed = luma_far < luma_min || luma_far > luma_max;
+
+  return vec4(select(far, near, crossed), center.a);
+}
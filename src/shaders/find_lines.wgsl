@@ -0,0 +1,235 @@
+struct Point {
+  position: vec2<f32>,
+  velocity: vec2<f32>,
+  // Multiplier of the point size.
+  size: f32,
+  // How strongly forces are resisted, 1 for an average point.
+  mass: f32,
+  // Rate per second the velocity decays at.
+  drag: f32,
+  // Seconds since the point was spawned.
+  age: f32,
+}
+
+struct Line {
+  a: u32,
+  b: u32,
+}
+
+// Arguments of the indirect draw, with one instance per line.
+struct DrawIndirect {
+  vertexCount: u32,
+  instanceCount: atomic<u32>,
+  firstVertex: u32,
+  firstInstance: u32,
+}
+
+struct LineStyle {
+  distance: f32,
+  width: f32,
+  // How many of its nearest neighbours each point may connect to, 0 for no limit.
+  maxConnections: u32,
+  // Whether the cursor connects to nearby points.
+  cursor: u32,
+}
+
+struct Cursor {
+  position: vec2<f32>,
+  // 1 while the cursor is inside the window.
+  inside: u32,
+}
+
+struct Grid {
+  cellSize: f32,
+  // Number of buckets the cells are hashed into, a power of two.
+  tableSize: u32,
+}
+
+// The distinct buckets of the 3x3 cells around a point. Neighbouring cells can share a
+// bucket, which must only be walked once.
+struct Neighbourhood {
+  buckets: array<u32, 9>,
+  count: u32,
+}
+
+// Stands in for a point index on the end of a line attached to the cursor.
+const CURSOR_INDEX = 0xffffffffu;
+
+// Must match MAX_CONNECTIONS_LIMIT.
+const MAX_CONNECTIONS = 16u;
+
+@group(0) @binding(0) var<storage, read> points: array<Point>;
+
+@group(0) @binding(1) var<storage, read_write> lines: array<Line>;
+
+@group(0) @binding(2) var<storage, read_write> draw: DrawIndirect;
+
+@group(0) @binding(3) var<uniform> style: LineStyle;
+
+// Squared distance to the furthest neighbour each point may connect to, written by
+// find_nearest when the connections are limited.
+@group(0) @binding(4) var<storage, read_write> nearest: array<f32>;
+
+@group(0) @binding(5) var<uniform> cursor: Cursor;
+
+// Spatial grid of the points, built by spatial_grid.wgsl.
+@group(1) @binding(0) var<uniform> grid: Grid;
+
+@group(1) @binding(1) var<storage, read> gridStarts: array<u32>;
+
+@group(1) @binding(2) var<storage, read> gridIndices: array<u32>;
+
+
+// Must match bucket in spatial_grid.wgsl.
+fn grid_bucket(cell: vec2<i32>) -> u32 {
+  return ((bitcast<u32>(cell.x) * 73856093u) ^ (bitcast<u32>(cell.y) * 19349663u))
+    & (grid.tableSize - 1u);
+}
+
+fn neighbourhood(position: vec2<f32>) -> Neighbourhood {
+  let cell = vec2<i32>(floor(position / grid.cellSize));
+
+  var hood: Neighbourhood;
+  hood.count = 0u;
+  for (var y = -1; y <= 1; y++) {
+    for (var x = -1; x <= 1; x++) {
+      let bucket = grid_bucket(cell + vec2(x, y));
+
+      var seen = false;
+      for (var k = 0u; k < hood.count; k++) {
+        seen = seen || hood.buckets[k] == bucket;
+      }
+      if (!seen) {
+        hood.buckets[hood.count] = bucket;
+        hood.count++;
+      }
+    }
+  }
+  return hood;
+}
+
+
+// Appends a line, returning false once the line buffer is full.
+fn push_line(line: Line) -> bool {
+  let index = atomicAdd(&draw.instanceCount, 1u);
+  if (index >= arrayLength(&lines)) {
+    // Out of room, undo so the draw stays within the buffer.
+    atomicSub(&draw.instanceCount, 1u);
+    return false;
+  }
+  lines[index] = line;
+  return true;
+}
+
+
+@compute
+@workgroup_size(64)
+fn find_nearest(
+  @builtin(global_invocation_id) id: vec3<u32>,
+  ) {
+  let i = id.x;
+
+  let count = arrayLength(&points);
+
+  if (i >= count) {
+    return;
+  }
+
+  // Unused slots never connect.
+  if (points[i].size <= 0.0) {
+    return;
+  }
+
+  let p = points[i].position;
+  let n = min(style.maxConnections, MAX_CONNECTIONS);
+
+  // The n smallest squared distances seen so far, in ascending order.
+  var best: array<f32, MAX_CONNECTIONS>;
+  for (var k = 0u; k < n; k++) {
+    best[k] = style.distance * style.distance;
+  }
+
+  let hood = neighbourhood(p);
+  for (var b = 0u; b < hood.count; b++) {
+    let bucket = hood.buckets[b];
+    for (var g = gridStarts[bucket]; g < gridStarts[bucket + 1u]; g++) {
+      let j = gridIndices[g];
+      let d = points[j].position - p;
+      let distance_squared = dot(d, d);
+      if (j == i || distance_squared >= best[n - 1u]) {
+        continue;
+      }
+
+      // Insertion sort step, dropping the current furthest.
+      var k = n - 1u;
+      while (k > 0u && best[k - 1u] > distance_squared) {
+        best[k] = best[k - 1u];
+        k--;
+      }
+      best[k] = distance_squared;
+    }
+  }
+
+  nearest[i] = best[n - 1u];
+}
+
+
+@compute
+@workgroup_size(64)
+fn main(
+  @builtin(global_invocation_id) id: vec3<u32>,
+  ) {
+  let i = id.x;
+
+  let count = arrayLength(&points);
+
+  if (i >= count) {
+    return;
+  }
+
+  // Unused slots never connect.
+  if (points[i].size <= 0.0) {
+    return;
+  }
+
+  let p = points[i].position;
+  let max_distance_squared = style.distance * style.distance;
+
+  // The cursor connects like a point of its own, regardless of the connection limit.
+  if (style.cursor != 0u && cursor.inside != 0u) {
+    let d = cursor.position - p;
+    if (dot(d, d) < max_distance_squared && !push_line(Line(i, CURSOR_INDEX))) {
+      return;
+    }
+  }
+
+  let hood = neighbourhood(p);
+  for (var b = 0u; b < hood.count; b++) {
+    let bucket = hood.buckets[b];
+    for (var g = gridStarts[bucket]; g < gridStarts[bucket + 1u]; g++) {
+      let j = gridIndices[g];
+
+      // Every pair is only checked by its lower index.
+      if (j <= i) {
+        continue;
+      }
+
+      let d = points[j].position - p;
+      let distance_squared = dot(d, d);
+      if (distance_squared >= max_distance_squared) {
+        continue;
+      }
+
+      // Only connect pairs that are among each other's nearest neighbours, so no point
+      // takes part in more than maxConnections lines.
+      if (style.maxConnections > 0u
+          && (distance_squared > nearest[i] || distance_squared > nearest[j])) {
+        continue;
+      }
+
+      if (!push_line(Line(i, j))) {
+        return;
+      }
+    }
+  }
+}
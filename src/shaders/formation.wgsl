@@ -0,0 +1,64 @@
+struct Point {
+  position: vec2<f32>,
+  velocity: vec2<f32>,
+  // Multiplier of the point size.
+  size: f32,
+  // How strongly forces are resisted, 1 for an average point.
+  mass: f32,
+  // Rate per second the velocity decays at.
+  drag: f32,
+  // Seconds since the point was spawned.
+  age: f32,
+}
+
+struct Formation {
+  // Width divided by height of the shape.
+  aspect: f32,
+  strength: f32,
+}
+
+// Fraction of the window the shape fills along its tighter dimension.
+const FILL = 0.8;
+
+@group(0) @binding(0) var<storage, read_write> points: array<Point>;
+
+// Where each point belongs in the shape, from 0 to 1 across the shape.
+@group(0) @binding(1) var<storage, read> targets: array<vec2<f32>>;
+
+@group(0) @binding(2) var<uniform> windowSize: vec2<f32>;
+
+@group(0) @binding(3) var<uniform> deltaTime: f32;
+
+@group(0) @binding(4) var<uniform> intensity: f32;
+
+@group(0) @binding(5) var<uniform> formation: Formation;
+
+
+@compute
+@workgroup_size(64)
+fn main(
+  @builtin(global_invocation_id) id: vec3<u32>,
+  ) {
+  let i = id.x;
+
+  if (i >= arrayLength(&points)) {
+    return;
+  }
+
+  // Fit the shape into the window, centered.
+  let height = min(windowSize.x / formation.aspect, windowSize.y) * FILL;
+  let size = vec2(height * formation.aspect, height);
+  let origin = (windowSize - size) * 0.5;
+
+  var p = points[i];
+  let target_position = origin + targets[i] * size;
+
+  // A damped spring that gets stiffer with the intensity, so the points wander loosely
+  // when it is quiet and snap into the shape when it is loud.
+  let stiffness = formation.strength * mix(0.5, 8.0, intensity);
+  let damping = sqrt(stiffness);
+  let acceleration = (target_position - p.position) * stiffness - p.velocity * damping;
+  p.velocity += acceleration * deltaTime;
+
+  points[i] = p;
+}
@@ -0,0 +1,119 @@
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) alpha: f32,
+};
+
+struct Point {
+  position: vec2<f32>,
+  velocity: vec2<f32>,
+  // Multiplier of the point size.
+  size: f32,
+  // How strongly forces are resisted, 1 for an average point.
+  mass: f32,
+  // Rate per second the velocity decays at.
+  drag: f32,
+  // Seconds since the point was spawned.
+  age: f32,
+}
+
+struct Line {
+  a: u32,
+  b: u32,
+}
+
+struct LineStyle {
+  distance: f32,
+  width: f32,
+  maxConnections: u32,
+  cursor: u32,
+}
+
+struct Cursor {
+  position: vec2<f32>,
+  inside: u32,
+}
+
+// Must match CURSOR_INDEX in find_lines.wgsl.
+const CURSOR_INDEX = 0xffffffffu;
+
+struct Intro {
+  progress: f32,
+  mode: u32,
+};
+
+@group(0) @binding(0)
+var<storage, read> points: array<Point>;
+
+@group(0) @binding(1)
+var<storage, read> lines: array<Line>;
+
+@group(0) @binding(2)
+var<uniform> windowSize: vec2<f32>;
+
+@group(0) @binding(3)
+var<uniform> intensity: f32;
+
+@group(0) @binding(4)
+var<uniform> style: LineStyle;
+
+@group(0) @binding(5)
+var<uniform> intro: Intro;
+
+@group(0) @binding(6)
+var<uniform> cursor: Cursor;
+
+fn end_position(index: u32) -> vec2<f32> {
+  if (index == CURSOR_INDEX) {
+    return cursor.position;
+  }
+  return points[index].position;
+}
+
+@vertex
+fn vs_main(
+  @builtin(vertex_index) vertex_index: u32,
+  @builtin(instance_index) instance_index: u32,
+  ) -> VertexOutput {
+
+  let line = lines[instance_index];
+  let a = end_position(line.a);
+  let b = end_position(line.b);
+
+  let along = b - a;
+  let line_length = max(length(along), 0.0001);
+  let normal = vec2(-along.y, along.x) / line_length * style.width * 0.5;
+
+  // Rendered using Triangle Strip, two vertices at each end.
+  var world = a;
+  if (vertex_index >= 2u) {
+    world = b;
+  }
+  if (vertex_index % 2u == 0u) {
+    world -= normal;
+  } else {
+    world += normal;
+  }
+
+  let ndc = vec2(
+    (world.x / windowSize.x) * 2.0 - 1.0,
+    1.0 - (world.y / windowSize.y) * 2.0
+  );
+
+  var out: VertexOutput;
+  out.clip_position = vec4<f32>(ndc, 0.0, 1.0);
+
+  // Fade out towards the connection distance, and only show up at the end of the intro
+  // once the points have settled.
+  var fade = 1.0;
+  if (intro.mode != 0u) {
+    fade = pow(intro.progress, 4.0);
+  }
+  out.alpha = (1.0 - line_length / style.distance) * intensity * fade;
+
+  return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  return vec4<f32>(1.0, 1.0, 1.0, in.alpha);
+}
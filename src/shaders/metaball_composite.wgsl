@@ -0,0 +1,36 @@
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var field: texture_2d<f32>;
+
+@group(0) @binding(1)
+var<uniform> intensity: f32;
+
+// Field value where the surface of the blobs lies, and how soft its edge is.
+const THRESHOLD = 0.5;
+const EDGE = 0.08;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+  let quad = array<vec2<f32>, 4>(
+    vec2(-1.0, -1.0),
+    vec2( 1.0, -1.0),
+    vec2(-1.0,  1.0),
+    vec2( 1.0,  1.0),
+  );
+
+  var out: VertexOutput;
+  out.clip_position = vec4(quad[vertex_index], 0.0, 1.0);
+
+  return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  let value = textureLoad(field, vec2<i32>(in.clip_position.xy), 0).r;
+  let coverage = smoothstep(THRESHOLD - EDGE, THRESHOLD + EDGE, value);
+
+  return vec4<f32>(1.0, 1.0, 1.0, coverage * intensity);
+}
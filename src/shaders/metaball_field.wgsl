@@ -0,0 +1,61 @@
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) vert_pos: vec2<f32>,
+};
+
+struct Point {
+  position: vec2<f32>,
+  velocity: vec2<f32>,
+  // Multiplier of the point size.
+  size: f32,
+  // How strongly forces are resisted, 1 for an average point.
+  mass: f32,
+  // Rate per second the velocity decays at.
+  drag: f32,
+  // Seconds since the point was spawned.
+  age: f32,
+};
+
+@group(0) @binding(0)
+var<storage, read> points: array<Point>;
+
+@group(0) @binding(1)
+var<uniform> windowSize: vec2<f32>;
+
+// Distance in pixels at which a point stops contributing to the field.
+@group(0) @binding(2)
+var<uniform> radius: f32;
+
+@vertex
+fn vs_main(
+  @builtin(vertex_index) vertex_index: u32,
+  @builtin(instance_index) instance_index: u32,
+  ) -> VertexOutput {
+
+  let quad = array<vec2<f32>, 4>(
+    vec2(-1.0, -1.0),
+    vec2( 1.0, -1.0),
+    vec2(-1.0,  1.0),
+    vec2( 1.0,  1.0),
+  );
+
+  let point = points[instance_index];
+  let world = point.position + quad[vertex_index] * radius * point.size;
+
+  let ndc = vec2(
+    (world.x / windowSize.x) * 2.0 - 1.0,
+    1.0 - (world.y / windowSize.y) * 2.0
+  );
+
+  var out: VertexOutput;
+  out.clip_position = vec4<f32>(ndc, 0.0, 1.0);
+  out.vert_pos = quad[vertex_index];
+  return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  // Smooth falloff reaching exactly 0 at the edge of the quad's circle.
+  let falloff = max(1.0 - dot(in.vert_pos, in.vert_pos), 0.0);
+  return vec4<f32>(falloff * falloff, 0.0, 0.0, 0.0);
+}
@@ -0,0 +1,38 @@
+struct VertexInput {
+  // Position in window pixels.
+  @location(0) position: vec2<f32>,
+  @location(1) uv: vec2<f32>,
+};
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) uv: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var t_atlas: texture_2d<f32>;
+@group(0) @binding(1)
+var s_atlas: sampler;
+
+@group(0) @binding(2)
+var<uniform> windowSize: vec2<f32>;
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+  let ndc = vec2(
+    (in.position.x / windowSize.x) * 2.0 - 1.0,
+    1.0 - (in.position.y / windowSize.y) * 2.0
+  );
+
+  var out: VertexOutput;
+  out.clip_position = vec4(ndc, 0.0, 1.0);
+  out.uv = in.uv;
+
+  return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  let coverage = textureSample(t_atlas, s_atlas, in.uv).r;
+  return vec4(1.0, 1.0, 1.0, coverage);
+}
@@ -0,0 +1,103 @@
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) uv: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> preset: u32;
+
+@group(0) @binding(1)
+var<uniform> time: f32;
+
+@group(0) @binding(2)
+var<uniform> intensity: f32;
+
+@group(0) @binding(3)
+var<uniform> windowSize: vec2<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+  let quad = array<vec2<f32>, 4>(
+    vec2(-1.0, -1.0),
+    vec2( 1.0, -1.0),
+    vec2(-1.0,  1.0),
+    vec2( 1.0,  1.0),
+  );
+
+  let pos = quad[vertex_index];
+
+  var out: VertexOutput;
+  out.clip_position = vec4(pos, 0.0, 1.0);
+  out.uv = vec2(pos.x * 0.5 + 0.5, 0.5 - pos.y * 0.5);
+
+  return out;
+}
+
+fn hash(p: vec2<f32>) -> f32 {
+  return fract(sin(dot(p, vec2(127.1, 311.7))) * 43758.5453);
+}
+
+fn value_noise(p: vec2<f32>) -> f32 {
+  let i = floor(p);
+  let f = fract(p);
+  let u = f * f * (3.0 - 2.0 * f);
+
+  let a = hash(i);
+  let b = hash(i + vec2(1.0, 0.0));
+  let c = hash(i + vec2(0.0, 1.0));
+  let d = hash(i + vec2(1.0, 1.0));
+
+  return mix(mix(a, b, u.x), mix(c, d, u.x), u.y);
+}
+
+fn fbm(p: vec2<f32>) -> f32 {
+  var value = 0.0;
+  var amplitude = 0.5;
+  var q = p;
+  for (var i = 0; i < 5; i++) {
+    value += amplitude * value_noise(q);
+    q = q * 2.0 + vec2(1.7, 9.2);
+    amplitude *= 0.5;
+  }
+  return value;
+}
+
+fn plasma(p: vec2<f32>, t: f32) -> vec3<f32> {
+  var v = sin(p.x * 3.0 + t);
+  v += sin((p.y * 3.0 + t) * 0.5);
+  v += sin((p.x * 3.0 + p.y * 3.0 + t) * 0.5);
+  let c = p + vec2(sin(t / 3.0), cos(t / 2.0)) * 0.5;
+  v += sin(sqrt(c.x * c.x * 9.0 + c.y * c.y * 9.0 + 1.0) + t);
+  v *= 0.5;
+
+  return vec3(sin(v * 3.1416), sin(v * 3.1416 + 2.094), sin(v * 3.1416 + 4.188)) * 0.5 + 0.5;
+}
+
+fn nebula(p: vec2<f32>, t: f32) -> vec3<f32> {
+  let warp = vec2(fbm(p + vec2(0.0, t)), fbm(p + vec2(5.2, -t)));
+  let n = fbm(p * 1.5 + warp * 2.0);
+
+  let deep = vec3(0.02, 0.01, 0.08);
+  let dust = vec3(0.35, 0.1, 0.45);
+  let glow = vec3(0.1, 0.5, 0.7);
+
+  return mix(mix(deep, dust, smoothstep(0.3, 0.7, n)), glow, smoothstep(0.6, 0.9, n));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  // Keep features square regardless of the window aspect ratio.
+  let aspect = windowSize.x / max(windowSize.y, 1.0);
+  let p = vec2(in.uv.x * aspect, in.uv.y) * 2.0;
+
+  var color: vec3<f32>;
+  if (preset == 1u) {
+    color = nebula(p, time * 0.03);
+  } else {
+    color = plasma(p, time * 0.2);
+  }
+
+  // Stay dark so the dots remain the focus, brightening slightly with the music.
+  let brightness = 0.15 + 0.2 * intensity;
+  return vec4(color * brightness, 1.0);
+}
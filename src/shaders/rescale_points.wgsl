@@ -0,0 +1,32 @@
+struct Point {
+  position: vec2<f32>,
+  velocity: vec2<f32>,
+  // Multiplier of the point size.
+  size: f32,
+  // How strongly forces are resisted, 1 for an average point.
+  mass: f32,
+  // Rate per second the velocity decays at.
+  drag: f32,
+  // Seconds since the point was spawned.
+  age: f32,
+}
+
+@group(0) @binding(0) var<storage, read_write> points: array<Point>;
+
+// New size of the window divided by the old one.
+@group(0) @binding(1) var<uniform> scale: vec2<f32>;
+
+@compute
+@workgroup_size(64)
+fn main(
+  @builtin(global_invocation_id) id: vec3<u32>,
+  ) {
+  let i = id.x;
+
+  // Unused slots are zeroed and stay that way.
+  if (i >= arrayLength(&points) || points[i].size <= 0.0) {
+    return;
+  }
+
+  points[i].position *= scale;
+}
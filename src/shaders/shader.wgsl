@@ -1,11 +1,23 @@
 struct VertexOutput {
   @builtin(position) clip_position: vec4<f32>,
   @location(0) vert_pos: vec2<f32>,
+  @location(1) color: vec3<f32>,
+  @location(2) fade: f32,
+  // Position in window pixels, with y pointing down.
+  @location(3) world: vec2<f32>,
 };
 
 struct Point {
   position: vec2<f32>,
   velocity: vec2<f32>,
+  // Multiplier of the point size.
+  size: f32,
+  // How strongly forces are resisted, 1 for an average point.
+  mass: f32,
+  // Rate per second the velocity decays at.
+  drag: f32,
+  // Seconds since the point was spawned.
+  age: f32,
 };
 
 struct Points {
@@ -24,6 +36,85 @@ var<uniform> pointSize: f32;
 @group(0) @binding(3)
 var<uniform> intensity: f32;
 
+// Ring buffer of the most recent intensities, one texel per frame.
+@group(0) @binding(4)
+var intensityHistory: texture_1d<f32>;
+
+// Index of the most recently written texel in intensityHistory.
+@group(0) @binding(5)
+var<uniform> intensityHistoryHead: u32;
+
+// External per-point data normalized to 0-1, negative for points without data.
+@group(0) @binding(6)
+var<storage, read> pointValues: array<f32>;
+
+struct PointDataStyle {
+  lowColor: vec4<f32>,
+  highColor: vec4<f32>,
+  minScale: f32,
+  maxScale: f32,
+};
+
+@group(0) @binding(7)
+var<uniform> pointDataStyle: PointDataStyle;
+
+struct Intro {
+  // 0 at startup, reaching 1 when the intro has finished.
+  progress: f32,
+  // 0 = none, 1 = fade, 2 = converge
+  mode: u32,
+};
+
+@group(0) @binding(8)
+var<uniform> intro: Intro;
+
+// The background image's bind group, only bound for fs_background.
+@group(1) @binding(0)
+var backgroundTexture: texture_2d<f32>;
+
+@group(1) @binding(1)
+var backgroundSampler: sampler;
+
+@group(1) @binding(3)
+var<uniform> windowPos: vec2<f32>;
+
+// The rectangle the background image covers on the monitor, in monitor pixels.
+struct Placement {
+  origin: vec2<f32>,
+  size: vec2<f32>,
+  tile: u32,
+  zoom: f32,
+};
+
+@group(1) @binding(4)
+var<uniform> backgroundPlacement: Placement;
+
+// Returns the point on the window edge closest to p, pushed just outside the window.
+fn nearest_edge(p: vec2<f32>) -> vec2<f32> {
+  let margin = pointSize * 4.0;
+  let left = p.x;
+  let right = windowSize.x - p.x;
+  let top = p.y;
+  let bottom = windowSize.y - p.y;
+  let nearest = min(min(left, right), min(top, bottom));
+
+  if (nearest == left) {
+    return vec2(-margin, p.y);
+  } else if (nearest == right) {
+    return vec2(windowSize.x + margin, p.y);
+  } else if (nearest == top) {
+    return vec2(p.x, -margin);
+  }
+  return vec2(p.x, windowSize.y + margin);
+}
+
+// Returns the intensity from `age` frames ago (0 is the current frame).
+fn intensity_history(age: u32) -> f32 {
+  let len = textureDimensions(intensityHistory);
+  let index = (intensityHistoryHead + len - (age % len)) % len;
+  return textureLoad(intensityHistory, index, 0).r;
+}
+
 @vertex
 fn vs_main(
   @builtin(vertex_index) vertex_index: u32,
@@ -43,9 +134,27 @@ fn vs_main(
 
   var out: VertexOutput;
 
-  let p = points.data[instance_index].position;
+  var p = points.data[instance_index].position;
+
+  // Ease out so points arrive gently.
+  let eased = 1.0 - pow(1.0 - intro.progress, 3.0);
+  out.fade = 1.0;
+  if (intro.mode != 0u) {
+    out.fade = eased;
+  }
+  if (intro.mode == 2u) {
+    p = mix(nearest_edge(p), p, eased);
+  }
 
-  let offset = quad[vertex_index] * pointSize * 0.5;
+  let value = pointValues[instance_index];
+  var size = pointSize * points.data[instance_index].size;
+  out.color = vec3(1.0);
+  if (value >= 0.0) {
+    size *= mix(pointDataStyle.minScale, pointDataStyle.maxScale, value);
+    out.color = mix(pointDataStyle.lowColor.rgb, pointDataStyle.highColor.rgb, value);
+  }
+
+  let offset = quad[vertex_index] * size * 0.5;
   let world = p + offset;
 
   let ndc = vec2(
@@ -56,11 +165,36 @@ fn vs_main(
 
   out.clip_position = vec4<f32>(ndc, 0.0, 1.0);
   out.vert_pos = quad[vertex_index];
+  out.world = world;
   return out;
 }
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  return point_color(in);
+}
+
+// Tints the point with the background image underneath it.
+@fragment
+fn fs_background(in: VertexOutput) -> @location(0) vec4<f32> {
+  let color = point_color(in);
+
+  // Same mapping as the background image shader, including its zoom.
+  var pixel = windowPos + in.world;
+  let center = backgroundPlacement.origin + backgroundPlacement.size * 0.5;
+  pixel = center + (pixel - center) / (1.0 + backgroundPlacement.zoom * intensity);
+  let uv = (pixel - backgroundPlacement.origin) / backgroundPlacement.size;
+
+  // Points outside an image that does not cover the monitor keep their own color.
+  if (backgroundPlacement.tile == 0u && (any(uv < vec2(0.0)) || any(uv > vec2(1.0)))) {
+    return color;
+  }
+
+  let background = textureSampleLevel(backgroundTexture, backgroundSampler, uv, 0.0);
+  return vec4(color.rgb * background.rgb, color.a);
+}
+
+fn point_color(in: VertexOutput) -> vec4<f32> {
   let len = length(in.vert_pos);
   if (len > 1.0) {
     discard;
@@ -70,18 +204,10 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
 
   if (len > falloff_point) {
     let intens = min(intensity, (1.0 - (len - falloff_point)) * intensity);
-    return vec4<f32>(1.0, 1.0, 1.0, intens);
+    return vec4<f32>(in.color, intens * in.fade);
   }
 
 
-  return vec4<f32>(1.0, 1.0, 1.0, intensity);
+  return vec4<f32>(in.color, intensity * in.fade);
 }
 
-
-// Idea:
-// Compute shader calculates the new position of each point stored in another array
-// Compute shader calculates the distance between each point and their higher index stored in an array of size [(POINTS * (POINTS - 1)) / 2]
-
-// Vertex shader makes points and lines for POINTS and (POINTS * (POINTS - 1)) / 2
-
-// Fragment shader discards if distance of line is greater than MIN_DISTANCE
@@ -0,0 +1,70 @@
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) vert_pos: vec2<f32>,
+  @location(1) alpha: f32,
+};
+
+struct Spark {
+  position: vec2<f32>,
+  velocity: vec2<f32>,
+  age: f32,
+  lifetime: f32,
+}
+
+// Diameter in pixels of a new spark. Sparks shrink as they age.
+const SPARK_SIZE = 4.0;
+
+@group(0) @binding(0)
+var<storage, read> sparks: array<Spark>;
+
+@group(0) @binding(1)
+var<uniform> windowSize: vec2<f32>;
+
+@vertex
+fn vs_main(
+  @builtin(vertex_index) vertex_index: u32,
+  @builtin(instance_index) instance_index: u32,
+  ) -> VertexOutput {
+
+  // Rendered using Triangle Strip:
+  // 3  4
+  // 
+  // 1  2
+  let quad = array<vec2<f32>, 4>(
+    vec2(-1.0, -1.0),
+    vec2( 1.0, -1.0),
+    vec2(-1.0,  1.0),
+    vec2( 1.0,  1.0),
+  );
+
+  let s = sparks[instance_index];
+  let life = clamp(s.age / max(s.lifetime, 0.0001), 0.0, 1.0);
+
+  var out: VertexOutput;
+
+  // Dead sparks collapse to nothing.
+  let size = SPARK_SIZE * (1.0 - life);
+  let world = s.position + quad[vertex_index] * size * 0.5;
+
+  let ndc = vec2(
+    (world.x / windowSize.x) * 2.0 - 1.0,
+    1.0 - (world.y / windowSize.y) * 2.0
+  );
+
+  out.clip_position = vec4<f32>(ndc, 0.0, 1.0);
+  out.vert_pos = quad[vertex_index];
+  out.alpha = 1.0 - life;
+  return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+  let d = length(in.vert_pos);
+  if (d > 1.0) {
+    discard;
+  }
+
+  // Hot white core fading to orange at the edge.
+  let color = mix(vec3(1.0, 0.95, 0.8), vec3(1.0, 0.5, 0.1), d);
+  return vec4(color, in.alpha * (1.0 - d * d));
+}
@@ -0,0 +1,119 @@
+struct Point {
+  position: vec2<f32>,
+  velocity: vec2<f32>,
+  // Multiplier of the point size.
+  size: f32,
+  // How strongly forces are resisted, 1 for an average point.
+  mass: f32,
+  // Rate per second the velocity decays at.
+  drag: f32,
+  // Seconds since the point was spawned.
+  age: f32,
+}
+
+struct Grid {
+  // Width and height in pixels of a cell, at least the largest neighbour distance.
+  cellSize: f32,
+  // Number of buckets the cells are hashed into, a power of two.
+  tableSize: u32,
+}
+
+// Must match the workgroup size of scan.
+const SCAN_THREADS = 256u;
+
+@group(0) @binding(0) var<storage, read> points: array<Point>;
+
+@group(0) @binding(1) var<uniform> grid: Grid;
+
+// Points per bucket. Scatter counts them back down to zero, so they start from zero again
+// on the next build without being cleared.
+@group(0) @binding(2) var<storage, read_write> counts: array<atomic<u32>>;
+
+// Where the points of each bucket start in indices, with one extra entry holding the total.
+@group(0) @binding(3) var<storage, read_write> starts: array<u32>;
+
+// Point indices sorted by bucket.
+@group(0) @binding(4) var<storage, read_write> indices: array<u32>;
+
+var<workgroup> sums: array<u32, SCAN_THREADS>;
+
+
+fn bucket(position: vec2<f32>) -> u32 {
+  let cell = vec2<i32>(floor(position / grid.cellSize));
+  return ((bitcast<u32>(cell.x) * 73856093u) ^ (bitcast<u32>(cell.y) * 19349663u))
+    & (grid.tableSize - 1u);
+}
+
+
+@compute
+@workgroup_size(64)
+fn count(
+  @builtin(global_invocation_id) id: vec3<u32>,
+  ) {
+  let i = id.x;
+
+  // Unused slots are left out of the grid.
+  if (i >= arrayLength(&points) || points[i].size <= 0.0) {
+    return;
+  }
+
+  atomicAdd(&counts[bucket(points[i].position)], 1u);
+}
+
+
+// Exclusive prefix sum of the counts in a single workgroup. Every thread sums a run of
+// buckets, the run totals are scanned in workgroup memory and every thread then writes the
+// starts of its run.
+@compute
+@workgroup_size(256)
+fn scan(
+  @builtin(local_invocation_id) id: vec3<u32>,
+  ) {
+  let thread = id.x;
+  let run = (grid.tableSize + SCAN_THREADS - 1u) / SCAN_THREADS;
+  let begin = min(thread * run, grid.tableSize);
+  let end = min(begin + run, grid.tableSize);
+
+  var total = 0u;
+  for (var k = begin; k < end; k++) {
+    total += atomicLoad(&counts[k]);
+  }
+  sums[thread] = total;
+  workgroupBarrier();
+
+  for (var offset = 1u; offset < SCAN_THREADS; offset *= 2u) {
+    var value = 0u;
+    if (thread >= offset) {
+      value = sums[thread - offset];
+    }
+    workgroupBarrier();
+    sums[thread] += value;
+    workgroupBarrier();
+  }
+
+  var running = sums[thread] - total;
+  for (var k = begin; k < end; k++) {
+    starts[k] = running;
+    running += atomicLoad(&counts[k]);
+  }
+
+  if (thread == SCAN_THREADS - 1u) {
+    starts[grid.tableSize] = sums[thread];
+  }
+}
+
+
+@compute
+@workgroup_size(64)
+fn scatter(
+  @builtin(global_invocation_id) id: vec3<u32>,
+  ) {
+  let i = id.x;
+
+  if (i >= arrayLength(&points) || points[i].size <= 0.0) {
+    return;
+  }
+
+  let b = bucket(points[i].position);
+  indices[starts[b] + atomicSub(&counts[b], 1u) - 1u] = i;
+}
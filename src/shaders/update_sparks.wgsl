@@ -0,0 +1,39 @@
+struct Spark {
+  position: vec2<f32>,
+  velocity: vec2<f32>,
+  age: f32,
+  // Sparks at least this old are dead.
+  lifetime: f32,
+}
+
+// Fraction of its speed a spark keeps after a second.
+const DRAG = 0.1;
+
+@group(0) @binding(0) var<storage, read_write> sparks: array<Spark>;
+
+@group(0) @binding(1) var<uniform> deltaTime: f32;
+
+
+@compute
+@workgroup_size(64)
+fn main(
+  @builtin(global_invocation_id) id: vec3<u32>,
+  ) {
+  let i = id.x;
+
+  if (i >= arrayLength(&sparks)) {
+    return;
+  }
+
+  var s = sparks[i];
+
+  if (s.age >= s.lifetime) {
+    return;
+  }
+
+  s.position += s.velocity * deltaTime;
+  s.velocity *= pow(DRAG, deltaTime);
+  s.age += deltaTime;
+
+  sparks[i] = s;
+}
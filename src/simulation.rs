@@ -0,0 +1,1476 @@
+use std::{sync::mpsc, time::Duration};
+
+use bytemuck::{Pod, Zeroable};
+use log::info;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use wgpu::util::DeviceExt;
+
+use crate::adaptive::{AdaptiveCount, AdaptiveCountOptions, IntensityCount};
+use crate::config::{Config, Direction, EdgeBehavior, LayerConfig, Motion, SizeDistribution};
+use crate::formation::{Formation, FormationOptions};
+use crate::impulses::{ImpulseOptions, Impulses};
+use crate::lines::lines_buffer_size;
+use crate::spatial_grid::SpatialGrid;
+
+/// Bounds of the time scale.
+const MIN_TIME_SCALE: f32 = 0.01;
+const MAX_TIME_SCALE: f32 = 20.0;
+/// Distance in pixels from the cursor points spawned by a click are scattered across.
+const CLICK_SPREAD: f32 = 20.0;
+
+/// Points being copied back from the GPU, which the device finishes when it is polled.
+pub(crate) struct PointsReadback {
+    staging_buffers: Vec<wgpu::Buffer>,
+    receiver: mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>,
+    /// Staging buffers not mapped yet.
+    pending: usize,
+}
+
+impl PointsReadback {
+    /// The live points once every copy has finished, or `None` while some are still under
+    /// way.
+    pub fn try_take(&mut self) -> anyhow::Result<Option<Vec<Point>>> {
+        while self.pending > 0 {
+            match self.receiver.try_recv() {
+                Ok(result) => {
+                    result?;
+                    self.pending -= 1;
+                }
+                Err(mpsc::TryRecvError::Empty) => return Ok(None),
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    anyhow::bail!("The points were not copied back from the GPU")
+                }
+            }
+        }
+
+        let mut points = Vec::new();
+        for staging_buffer in std::mem::take(&mut self.staging_buffers) {
+            let layer_points: Vec<Point> =
+                bytemuck::cast_slice(&staging_buffer.get_mapped_range(..)).to_vec();
+            points.extend(layer_points.into_iter().filter(Point::is_alive));
+            staging_buffer.unmap();
+        }
+
+        Ok(Some(points))
+    }
+}
+
+/// The points and the forces moving them, stepped by compute passes on the device it was
+/// created with. It knows nothing of windows, so it runs in any wgpu context, and is drawn
+/// by a [`Renderer`](crate::renderer::Renderer).
+pub struct Simulation {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    /// Size in pixels of the area the points move in.
+    width: u32,
+    height: u32,
+
+    window_size_buffer: wgpu::Buffer,
+    delta_time_buffer: wgpu::Buffer,
+    time_buffer: wgpu::Buffer,
+    elapsed_time: f32,
+    /// Multiplier of the time the simulation advances by.
+    time_scale: f32,
+    drift_buffer: wgpu::Buffer,
+    forces_buffer: wgpu::Buffer,
+    forces: ForcesUniform,
+    cursor_buffer: wgpu::Buffer,
+    cursor: CursorUniform,
+    intensity_buffer: wgpu::Buffer,
+
+    compute_new_positions_pipeline: wgpu::ComputePipeline,
+    /// Pushes overlapping points apart, absent when collisions are off.
+    collide_pipeline: Option<wgpu::ComputePipeline>,
+    /// Layout of the bind groups querying the neighbour grids of the layers.
+    grid_bind_group_layout: wgpu::BindGroupLayout,
+    /// Stretches the positions of the points when the area they move in is resized.
+    rescale_pipeline: wgpu::ComputePipeline,
+    rescale_buffer: wgpu::Buffer,
+
+    /// Point layers, from back to front.
+    layers: Vec<Layer>,
+    /// Points spawned or removed by one click.
+    click_points: usize,
+    adaptive_count: Option<AdaptiveCount>,
+    intensity_count: Option<IntensityCount>,
+    /// Source of every random number on the CPU, seeded by `--seed`.
+    rng: StdRng,
+    impulses: Impulses,
+}
+
+impl Simulation {
+    /// Scatters the points of `config` over an area of `width` by `height` pixels, on
+    /// `device`.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+        height: u32,
+        config: &Config,
+    ) -> anyhow::Result<Self> {
+        let mut rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        };
+
+        let window_size = WindowSize {
+            size: [width as f32, height as f32],
+        };
+
+        let window_size_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Window Size Buffer"),
+            contents: bytemuck::bytes_of(&window_size),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let delta_time = DeltaTime { dt: 0.016 };
+
+        let delta_time_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Delta Time Buffer"),
+            contents: bytemuck::bytes_of(&delta_time),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let time_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Time Buffer"),
+            contents: bytemuck::bytes_of(&0f32),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let drift = [0f32; 2];
+
+        let drift_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Drift Buffer"),
+            contents: bytemuck::bytes_of(&drift),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let cursor = CursorUniform {
+            position: [0.0, 0.0],
+            inside: 0,
+            force: config.cursor_force,
+            radius: config.cursor_radius,
+            _padding: 0,
+        };
+
+        let cursor_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Cursor Buffer"),
+            contents: bytemuck::bytes_of(&cursor),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let forces = ForcesUniform::new(config, rng.random());
+
+        let forces_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Forces Buffer"),
+            contents: bytemuck::bytes_of(&forces),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let mut attractors: Vec<AttractorUniform> = config
+            .attractors
+            .iter()
+            .map(|attractor| AttractorUniform {
+                center: [attractor.x, attractor.y],
+                strength: attractor.strength,
+                radius: attractor.radius,
+                orbit: attractor.orbit,
+                period: attractor.period,
+            })
+            .collect();
+        // Storage buffers cannot be empty, so stand in an attractor without any pull.
+        if attractors.is_empty() {
+            attractors.push(AttractorUniform::zeroed());
+        }
+
+        let attractors_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Attractors Buffer"),
+            contents: bytemuck::cast_slice(&attractors),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        let intensity = 0.8f32;
+
+        let intensity_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Intensity Buffer"),
+            contents: bytemuck::bytes_of(&intensity),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let grid_bind_group_layout = SpatialGrid::create_query_bind_group_layout(device);
+
+        let (
+            compute_new_positions_pipeline,
+            collide_pipeline,
+            compute_new_positions_bind_group_layout,
+        ) = Self::create_compute_new_positions_pipeline(
+            device,
+            (config.collision_radius > 0.0 || config.spacing > 0.0)
+                .then_some(&grid_bind_group_layout),
+        );
+
+        let rescale_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Rescale Buffer"),
+            contents: bytemuck::bytes_of(&[1f32; 2]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let (rescale_pipeline, rescale_bind_group_layout) = Self::create_rescale_pipeline(device);
+
+        let formation_mask = match (&config.mask, &config.text) {
+            (Some(path), _) => Some(Formation::load_mask(path, config.mask_invert)?),
+            (None, Some(text)) => Some(Formation::text_mask(text, config.mask_invert)?),
+            (None, None) => None,
+        };
+
+        let impulses = Impulses::new(
+            device,
+            StdRng::from_rng(&mut rng),
+            ImpulseOptions {
+                vortex_strength: config.vortex_strength,
+                vortex_radius: config.vortex_radius,
+                vortex_decay: config.vortex_decay,
+                explosion_speed: config.explosion_speed,
+                explosion_threshold: config.explosion_threshold,
+                explosion_decay: config.explosion_decay,
+            },
+        );
+
+        let layer_configs = layer_configs(config);
+
+        let adaptive_count = config.target_fps.map(|target_fps| {
+            AdaptiveCount::new(AdaptiveCountOptions {
+                target_fps,
+                min_count: config.min_points.max(1),
+                max_count: config.max_points.max(config.min_points).max(1),
+            })
+        });
+
+        let front_layer_count = layer_configs
+            .last()
+            .and_then(|layer_config| layer_config.count)
+            .unwrap_or(config.points as usize);
+        let intensity_count = config.points_follow_intensity.then(|| {
+            let min_count = config.min_points.max(1);
+            IntensityCount::new(
+                min_count,
+                config.max_points.max(min_count),
+                front_layer_count,
+            )
+        });
+
+        let layers = layer_configs
+            .iter()
+            .enumerate()
+            .map(|(index, layer_config)| -> anyhow::Result<Layer> {
+                let mut count = layer_config.count.unwrap_or(config.points as usize);
+
+                // Only the front layer has room for points spawned by clicks, and for the
+                // points added to hold the target frame rate or to follow the intensity.
+                let mut capacity = 0;
+                if index == layer_configs.len() - 1 {
+                    capacity = config.click_capacity;
+                    if let Some(adaptive_count) = &adaptive_count {
+                        count = adaptive_count.clamp(count);
+                        capacity += adaptive_count.max_count() - count;
+                    }
+                    if let Some(intensity_count) = &intensity_count {
+                        count = intensity_count.clamp(count);
+                        capacity += intensity_count.max_count() - count;
+                    }
+                }
+                let points_count = count + capacity;
+                Self::check_points_count(device, points_count)?;
+
+                let spawn_options = SpawnOptions {
+                    size_scale: layer_config.size,
+                    speed: layer_config.speed,
+                    ..SpawnOptions::new(config)
+                };
+                let mut points = Self::create_points(count, window_size, spawn_options, &mut rng);
+                points.resize(points_count, Point::zeroed());
+
+                let points_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(&format!("Points Buffer {index}")),
+                    contents: bytemuck::cast_slice(&points),
+                    usage: wgpu::BufferUsages::STORAGE
+                        | wgpu::BufferUsages::COPY_DST
+                        | wgpu::BufferUsages::COPY_SRC,
+                });
+
+                let previous_points_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some(&format!("Previous Points Buffer {index}")),
+                    size: points_buffer.size(),
+                    usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                });
+
+                let compute_new_positions_bind_group =
+                    Self::create_compute_new_positions_bind_group(
+                        device,
+                        &compute_new_positions_bind_group_layout,
+                        &points_buffer,
+                        &window_size_buffer,
+                        &delta_time_buffer,
+                        &drift_buffer,
+                        &cursor_buffer,
+                        &forces_buffer,
+                        &time_buffer,
+                        &intensity_buffer,
+                        &attractors_buffer,
+                        impulses.buffer(),
+                        &previous_points_buffer,
+                    );
+
+                let rescale_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Rescale Bind Group"),
+                    layout: &rescale_bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: points_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: rescale_buffer.as_entire_binding(),
+                        },
+                    ],
+                });
+
+                let formation = formation_mask
+                    .as_ref()
+                    .map(|mask| {
+                        Formation::new(
+                            device,
+                            &points_buffer,
+                            points_count,
+                            &window_size_buffer,
+                            &delta_time_buffer,
+                            &intensity_buffer,
+                            mask,
+                            &mut rng,
+                            FormationOptions {
+                                strength: config.mask_strength,
+                            },
+                        )
+                    })
+                    .transpose()?;
+
+                // Neighbour queries look one cell around a point, so the cells span the
+                // furthest distance anything is looked for at. The renderer finds the lines
+                // between the points in the grid too.
+                let connection_distance =
+                    layer_config.distance.unwrap_or(config.connection_distance);
+                let grid = (connection_distance > 0.0 || collide_pipeline.is_some()).then(|| {
+                    SpatialGrid::new(
+                        device,
+                        &grid_bind_group_layout,
+                        &points_buffer,
+                        points_count,
+                        connection_distance
+                            .max(config.collision_radius)
+                            .max(config.spacing),
+                    )
+                });
+
+                Ok(Layer {
+                    points_buffer,
+                    previous_points_buffer,
+                    points_count,
+                    free_slots: (count..points_count).rev().collect(),
+                    spawn_options,
+                    compute_new_positions_bind_group,
+                    rescale_bind_group,
+                    formation,
+                    grid,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Self {
+            device: device.clone(),
+            queue: queue.clone(),
+            width,
+            height,
+            window_size_buffer,
+            delta_time_buffer,
+            time_buffer,
+            elapsed_time: 0.0,
+            time_scale: config.time_scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE),
+            drift_buffer,
+            forces_buffer,
+            forces,
+            cursor_buffer,
+            cursor,
+            intensity_buffer,
+            compute_new_positions_pipeline,
+            collide_pipeline,
+            grid_bind_group_layout,
+            rescale_pipeline,
+            rescale_buffer,
+            layers,
+            click_points: config.click_points,
+            adaptive_count,
+            intensity_count,
+            rng,
+            impulses,
+        })
+    }
+
+    /// Size in pixels of the area the points move in.
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Seconds the simulation has advanced by, following the time scale.
+    pub fn elapsed_time(&self) -> f32 {
+        self.elapsed_time
+    }
+
+    /// Stretches the field to `width` by `height` pixels instead of starting over.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        let scale = [
+            width as f32 / self.width.max(1) as f32,
+            height as f32 / self.height.max(1) as f32,
+        ];
+        self.width = width;
+        self.height = height;
+
+        let window_size = WindowSize {
+            size: [width as f32, height as f32],
+        };
+        self.queue.write_buffer(
+            &self.window_size_buffer,
+            0,
+            bytemuck::bytes_of(&window_size),
+        );
+
+        self.queue
+            .write_buffer(&self.rescale_buffer, 0, bytemuck::bytes_of(&scale));
+
+        // The points stay on the GPU, as reading them back would stall every resize and
+        // cannot block at all on the web.
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Rescale Encoder"),
+            });
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Rescale Pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&self.rescale_pipeline);
+            for layer in &self.layers {
+                compute_pass.set_bind_group(0, &layer.rescale_bind_group, &[]);
+                compute_pass.dispatch_workgroups((layer.points_count as u32).div_ceil(64), 1, 1);
+            }
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Advances the simulation by `delta_time` of real time at `intensity`, from 0 to 1.
+    /// The moves themselves are made by the passes `encode` records.
+    pub fn update(&mut self, delta_time: Duration, intensity: f32) {
+        if let Some(adaptive_count) = &mut self.adaptive_count
+            && let Some(layer) = self.layers.last_mut()
+            && let Some(count) = adaptive_count.tick(delta_time, layer.alive_count())
+        {
+            let window_size = WindowSize {
+                size: [self.width as f32, self.height as f32],
+            };
+            layer.set_alive_count(&self.queue, count, window_size, &mut self.rng);
+        }
+
+        let delta_time = delta_time.as_secs_f32();
+        // Only the animation follows the time scale, the audio analysis keeps real time.
+        let simulation_delta_time = delta_time * self.time_scale;
+        self.queue.write_buffer(
+            &self.delta_time_buffer,
+            0,
+            bytemuck::bytes_of(&simulation_delta_time),
+        );
+
+        self.elapsed_time += simulation_delta_time;
+        self.queue
+            .write_buffer(&self.time_buffer, 0, bytemuck::bytes_of(&self.elapsed_time));
+
+        if let Some(intensity_count) = &mut self.intensity_count
+            && let Some(layer) = self.layers.last_mut()
+            && let Some(count) = intensity_count.tick(delta_time, intensity, layer.alive_count())
+        {
+            let window_size = WindowSize {
+                size: [self.width as f32, self.height as f32],
+            };
+            layer.set_alive_count(&self.queue, count, window_size, &mut self.rng);
+        }
+
+        self.queue
+            .write_buffer(&self.intensity_buffer, 0, bytemuck::bytes_of(&intensity));
+
+        let window_size = [self.width as f32, self.height as f32];
+        self.impulses
+            .update(&self.queue, delta_time, intensity, window_size);
+    }
+
+    /// Records the passes moving the points by the time of the last `update` into `encoder`.
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder) {
+        // Only the neighbour passes read the previous points, so the copy is skipped without
+        // them.
+        if self.collide_pipeline.is_some() {
+            for layer in &self.layers {
+                encoder.copy_buffer_to_buffer(
+                    &layer.points_buffer,
+                    0,
+                    &layer.previous_points_buffer,
+                    0,
+                    layer.points_buffer.size(),
+                );
+            }
+        }
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Compute New Positions Pass"),
+            timestamp_writes: None,
+        });
+
+        for layer in &self.layers {
+            if let Some(formation) = &layer.formation {
+                formation.steer(&mut compute_pass);
+            }
+
+            compute_pass.set_bind_group(0, &layer.compute_new_positions_bind_group, &[]);
+            let num_dispatches = (layer.points_count as u32).div_ceil(64);
+
+            // Collisions use the grid built from the end of the previous frame, which
+            // matches the previous points they read.
+            if let Some(collide_pipeline) = &self.collide_pipeline
+                && let Some(grid) = &layer.grid
+            {
+                compute_pass.set_pipeline(collide_pipeline);
+                compute_pass.set_bind_group(1, grid.query_bind_group(), &[]);
+                compute_pass.dispatch_workgroups(num_dispatches, 1, 1);
+            }
+
+            compute_pass.set_pipeline(&self.compute_new_positions_pipeline);
+            compute_pass.dispatch_workgroups(num_dispatches, 1, 1);
+
+            if let Some(grid) = &layer.grid {
+                grid.build(&mut compute_pass);
+            }
+        }
+    }
+
+    /// Applies the options of `config` that feed uniforms, leaving the rest as it is.
+    pub(crate) fn reconfigure(&mut self, config: &Config) {
+        self.time_scale = config.time_scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+        self.cursor.force = config.cursor_force;
+        self.cursor.radius = config.cursor_radius;
+        self.queue
+            .write_buffer(&self.cursor_buffer, 0, bytemuck::bytes_of(&self.cursor));
+        self.click_points = config.click_points;
+
+        self.forces = ForcesUniform::new(config, self.forces.seed);
+        self.queue
+            .write_buffer(&self.forces_buffer, 0, bytemuck::bytes_of(&self.forces));
+        for (index, layer_config) in layer_configs(config).into_iter().enumerate() {
+            let connection_distance = layer_config.distance.unwrap_or(config.connection_distance);
+            self.set_connection_distance(index, connection_distance);
+        }
+    }
+
+    /// Multiplies the time scale by `factor`, returning the new one.
+    pub fn scale_time(&mut self, factor: f32) -> f32 {
+        self.time_scale = (self.time_scale * factor).clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+        self.time_scale
+    }
+
+    /// Multiplies the number of points in the front layer by `factor`, adding or removing at
+    /// least one, as far as the layer has room for them. Returns the new number and whether
+    /// the layer is full.
+    pub fn scale_points(&mut self, factor: f32) -> Option<(usize, bool)> {
+        let layer = self.layers.last_mut()?;
+
+        let alive = layer.alive_count();
+        let mut count = (alive as f32 * factor).round() as usize;
+        if count == alive {
+            count = if factor > 1.0 {
+                alive + 1
+            } else {
+                alive.saturating_sub(1)
+            };
+        }
+        let count = count.clamp(1, layer.points_count);
+        let window_size = WindowSize {
+            size: [self.width as f32, self.height as f32],
+        };
+        layer.set_alive_count(&self.queue, count, window_size, &mut self.rng);
+
+        Some((count, count == layer.points_count))
+    }
+
+    /// Makes the neighbour grid of the layer at `index` reach as far as lines of
+    /// `connection_distance`, and as collisions look.
+    pub(crate) fn set_connection_distance(&mut self, index: usize, connection_distance: f32) {
+        let reach = self.forces.collision_radius.max(self.forces.spacing);
+        if let Some(grid) = self
+            .layers
+            .get_mut(index)
+            .and_then(|layer| layer.grid.as_mut())
+        {
+            grid.set_cell_size(&self.queue, connection_distance.max(reach));
+        }
+    }
+
+    /// Moves the point the cursor force pulls toward or pushes away from, in pixels.
+    /// `None` when the cursor has left the area, which turns the force off.
+    pub fn set_cursor(&mut self, position: Option<[f32; 2]>) {
+        self.cursor.inside = position.is_some() as u32;
+        if let Some(position) = position {
+            self.cursor.position = position;
+        }
+        self.queue
+            .write_buffer(&self.cursor_buffer, 0, bytemuck::bytes_of(&self.cursor));
+    }
+
+    /// Spawns points around the cursor in the front layer, as long as it has room for them.
+    pub fn spawn_points_at_cursor(&mut self) {
+        let Some(layer) = self.layers.last_mut() else {
+            return;
+        };
+        if self.cursor.inside == 0 {
+            return;
+        }
+
+        let rng = &mut self.rng;
+        for _ in 0..self.click_points {
+            let angle = rng.random_range(0.0..std::f32::consts::TAU);
+            let offset = rng.random_range(0.0..CLICK_SPREAD);
+            let position = [
+                self.cursor.position[0] + angle.cos() * offset,
+                self.cursor.position[1] + angle.sin() * offset,
+            ];
+            let point = Self::create_point(position, layer.spawn_options, rng);
+            if !layer.spawn(&self.queue, point) {
+                info!("No room for more points");
+                break;
+            }
+        }
+    }
+
+    /// Removes the points of the front layer nearest to the cursor.
+    pub fn remove_points_at_cursor(&mut self) {
+        let Some(layer) = self.layers.last() else {
+            return;
+        };
+        if self.cursor.inside == 0 {
+            return;
+        }
+
+        let points = match self.read_buffer_points(&layer.points_buffer, layer.points_count) {
+            Ok(points) => points,
+            Err(e) => {
+                log::error!("Unable to read points to remove: {e}");
+                return;
+            }
+        };
+
+        let cursor = self.cursor.position;
+        let mut nearest: Vec<(usize, f32)> = points
+            .iter()
+            .enumerate()
+            .filter(|(_, point)| point.is_alive())
+            .map(|(slot, point)| {
+                let dx = point.position[0] - cursor[0];
+                let dy = point.position[1] - cursor[1];
+                (slot, dx * dx + dy * dy)
+            })
+            .collect();
+        nearest.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        let Some(layer) = self.layers.last_mut() else {
+            return;
+        };
+        for &(slot, _) in nearest.iter().take(self.click_points) {
+            layer.remove(&self.queue, slot);
+        }
+    }
+
+    /// Shifts every point by `drift` pixels, which keeps OLED pixels from burning in.
+    pub(crate) fn set_drift(&self, drift: [f32; 2]) {
+        self.queue
+            .write_buffer(&self.drift_buffer, 0, bytemuck::bytes_of(&drift));
+    }
+
+    pub(crate) fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    pub(crate) fn grid_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.grid_bind_group_layout
+    }
+
+    pub(crate) fn window_size_buffer(&self) -> &wgpu::Buffer {
+        &self.window_size_buffer
+    }
+
+    pub(crate) fn delta_time_buffer(&self) -> &wgpu::Buffer {
+        &self.delta_time_buffer
+    }
+
+    pub(crate) fn time_buffer(&self) -> &wgpu::Buffer {
+        &self.time_buffer
+    }
+
+    pub(crate) fn cursor_buffer(&self) -> &wgpu::Buffer {
+        &self.cursor_buffer
+    }
+
+    pub(crate) fn intensity_buffer(&self) -> &wgpu::Buffer {
+        &self.intensity_buffer
+    }
+
+    /// Copies the live points of every layer back from the GPU, blocking until the copies
+    /// have finished.
+    #[cfg(feature = "ambient-audio")]
+    pub(crate) fn read_points(&self) -> anyhow::Result<Vec<Point>> {
+        let mut readback = self.start_reading_points();
+        self.device.poll(wgpu::PollType::wait_indefinitely())?;
+
+        readback
+            .try_take()?
+            .ok_or_else(|| anyhow::anyhow!("The points were not copied back from the GPU"))
+    }
+
+    /// Starts copying the live points of every layer back from the GPU, without waiting for
+    /// the copies to finish.
+    pub(crate) fn start_reading_points(&self) -> PointsReadback {
+        let (sender, receiver) = mpsc::channel();
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Points Readback Encoder"),
+            });
+
+        let staging_buffers: Vec<_> = self
+            .layers
+            .iter()
+            .map(|layer| {
+                let size = (layer.points_count * size_of::<Point>()) as wgpu::BufferAddress;
+                let staging_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Points Readback Buffer"),
+                    size,
+                    usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                });
+                encoder.copy_buffer_to_buffer(&layer.points_buffer, 0, &staging_buffer, 0, size);
+                staging_buffer
+            })
+            .collect();
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        for staging_buffer in &staging_buffers {
+            let sender = sender.clone();
+            staging_buffer.map_async(wgpu::MapMode::Read, .., move |result| {
+                let _ = sender.send(result);
+            });
+        }
+
+        PointsReadback {
+            pending: staging_buffers.len(),
+            staging_buffers,
+            receiver,
+        }
+    }
+
+    /// Copies the first `points_count` points of a points buffer back from the GPU, blocking
+    /// until the copy has finished.
+    fn read_buffer_points(
+        &self,
+        points_buffer: &wgpu::Buffer,
+        points_count: usize,
+    ) -> anyhow::Result<Vec<Point>> {
+        let size = (points_count * size_of::<Point>()) as wgpu::BufferAddress;
+
+        let staging_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Points Readback Buffer"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Points Readback Encoder"),
+            });
+        encoder.copy_buffer_to_buffer(points_buffer, 0, &staging_buffer, 0, size);
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        staging_buffer.map_async(wgpu::MapMode::Read, .., move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::PollType::wait_indefinitely())?;
+        receiver.recv()??;
+
+        let points = bytemuck::cast_slice(&staging_buffer.get_mapped_range(..)).to_vec();
+        staging_buffer.unmap();
+
+        Ok(points)
+    }
+
+    /// Creates the pipeline moving the points and, when given the layout of the spatial grid
+    /// it queries, the one pushing overlapping points apart. Both share the returned bind
+    /// group layout.
+    fn create_compute_new_positions_pipeline(
+        device: &wgpu::Device,
+        collide_grid_layout: Option<&wgpu::BindGroupLayout>,
+    ) -> (
+        wgpu::ComputePipeline,
+        Option<wgpu::ComputePipeline>,
+        wgpu::BindGroupLayout,
+    ) {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Compute New Positions Bind Group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 7,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 8,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 9,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 10,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Compute New Positions Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                include_str!("shaders/compute_new_positions.wgsl").into(),
+            ),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Compute New Positions Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Compute New Positions Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let collide_pipeline = collide_grid_layout.map(|grid_layout| {
+            let collide_pipeline_layout =
+                device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("Collide Points Pipeline Layout"),
+                    bind_group_layouts: &[&bind_group_layout, grid_layout],
+                    push_constant_ranges: &[],
+                });
+
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Collide Points Pipeline"),
+                layout: Some(&collide_pipeline_layout),
+                module: &shader,
+                entry_point: Some("collide"),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        });
+
+        (compute_pipeline, collide_pipeline, bind_group_layout)
+    }
+
+    /// Creates the pipeline stretching the positions of the points, and the layout of its
+    /// bind groups.
+    fn create_rescale_pipeline(
+        device: &wgpu::Device,
+    ) -> (wgpu::ComputePipeline, wgpu::BindGroupLayout) {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Rescale Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Rescale Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/rescale_points.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Rescale Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Rescale Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        (pipeline, bind_group_layout)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn create_compute_new_positions_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        points_buffer: &wgpu::Buffer,
+        window_size_buffer: &wgpu::Buffer,
+        delta_time_buffer: &wgpu::Buffer,
+        drift_buffer: &wgpu::Buffer,
+        cursor_buffer: &wgpu::Buffer,
+        forces_buffer: &wgpu::Buffer,
+        time_buffer: &wgpu::Buffer,
+        intensity_buffer: &wgpu::Buffer,
+        attractors_buffer: &wgpu::Buffer,
+        impulses_buffer: &wgpu::Buffer,
+        previous_points_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Compute New Positions Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: points_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: window_size_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: delta_time_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: drift_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: cursor_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: forces_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: time_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 7,
+                    resource: intensity_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 8,
+                    resource: attractors_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 9,
+                    resource: impulses_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 10,
+                    resource: previous_points_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+
+    /// Fails when the buffers for `points_count` points would exceed the limits of the device.
+    fn check_points_count(device: &wgpu::Device, points_count: usize) -> anyhow::Result<()> {
+        let limits = device.limits();
+        let max_size = (limits.max_storage_buffer_binding_size as wgpu::BufferAddress)
+            .min(limits.max_buffer_size);
+        let size = ((points_count * size_of::<Point>()) as wgpu::BufferAddress)
+            .max(lines_buffer_size(points_count));
+
+        if size > max_size {
+            return Err(anyhow::anyhow!(
+                "{points_count} points need a {size} byte buffer, but the GPU supports at most \
+                 {max_size} bytes"
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn create_points(
+        points_count: usize,
+        window_size: WindowSize,
+        spawn_options: SpawnOptions,
+        rng: &mut impl Rng,
+    ) -> Vec<Point> {
+        let width = window_size.size[0] as u32;
+        let height = window_size.size[1] as u32;
+
+        let mut points = Vec::<Point>::with_capacity(points_count);
+        for _ in 0..points_count {
+            let x = rng.random_range(0..width) as f32;
+            let y = rng.random_range(0..height) as f32;
+            points.push(Self::create_point([x, y], spawn_options, rng));
+        }
+
+        points
+    }
+
+    /// Creates a point at `position` moving in a random direction.
+    fn create_point(position: [f32; 2], spawn_options: SpawnOptions, rng: &mut impl Rng) -> Point {
+        let (vx, vy) = match spawn_options.direction {
+            Direction::Diagonal => (
+                spawn_options.random_speed(rng) * random_sign(rng),
+                spawn_options.random_speed(rng) * random_sign(rng),
+            ),
+            Direction::Any => {
+                let angle = rng.random_range(0.0..std::f32::consts::TAU);
+                let speed = spawn_options.random_speed(rng);
+                (angle.cos() * speed, angle.sin() * speed)
+            }
+            Direction::Horizontal => (spawn_options.random_speed(rng) * random_sign(rng), 0.0),
+            Direction::Vertical => (0.0, spawn_options.random_speed(rng) * random_sign(rng)),
+        };
+        Point {
+            position,
+            velocity: [vx * spawn_options.speed, vy * spawn_options.speed],
+            size: spawn_options.random_size(rng),
+            mass: random_around_one(
+                spawn_options.mass_distribution,
+                spawn_options.mass_variation,
+                rng,
+            ),
+            drag: spawn_options.drag
+                * random_around_one(
+                    spawn_options.drag_distribution,
+                    spawn_options.drag_variation,
+                    rng,
+                ),
+            age: 0.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub(crate) struct Point {
+    pub position: [f32; 2],
+    pub velocity: [f32; 2],
+    /// Multiplier of the point size.
+    pub size: f32,
+    /// How strongly forces are resisted, 1 for an average point.
+    pub mass: f32,
+    /// Rate per second the velocity decays at.
+    pub drag: f32,
+    /// Seconds since the point was spawned.
+    pub age: f32,
+}
+
+impl Point {
+    /// Unused slots of a points buffer hold zeroed points, which are neither drawn nor
+    /// connected.
+    pub fn is_alive(&self) -> bool {
+        self.size > 0.0
+    }
+}
+
+/// How newly created points are randomized.
+#[derive(Copy, Clone)]
+struct SpawnOptions {
+    size_distribution: SizeDistribution,
+    size_variation: f32,
+    /// Multiplier of every size, set per layer.
+    size_scale: f32,
+    /// Multiplier of every velocity, set per layer.
+    speed: f32,
+    min_speed: f32,
+    max_speed: f32,
+    direction: Direction,
+    mass_distribution: SizeDistribution,
+    mass_variation: f32,
+    drag: f32,
+    drag_distribution: SizeDistribution,
+    drag_variation: f32,
+}
+
+impl SpawnOptions {
+    fn new(config: &Config) -> Self {
+        Self {
+            size_distribution: config.point_size_distribution,
+            size_variation: config.point_size_variation,
+            size_scale: 1.0,
+            speed: 1.0,
+            min_speed: config.min_speed.max(0.0),
+            max_speed: config.max_speed.max(config.min_speed).max(0.0),
+            direction: config.direction,
+            mass_distribution: config.mass_distribution,
+            mass_variation: config.mass_variation,
+            drag: config.drag.max(0.0),
+            drag_distribution: config.drag_distribution,
+            drag_variation: config.drag_variation,
+        }
+    }
+
+    /// Draws a speed in pixels per second between `min_speed` and `max_speed`.
+    fn random_speed(&self, rng: &mut impl Rng) -> f32 {
+        if self.max_speed > self.min_speed {
+            rng.random_range(self.min_speed..self.max_speed)
+        } else {
+            self.min_speed
+        }
+    }
+
+    /// Draws a point size multiplier around `size_scale`, spread by `size_variation`.
+    fn random_size(&self, rng: &mut impl Rng) -> f32 {
+        random_around_one(self.size_distribution, self.size_variation, rng) * self.size_scale
+    }
+}
+
+fn random_sign(rng: &mut impl Rng) -> f32 {
+    if rng.random_bool(0.5) { -1.0 } else { 1.0 }
+}
+
+/// Draws a multiplier around 1 from `distribution`, spread by `variation`.
+fn random_around_one(distribution: SizeDistribution, variation: f32, rng: &mut impl Rng) -> f32 {
+    if variation <= 0.0 {
+        return 1.0;
+    }
+
+    let value = match distribution {
+        SizeDistribution::Uniform => 1.0 + variation * rng.random_range(-1.0..=1.0),
+        SizeDistribution::Normal => {
+            // Box-Muller transform, clamped to the same range as the uniform distribution.
+            let u1: f32 = rng.random_range(f32::EPSILON..1.0);
+            let u2: f32 = rng.random();
+            let normal = (-2.0 * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos();
+            1.0 + (variation * 0.5 * normal).clamp(-variation, variation)
+        }
+        SizeDistribution::Skewed => 1.0 - variation + 2.0 * variation * rng.random::<f32>().powi(4),
+    };
+
+    value.max(0.1)
+}
+
+/// One independent field of points with its own buffers and point-based subsystems.
+/// Every layer uses the same pipelines with its own bind groups.
+pub(crate) struct Layer {
+    pub points_buffer: wgpu::Buffer,
+    /// Copy of the points from the start of the frame. Passes that depend on the neighbours
+    /// of a point read them from here, so they see one consistent state instead of racing
+    /// with the writes to the points buffer.
+    previous_points_buffer: wgpu::Buffer,
+    /// Slots in the points buffer, including the unused ones.
+    pub points_count: usize,
+    /// Unused slots, filled by points spawned with clicks.
+    free_slots: Vec<usize>,
+    spawn_options: SpawnOptions,
+    compute_new_positions_bind_group: wgpu::BindGroup,
+    rescale_bind_group: wgpu::BindGroup,
+    formation: Option<Formation>,
+    /// Present when lines or collisions look for neighbours.
+    pub grid: Option<SpatialGrid>,
+}
+
+/// Forces acting on every point.
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct ForcesUniform {
+    /// Constant acceleration in pixels per second squared.
+    gravity: [f32; 2],
+    /// Points closer than this push each other apart.
+    collision_radius: f32,
+    collision_strength: f32,
+    /// Points closer than this gently push each other apart to keep the field even.
+    spacing: f32,
+    spacing_strength: f32,
+    /// 0 to bounce off the window edges, 1 to wrap around and 2 to respawn.
+    edge: u32,
+    /// 0 to move in straight lines, 1 to follow the flow field.
+    motion: u32,
+    /// Size in pixels of the swirls of the flow field.
+    flow_scale: f32,
+    /// Speed in pixels per second of the flow field.
+    flow_speed: f32,
+    /// Multipliers of the velocity at zero and full intensity.
+    silent_speed: f32,
+    loud_speed: f32,
+    /// Average seconds a point lives before it respawns, 0 to live forever.
+    lifetime: f32,
+    /// Seed of the random numbers of the compute shader.
+    seed: u32,
+}
+
+impl ForcesUniform {
+    fn new(config: &Config, seed: u32) -> Self {
+        Self {
+            gravity: [config.gravity.0, config.gravity.1],
+            collision_radius: config.collision_radius,
+            collision_strength: config.collision_strength,
+            spacing: config.spacing,
+            spacing_strength: config.spacing_strength,
+            edge: match config.edge {
+                EdgeBehavior::Bounce => 0,
+                EdgeBehavior::Wrap => 1,
+                EdgeBehavior::Respawn => 2,
+            },
+            motion: match config.motion {
+                Motion::Straight => 0,
+                Motion::Flow => 1,
+            },
+            flow_scale: config.flow_scale,
+            flow_speed: config.flow_speed,
+            silent_speed: config.silent_speed,
+            loud_speed: config.loud_speed,
+            lifetime: config.point_lifetime,
+            seed,
+        }
+    }
+}
+
+/// The point layers of `config`, from back to front.
+pub(crate) fn layer_configs(config: &Config) -> Vec<LayerConfig> {
+    if config.layers.is_empty() {
+        vec![LayerConfig::default()]
+    } else {
+        config.layers.clone()
+    }
+}
+
+impl Layer {
+    fn alive_count(&self) -> usize {
+        self.points_count - self.free_slots.len()
+    }
+
+    /// Writes a point into a free slot, returning false when the layer is full.
+    fn spawn(&mut self, queue: &wgpu::Queue, point: Point) -> bool {
+        let Some(slot) = self.free_slots.pop() else {
+            return false;
+        };
+        queue.write_buffer(
+            &self.points_buffer,
+            (slot * size_of::<Point>()) as wgpu::BufferAddress,
+            bytemuck::bytes_of(&point),
+        );
+        true
+    }
+
+    /// Frees the slot of a live point.
+    fn remove(&mut self, queue: &wgpu::Queue, slot: usize) {
+        queue.write_buffer(
+            &self.points_buffer,
+            (slot * size_of::<Point>()) as wgpu::BufferAddress,
+            bytemuck::bytes_of(&Point::zeroed()),
+        );
+        self.free_slots.push(slot);
+    }
+
+    /// Spawns points at random spots or removes the last ones until `count` are alive.
+    fn set_alive_count(
+        &mut self,
+        queue: &wgpu::Queue,
+        count: usize,
+        window_size: WindowSize,
+        rng: &mut impl Rng,
+    ) {
+        let alive = self.alive_count();
+
+        if count > alive {
+            let points =
+                Simulation::create_points(count - alive, window_size, self.spawn_options, rng);
+            for point in points {
+                if !self.spawn(queue, point) {
+                    break;
+                }
+            }
+        } else {
+            let mut free = vec![false; self.points_count];
+            for &slot in &self.free_slots {
+                free[slot] = true;
+            }
+            let doomed: Vec<usize> = (0..self.points_count)
+                .rev()
+                .filter(|&slot| !free[slot])
+                .take(alive - count)
+                .collect();
+            for slot in doomed {
+                self.remove(queue, slot);
+            }
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct AttractorUniform {
+    /// Center from 0 to 1 across the window.
+    center: [f32; 2],
+    /// Acceleration toward the attractor in pixels per second squared, negative to repel.
+    strength: f32,
+    radius: f32,
+    /// Radius in pixels of the circle the attractor moves along around its center.
+    orbit: f32,
+    /// Seconds per orbit.
+    period: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct CursorUniform {
+    position: [f32; 2],
+    /// 1 while the cursor is inside the window.
+    inside: u32,
+    /// Acceleration toward the cursor in pixels per second squared, negative to repel.
+    force: f32,
+    radius: f32,
+    _padding: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct WindowSize {
+    size: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct DeltaTime {
+    dt: f32,
+}
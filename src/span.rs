@@ -0,0 +1,182 @@
+use std::sync::Arc;
+
+use anyhow::{Result, bail};
+use winit::{dpi::PhysicalPosition, window::Window};
+
+use crate::canvas::span_origins;
+
+/// A window of a span, showing the part of the field on its monitor.
+struct Screen {
+    window: Arc<Window>,
+    surface: wgpu::Surface<'static>,
+    config: wgpu::SurfaceConfiguration,
+    origin: PhysicalPosition<i32>,
+}
+
+/// Renders one field covering the whole desktop into an offscreen texture and copies the part
+/// on each monitor into the window there, so points and lines flow across the edges between
+/// monitors.
+pub struct Span {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    screens: Vec<Screen>,
+}
+
+impl Span {
+    /// Sets up a span over `windows`, with `first_surface` already created for the first one.
+    /// The screens present the way `config` describes, which also gives the size of the whole
+    /// field.
+    pub fn new(
+        instance: &wgpu::Instance,
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        first_surface: wgpu::Surface<'static>,
+        windows: &[Arc<Window>],
+        config: &wgpu::SurfaceConfiguration,
+    ) -> Result<Self> {
+        let max_size = device.limits().max_texture_dimension_2d;
+        if config.width > max_size || config.height > max_size {
+            bail!(
+                "The desktop is {}x{}, larger than the {max_size}x{max_size} pixels the graphics \
+                 adapter can draw at once",
+                config.width,
+                config.height
+            );
+        }
+
+        let mut surfaces = vec![first_surface];
+        for window in windows.iter().skip(1) {
+            surfaces.push(instance.create_surface(window.clone())?);
+        }
+
+        let mut screens = Vec::new();
+        for ((window, surface), origin) in windows.iter().zip(surfaces).zip(span_origins(windows)) {
+            let caps = surface.get_capabilities(adapter);
+            if !caps.usages.contains(wgpu::TextureUsages::COPY_DST) {
+                bail!("The surface of {:?} cannot be copied into", window.id());
+            }
+            if !caps.formats.contains(&config.format) {
+                bail!(
+                    "The surface of {:?} does not support {:?}",
+                    window.id(),
+                    config.format
+                );
+            }
+
+            let size = window.inner_size();
+            let config = wgpu::SurfaceConfiguration {
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_DST,
+                width: size.width.max(1),
+                height: size.height.max(1),
+                ..config.clone()
+            };
+            surface.configure(device, &config);
+
+            screens.push(Screen {
+                window: window.clone(),
+                surface,
+                config,
+                origin,
+            });
+        }
+
+        let (texture, view) = Self::create_texture(device, config);
+
+        Ok(Self {
+            texture,
+            view,
+            screens,
+        })
+    }
+
+    /// The texture the whole field is rendered into.
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    /// Follows the windows to their current size and place, with `config` giving the size of
+    /// the whole field.
+    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        let windows: Vec<_> = self
+            .screens
+            .iter()
+            .map(|screen| screen.window.clone())
+            .collect();
+        for (screen, origin) in self.screens.iter_mut().zip(span_origins(&windows)) {
+            let size = screen.window.inner_size();
+            screen.origin = origin;
+            if size.width == 0 || size.height == 0 {
+                continue;
+            }
+            screen.config.width = size.width;
+            screen.config.height = size.height;
+            screen.surface.configure(device, &screen.config);
+        }
+
+        (self.texture, self.view) = Self::create_texture(device, config);
+    }
+
+    /// Copies the part of the rendered field on each monitor into its window and presents
+    /// them all.
+    pub fn present(
+        &self,
+        mut encoder: wgpu::CommandEncoder,
+        queue: &wgpu::Queue,
+    ) -> Result<(), wgpu::SurfaceError> {
+        let mut outputs = Vec::new();
+        for screen in &self.screens {
+            let output = screen.surface.get_current_texture()?;
+
+            let x = (screen.origin.x.max(0) as u32).min(self.texture.width());
+            let y = (screen.origin.y.max(0) as u32).min(self.texture.height());
+            let width = screen.config.width.min(self.texture.width() - x);
+            let height = screen.config.height.min(self.texture.height() - y);
+            encoder.copy_texture_to_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture: &self.texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x, y, z: 0 },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                output.texture.as_image_copy(),
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+
+            outputs.push(output);
+        }
+
+        queue.submit(std::iter::once(encoder.finish()));
+        for output in outputs {
+            output.present();
+        }
+
+        Ok(())
+    }
+
+    fn create_texture(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+    ) -> (wgpu::Texture, wgpu::TextureView) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Span Texture"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        (texture, view)
+    }
+}
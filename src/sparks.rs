@@ -0,0 +1,300 @@
+use bytemuck::{Pod, Zeroable};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use wgpu::util::DeviceExt;
+
+use crate::beat::{BEAT_COOLDOWN, BEAT_THRESHOLD, BeatDetector};
+
+/// Sparks emitted by the strongest possible hit.
+const SPARKS_PER_BURST: f32 = 48.0;
+/// Speed range in pixels per second of new sparks.
+const SPARK_SPEED: (f32, f32) = (80.0, 400.0);
+
+pub struct SparkOptions {
+    /// Most sparks alive at once, at least 1. New sparks replace the oldest ones.
+    pub pool_size: usize,
+    /// Average seconds a spark lives.
+    pub lifetime: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct Spark {
+    position: [f32; 2],
+    velocity: [f32; 2],
+    age: f32,
+    /// Sparks at least this old are dead. Zero for unused slots.
+    lifetime: f32,
+}
+
+/// Short-lived sparks bursting from random spots on hits in the music, drawn over the points.
+///
+/// Sparks live in a ring buffer of fixed size: bursts are written over the oldest slots from
+/// the CPU, and a compute pass moves and ages them.
+pub struct Sparks {
+    options: SparkOptions,
+    sparks_buffer: wgpu::Buffer,
+    compute_pipeline: wgpu::ComputePipeline,
+    compute_bind_group: wgpu::BindGroup,
+    render_pipeline: wgpu::RenderPipeline,
+    render_bind_group: wgpu::BindGroup,
+    beat_detector: BeatDetector,
+    rng: StdRng,
+    since_burst: f32,
+    /// Slot the next spark is written to.
+    next: usize,
+}
+
+impl Sparks {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        window_size_buffer: &wgpu::Buffer,
+        delta_time_buffer: &wgpu::Buffer,
+        rng: StdRng,
+        options: SparkOptions,
+    ) -> Self {
+        let sparks_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Sparks Buffer"),
+            contents: bytemuck::cast_slice(&vec![Spark::zeroed(); options.pool_size]),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let compute_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Sparks Compute Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let compute_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Sparks Compute Bind Group"),
+            layout: &compute_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: sparks_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: delta_time_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let compute_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Update Sparks Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/update_sparks.wgsl").into()),
+        });
+
+        let compute_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Sparks Compute Pipeline Layout"),
+                bind_group_layouts: &[&compute_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Sparks Compute Pipeline"),
+            layout: Some(&compute_pipeline_layout),
+            module: &compute_shader,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let render_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Sparks Render Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let render_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Sparks Render Bind Group"),
+            layout: &render_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: sparks_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: window_size_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let render_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Sparks Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/sparks.wgsl").into()),
+        });
+
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Sparks Render Pipeline Layout"),
+                bind_group_layouts: &[&render_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        // Overlapping sparks add up to a brighter glow.
+        let additive = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::SrcAlpha,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::OVER,
+        };
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Sparks Render Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &render_shader,
+                entry_point: Some("vs_main"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &render_shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(additive),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            options,
+            sparks_buffer,
+            compute_pipeline,
+            compute_bind_group,
+            render_pipeline,
+            render_bind_group,
+            beat_detector: BeatDetector::default(),
+            rng,
+            since_burst: BEAT_COOLDOWN,
+            next: 0,
+        }
+    }
+
+    /// Emits a burst when the intensity hits hard enough.
+    pub fn update(
+        &mut self,
+        queue: &wgpu::Queue,
+        delta_time: f32,
+        intensity: f32,
+        window_size: [f32; 2],
+    ) {
+        let hit = self.beat_detector.update(intensity, delta_time);
+        self.since_burst += delta_time;
+
+        if hit < BEAT_THRESHOLD || self.since_burst < BEAT_COOLDOWN {
+            return;
+        }
+        self.since_burst = 0.0;
+
+        let rng = &mut self.rng;
+        // Scaled rather than drawn from a range, which would be empty for a minimized window.
+        let origin = [
+            rng.random::<f32>() * window_size[0],
+            rng.random::<f32>() * window_size[1],
+        ];
+        let count = ((SPARKS_PER_BURST * hit) as usize).min(self.options.pool_size);
+
+        let sparks: Vec<Spark> = (0..count)
+            .map(|_| {
+                let angle = rng.random_range(0.0..std::f32::consts::TAU);
+                let speed = rng.random_range(SPARK_SPEED.0..SPARK_SPEED.1) * (0.5 + hit);
+                Spark {
+                    position: origin,
+                    velocity: [angle.cos() * speed, angle.sin() * speed],
+                    age: 0.0,
+                    lifetime: self.options.lifetime * rng.random_range(0.5..1.5),
+                }
+            })
+            .collect();
+
+        // Write over the oldest slots, wrapping around the end of the pool.
+        let (tail, wrapped) = sparks.split_at(count.min(self.options.pool_size - self.next));
+        queue.write_buffer(
+            &self.sparks_buffer,
+            (self.next * size_of::<Spark>()) as wgpu::BufferAddress,
+            bytemuck::cast_slice(tail),
+        );
+        if !wrapped.is_empty() {
+            queue.write_buffer(&self.sparks_buffer, 0, bytemuck::cast_slice(wrapped));
+        }
+        self.next = (self.next + count) % self.options.pool_size;
+    }
+
+    /// Moves and ages the sparks.
+    pub fn simulate(&self, compute_pass: &mut wgpu::ComputePass) {
+        compute_pass.set_pipeline(&self.compute_pipeline);
+        compute_pass.set_bind_group(0, &self.compute_bind_group, &[]);
+        compute_pass.dispatch_workgroups((self.options.pool_size as u32).div_ceil(64), 1, 1);
+    }
+
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.render_bind_group, &[]);
+        render_pass.draw(0..4, 0..self.options.pool_size as u32);
+    }
+}
@@ -0,0 +1,223 @@
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct GridUniform {
+    cell_size: f32,
+    table_size: u32,
+}
+
+/// A spatial hash of the points, so neighbour queries only look at the points in the 3x3
+/// cells around a point instead of at every point.
+///
+/// The cells are hashed into a table with a bucket per point. Building the grid counts the
+/// points per bucket, turns the counts into start offsets with a prefix sum and scatters the
+/// point indices into a list sorted by bucket. Consumers bind the query bind group and walk
+/// the buckets of the neighbouring cells.
+pub struct SpatialGrid {
+    count_pipeline: wgpu::ComputePipeline,
+    scan_pipeline: wgpu::ComputePipeline,
+    scatter_pipeline: wgpu::ComputePipeline,
+    build_bind_group: wgpu::BindGroup,
+    query_bind_group: wgpu::BindGroup,
+    grid_buffer: wgpu::Buffer,
+    grid: GridUniform,
+    points_count: usize,
+}
+
+impl SpatialGrid {
+    /// Layout of the bind group neighbour queries read the grid through. Create it once and
+    /// share it between the grids and the pipelines querying them.
+    pub fn create_query_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        let entry = |binding, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Spatial Grid Query Bind Group Layout"),
+            entries: &[
+                entry(0, wgpu::BufferBindingType::Uniform),
+                entry(1, wgpu::BufferBindingType::Storage { read_only: true }),
+                entry(2, wgpu::BufferBindingType::Storage { read_only: true }),
+            ],
+        })
+    }
+
+    /// `cell_size` must be at least the largest distance neighbours are looked for at.
+    pub fn new(
+        device: &wgpu::Device,
+        query_bind_group_layout: &wgpu::BindGroupLayout,
+        points_buffer: &wgpu::Buffer,
+        points_count: usize,
+        cell_size: f32,
+    ) -> Self {
+        let table_size = points_count.next_power_of_two().max(64);
+
+        let grid = GridUniform {
+            cell_size: cell_size.max(1.0),
+            table_size: table_size as u32,
+        };
+
+        let grid_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Spatial Grid Buffer"),
+            contents: bytemuck::bytes_of(&grid),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let counts_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Spatial Grid Counts Buffer"),
+            contents: bytemuck::cast_slice(&vec![0u32; table_size]),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        let starts_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Spatial Grid Starts Buffer"),
+            contents: bytemuck::cast_slice(&vec![0u32; table_size + 1]),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        let indices_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Spatial Grid Indices Buffer"),
+            size: (points_count * size_of::<u32>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let entry = |binding, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let build_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Spatial Grid Build Bind Group Layout"),
+                entries: &[
+                    entry(0, wgpu::BufferBindingType::Storage { read_only: true }),
+                    entry(1, wgpu::BufferBindingType::Uniform),
+                    entry(2, wgpu::BufferBindingType::Storage { read_only: false }),
+                    entry(3, wgpu::BufferBindingType::Storage { read_only: false }),
+                    entry(4, wgpu::BufferBindingType::Storage { read_only: false }),
+                ],
+            });
+
+        let build_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Spatial Grid Build Bind Group"),
+            layout: &build_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: points_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: grid_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: counts_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: starts_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: indices_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let query_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Spatial Grid Query Bind Group"),
+            layout: query_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: grid_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: starts_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: indices_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Spatial Grid Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/spatial_grid.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Spatial Grid Pipeline Layout"),
+            bind_group_layouts: &[&build_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let create_pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(&format!("Spatial Grid {entry_point} Pipeline")),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+
+        Self {
+            count_pipeline: create_pipeline("count"),
+            scan_pipeline: create_pipeline("scan"),
+            scatter_pipeline: create_pipeline("scatter"),
+            build_bind_group,
+            query_bind_group,
+            grid_buffer,
+            grid,
+            points_count,
+        }
+    }
+
+    /// Changes the size of the cells, which must stay at least the largest distance
+    /// neighbours are looked for at. Takes effect when the grid is next built.
+    pub fn set_cell_size(&mut self, queue: &wgpu::Queue, cell_size: f32) {
+        self.grid.cell_size = cell_size.max(1.0);
+        queue.write_buffer(&self.grid_buffer, 0, bytemuck::bytes_of(&self.grid));
+    }
+
+    /// Sorts the points into the grid. Queries see the positions the points had when the
+    /// grid was last built.
+    pub fn build(&self, compute_pass: &mut wgpu::ComputePass) {
+        let num_dispatches = (self.points_count as u32).div_ceil(64);
+        compute_pass.set_bind_group(0, &self.build_bind_group, &[]);
+
+        compute_pass.set_pipeline(&self.count_pipeline);
+        compute_pass.dispatch_workgroups(num_dispatches, 1, 1);
+
+        compute_pass.set_pipeline(&self.scan_pipeline);
+        compute_pass.dispatch_workgroups(1, 1, 1);
+
+        compute_pass.set_pipeline(&self.scatter_pipeline);
+        compute_pass.dispatch_workgroups(num_dispatches, 1, 1);
+    }
+
+    pub fn query_bind_group(&self) -> &wgpu::BindGroup {
+        &self.query_bind_group
+    }
+}
@@ -1,87 +1,185 @@
-use bytemuck::{Pod, Zeroable};
 use log::info;
-use rand::prelude::*;
-use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use std::rc::Rc;
-use std::sync::Arc;
 use std::time::Duration;
-use std::vec;
-use wgpu::util::DeviceExt;
-use winit::window::Window;
-
-#[cfg(target_arch = "wasm32")]
-use wasm_bindgen::prelude::*;
-
-use crate::volume_providers::volume_provider::{VolumeProvider, get_volume_provider};
-
-const SAMPLE_COUNT: u32 = 4;
-
+use winit::dpi::PhysicalSize;
+
+use crate::canvas::Canvas;
+
+#[cfg(feature = "ambient-audio")]
+use crate::ambient_audio::AmbientAudio;
+use crate::config::{AntiAliasing, Config, PresentMode};
+use crate::flow_export::{self, FlowExporter};
+use crate::hooks::{HookRunner, Hooks};
+#[cfg(target_os = "linux")]
+use crate::hyprland::{self, WindowTracker};
+use crate::oled::OledMode;
+use crate::recorder::{Recorder, RecordingOptions};
+use crate::renderer::{Renderer, RendererOptions};
+use crate::simulation::{PointsReadback, Simulation};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::span::Span;
+use crate::volume_providers::file_volume_provider::FileVolumeProvider;
+use crate::volume_providers::volume_provider::{
+    VolumeProvider, fallback_volume_provider, get_volume_provider,
+};
+
+/// Seconds a boost of the intensity takes to fade out.
+const INTENSITY_BOOST_FADE: f32 = 1.5;
+/// Bounds of the gain set with the keyboard on top of the automatic one.
+const MIN_INTENSITY_GAIN: f32 = 0.1;
+const MAX_INTENSITY_GAIN: f32 = 10.0;
+
+/// Runs a [`Simulation`] and a [`Renderer`] in a canvas: picks the adapter, owns the surface
+/// the frames are presented to and feeds them the intensity of the audio.
 pub struct State {
-    pub window: Arc<Window>,
+    pub canvas: Canvas,
 
-    surface: wgpu::Surface<'static>,
+    /// Absent when spanning monitors, where every window has a surface of its own.
+    surface: Option<wgpu::Surface<'static>>,
     device: wgpu::Device,
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
     is_surface_configured: bool,
-    msaa_texture_view: wgpu::TextureView,
 
-    window_size_buffer: wgpu::Buffer,
-    window_pos_buffer: wgpu::Buffer,
-    delta_time_buffer: wgpu::Buffer,
-    intensity_buffer: wgpu::Buffer,
+    simulation: Simulation,
+    renderer: Renderer,
+
     last_intensity: f32,
     intensity_multiplier: f32,
-    points_buffer: wgpu::Buffer,
+    /// Multiplies the intensity after the automatic gain control, set with the keyboard.
+    intensity_gain: f32,
+    /// Floor of the intensity raised by a boost, fading back to 0.
+    intensity_boost: f32,
 
-    compute_new_positions_pipeline: wgpu::ComputePipeline,
-    compute_new_positions_bind_group: wgpu::BindGroup,
+    volume_provider: Rc<dyn VolumeProvider>,
 
-    render_pipeline: wgpu::RenderPipeline,
-    render_bind_group: wgpu::BindGroup,
+    flow_exporter: Option<FlowExporter>,
+    /// Velocity fields waiting for their points to be copied back, with the files they go to.
+    flow_exports: Vec<(PointsReadback, PathBuf)>,
+    flow_cell_size: u32,
+    oled_mode: Option<OledMode>,
+    recorder: Option<Recorder>,
+    #[cfg(not(target_arch = "wasm32"))]
+    span: Option<Span>,
+    #[cfg(feature = "ambient-audio")]
+    ambient_audio: Option<AmbientAudio>,
+    #[cfg(target_os = "linux")]
+    window_tracker: Option<WindowTracker>,
+    /// What the sizes in the config were scaled by, unless they are in physical pixels.
+    scale_factor: Option<f64>,
+    hooks: HookRunner,
+}
 
-    background_image_state: Option<(wgpu::RenderPipeline, wgpu::BindGroup)>,
+impl State {
+    /// Starts `state` over in the same canvas with `config`, going back to `fallback` when
+    /// that fails.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn restart(
+        state: &mut Option<State>,
+        config: &Config,
+        fallback: &Config,
+    ) -> anyhow::Result<()> {
+        let Some(old_state) = state.take() else {
+            return Ok(());
+        };
+        let canvas = old_state.canvas.clone();
+        let hooks = old_state.hooks.hooks().clone();
+        // The old state has to let go of the surface before the new one can take it.
+        drop(old_state);
+
+        let result =
+            match pollster::block_on(State::with_canvas(canvas.clone(), config, hooks.clone())) {
+                Ok(new_state) => {
+                    *state = Some(new_state);
+                    Ok(())
+                }
+                Err(e) => {
+                    *state = pollster::block_on(State::with_canvas(canvas, fallback, hooks))
+                        .inspect_err(|e| log::error!("Unable to restore the previous state: {e}"))
+                        .ok();
+                    Err(e)
+                }
+            };
 
-    points_count: usize,
+        if let Some(state) = state {
+            let size = state.canvas.size();
+            state.resize(size.width, size.height);
+            state.canvas.request_redraw();
+        }
 
-    volume_provider: Rc<dyn VolumeProvider>,
-}
+        result
+    }
 
-impl State {
-    pub async fn new(
-        window: Arc<Window>,
-        background_image: Option<String>,
+    /// Sets up drawing into `canvas` with `config`, calling `hooks` from the frames, drawing
+    /// the effects among them and taking the intensity from their volume provider, if they
+    /// have one.
+    pub(crate) async fn with_canvas(
+        canvas: Canvas,
+        config: &Config,
+        hooks: Hooks,
     ) -> anyhow::Result<Self> {
-        let size = window.inner_size();
+        let recording = RecordingOptions::new(config);
+
+        // Sizes and distances are taken in logical pixels so they look the same on HiDPI
+        // displays. Recordings have no display to scale for.
+        let scale_factor =
+            (!config.physical_pixels && recording.is_none()).then(|| canvas.scale_factor());
+        let config = if let Some(scale_factor) = scale_factor
+            && scale_factor != 1.0
+        {
+            info!("Scaling sizes by {scale_factor}");
+            &config.scaled(scale_factor as f32)
+        } else {
+            config
+        };
 
-        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-            #[cfg(not(target_arch = "wasm32"))]
-            backends: wgpu::Backends::PRIMARY,
-            #[cfg(target_arch = "wasm32")]
-            backends: wgpu::Backends::GL,
-            ..Default::default()
-        });
+        // Recordings render offscreen at a fixed size, regardless of the window.
+        let size = match &recording {
+            Some(recording) => PhysicalSize::from(recording.size),
+            None => canvas.size(),
+        };
 
-        let surface = instance.create_surface(window.clone())?;
+        let instance = Self::create_instance();
 
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                force_fallback_adapter: false,
-                compatible_surface: Some(&surface),
-            })
-            .await?;
+        let surface = canvas.create_surface(&instance)?;
+
+        let adapter = match &config.gpu {
+            #[cfg(not(target_arch = "wasm32"))]
+            Some(gpu) => Self::choose_adapter(&instance, &surface, gpu)?,
+            _ => {
+                instance
+                    .request_adapter(&wgpu::RequestAdapterOptions {
+                        power_preference: wgpu::PowerPreference::default(),
+                        force_fallback_adapter: false,
+                        compatible_surface: Some(&surface),
+                    })
+                    .await?
+            }
+        };
+        let adapter_info = adapter.get_info();
+        info!(
+            "Using the {} graphics adapter through {:?}",
+            adapter_info.name, adapter_info.backend
+        );
+
+        let required_limits = match &canvas {
+            // The textures of a span cover the whole desktop, which can be larger than the
+            // defaults allow.
+            #[cfg(not(target_arch = "wasm32"))]
+            Canvas::Span(_) => adapter.limits(),
+            _ if cfg!(target_arch = "wasm32") => wgpu::Limits::downlevel_webgl2_defaults(),
+            _ => wgpu::Limits::default(),
+        };
 
         let (device, queue) = adapter
             .request_device(&wgpu::DeviceDescriptor {
                 label: None,
-                required_features: wgpu::Features::empty(),
+                // Needed for sample counts other than 1 and 4.
+                required_features: adapter.features()
+                    & wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
                 experimental_features: wgpu::ExperimentalFeatures::disabled(),
-                required_limits: if cfg!(target_arch = "wasm32") {
-                    wgpu::Limits::downlevel_webgl2_defaults()
-                } else {
-                    wgpu::Limits::default()
-                },
+                required_limits,
                 memory_hints: Default::default(),
                 trace: wgpu::Trace::Off,
             })
@@ -95,524 +193,348 @@ impl State {
             .copied()
             .unwrap_or(surface_caps.formats[0]);
 
-        let config = wgpu::SurfaceConfiguration {
+        let surface_config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: surface_format,
             width: size.width,
             height: size.height,
-            present_mode: surface_caps.present_modes[0],
+            present_mode: Self::choose_present_mode(&surface_caps, config.present_mode),
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
         };
 
-        let msaa_texture_view = Self::create_msaa_texture(&device, &config);
-
-        let window_size = WindowSize {
-            size: [size.width as f32, size.height as f32],
+        // A span renders offscreen and hands out the parts to the windows on each monitor.
+        // It is set up first, as it checks that the desktop fits into a texture.
+        #[cfg(not(target_arch = "wasm32"))]
+        let (surface, span) = match &canvas {
+            Canvas::Span(windows) => (
+                None,
+                Some(Span::new(
+                    &instance,
+                    &adapter,
+                    &device,
+                    surface,
+                    windows,
+                    &surface_config,
+                )?),
+            ),
+            _ => (Some(surface), None),
         };
+        #[cfg(target_arch = "wasm32")]
+        let surface = Some(surface);
 
-        let window_size_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Window Size Buffer"),
-            contents: bytemuck::bytes_of(&window_size),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-
-        let window_pos = window
-            .inner_position()
-            .unwrap_or(winit::dpi::PhysicalPosition { x: 0, y: 0 });
-        let window_pos = WindowSize {
-            size: [window_pos.x as f32, window_pos.y as f32],
+        let sample_count = match config.aa {
+            AntiAliasing::Msaa => Self::choose_sample_count(&adapter, surface_format, config.msaa),
+            AntiAliasing::Fxaa | AntiAliasing::Off => 1,
         };
 
-        let window_pos_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Window Position Buffer"),
-            contents: bytemuck::bytes_of(&window_pos),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-
-        let delta_time = DeltaTime { dt: 0.016 };
-
-        let delta_time_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Delta Time Buffer"),
-            contents: bytemuck::bytes_of(&delta_time),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
+        // Recordings run at a fixed frame rate, so there is nothing to adapt the number of
+        // points to.
+        let simulation_config = Config {
+            target_fps: config.target_fps.filter(|_| recording.is_none()),
+            ..config.clone()
+        };
+        let simulation =
+            Simulation::new(&device, &queue, size.width, size.height, &simulation_config)?;
+
+        // Without options to show the overlay is still there for notices, except in
+        // recordings, which take no keyboard input.
+        let mut renderer = Renderer::with_options(
+            &device,
+            &queue,
+            config,
+            &simulation,
+            RendererOptions {
+                format: surface_format,
+                width: size.width,
+                height: size.height,
+                sample_count,
+                monitor: (canvas.monitor_name(), canvas.monitor_size()),
+                window_pos: locate_window(&canvas),
+                notices: recording.is_none(),
+            },
+        )?;
 
-        let point_size = 5f32;
+        for (placement, create_effect) in &hooks.effects {
+            if let Err(e) = renderer.add_effect(create_effect(), *placement) {
+                log::error!("Unable to set up an effect: {e:#}");
+            }
+        }
 
-        let point_size_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Point Size Buffer"),
-            contents: bytemuck::bytes_of(&point_size),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
+        // Audio capture is only opened when nothing else gives the intensity.
+        let volume_provider: Rc<dyn VolumeProvider> =
+            match (&hooks.volume_provider, &config.intensity_file) {
+                (Some(volume_provider), _) => volume_provider.clone(),
+                (None, Some(path)) => Rc::new(FileVolumeProvider::new(path)?),
+                (None, None) => get_volume_provider(),
+            };
 
-        let intensity = 0.8f32;
+        let recorder = recording
+            .map(|recording| Recorder::new(&device, surface_config.format, recording))
+            .transpose()?;
 
-        let intensity_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Intensity Buffer"),
-            contents: bytemuck::bytes_of(&intensity),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        let flow_exporter = config.export_flow.clone().map(|path| {
+            FlowExporter::new(
+                path,
+                Duration::from_secs_f32(config.export_flow_interval.max(0.0)),
+            )
         });
 
-        let points_count = 1000;
-
-        let points = Self::create_points(points_count, window_size);
-
-        let points_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Points Buffer"),
-            contents: bytemuck::cast_slice(&points),
-            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        let oled_mode = config.oled.then(|| {
+            OledMode::new(
+                config.oled_max_brightness,
+                Duration::from_secs_f32(config.oled_dim_after.max(0.0)),
+                config.oled_dim_brightness,
+            )
         });
 
-        let (compute_new_positions_pipeline, compute_new_positions_bind_group) =
-            Self::create_compute_new_positions_pipeline(
-                &device,
-                &points_buffer,
-                &window_size_buffer,
-                &delta_time_buffer,
-            );
-
-        let background_image_state = if let Some(background_image) = background_image {
-            let monitor_size = window
-                .available_monitors()
-                .into_iter()
-                .find(|_| true)
-                .unwrap()
-                .size();
-            info!("Monitor size: {monitor_size:?}");
-            let background_image_rgba = image::ImageReader::open(background_image)?
-                .decode()?
-                .resize_to_fill(
-                    monitor_size.width,
-                    monitor_size.height,
-                    image::imageops::FilterType::Lanczos3,
-                )
-                .to_rgba8();
-            let dimensions = background_image_rgba.dimensions();
-            let background_image_texture_size = wgpu::Extent3d {
-                width: dimensions.0,
-                height: dimensions.1,
-                depth_or_array_layers: 1,
-            };
-
-            let background_image_texture = device.create_texture(&wgpu::TextureDescriptor {
-                label: Some("Background Image Texture"),
-                size: background_image_texture_size,
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
-                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-                view_formats: &[],
-            });
-
-            queue.write_texture(
-                wgpu::TexelCopyTextureInfo {
-                    texture: &background_image_texture,
-                    mip_level: 0,
-                    origin: wgpu::Origin3d::ZERO,
-                    aspect: wgpu::TextureAspect::All,
-                },
-                &background_image_rgba,
-                wgpu::TexelCopyBufferLayout {
-                    offset: 0,
-                    bytes_per_row: Some(4 * dimensions.0),
-                    rows_per_image: Some(dimensions.1),
-                },
-                background_image_texture_size,
-            );
-
-            let background_image_texture_view =
-                background_image_texture.create_view(&wgpu::TextureViewDescriptor::default());
-
-            let background_image_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-                address_mode_u: wgpu::AddressMode::Repeat,
-                address_mode_v: wgpu::AddressMode::Repeat,
-                address_mode_w: wgpu::AddressMode::Repeat,
-                mag_filter: wgpu::FilterMode::Linear,
-                min_filter: wgpu::FilterMode::Nearest,
-                mipmap_filter: wgpu::FilterMode::Nearest,
-                ..Default::default()
-            });
-
-            let background_image_bind_group_layout =
-                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    label: Some("Background Image Bind Group Layout"),
-                    entries: &[
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 0,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Texture {
-                                multisampled: false,
-                                view_dimension: wgpu::TextureViewDimension::D2,
-                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                            },
-                            count: None,
-                        },
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 1,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                            count: None,
-                        },
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 2,
-                            visibility: wgpu::ShaderStages::VERTEX,
-                            ty: wgpu::BindingType::Buffer {
-                                ty: wgpu::BufferBindingType::Uniform,
-                                has_dynamic_offset: false,
-                                min_binding_size: None,
-                            },
-                            count: None,
-                        },
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 3,
-                            visibility: wgpu::ShaderStages::VERTEX,
-                            ty: wgpu::BindingType::Buffer {
-                                ty: wgpu::BufferBindingType::Uniform,
-                                has_dynamic_offset: false,
-                                min_binding_size: None,
-                            },
-                            count: None,
-                        },
-                    ],
-                });
-
-            let background_image_bind_group =
-                device.create_bind_group(&wgpu::BindGroupDescriptor {
-                    label: Some("Background Image Bind Group"),
-                    layout: &background_image_bind_group_layout,
-                    entries: &[
-                        wgpu::BindGroupEntry {
-                            binding: 0,
-                            resource: wgpu::BindingResource::TextureView(
-                                &background_image_texture_view,
-                            ),
-                        },
-                        wgpu::BindGroupEntry {
-                            binding: 1,
-                            resource: wgpu::BindingResource::Sampler(&background_image_sampler),
-                        },
-                        wgpu::BindGroupEntry {
-                            binding: 2,
-                            resource: window_size_buffer.as_entire_binding(),
-                        },
-                        wgpu::BindGroupEntry {
-                            binding: 3,
-                            resource: window_pos_buffer.as_entire_binding(),
-                        },
-                    ],
-                });
-
-            let background_image_shader =
-                device.create_shader_module(wgpu::ShaderModuleDescriptor {
-                    label: Some("Background Image Shader"),
-                    source: wgpu::ShaderSource::Wgsl(
-                        include_str!("shaders/background_image_shader.wgsl").into(),
-                    ),
-                });
-
-            let background_image_render_pipeline_layout =
-                device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: Some("Background Image Render Pipeline Layout"),
-                    bind_group_layouts: &[&background_image_bind_group_layout],
-                    push_constant_ranges: &[],
-                });
-
-            let background_image_render_pipeline =
-                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                    label: Some("Background Image Render Pipeline"),
-                    layout: Some(&background_image_render_pipeline_layout),
-                    vertex: wgpu::VertexState {
-                        module: &background_image_shader,
-                        entry_point: Some("vs_main"),
-                        compilation_options: wgpu::PipelineCompilationOptions::default(),
-                        buffers: &[],
-                    },
-                    fragment: Some(wgpu::FragmentState {
-                        module: &background_image_shader,
-                        entry_point: Some("fs_main"),
-                        targets: &[Some(wgpu::ColorTargetState {
-                            format: config.format,
-                            blend: Some(wgpu::BlendState::REPLACE),
-                            write_mask: wgpu::ColorWrites::ALL,
-                        })],
-                        compilation_options: wgpu::PipelineCompilationOptions::default(),
-                    }),
-                    primitive: wgpu::PrimitiveState {
-                        topology: wgpu::PrimitiveTopology::TriangleStrip,
-                        ..Default::default()
-                    },
-                    depth_stencil: None,
-                    multisample: wgpu::MultisampleState {
-                        count: SAMPLE_COUNT,
-                        mask: !0,
-                        alpha_to_coverage_enabled: false,
-                    },
-                    multiview: None,
-                    cache: None,
-                });
-
-            Some((
-                background_image_render_pipeline,
-                background_image_bind_group,
-            ))
+        #[cfg(feature = "ambient-audio")]
+        let ambient_audio = if config.ambient_audio {
+            AmbientAudio::new(config.ambient_audio_volume)
+                .inspect_err(|e| log::error!("Unable to start ambient audio: {e}"))
+                .ok()
         } else {
             None
         };
 
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/shader.wgsl").into()),
-        });
-
-        let render_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Render Bind Group Layout"),
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::VERTEX,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Storage { read_only: true },
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::VERTEX,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 2,
-                        visibility: wgpu::ShaderStages::VERTEX,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 3,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                ],
-            });
-
-        let render_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Render Bind Group"),
-            layout: &render_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: points_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: window_size_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: point_size_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 3,
-                    resource: intensity_buffer.as_entire_binding(),
-                },
-            ],
-        });
-
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&render_bind_group_layout],
-                push_constant_ranges: &[],
-            });
-
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-                buffers: &[],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleStrip,
-                ..Default::default()
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: SAMPLE_COUNT,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-            cache: None,
-        });
-
-        let volume_provider = get_volume_provider();
+        // Layer-shell surfaces are not Hyprland clients and never move.
+        #[cfg(target_os = "linux")]
+        let window_tracker = matches!(canvas, Canvas::Window(_))
+            .then(WindowTracker::spawn)
+            .flatten();
 
         Ok(Self {
-            window,
+            canvas,
             surface,
             device,
             queue,
-            config,
-            is_surface_configured: false,
-            msaa_texture_view,
-            window_size_buffer,
-            window_pos_buffer,
-            delta_time_buffer,
-            intensity_buffer,
-            last_intensity: intensity,
+            config: surface_config,
+            // Recordings never present to the surface.
+            is_surface_configured: recorder.is_some(),
+            simulation,
+            renderer,
+            last_intensity: 0.8,
             intensity_multiplier: 1.0,
-            points_buffer,
-            compute_new_positions_pipeline,
-            compute_new_positions_bind_group,
-            render_pipeline,
-            render_bind_group,
-            background_image_state,
-            points_count,
+            intensity_gain: 1.0,
+            intensity_boost: 0.0,
             volume_provider,
+            flow_exporter,
+            flow_exports: Vec::new(),
+            flow_cell_size: config.export_flow_cell_size,
+            oled_mode,
+            recorder,
+            #[cfg(not(target_arch = "wasm32"))]
+            span,
+            #[cfg(feature = "ambient-audio")]
+            ambient_audio,
+            #[cfg(target_os = "linux")]
+            window_tracker,
+            scale_factor,
+            hooks: HookRunner::new(hooks),
         })
     }
 
+    /// Lines the background up again after the window has moved.
+    pub fn update_window_pos(&self) {
+        self.renderer.set_window_pos(locate_window(&self.canvas));
+    }
+
+    /// Loads the background image again when the canvas has ended up on another monitor or
+    /// its monitor changed resolution, so it keeps covering the monitor exactly.
+    pub fn refresh_monitor(&mut self) {
+        let monitor = (self.canvas.monitor_name(), self.canvas.monitor_size());
+        if monitor == *self.renderer.monitor() {
+            return;
+        }
+        info!("Monitor changed to {:?} at {:?}", monitor.0, monitor.1);
+        self.update_window_pos();
+        self.renderer.set_monitor(&self.simulation, monitor);
+    }
+
+    /// Whether the canvas moved to a display with another scale factor than the sizes were
+    /// scaled for, so the state has to start over to look the same there.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn needs_rescale(&self) -> bool {
+        self.scale_factor
+            .is_some_and(|scale_factor| scale_factor != self.canvas.scale_factor())
+    }
+
     pub fn resize(&mut self, width: u32, height: u32) {
+        // Recordings keep the size they were started with.
+        if self.recorder.is_some() {
+            return;
+        }
+
         if width > 0 && height > 0 {
             self.config.width = width;
             self.config.height = height;
-            self.surface.configure(&self.device, &self.config);
+            if let Some(surface) = &self.surface {
+                surface.configure(&self.device, &self.config);
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(span) = &mut self.span {
+                span.resize(&self.device, &self.config);
+            }
             self.is_surface_configured = true;
 
-            self.msaa_texture_view = Self::create_msaa_texture(&self.device, &self.config);
-
-            let window_size = WindowSize {
-                size: [width as f32, height as f32],
-            };
-            self.queue.write_buffer(
-                &self.window_size_buffer,
-                0,
-                bytemuck::bytes_of(&window_size),
-            );
-
-            let window_pos = self
-                .get_window_pos()
-                .unwrap_or(WindowSize { size: [0.0, 0.0] });
-            self.queue
-                .write_buffer(&self.window_pos_buffer, 0, bytemuck::bytes_of(&window_pos));
-
-            let points = Self::create_points(self.points_count, window_size);
-            self.queue
-                .write_buffer(&self.points_buffer, 0, bytemuck::cast_slice(&points));
+            self.simulation.resize(width, height);
+            self.renderer.resize(width, height);
+            self.update_window_pos();
+            self.hooks.resize(width, height);
         }
     }
 
     pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        self.window.request_redraw();
+        self.canvas.request_redraw();
 
         if !self.is_surface_configured {
             return Ok(());
         }
 
-        let output = self.surface.get_current_texture()?;
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(span) = &self.span {
+            let encoder = self.encode_frame(span.view());
+            return span.present(encoder, &self.queue);
+        }
+
+        let Some(surface) = &self.surface else {
+            return Ok(());
+        };
+        let output = surface.get_current_texture()?;
 
         let view = output
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
 
+        let encoder = self.encode_frame(&view);
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+        output.present();
+
+        Ok(())
+    }
+
+    /// A texture the size of the surface for `draw_offscreen` to draw into.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn create_offscreen_view(&self) -> wgpu::TextureView {
+        self.device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Offscreen Texture"),
+                size: wgpu::Extent3d {
+                    width: self.config.width,
+                    height: self.config.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: self.config.format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    /// Advances the simulation by `delta_time` and draws the frame into `view` instead of
+    /// the surface, returning once the GPU has finished it. Frames drawn this way do not
+    /// wait for the display, which is what benchmarks want to measure.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn draw_offscreen(
+        &mut self,
+        delta_time: Duration,
+        view: &wgpu::TextureView,
+    ) -> anyhow::Result<()> {
+        self.update(delta_time);
+        let encoder = self.encode_frame(view);
+        self.queue.submit(std::iter::once(encoder.finish()));
+        self.device.poll(wgpu::PollType::wait_indefinitely())?;
+
+        Ok(())
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// Advances the simulation by one frame of the recording, renders it and sends it to
+    /// the encoder. Returns true once the recording is complete and has been written.
+    pub fn record_frame(&mut self) -> anyhow::Result<bool> {
+        // Taken out while rendering, so a failed recording is dropped and stops ffmpeg.
+        let Some(mut recorder) = self.recorder.take() else {
+            return Ok(true);
+        };
+
+        self.update(recorder.frame_time());
+
+        let mut encoder = self.encode_frame(recorder.view());
+        recorder.copy_frame(&mut encoder);
+        self.queue.submit(std::iter::once(encoder.finish()));
+        recorder.write_frame(&self.device)?;
+
+        if recorder.is_finished() {
+            recorder.finish()?;
+            return Ok(true);
+        }
+
+        self.recorder = Some(recorder);
+        Ok(false)
+    }
+
+    /// Records the commands drawing one frame of the simulation into `view`.
+    fn encode_frame(&self, view: &wgpu::TextureView) -> wgpu::CommandEncoder {
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
 
-        {
-            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-                label: Some("Compute New Positions Pass"),
-                timestamp_writes: None,
-            });
+        self.simulation.encode(&mut encoder);
+        self.renderer.encode(&self.simulation, &mut encoder, view);
 
-            compute_pass.set_pipeline(&self.compute_new_positions_pipeline);
-            compute_pass.set_bind_group(0, &self.compute_new_positions_bind_group, &[]);
+        encoder
+    }
 
-            let num_dispatches = (self.points_count as u32 + 63) / 64;
-            compute_pass.dispatch_workgroups(num_dispatches, 1, 1);
+    pub fn update(&mut self, delta_time: Duration) {
+        if let Some(flow_exporter) = &mut self.flow_exporter
+            && flow_exporter.tick(delta_time)
+        {
+            let path = flow_exporter.path().to_owned();
+            self.flow_exports
+                .push((self.simulation.start_reading_points(), path));
         }
+        self.finish_flow_exports();
 
+        #[cfg(feature = "ambient-audio")]
+        if let Some(ambient_audio) = &mut self.ambient_audio
+            && ambient_audio.tick(delta_time)
         {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &self.msaa_texture_view,
-                    resolve_target: Some(&view),
-                    depth_slice: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.0,
-                            g: 0.0,
-                            b: 0.0,
-                            a: 1.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
-
-            if let Some((background_image_render_pipeline, background_image_bind_group)) =
-                &self.background_image_state
-            {
-                render_pass.set_pipeline(background_image_render_pipeline);
-                render_pass.set_bind_group(0, background_image_bind_group, &[]);
-                render_pass.draw(0..4, 0..1);
-            }
-
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, &self.render_bind_group, &[]);
-            render_pass.draw(0..4, 0..self.points_count as u32);
+            self.analyze_ambient_audio();
         }
 
-        self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
+        #[cfg(target_os = "linux")]
+        if let Some(at) = self
+            .window_tracker
+            .as_ref()
+            .and_then(WindowTracker::take_moved)
+        {
+            self.renderer.set_window_pos(window_pos(&self.canvas, at));
+        }
 
-        Ok(())
-    }
+        if let Some(oled_mode) = &mut self.oled_mode {
+            oled_mode.update(delta_time);
+            self.simulation.set_drift(oled_mode.drift());
+        }
 
-    pub fn update(&mut self, delta_time: Duration) {
+        let frame_time = delta_time;
         let delta_time = delta_time.as_secs_f32();
-        self.queue
-            .write_buffer(&self.delta_time_buffer, 0, bytemuck::bytes_of(&delta_time));
 
-        let mut intensity = if let Some(intensity) = self.volume_provider.poll_volume().unwrap() {
+        let volume = self.volume_provider.poll_volume().unwrap_or_else(|e| {
+            log::warn!("Lost the audio: {e}");
+            self.volume_provider = fallback_volume_provider();
+            None
+        });
+        let mut intensity = if let Some(intensity) = volume {
             intensity * self.intensity_multiplier
         } else {
             f32::max(self.last_intensity - delta_time / 20.0, 0.0)
@@ -628,202 +550,349 @@ impl State {
                 f32::min(self.intensity_multiplier + delta_time / 20.0, 100.0);
         }
 
-        self.queue
-            .write_buffer(&self.intensity_buffer, 0, bytemuck::bytes_of(&intensity));
         self.last_intensity = intensity;
+        intensity = (intensity * self.intensity_gain).min(1.0);
+
+        // A boost only shows, it does not feed into the gain control above.
+        if self.intensity_boost > 0.0 {
+            intensity = intensity.max(self.intensity_boost);
+            self.intensity_boost =
+                (self.intensity_boost - delta_time / INTENSITY_BOOST_FADE).max(0.0);
+        }
+
+        if let Some(oled_mode) = &self.oled_mode {
+            intensity = intensity.min(oled_mode.brightness_cap());
+        }
+
+        self.simulation.update(frame_time, intensity);
+        self.renderer
+            .update(&self.simulation, frame_time, intensity);
+
+        #[cfg(feature = "ambient-audio")]
+        if let Some(ambient_audio) = &self.ambient_audio {
+            ambient_audio.set_intensity(intensity);
+        }
+
+        self.hooks.frame(intensity, frame_time);
     }
 
-    fn create_compute_new_positions_pipeline(
-        device: &wgpu::Device,
-        points_buffer: &wgpu::Buffer,
-        window_size_buffer: &wgpu::Buffer,
-        delta_time_buffer: &wgpu::Buffer,
-    ) -> (wgpu::ComputePipeline, wgpu::BindGroup) {
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("Compute New Positions Bind Group layout"),
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Storage { read_only: false },
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 2,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-            ],
-        });
+    /// Whether `new` differs from `old` only in options that feed uniforms or the frame
+    /// loop, which `reconfigure` can apply in place.
+    pub fn can_reconfigure(old: &Config, new: &Config) -> bool {
+        let mut rest = new.clone();
+        rest.time_scale = old.time_scale;
+        rest.max_fps = old.max_fps;
+        rest.min_point_size = old.min_point_size;
+        rest.max_point_size = old.max_point_size;
+        rest.cursor_force = old.cursor_force;
+        rest.cursor_radius = old.cursor_radius;
+        rest.background_color = old.background_color;
+        rest.click_points = old.click_points;
+        rest.connection_distance = old.connection_distance;
+        rest.gravity = old.gravity;
+        rest.motion = old.motion;
+        rest.flow_scale = old.flow_scale;
+        rest.flow_speed = old.flow_speed;
+        rest.silent_speed = old.silent_speed;
+        rest.loud_speed = old.loud_speed;
+        rest.point_lifetime = old.point_lifetime;
+        rest.edge = old.edge;
+        rest.collision_radius = old.collision_radius;
+        rest.collision_strength = old.collision_strength;
+        rest.spacing = old.spacing;
+        rest.spacing_strength = old.spacing_strength;
+
+        // Lines and collisions have passes of their own, which are only there when on.
+        let collides = |config: &Config| config.collision_radius > 0.0 || config.spacing > 0.0;
+        rest == *old
+            && (new.connection_distance > 0.0) == (old.connection_distance > 0.0)
+            && collides(new) == collides(old)
+    }
 
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Compute New Positions Bind Group"),
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: points_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: window_size_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: delta_time_buffer.as_entire_binding(),
-                },
-            ],
-        });
+    /// Applies `new` in place of `old` when `can_reconfigure` allows it, returning whether it
+    /// did. Anything else needs the state started over.
+    pub fn reconfigure(&mut self, old: &Config, new: &Config) -> bool {
+        if !Self::can_reconfigure(old, new) {
+            return false;
+        }
 
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Compute New Positions Shader"),
-            source: wgpu::ShaderSource::Wgsl(
-                include_str!("shaders/compute_new_positions.wgsl").into(),
-            ),
-        });
+        let new = &match self.scale_factor {
+            Some(scale_factor) => new.scaled(scale_factor as f32),
+            None => new.clone(),
+        };
+        self.simulation.reconfigure(new);
+        self.renderer.reconfigure(new);
 
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("Compute New Positions Pipeline Layout"),
-            bind_group_layouts: &[&bind_group_layout],
-            push_constant_ranges: &[],
-        });
+        true
+    }
 
-        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("Compute New Positions Pipeline"),
-            layout: Some(&pipeline_layout),
-            module: &shader,
-            entry_point: Some("main"),
-            compilation_options: Default::default(),
-            cache: None,
-        });
+    /// Multiplies the time scale by `factor`, e.g. from the + and - keys.
+    pub fn scale_time(&mut self, factor: f32) {
+        let time_scale = self.simulation.scale_time(factor);
+        self.renderer.notify(format!("Time scale {time_scale:.2}"));
+    }
+
+    /// Multiplies the number of points in the front layer by `factor`, adding or removing at
+    /// least one, as far as the layer has room for them.
+    pub fn scale_points(&mut self, factor: f32) {
+        let Some((count, full)) = self.simulation.scale_points(factor) else {
+            return;
+        };
 
-        (compute_pipeline, bind_group)
+        let text = match full && factor > 1.0 {
+            true => format!("Points {count} (no room for more)"),
+            false => format!("Points {count}"),
+        };
+        self.renderer.notify(text);
     }
 
-    fn create_points(points_count: usize, window_size: WindowSize) -> Vec<Point> {
-        let width = window_size.size[0] as u32;
-        let height = window_size.size[1] as u32;
+    /// Multiplies the connection distance of every layer that draws lines by `factor`.
+    pub fn scale_connection_distance(&mut self, factor: f32) {
+        match self
+            .renderer
+            .scale_connection_distance(&mut self.simulation, factor)
+        {
+            Some(distance) => self
+                .renderer
+                .notify(format!("Connection distance {distance:.0}")),
+            None => self
+                .renderer
+                .notify("No lines to change the distance of".to_owned()),
+        }
+    }
 
-        let mut rng = rand::rng();
+    /// Multiplies the gain on top of the automatic gain control by `factor`.
+    pub fn scale_intensity(&mut self, factor: f32) {
+        self.intensity_gain =
+            (self.intensity_gain * factor).clamp(MIN_INTENSITY_GAIN, MAX_INTENSITY_GAIN);
+        self.renderer
+            .notify(format!("Intensity gain {:.2}", self.intensity_gain));
+    }
 
-        let mut points = Vec::<Point>::with_capacity(points_count);
-        for _ in 0..points_count {
-            let x = rng.random_range(0..width) as f32;
-            let y = rng.random_range(0..height) as f32;
+    /// Drives the intensity up to its maximum, fading back to the music over a moment.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn boost_intensity(&mut self) {
+        info!("Boosting the intensity");
+        self.intensity_boost = 1.0;
+    }
 
-            let invertx = rng.random_bool(0.5);
-            let inverty = rng.random_bool(0.5);
-            let vx = rng.random_range(1.0..3.0) * if invertx { -1.0 } else { 1.0 };
-            let vy = rng.random_range(1.0..3.0) * if inverty { -1.0 } else { 1.0 };
-            points.push(Point {
-                position: [x, y],
-                velocity: [vx, vy],
-            });
+    /// Pauses or resumes audio capture while rendering is paused.
+    pub fn set_paused(&self, paused: bool) {
+        if let Err(e) = self.volume_provider.set_paused(paused) {
+            log::error!("Unable to pause audio capture: {e}");
         }
+    }
 
-        points
+    /// Moves the point the cursor force pulls toward or pushes away from, in window pixels.
+    /// `None` when the cursor has left the window, which turns the force off.
+    pub fn set_cursor(&mut self, position: Option<[f32; 2]>) {
+        self.simulation.set_cursor(position);
     }
 
-    fn create_msaa_texture(
-        device: &wgpu::Device,
-        config: &wgpu::SurfaceConfiguration,
-    ) -> wgpu::TextureView {
-        let texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("MSAA Color Texture"),
-            size: wgpu::Extent3d {
-                width: config.width,
-                height: config.height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: SAMPLE_COUNT,
-            dimension: wgpu::TextureDimension::D2,
-            format: config.format,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            view_formats: &[],
+    /// Spawns points around the cursor in the front layer, as long as it has room for them.
+    pub fn spawn_points_at_cursor(&mut self) {
+        self.simulation.spawn_points_at_cursor();
+    }
+
+    /// Removes the points of the front layer nearest to the cursor.
+    pub fn remove_points_at_cursor(&mut self) {
+        self.simulation.remove_points_at_cursor();
+    }
+
+    /// Notifies the renderer of user input, which restarts idle dimming.
+    pub fn register_activity(&mut self) {
+        if let Some(oled_mode) = &mut self.oled_mode {
+            oled_mode.register_activity();
+        }
+    }
+
+    /// Starts exporting one frame of the velocity field to `path`, or to the path of
+    /// --export-flow when none is given. The file is written a frame or so later, once the
+    /// points have been copied back from the GPU.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn export_flow(&mut self, path: Option<PathBuf>) -> anyhow::Result<()> {
+        let path = path
+            .or_else(|| Some(self.flow_exporter.as_ref()?.path().to_owned()))
+            .ok_or_else(|| anyhow::anyhow!("No path was given and --export-flow is not set"))?;
+        self.flow_exports
+            .push((self.simulation.start_reading_points(), path));
+
+        Ok(())
+    }
+
+    /// Writes the velocity fields whose points have been copied back by now, without waiting
+    /// for the rest.
+    pub fn finish_flow_exports(&mut self) {
+        if self.flow_exports.is_empty() {
+            return;
+        }
+        if let Err(e) = self.device.poll(wgpu::PollType::Poll) {
+            log::error!("Unable to export velocity field: {e}");
+        }
+
+        let (width, height) = (self.config.width, self.config.height);
+        let cell_size = self.flow_cell_size;
+        self.flow_exports.retain_mut(|(readback, path)| {
+            let result = match readback.try_take() {
+                Ok(Some(points)) => flow_export::export(path, &points, width, height, cell_size),
+                Ok(None) => return true,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                log::error!("Unable to export velocity field to {}: {e}", path.display());
+            }
+            false
         });
+    }
 
-        texture.create_view(&wgpu::TextureViewDescriptor::default())
+    #[cfg(feature = "ambient-audio")]
+    fn analyze_ambient_audio(&self) {
+        let Some(ambient_audio) = &self.ambient_audio else {
+            return;
+        };
+
+        match self.simulation.read_points() {
+            Ok(points) => ambient_audio.analyze(&points, self.config.width, self.config.height),
+            Err(e) => log::error!("Unable to read points for ambient audio: {e}"),
+        }
     }
 
-    fn get_window_pos(&self) -> anyhow::Result<WindowSize> {
-        #[cfg(not(target_arch = "wasm32"))]
-        #[cfg(target_os = "linux")]
-        {
-            use anyhow::anyhow;
-            use std::process::{self, Command};
+    /// The instance the adapters are looked for on, with the backends drawing works with.
+    pub(crate) fn create_instance() -> wgpu::Instance {
+        wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            #[cfg(not(target_arch = "wasm32"))]
+            backends: wgpu::Backends::PRIMARY,
+            #[cfg(target_arch = "wasm32")]
+            backends: wgpu::Backends::GL,
+            ..Default::default()
+        })
+    }
+
+    /// The adapters of `instance` there are to pick from with --gpu, in the order of their
+    /// indices.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn adapters(instance: &wgpu::Instance) -> Vec<wgpu::Adapter> {
+        instance.enumerate_adapters(wgpu::Backends::PRIMARY)
+    }
 
-            let pid = process::id();
+    /// The adapter --gpu asks for, by its index or a part of its name.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn choose_adapter(
+        instance: &wgpu::Instance,
+        surface: &wgpu::Surface,
+        gpu: &str,
+    ) -> anyhow::Result<wgpu::Adapter> {
+        let adapters = Self::adapters(instance);
+        let adapter = match gpu.parse::<usize>() {
+            Ok(index) => adapters.into_iter().nth(index),
+            Err(_) => {
+                let gpu = gpu.to_lowercase();
+                adapters
+                    .into_iter()
+                    .find(|adapter| adapter.get_info().name.to_lowercase().contains(&gpu))
+            }
+        };
 
-            let json = Command::new("hyprctl")
-                .args(["-j", "clients"])
-                .output()?
-                .stdout;
+        let adapter = adapter.ok_or_else(|| {
+            anyhow::anyhow!("No graphics adapter matches --gpu {gpu}, the info command lists them")
+        })?;
+        if !adapter.is_surface_supported(surface) {
+            anyhow::bail!(
+                "The graphics adapter {} cannot draw to the window",
+                adapter.get_info().name
+            );
+        }
 
-            let clients: Vec<HyprClient> = serde_json::from_slice(&json)?;
+        Ok(adapter)
+    }
 
-            info!("My pid {pid}");
-            info!("Found clients {clients:?}");
+    /// The present mode asked for, or vsync when the surface does not support it.
+    fn choose_present_mode(
+        surface_caps: &wgpu::SurfaceCapabilities,
+        requested: PresentMode,
+    ) -> wgpu::PresentMode {
+        let present_mode = requested.to_wgpu();
+        // The auto modes fall back on their own, and FIFO is supported everywhere.
+        if requested == PresentMode::Auto || surface_caps.present_modes.contains(&present_mode) {
+            return present_mode;
+        }
 
-            let client = clients
-                .iter()
-                .find(|c| c.pid == pid)
-                .ok_or_else(|| anyhow!("No client found"))?;
+        log::warn!(
+            "The {present_mode:?} present mode is not supported, only {:?}, using Fifo instead",
+            surface_caps.present_modes
+        );
+        wgpu::PresentMode::Fifo
+    }
 
-            let x = client.at[0];
-            let monitor_height = self
-                .window
-                .current_monitor()
-                .ok_or_else(|| anyhow!("No current monitor found"))?
-                .size()
-                .height as f32;
-            let y = monitor_height - (self.window.inner_size().height as f32 + client.at[1]);
+    /// MSAA sample counts `adapter` can render `format` with, from the fewest to the most.
+    pub(crate) fn supported_sample_counts(
+        adapter: &wgpu::Adapter,
+        format: wgpu::TextureFormat,
+    ) -> Vec<u32> {
+        // Without adapter specific format features only the sample counts WebGPU
+        // guarantees are allowed.
+        let adapter_specific = adapter
+            .features()
+            .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES);
+        let flags = adapter.get_texture_format_features(format).flags;
+
+        [1, 2, 4, 8]
+            .into_iter()
+            .filter(|&count| {
+                count == 1
+                    || (flags.sample_count_supported(count) && (adapter_specific || count == 4))
+            })
+            .collect()
+    }
 
-            Ok(WindowSize { size: [x, y] })
+    /// Returns the largest sample count up to `requested` that the adapter can render
+    /// `format` with, warning when that is not the requested count.
+    fn choose_sample_count(
+        adapter: &wgpu::Adapter,
+        format: wgpu::TextureFormat,
+        requested: u32,
+    ) -> u32 {
+        let sample_count = Self::supported_sample_counts(adapter, format)
+            .into_iter()
+            .rev()
+            .find(|&count| count <= requested)
+            .unwrap_or(1);
+
+        if sample_count != requested {
+            log::warn!(
+                "{requested}x MSAA is not supported for {format:?}, using {sample_count}x instead"
+            );
         }
+
+        sample_count
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct HyprClient {
-    pid: u32,
-    at: Vec<f32>,
-}
+/// Finds where the canvas is on its monitor, for lining the background up with the desktop
+/// wallpaper. Wayland does not tell clients where their windows are, so Hyprland is asked
+/// directly. Elsewhere winit reports the position where the platform allows it, and on other
+/// Wayland compositors the canvas is assumed to be in the top left corner of the monitor.
+fn locate_window(canvas: &Canvas) -> [f32; 2] {
+    #[cfg(target_os = "linux")]
+    if matches!(canvas, Canvas::Window(_)) && hyprland::is_running() {
+        match hyprland::window_position() {
+            Ok(at) => return window_pos(canvas, at),
+            Err(e) => log::warn!("Unable to ask Hyprland where the window is: {e}"),
+        }
+    }
 
-#[repr(C)]
-#[derive(Copy, Clone, Pod, Zeroable)]
-struct Point {
-    position: [f32; 2],
-    velocity: [f32; 2],
+    let position = canvas.position();
+    window_pos(canvas, [position.x as f32, position.y as f32])
 }
 
-#[repr(C)]
-#[derive(Copy, Clone, Pod, Zeroable)]
-struct WindowSize {
-    size: [f32; 2],
-}
+/// Converts the top left corner of the canvas on its monitor to the offset of its bottom left
+/// corner from the bottom left of the monitor, as the shaders expect it.
+fn window_pos(canvas: &Canvas, top_left: [f32; 2]) -> [f32; 2] {
+    let height = canvas.size().height;
+    let monitor_height = canvas.monitor_size().map_or(height, |size| size.height);
 
-#[repr(C)]
-#[derive(Copy, Clone, Pod, Zeroable)]
-struct DeltaTime {
-    dt: f32,
+    [
+        top_left[0],
+        monitor_height as f32 - (height as f32 + top_left[1]),
+    ]
 }
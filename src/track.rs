@@ -0,0 +1,53 @@
+use std::{
+    process::Command,
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+    time::Duration,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Follows the track playing in any MPRIS media player, using `playerctl`.
+pub struct TrackMonitor {
+    track: Arc<Mutex<Option<String>>>,
+}
+
+impl TrackMonitor {
+    pub fn spawn() -> Self {
+        let track = Arc::new(Mutex::new(None));
+
+        {
+            let track = Arc::clone(&track);
+            // Stops once the monitor is dropped, as with the state starting over.
+            thread::spawn(move || {
+                while Arc::strong_count(&track) > 1 {
+                    let playing = playing_track();
+                    *track.lock().unwrap_or_else(PoisonError::into_inner) = playing;
+                    thread::sleep(POLL_INTERVAL);
+                }
+            });
+        }
+
+        Self { track }
+    }
+
+    /// The artist and title of the playing track, if anything is playing.
+    pub fn track(&self) -> Option<String> {
+        self.track
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+fn playing_track() -> Option<String> {
+    let output = Command::new("playerctl")
+        .args(["metadata", "--format", "{{status}}\n{{artist}} - {{title}}"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (status, track) = stdout.trim_end().split_once('\n')?;
+    (status == "Playing").then(|| track.trim_start_matches(" - ").to_string())
+}
@@ -0,0 +1,132 @@
+use std::{
+    io::Read,
+    path::Path,
+    process::{Child, Command, Stdio},
+    sync::mpsc::{Receiver, sync_channel},
+    thread,
+};
+
+use anyhow::{Result, anyhow};
+
+const VIDEO_EXTENSIONS: [&str; 7] = ["mp4", "m4v", "mkv", "webm", "mov", "avi", "ogv"];
+
+pub fn is_video(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| VIDEO_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Returns the width and height of the first video stream in `path`, using `ffprobe`.
+pub fn probe_size(path: &Path) -> Result<(u32, u32)> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-select_streams",
+            "v:0",
+            "-show_entries",
+            "stream=width,height",
+            "-of",
+            "csv=p=0:s=x",
+        ])
+        .arg(path)
+        .output()
+        .map_err(|e| anyhow!("Failed to start ffprobe: {e}"))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .trim()
+        .split_once('x')
+        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+        .ok_or(anyhow!(
+            "Failed to read the video size of {}",
+            path.display()
+        ))
+}
+
+/// Decodes a looping video into RGBA frames by piping it through an `ffmpeg` subprocess.
+pub struct VideoDecoder {
+    child: Child,
+    frames: Receiver<Vec<u8>>,
+    sync_to_render: bool,
+}
+
+impl VideoDecoder {
+    /// Starts decoding `path` scaled to `width` x `height`, blurred with a
+    /// gaussian of standard deviation `blur` unless it is zero.
+    ///
+    /// When `sync_to_render` is set every rendered frame shows the next video frame, so
+    /// playback speed follows the render rate. Otherwise playback runs at the video's
+    /// own frame rate and frames are dropped or repeated as needed.
+    pub fn spawn(
+        path: &Path,
+        width: u32,
+        height: u32,
+        blur: f32,
+        sync_to_render: bool,
+    ) -> Result<Self> {
+        let mut command = Command::new("ffmpeg");
+        command.args(["-loglevel", "error", "-nostdin", "-stream_loop", "-1"]);
+        if !sync_to_render {
+            command.arg("-re");
+        }
+        let mut filter = format!("scale={width}:{height}");
+        if blur > 0.0 {
+            filter.push_str(&format!(",gblur=sigma={blur}"));
+        }
+
+        command
+            .arg("-i")
+            .arg(path)
+            .args(["-vf", &filter, "-f", "rawvideo", "-pix_fmt", "rgba", "-"])
+            .stdout(Stdio::piped());
+
+        let mut child = command
+            .spawn()
+            .map_err(|e| anyhow!("Failed to start ffmpeg: {e}"))?;
+        let mut stdout = child
+            .stdout
+            .take()
+            .ok_or(anyhow!("Failed to capture ffmpeg output"))?;
+
+        // A small bound keeps memory in check and, when syncing to the render rate,
+        // blocks the decoder until the renderer has consumed a frame.
+        let (sender, frames) = sync_channel(2);
+        let frame_size = (width * height * 4) as usize;
+
+        thread::spawn(move || {
+            loop {
+                let mut frame = vec![0u8; frame_size];
+                if let Err(e) = stdout.read_exact(&mut frame) {
+                    log::warn!("Video decoding stopped: {e}");
+                    break;
+                }
+                if sender.send(frame).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            child,
+            frames,
+            sync_to_render,
+        })
+    }
+
+    /// Returns the frame to display next, if a new one is ready.
+    pub fn next_frame(&self) -> Option<Vec<u8>> {
+        if self.sync_to_render {
+            self.frames.try_recv().ok()
+        } else {
+            self.frames.try_iter().last()
+        }
+    }
+}
+
+impl Drop for VideoDecoder {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,40 @@
+use std::{cell::Cell, fs, path::Path};
+
+use anyhow::{Result, anyhow};
+
+use crate::volume_providers::volume_provider::VolumeProvider;
+
+/// Replays intensity recorded in a file, one value per line and per poll, so renders can be
+/// reproduced exactly. Once the values run out no intensity is reported.
+pub struct FileVolumeProvider {
+    values: Vec<f32>,
+    next: Cell<usize>,
+}
+
+impl FileVolumeProvider {
+    pub fn new(path: &Path) -> Result<Self> {
+        let values = fs::read_to_string(path)?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                line.parse::<f32>()
+                    .map_err(|e| anyhow!("Invalid intensity '{line}' in {}: {e}", path.display()))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            values,
+            next: Cell::new(0),
+        })
+    }
+}
+
+impl VolumeProvider for FileVolumeProvider {
+    fn poll_volume(&self) -> Result<Option<f32>> {
+        let index = self.next.get();
+        self.next.set(index + 1);
+
+        Ok(self.values.get(index).copied())
+    }
+}
@@ -1,6 +1,9 @@
 pub mod volume_provider;
 
 mod constant_volume_provider;
+pub mod file_volume_provider;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod synthetic_volume_provider;
 
 #[cfg(feature = "pulseaudio")]
 mod pulse;
@@ -2,7 +2,7 @@ use std::{cell::RefCell, rc::Rc};
 
 use crate::volume_providers::volume_provider::VolumeProvider;
 use anyhow::Result;
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
 use clap::crate_version;
 use libpulse_binding::def::BufferAttr;
 use libpulse_binding::{
@@ -29,16 +29,16 @@ impl PulseAudioVolumeProvider {
         let mut proplist = Proplist::new().ok_or(anyhow!("Failed to create Proplist"))?;
         proplist
             .set_str(properties::APPLICATION_NAME, "Connecting Dots")
-            .unwrap();
+            .map_err(|_| anyhow!("Failed to set the application properties"))?;
         proplist
             .set_str(properties::APPLICATION_ID, "org.mads256h.connectingdots")
-            .unwrap();
+            .map_err(|_| anyhow!("Failed to set the application properties"))?;
         proplist
             .set_str(properties::APPLICATION_ICON_NAME, "audio-card")
-            .unwrap();
+            .map_err(|_| anyhow!("Failed to set the application properties"))?;
         proplist
             .set_str(properties::APPLICATION_VERSION, crate_version!())
-            .unwrap();
+            .map_err(|_| anyhow!("Failed to set the application properties"))?;
         let context = Rc::new(RefCell::new(
             Context::new_with_proplist(&*main_loop.borrow(), "connecting_dots", &proplist)
                 .ok_or(anyhow!("Failed to create context"))?,
@@ -49,7 +49,7 @@ impl PulseAudioVolumeProvider {
         loop {
             match context.borrow().get_state() {
                 State::Ready => break,
-                State::Failed | State::Terminated => panic!("Failed to connect to pulseaudio"),
+                State::Failed | State::Terminated => bail!("Failed to connect to pulseaudio"),
                 _ => main_loop.borrow_mut().iterate(false),
             };
         }
@@ -119,7 +119,7 @@ impl PulseAudioVolumeProvider {
                 }
                 stream::State::Ready => break,
                 stream::State::Failed | stream::State::Terminated => {
-                    panic!("Failed to connect monitor stream")
+                    bail!("Failed to connect monitor stream")
                 }
             };
         }
@@ -153,4 +153,19 @@ impl VolumeProvider for PulseAudioVolumeProvider {
             }
         }
     }
+
+    fn set_paused(&self, paused: bool) -> Result<()> {
+        let operation = if paused {
+            self.monitor_stream.borrow_mut().cork(None)
+        } else {
+            self.monitor_stream.borrow_mut().uncork(None)
+        };
+
+        let mut main_loop = self.main_loop.borrow_mut();
+        while operation.get_state() == pa_operation_state_t::Running {
+            main_loop.iterate(false);
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,24 @@
+use std::cell::Cell;
+
+use crate::volume_providers::volume_provider::VolumeProvider;
+
+/// Polls per beat, which is 120 beats per minute at 60 frames per second.
+const POLLS_PER_BEAT: u32 = 30;
+
+/// A steady beat standing in for the music, one step per poll, so benchmarks see the same
+/// intensities on every run.
+#[derive(Default)]
+pub struct SyntheticVolumeProvider {
+    polls: Cell<u32>,
+}
+
+impl VolumeProvider for SyntheticVolumeProvider {
+    fn poll_volume(&self) -> anyhow::Result<Option<f32>> {
+        let polls = self.polls.get();
+        self.polls.set(polls.wrapping_add(1));
+
+        // Jumps up on the beat and decays until the next one.
+        let phase = (polls % POLLS_PER_BEAT) as f32 / POLLS_PER_BEAT as f32;
+        Ok(Some(0.2 + 0.8 * (-6.0 * phase).exp()))
+    }
+}
@@ -4,17 +4,44 @@ use crate::volume_providers::pulse::PulseAudioVolumeProvider;
 use anyhow::Result;
 use std::rc::Rc;
 
+/// Where the intensity the points move with comes from, polled once per frame.
 pub trait VolumeProvider {
+    /// The loudness since the last poll, or `None` when there is nothing new and the
+    /// intensity should fade out. Louder than 1 is fine, as the intensity is scaled to the
+    /// loudest it has been.
     fn poll_volume(&self) -> Result<Option<f32>>;
+
+    /// Stops capturing audio while paused, so the provider doesn't keep audio devices awake.
+    fn set_paused(&self, _paused: bool) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Names of the volume providers built in, with what each one listens to.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn volume_provider_names() -> Vec<&'static str> {
+    [
+        #[cfg(feature = "pulseaudio")]
+        "pulseaudio: the default sink's monitor source",
+        "file: one intensity per frame, with --intensity-file",
+        "constant: a fixed intensity when nothing else is available",
+    ]
+    .to_vec()
 }
 
 pub fn get_volume_provider() -> Rc<dyn VolumeProvider> {
     #[cfg(feature = "pulseaudio")]
-    {
-        if let Ok(pulse_volume_provider) = PulseAudioVolumeProvider::new() {
-            return Rc::new(pulse_volume_provider);
-        }
-
-        Rc::new(ConstantVolumeProvider::new(0.8))
+    match PulseAudioVolumeProvider::new() {
+        Ok(pulse_volume_provider) => return Rc::new(pulse_volume_provider),
+        Err(e) => log::warn!("Unable to capture audio through PulseAudio: {e}"),
     }
+
+    fallback_volume_provider()
+}
+
+/// Stands in for a volume provider that failed, keeping the points moving at a fixed
+/// intensity.
+pub fn fallback_volume_provider() -> Rc<dyn VolumeProvider> {
+    log::warn!("Using a constant intensity instead of the music");
+    Rc::new(ConstantVolumeProvider::new(0.8))
 }
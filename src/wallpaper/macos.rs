@@ -0,0 +1,41 @@
+use std::{ffi::c_void, ptr::NonNull};
+
+use anyhow::anyhow;
+use objc2_app_kit::{NSView, NSWindowCollectionBehavior};
+
+/// kCGDesktopWindowLevelKey, the level the desktop picture is drawn at.
+const DESKTOP_WINDOW_LEVEL_KEY: i32 = 2;
+
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    fn CGWindowLevelForKey(key: i32) -> i32;
+}
+
+/// Moves the window of `ns_view` down to the desktop picture, over its whole screen and on
+/// every space.
+pub fn attach(ns_view: NonNull<c_void>) -> anyhow::Result<()> {
+    // SAFETY: winit hands out a valid NSView, and windows are only touched on the main thread.
+    let view: &NSView = unsafe { ns_view.cast().as_ref() };
+    let window = view
+        .window()
+        .ok_or_else(|| anyhow!("The view is not in a window"))?;
+
+    // SAFETY: Plain property setters on a live window.
+    unsafe {
+        window.setLevel(CGWindowLevelForKey(DESKTOP_WINDOW_LEVEL_KEY) as isize);
+        // Shown on every space, left in place by Mission Control and skipped when cycling
+        // through windows.
+        window.setCollectionBehavior(
+            NSWindowCollectionBehavior::CanJoinAllSpaces
+                | NSWindowCollectionBehavior::Stationary
+                | NSWindowCollectionBehavior::IgnoresCycle,
+        );
+    }
+    window.setIgnoresMouseEvents(true);
+
+    if let Some(screen) = window.screen() {
+        window.setFrame_display(screen.frame(), true);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,48 @@
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "windows")]
+mod windows;
+
+use winit::window::Window;
+
+/// Turns the window into an animated wallpaper behind the desktop icons.
+pub fn attach(window: &Window) -> anyhow::Result<()> {
+    #[cfg(target_os = "windows")]
+    {
+        use anyhow::{anyhow, bail};
+        use winit::raw_window_handle::{HasWindowHandle, RawWindowHandle};
+
+        let RawWindowHandle::Win32(handle) = window.window_handle()?.as_raw() else {
+            bail!("Not a Win32 window");
+        };
+        let monitor = window
+            .current_monitor()
+            .ok_or_else(|| anyhow!("No monitor found"))?;
+        let position = monitor.position();
+        let size = monitor.size();
+
+        windows::attach(
+            handle.hwnd.get(),
+            [position.x, position.y],
+            [size.width, size.height],
+        )
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        use anyhow::bail;
+        use winit::raw_window_handle::{HasWindowHandle, RawWindowHandle};
+
+        let RawWindowHandle::AppKit(handle) = window.window_handle()?.as_raw() else {
+            bail!("Not an AppKit window");
+        };
+
+        macos::attach(handle.ns_view)
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        let _ = window;
+        anyhow::bail!("--wallpaper is only supported on Windows and macOS")
+    }
+}
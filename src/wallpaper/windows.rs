@@ -0,0 +1,74 @@
+use std::ptr;
+
+use anyhow::bail;
+use windows_sys::{
+    Win32::{
+        Foundation::{BOOL, HWND, LPARAM, TRUE},
+        UI::WindowsAndMessaging::{
+            EnumWindows, FindWindowExW, FindWindowW, GetSystemMetrics, SM_XVIRTUALSCREEN,
+            SM_YVIRTUALSCREEN, SMTO_NORMAL, SWP_NOACTIVATE, SWP_NOZORDER, SendMessageTimeoutW,
+            SetParent, SetWindowPos,
+        },
+    },
+    w,
+};
+
+/// Undocumented message asking Progman to create a WorkerW window between the desktop icons
+/// and the wallpaper, used by every live wallpaper engine.
+const SPAWN_WORKERW: u32 = 0x052C;
+
+/// Moves the window into the WorkerW behind the desktop icons and over the monitor at
+/// `position` with `size`, in screen coordinates.
+pub fn attach(hwnd: HWND, position: [i32; 2], size: [u32; 2]) -> anyhow::Result<()> {
+    // SAFETY: Plain Win32 calls on window handles, which fail gracefully on stale handles.
+    unsafe {
+        let progman = FindWindowW(w!("Progman"), ptr::null());
+        if progman == 0 {
+            bail!("No Progman window found, is Explorer running?");
+        }
+
+        let mut result = 0;
+        SendMessageTimeoutW(progman, SPAWN_WORKERW, 0, 0, SMTO_NORMAL, 1000, &mut result);
+
+        let mut workerw: HWND = 0;
+        EnumWindows(Some(find_workerw), &mut workerw as *mut HWND as LPARAM);
+        // Since Windows 11 24H2 the WorkerW is a child of Progman instead of a sibling of the
+        // icons.
+        if workerw == 0 {
+            workerw = FindWindowExW(progman, 0, w!("WorkerW"), ptr::null());
+        }
+        if workerw == 0 {
+            bail!("No WorkerW window found behind the desktop icons");
+        }
+
+        if SetParent(hwnd, workerw) == 0 {
+            bail!("Unable to move the window behind the desktop icons");
+        }
+
+        // Children are positioned relative to the WorkerW, which spans the virtual screen.
+        let x = position[0] - GetSystemMetrics(SM_XVIRTUALSCREEN);
+        let y = position[1] - GetSystemMetrics(SM_YVIRTUALSCREEN);
+        SetWindowPos(
+            hwnd,
+            0,
+            x,
+            y,
+            size[0] as i32,
+            size[1] as i32,
+            SWP_NOZORDER | SWP_NOACTIVATE,
+        );
+    }
+
+    Ok(())
+}
+
+/// Finds the WorkerW following the top-level window that hosts the desktop icons.
+unsafe extern "system" fn find_workerw(hwnd: HWND, workerw: LPARAM) -> BOOL {
+    // SAFETY: `workerw` points at the HWND passed to EnumWindows by attach.
+    unsafe {
+        if FindWindowExW(hwnd, 0, w!("SHELLDLL_DefView"), ptr::null()) != 0 {
+            *(workerw as *mut HWND) = FindWindowExW(0, hwnd, w!("WorkerW"), ptr::null());
+        }
+    }
+    TRUE
+}
@@ -0,0 +1,151 @@
+use smithay_client_toolkit::reexports::{
+    client::{
+        Connection, Dispatch, QueueHandle, globals::GlobalList, protocol::wl_surface::WlSurface,
+    },
+    protocols::wp::{
+        fractional_scale::v1::client::{
+            wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1,
+            wp_fractional_scale_v1::{self, WpFractionalScaleV1},
+        },
+        viewporter::client::{wp_viewport::WpViewport, wp_viewporter::WpViewporter},
+    },
+};
+use winit::dpi::{LogicalSize, PhysicalSize};
+
+/// Told about the scale the compositor prefers surfaces to be drawn at.
+pub trait ScaleHandler: Sized {
+    fn scale_changed(&mut self, qh: &QueueHandle<Self>, surface: &WlSurface, scale: f64);
+}
+
+/// Scales surfaces managed outside of winit. The compositor tells the preferred scale of each
+/// surface through wp-fractional-scale, or as a whole number through the compositor handler
+/// where that is missing, and wp-viewporter shows the buffer drawn at that scale at the
+/// logical size of the surface.
+pub struct Scaling {
+    fractional_scale_manager: Option<WpFractionalScaleManagerV1>,
+    viewporter: Option<WpViewporter>,
+}
+
+impl Scaling {
+    pub fn bind<D>(globals: &GlobalList, qh: &QueueHandle<D>) -> Self
+    where
+        D: Dispatch<WpFractionalScaleManagerV1, ()> + Dispatch<WpViewporter, ()> + 'static,
+    {
+        Self {
+            fractional_scale_manager: globals.bind(qh, 1..=1, ()).ok(),
+            viewporter: globals.bind(qh, 1..=1, ()).ok(),
+        }
+    }
+
+    /// Starts following the preferred scale of `surface`.
+    pub fn scale_surface<D>(&self, surface: &WlSurface, qh: &QueueHandle<D>) -> SurfaceScale
+    where
+        D: Dispatch<WpFractionalScaleV1, WlSurface> + Dispatch<WpViewport, ()> + 'static,
+    {
+        let viewport = self
+            .viewporter
+            .as_ref()
+            .map(|viewporter| viewporter.get_viewport(surface, qh, ()));
+        // Fractional scales need a viewport to show the buffer at the logical size.
+        let fractional_scale = self
+            .fractional_scale_manager
+            .as_ref()
+            .filter(|_| viewport.is_some())
+            .map(|manager| manager.get_fractional_scale(surface, qh, surface.clone()));
+
+        SurfaceScale {
+            surface: surface.clone(),
+            fractional_scale,
+            viewport,
+            scale: 1.0,
+        }
+    }
+}
+
+/// The scale one surface is drawn at.
+pub struct SurfaceScale {
+    surface: WlSurface,
+    fractional_scale: Option<WpFractionalScaleV1>,
+    viewport: Option<WpViewport>,
+    scale: f64,
+}
+
+impl SurfaceScale {
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Whether the compositor tells fractional scales for the surface, which take over from
+    /// the whole numbers of the compositor handler.
+    pub fn is_fractional(&self) -> bool {
+        self.fractional_scale.is_some()
+    }
+
+    /// Draws the surface at `scale` from now on, returning whether that changed anything.
+    pub fn set_scale(&mut self, scale: f64) -> bool {
+        // Without a viewport the buffer can only be scaled by whole numbers.
+        let scale = match self.viewport {
+            Some(_) => scale,
+            None => scale.round().max(1.0),
+        };
+        if scale == self.scale {
+            return false;
+        }
+        self.scale = scale;
+        true
+    }
+
+    /// Shows the buffer at `size` in logical pixels from the next commit on, and returns the
+    /// size of the buffer to draw.
+    pub fn apply(&self, size: LogicalSize<u32>) -> PhysicalSize<u32> {
+        match &self.viewport {
+            Some(viewport) => viewport.set_destination(size.width as i32, size.height as i32),
+            None => self.surface.set_buffer_scale(self.scale as i32),
+        }
+
+        let size: PhysicalSize<u32> = size.to_physical(self.scale);
+        PhysicalSize::new(size.width.max(1), size.height.max(1))
+    }
+}
+
+impl Drop for SurfaceScale {
+    fn drop(&mut self) {
+        if let Some(fractional_scale) = &self.fractional_scale {
+            fractional_scale.destroy();
+        }
+        if let Some(viewport) = &self.viewport {
+            viewport.destroy();
+        }
+    }
+}
+
+impl<D> Dispatch<WpFractionalScaleV1, WlSurface, D> for Scaling
+where
+    D: Dispatch<WpFractionalScaleV1, WlSurface> + ScaleHandler,
+{
+    fn event(
+        state: &mut D,
+        _proxy: &WpFractionalScaleV1,
+        event: wp_fractional_scale_v1::Event,
+        surface: &WlSurface,
+        _conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) {
+        if let wp_fractional_scale_v1::Event::PreferredScale { scale } = event {
+            // The scale comes in 120ths.
+            state.scale_changed(qh, surface, scale as f64 / 120.0);
+        }
+    }
+}
+
+/// Implements the dispatching `Scaling` needs for `$ty`, which has to be a `ScaleHandler`.
+macro_rules! delegate_scaling {
+    ($ty:ty) => {
+        smithay_client_toolkit::reexports::client::delegate_noop!($ty: ignore smithay_client_toolkit::reexports::protocols::wp::fractional_scale::v1::client::wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1);
+        smithay_client_toolkit::reexports::client::delegate_noop!($ty: ignore smithay_client_toolkit::reexports::protocols::wp::viewporter::client::wp_viewporter::WpViewporter);
+        smithay_client_toolkit::reexports::client::delegate_noop!($ty: ignore smithay_client_toolkit::reexports::protocols::wp::viewporter::client::wp_viewport::WpViewport);
+        smithay_client_toolkit::reexports::client::delegate_dispatch!($ty: [smithay_client_toolkit::reexports::protocols::wp::fractional_scale::v1::client::wp_fractional_scale_v1::WpFractionalScaleV1: smithay_client_toolkit::reexports::client::protocol::wl_surface::WlSurface] => $crate::wayland_scale::Scaling);
+    };
+}
+
+pub(crate) use delegate_scaling;
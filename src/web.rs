@@ -0,0 +1,269 @@
+use std::{
+    cell::{Cell, RefCell},
+    ffi::OsString,
+    rc::Rc,
+};
+
+use wasm_bindgen::{JsCast, prelude::*};
+use web_sys::{HtmlCanvasElement, ResizeObserver};
+
+use crate::{
+    config::Config, run_options::RunOptions, volume_providers::volume_provider::VolumeProvider,
+};
+
+/// Intensity the points move with until the page sets one, as there is no music to follow.
+const DEFAULT_INTENSITY: f32 = 0.8;
+/// Shorter names links may use for options in the query string, besides their long names.
+const QUERY_ALIASES: &[(&str, &str)] = &[("connect", "connection-distance"), ("theme", "preset")];
+
+/// A call of the JavaScript API, carried out by the app between frames.
+#[derive(Debug)]
+pub(crate) enum Command {
+    Stop,
+    /// Applies the config, in place when it can or by starting over.
+    Configure(Box<Config>),
+    /// Resizes the canvas to a size in CSS pixels.
+    Resize(u32, u32),
+    /// Fits the canvas's pixels to its size on the page.
+    Fit,
+}
+
+/// The canvas of the page the app draws into, and the calls of the page waiting for it.
+#[derive(Debug, Clone)]
+pub(crate) struct Page {
+    pub canvas_id: String,
+    pub commands: Rc<RefCell<Vec<Command>>>,
+}
+
+impl Default for Page {
+    fn default() -> Self {
+        Self {
+            canvas_id: "canvas".to_owned(),
+            commands: Rc::default(),
+        }
+    }
+}
+
+impl Page {
+    /// The commands sent since the last call, oldest first.
+    pub fn take_commands(&self) -> Vec<Command> {
+        std::mem::take(&mut *self.commands.borrow_mut())
+    }
+}
+
+/// Watches the canvas for changing size on the page, and for changes of the device pixels
+/// per CSS pixel, as when the page is zoomed or moved to another monitor, asking the app to
+/// fit the canvas to them.
+pub(crate) struct CanvasObserver {
+    canvas: HtmlCanvasElement,
+    commands: Rc<RefCell<Vec<Command>>>,
+    resize_observer: ResizeObserver,
+    _on_resize: Closure<dyn FnMut()>,
+    pixel_ratio: Cell<f64>,
+}
+
+impl CanvasObserver {
+    pub fn new(canvas: HtmlCanvasElement, page: &Page) -> anyhow::Result<Self> {
+        let commands = page.commands.clone();
+        let on_resize = Closure::<dyn FnMut()>::new(move || {
+            commands.borrow_mut().push(Command::Fit);
+        });
+        let resize_observer = ResizeObserver::new(on_resize.as_ref().unchecked_ref())
+            .map_err(|e| anyhow::anyhow!("Unable to watch the size of the canvas: {e:?}"))?;
+        resize_observer.observe(&canvas);
+
+        Ok(Self {
+            canvas,
+            commands: page.commands.clone(),
+            resize_observer,
+            _on_resize: on_resize,
+            pixel_ratio: Cell::new(pixel_ratio()),
+        })
+    }
+
+    /// Asks the app to fit the canvas when the device pixels per CSS pixel changed, which
+    /// browsers have no event for that fires on every change.
+    pub fn poll(&self) {
+        let pixel_ratio = pixel_ratio();
+        if pixel_ratio != self.pixel_ratio.replace(pixel_ratio) {
+            log::info!("Device pixel ratio changed to {pixel_ratio}");
+            self.commands.borrow_mut().push(Command::Fit);
+        }
+    }
+
+    /// Gives the canvas a pixel for every device pixel it covers, returning its new size.
+    pub fn fit(&self) -> (u32, u32) {
+        let pixel_ratio = pixel_ratio();
+        let width = (self.canvas.client_width() as f64 * pixel_ratio).round() as u32;
+        let height = (self.canvas.client_height() as f64 * pixel_ratio).round() as u32;
+        self.canvas.set_width(width);
+        self.canvas.set_height(height);
+
+        (width, height)
+    }
+}
+
+impl Drop for CanvasObserver {
+    fn drop(&mut self) {
+        self.resize_observer.disconnect();
+    }
+}
+
+fn pixel_ratio() -> f64 {
+    web_sys::window().map_or(1.0, |window| window.device_pixel_ratio())
+}
+
+/// Takes the intensity from what the page last set.
+struct PageVolumeProvider {
+    intensity: Rc<Cell<f32>>,
+}
+
+impl VolumeProvider for PageVolumeProvider {
+    fn poll_volume(&self) -> anyhow::Result<Option<f32>> {
+        Ok(Some(self.intensity.get()))
+    }
+}
+
+/// The visualization for scripts of a web page, drawing into one of its canvases.
+#[wasm_bindgen(js_name = ConnectingDots)]
+pub struct WebConnectingDots {
+    /// The command line the config is parsed from, starting with the program name and the
+    /// options in the query string of the page.
+    args: Vec<OsString>,
+    /// The page the visualization runs on, once started.
+    page: Option<Page>,
+    intensity: Rc<Cell<f32>>,
+}
+
+#[wasm_bindgen(js_class = ConnectingDots)]
+impl WebConnectingDots {
+    #[wasm_bindgen(constructor)]
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            args: query_args(),
+            page: None,
+            intensity: Rc::new(Cell::new(DEFAULT_INTENSITY)),
+        }
+    }
+
+    /// Starts drawing into the canvas with the id `canvas_id`, with the options in the
+    /// query string of the page, like `?points=2000&connect=120`. A page can only start one
+    /// visualization, as winit can only create one event loop on it.
+    pub fn start(&mut self, canvas_id: &str) -> Result<(), JsError> {
+        if self.page.is_some() {
+            return Err(JsError::new("The visualization has been started already"));
+        }
+        let has_canvas = web_sys::window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.get_element_by_id(canvas_id))
+            .is_some();
+        if !has_canvas {
+            return Err(JsError::new(&format!(
+                "There is no canvas with the id {canvas_id}"
+            )));
+        }
+
+        let page = Page {
+            canvas_id: canvas_id.to_owned(),
+            ..Page::default()
+        };
+        let mut options =
+            RunOptions::from(parse_config(&self.args)?).volume_provider(PageVolumeProvider {
+                intensity: self.intensity.clone(),
+            });
+        options.page = page.clone();
+        crate::run(options).map_err(|e| JsError::new(&format!("{e:#}")))?;
+        self.page = Some(page);
+
+        Ok(())
+    }
+
+    /// Stops drawing and lets go of the canvas.
+    pub fn stop(&mut self) {
+        self.send(Command::Stop);
+    }
+
+    /// Moves the points as if the music had `intensity`, where the loudest intensity set so
+    /// far moves them the most.
+    #[wasm_bindgen(js_name = setIntensity)]
+    pub fn set_intensity(&self, intensity: f32) {
+        self.intensity.set(intensity.max(0.0));
+    }
+
+    /// Switches to the preset called `name`, like `calm` or `minimal`.
+    #[wasm_bindgen(js_name = setTheme)]
+    pub fn set_theme(&mut self, name: &str) -> Result<(), JsError> {
+        let mut args: Vec<_> = self
+            .args
+            .iter()
+            .filter(|arg| !arg.to_string_lossy().starts_with("--preset="))
+            .cloned()
+            .collect();
+        args.push(format!("--preset={name}").into());
+
+        let config = parse_config(&args)?;
+        self.args = args;
+        self.send(Command::Configure(Box::new(config)));
+
+        Ok(())
+    }
+
+    /// Resizes the canvas to `width` by `height` CSS pixels.
+    pub fn resize(&self, width: u32, height: u32) {
+        self.send(Command::Resize(width, height));
+    }
+}
+
+impl WebConnectingDots {
+    /// Hands `command` to the app, unless it is not running.
+    fn send(&self, command: Command) {
+        if let Some(page) = &self.page {
+            page.commands.borrow_mut().push(command);
+        }
+    }
+}
+
+/// The options in the query string of the page's URL, like `?points=2000&theme=calm`, as
+/// command line arguments after the program name. A parameter without a value turns a flag
+/// on.
+fn query_args() -> Vec<OsString> {
+    let search = web_sys::window()
+        .and_then(|window| window.location().search().ok())
+        .unwrap_or_default();
+
+    let mut args = vec!["connecting-dots".into()];
+    for parameter in search.trim_start_matches('?').split('&') {
+        let (name, value) = match parameter.split_once('=') {
+            Some((name, value)) => (decode_query(name), Some(decode_query(value))),
+            None => (decode_query(parameter), None),
+        };
+        if name.is_empty() {
+            continue;
+        }
+        let name = QUERY_ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == name)
+            .map_or(name.as_str(), |(_, long)| long);
+
+        args.push(match value {
+            Some(value) => format!("--{name}={value}").into(),
+            None => format!("--{name}").into(),
+        });
+    }
+
+    args
+}
+
+/// Undoes the percent-encoding of a name or value in a query string, where + stands for a
+/// space.
+fn decode_query(text: &str) -> String {
+    let text = text.replace('+', " ");
+    web_sys::js_sys::decode_uri_component(&text)
+        .map(String::from)
+        .unwrap_or(text)
+}
+
+fn parse_config(args: &[OsString]) -> Result<Config, JsError> {
+    Config::try_parse_from(args.to_vec()).map_err(|e| JsError::new(&e.to_string()))
+}
@@ -0,0 +1,30 @@
+use std::env;
+
+use anyhow::{Context, anyhow};
+use winit::dpi::PhysicalSize;
+use x11rb::protocol::xproto::ConnectionExt;
+
+/// Finds the window XScreenSaver wants its hacks to draw into, from `$XSCREENSAVER_WINDOW`,
+/// along with its size. Returns `None` when not started by XScreenSaver.
+pub fn window() -> anyhow::Result<Option<(u32, PhysicalSize<u32>)>> {
+    let Ok(id) = env::var("XSCREENSAVER_WINDOW") else {
+        return Ok(None);
+    };
+    let id = id.trim();
+    let window = match id.strip_prefix("0x").or_else(|| id.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => id.parse(),
+    }
+    .with_context(|| format!("Invalid XSCREENSAVER_WINDOW {id:?}"))?;
+
+    let (connection, _) = x11rb::connect(None).context("Unable to connect to the X server")?;
+    let geometry = connection
+        .get_geometry(window)?
+        .reply()
+        .map_err(|e| anyhow!("Unable to get the size of window {window:#x}: {e}"))?;
+
+    Ok(Some((
+        window,
+        PhysicalSize::new(geometry.width as u32, geometry.height as u32),
+    )))
+}